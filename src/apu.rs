@@ -3,6 +3,7 @@ use self::channel2::Channel2;
 use self::channel3::Channel3;
 use self::channel4::Channel4;
 use crate::bitutils::*;
+use crate::savestate::{StateWriter, StateReader, SaveStateError};
 mod channel1;
 mod channel2;
 mod channel3;
@@ -62,6 +63,43 @@ impl APU {
         r
     }
 
+    pub fn save_state(&self, w: &mut StateWriter) {
+        w.write_u16(self.state.sample_tick);
+        w.write_u16(self.state.frame_sequencer);
+        w.write_u16(self.state.frame_sequencer_counter);
+
+        w.write_bool(self.registers.sound_enabled);
+        w.write_u8(self.registers.enabled_terminals);
+        w.write_u8(self.registers.left_volume);
+        w.write_u8(self.registers.right_volume);
+
+        self.channel1.save_state(w);
+        self.channel2.save_state(w);
+        self.channel3.save_state(w);
+        self.channel4.save_state(w);
+    }
+
+    pub fn load_state(&mut self, r: &mut StateReader) -> Result<(), SaveStateError> {
+        self.state.sample_tick = r.read_u16()?;
+        self.state.frame_sequencer = r.read_u16()?;
+        self.state.frame_sequencer_counter = r.read_u16()?;
+
+        self.registers.sound_enabled = r.read_bool()?;
+        self.registers.enabled_terminals = r.read_u8()?;
+        self.registers.left_volume = r.read_u8()?;
+        self.registers.right_volume = r.read_u8()?;
+
+        self.channel1.load_state(r)?;
+        self.channel2.load_state(r)?;
+        self.channel3.load_state(r)?;
+        self.channel4.load_state(r)?;
+
+        // drop any samples generated before the state was restored
+        self.samples.clear();
+
+        Ok(())
+    }
+
     pub fn tick(&mut self) {
         if !self.registers.sound_enabled {
             return;
@@ -1,3 +1,4 @@
+use crate::savestate::{StateWriter, StateReader, SaveStateError};
 
 pub struct Channel1 {
     pub enabled: bool,
@@ -141,14 +142,12 @@ impl Channel1 {
             self.envelope_timer = self.envelope_period;
 
             if self.envelope_period != 0 {
-                let volume;
-
-                if self.envelope_direction {
-                    volume = self.volume.wrapping_add(1);
+                let volume = if self.envelope_direction {
+                    self.volume.wrapping_add(1)
                 }
                 else {
-                    volume = self.volume.wrapping_sub(1);
-                }
+                    self.volume.wrapping_sub(1)
+                };
 
                 if volume <= 15 {
                     self.volume = volume;
@@ -157,6 +156,56 @@ impl Channel1 {
         }
     }
 
+    pub fn save_state(&self, w: &mut StateWriter) {
+        w.write_bool(self.enabled);
+        w.write_bool(self.dac_enabled);
+        w.write_i16(self.output_timer);
+        w.write_u16(self.output_timer_period);
+        w.write_u8(self.volume);
+        w.write_u16(self.frequency);
+        w.write_u8(self.length_counter);
+        w.write_bool(self.length_counter_enabled);
+        w.write_u8(self.envelope_timer);
+        w.write_bool(self.envelope_direction);
+        w.write_u8(self.envelope_period);
+        w.write_u8(self.envelope_initial);
+        w.write_bool(self.sweep_enabled);
+        w.write_u8(self.sweep_period);
+        w.write_u8(self.sweep_shift);
+        w.write_bool(self.sweep_direction);
+        w.write_i16(self.sweep_timer);
+        w.write_u16(self.sweep_frequency_shadow);
+        w.write_u8(self.waveform_index);
+        w.write_u8(self.duty);
+        w.write_u8(self.output);
+    }
+
+    pub fn load_state(&mut self, r: &mut StateReader) -> Result<(), SaveStateError> {
+        self.enabled = r.read_bool()?;
+        self.dac_enabled = r.read_bool()?;
+        self.output_timer = r.read_i16()?;
+        self.output_timer_period = r.read_u16()?;
+        self.volume = r.read_u8()?;
+        self.frequency = r.read_u16()?;
+        self.length_counter = r.read_u8()?;
+        self.length_counter_enabled = r.read_bool()?;
+        self.envelope_timer = r.read_u8()?;
+        self.envelope_direction = r.read_bool()?;
+        self.envelope_period = r.read_u8()?;
+        self.envelope_initial = r.read_u8()?;
+        self.sweep_enabled = r.read_bool()?;
+        self.sweep_period = r.read_u8()?;
+        self.sweep_shift = r.read_u8()?;
+        self.sweep_direction = r.read_bool()?;
+        self.sweep_timer = r.read_i16()?;
+        self.sweep_frequency_shadow = r.read_u16()?;
+        self.waveform_index = r.read_u8()?;
+        self.duty = r.read_u8()?;
+        self.output = r.read_u8()?;
+
+        Ok(())
+    }
+
     pub fn get_output(&self) -> u8 {
         self.output
     }
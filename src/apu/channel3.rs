@@ -1,3 +1,4 @@
+use crate::savestate::{StateWriter, StateReader, SaveStateError};
 
 pub struct Channel3 {
     pub enabled: bool,
@@ -70,6 +71,38 @@ impl Channel3 {
         }
     }
 
+    pub fn save_state(&self, w: &mut StateWriter) {
+        w.write_bool(self.enabled);
+        w.write_bool(self.dac_enabled);
+        w.write_u8(self.output_level);
+        w.write_u16(self.length_counter);
+        w.write_bool(self.length_counter_enabled);
+        w.write_u16(self.waveform_timer_load);
+        w.write_i16(self.waveform_timer);
+        w.write_u8(self.waveform_position);
+        w.write_u8(self.waveform_sample_buffer);
+        w.write_bytes(&self.waveform_data);
+        w.write_u16(self.frequency);
+        w.write_u8(self.output);
+    }
+
+    pub fn load_state(&mut self, r: &mut StateReader) -> Result<(), SaveStateError> {
+        self.enabled = r.read_bool()?;
+        self.dac_enabled = r.read_bool()?;
+        self.output_level = r.read_u8()?;
+        self.length_counter = r.read_u16()?;
+        self.length_counter_enabled = r.read_bool()?;
+        self.waveform_timer_load = r.read_u16()?;
+        self.waveform_timer = r.read_i16()?;
+        self.waveform_position = r.read_u8()?;
+        self.waveform_sample_buffer = r.read_u8()?;
+        r.read_bytes(&mut self.waveform_data)?;
+        self.frequency = r.read_u16()?;
+        self.output = r.read_u8()?;
+
+        Ok(())
+    }
+
     pub fn get_output(&self) -> u8 {
         self.output
     }
@@ -1,3 +1,4 @@
+use crate::savestate::{StateWriter, StateReader, SaveStateError};
 
 const DIVISORS: [u8; 8] = [ 8, 16, 32, 48, 64, 80, 96, 112 ]; 
 
@@ -118,6 +119,48 @@ impl Channel4 {
         }
     }
 
+    pub fn save_state(&self, w: &mut StateWriter) {
+        w.write_bool(self.enabled);
+        w.write_bool(self.dac_enabled);
+        w.write_u16(self.output_timer);
+        w.write_u16(self.output_timer_period);
+        w.write_u8(self.volume);
+        w.write_u16(self.lfsr);
+        w.write_bool(self.width);
+        w.write_u8(self.divisor_shift);
+        w.write_u8(self.divisor);
+        w.write_u8(self.length_counter);
+        w.write_bool(self.length_counter_enabled);
+        w.write_u8(self.envelope_timer);
+        w.write_bool(self.envelope_direction);
+        w.write_u8(self.envelope_period);
+        w.write_u8(self.envelope_initial);
+        w.write_f32(self.output);
+        w.write_u32(self.output_length);
+    }
+
+    pub fn load_state(&mut self, r: &mut StateReader) -> Result<(), SaveStateError> {
+        self.enabled = r.read_bool()?;
+        self.dac_enabled = r.read_bool()?;
+        self.output_timer = r.read_u16()?;
+        self.output_timer_period = r.read_u16()?;
+        self.volume = r.read_u8()?;
+        self.lfsr = r.read_u16()?;
+        self.width = r.read_bool()?;
+        self.divisor_shift = r.read_u8()?;
+        self.divisor = r.read_u8()?;
+        self.length_counter = r.read_u8()?;
+        self.length_counter_enabled = r.read_bool()?;
+        self.envelope_timer = r.read_u8()?;
+        self.envelope_direction = r.read_bool()?;
+        self.envelope_period = r.read_u8()?;
+        self.envelope_initial = r.read_u8()?;
+        self.output = r.read_f32()?;
+        self.output_length = r.read_u32()?;
+
+        Ok(())
+    }
+
     pub fn get_output(&mut self) -> f32 {
         let r = self.output / (self.output_length as f32);
         self.output = 0.0;
@@ -0,0 +1,359 @@
+#![deny(clippy::all)]
+#![forbid(unsafe_code)]
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::process::exit;
+use std::time::{Duration, Instant};
+use clap::{Arg, App};
+
+use rust_gameboy::serial::LinkCable;
+use rust_gameboy::infrared;
+use rust_gameboy::{Machine, ROM, GameBoyModel, PPURenderer, SCREEN_WIDTH, SCREEN_HEIGHT};
+
+const EXIT_SUCCESS: i32 = 0;
+const EXIT_FAILURE: i32 = 1;
+const EXIT_TIMEOUT: i32 = 2;
+const EXIT_ERROR: i32 = 3;
+
+// Every step runs at least one machine cycle, so a frame never takes more steps than this.
+// It bounds --frames when the LCD is off and the frame count doesn't move.
+const MAX_STEPS_PER_FRAME: u64 = 70224 / 4;
+
+// How often the limits are checked when no frame is finished
+const LIMIT_CHECK_STEPS: u64 = 4096;
+
+enum RunResult {
+    Success,
+    Failure,
+    Timeout,
+}
+
+struct RunOptions {
+    max_frames: Option<u64>,
+    timeout: Option<Duration>,
+    until_pc: Option<u16>,
+    until_serial: Option<String>,
+    fail_serial: Option<String>,
+}
+
+fn main() {
+    env_logger::init();
+
+    match run() {
+        Ok(code) => exit(code),
+        Err(e) => {
+            eprintln!("error: {}", e);
+            exit(EXIT_ERROR);
+        }
+    }
+}
+
+fn run() -> Result<i32, Box<dyn std::error::Error>> {
+    let cli_matches = get_cli_matches();
+    let opt_rom_file = cli_matches.value_of("rom").unwrap();
+    let opt_rom_entry = cli_matches.value_of("rom-entry");
+    let opt_cheats = cli_matches.value_of("cheats");
+    let opt_patches: Vec<&str> = cli_matches.values_of("patch").map(|v| v.collect()).unwrap_or_default();
+    let opt_no_bootrom = cli_matches.occurrences_of("no-bootrom") > 0;
+    let opt_hardware = cli_matches.value_of("hardware").unwrap_or("");
+    let opt_screenshot = cli_matches.value_of("screenshot");
+    let opt_print_serial = cli_matches.occurrences_of("print-serial") > 0;
+    let opt_renderer = cli_matches.value_of("renderer").unwrap_or("");
+    let opt_link_listen = cli_matches.value_of("link-listen");
+    let opt_link_connect = cli_matches.value_of("link-connect");
+    let opt_infrared = cli_matches.value_of("infrared").unwrap_or("");
+    let opt_camera_image = cli_matches.value_of("camera-image");
+
+    let options = RunOptions {
+        max_frames: match cli_matches.value_of("frames") {
+            Some(v) => Some(v.parse()?),
+            None => None
+        },
+        timeout: match cli_matches.value_of("timeout") {
+            Some(v) => Some(Duration::from_secs_f32(v.parse()?)),
+            None => None
+        },
+        until_pc: match cli_matches.value_of("until-pc") {
+            Some(v) => Some(u16::from_str_radix(v.trim_start_matches("0x"), 16)?),
+            None => None
+        },
+        until_serial: cli_matches.value_of("until-serial").map(|s| s.to_owned()),
+        fail_serial: cli_matches.value_of("fail-serial").map(|s| s.to_owned()),
+    };
+
+    if options.max_frames.is_none() && options.timeout.is_none() {
+        return Err("either --frames or --timeout must be given".into());
+    }
+
+    // Force hardware model ?
+    let hw: Option<GameBoyModel> = match opt_hardware {
+        "DMG" => Some(GameBoyModel::DMG),
+        "GBC" => Some(GameBoyModel::GBC),
+        _ => None
+    };
+
+    // Read the ROM directly so no battery saves are loaded or written back
+    let bytes = ROM::read_file(opt_rom_file, opt_rom_entry)?;
+    let bytes = ROM::apply_patches(bytes, opt_rom_file, &opt_patches)?;
+    let rom = ROM::from_bytes(&bytes)?;
+    for warning in rom.get_warnings() {
+        eprintln!("warning: {}", warning);
+    }
+
+    let mut machine = Machine::new(rom, hw);
+
+    if opt_renderer == "fifo" {
+        machine.set_renderer(PPURenderer::PixelFifo);
+    }
+
+    machine.start(opt_no_bootrom);
+
+    if let Some(filename) = opt_cheats {
+        machine.get_cheats_mut().load_file(filename)?;
+    }
+
+    // What the Pocket Camera sees
+    if let Some(filename) = opt_camera_image {
+        let (width, height, pixels) = load_camera_image(filename)?;
+        machine.set_camera_image(width, height, &pixels);
+    }
+
+    // What the IR ports see, the CGB one and the one on HuC carts
+    if opt_infrared == "loopback" {
+        machine.set_infrared_peer(Box::new(infrared::Loopback::new()));
+        machine.set_cartridge_infrared_peer(Box::new(infrared::Loopback::new()));
+    }
+
+    // Link cable to another emulator
+    if let Some(address) = opt_link_listen {
+        println!("Waiting for link cable connection on {}", address);
+        machine.set_link_peer(Box::new(LinkCable::listen(address)?));
+    }
+    else if let Some(address) = opt_link_connect {
+        machine.set_link_peer(Box::new(LinkCable::connect(address)?));
+    }
+
+    // only kept when something looks at it
+    if opt_print_serial || options.until_serial.is_some() || options.fail_serial.is_some() {
+        machine.set_serial_capture(true);
+    }
+
+    let result = run_machine(&mut machine, &options);
+
+    if opt_print_serial {
+        println!("{}", String::from_utf8_lossy(machine.get_serial_output()));
+    }
+
+    if let Some(filename) = opt_screenshot {
+        write_screenshot(&mut machine, filename)?;
+    }
+
+    Ok(match result {
+        RunResult::Success => EXIT_SUCCESS,
+        RunResult::Failure => EXIT_FAILURE,
+        RunResult::Timeout => EXIT_TIMEOUT,
+    })
+}
+
+fn run_machine(machine: &mut Machine, options: &RunOptions) -> RunResult {
+    let has_condition = options.until_pc.is_some() || options.until_serial.is_some();
+    let start = Instant::now();
+    let max_steps = options.max_frames.map(|max| max.saturating_mul(MAX_STEPS_PER_FRAME));
+    let mut steps: u64 = 0;
+
+    loop {
+        machine.step();
+        steps += 1;
+
+        if let Some(pc) = options.until_pc {
+            if machine.get_registers().pc == pc {
+                return RunResult::Success;
+            }
+        }
+
+        if !machine.is_vblank() {
+            // A ROM that turned the LCD off or a link peer that doesn't answer never reaches VBlank
+            if steps.is_multiple_of(LIMIT_CHECK_STEPS) && out_of_limits(machine, options, start, steps, max_steps) {
+                return if has_condition { RunResult::Timeout } else { RunResult::Success };
+            }
+
+            continue;
+        }
+
+        // Serial output is only checked once per frame
+        machine.get_framebuffer();
+
+        let serial = String::from_utf8_lossy(machine.get_serial_output());
+
+        if let Some(text) = &options.fail_serial {
+            if serial.contains(text.as_str()) {
+                return RunResult::Failure;
+            }
+        }
+
+        if let Some(text) = &options.until_serial {
+            if serial.contains(text.as_str()) {
+                return RunResult::Success;
+            }
+        }
+
+        if out_of_limits(machine, options, start, steps, max_steps) {
+            // Running for a fixed amount of frames with nothing to wait for is a success
+            return if has_condition { RunResult::Timeout } else { RunResult::Success };
+        }
+    }
+}
+
+fn out_of_limits(machine: &Machine, options: &RunOptions, start: Instant, steps: u64, max_steps: Option<u64>) -> bool {
+    let out_of_frames = options.max_frames.is_some_and(|max| machine.get_frame_count() >= max);
+    let out_of_steps = max_steps.is_some_and(|max| steps >= max);
+    let out_of_time = options.timeout.is_some_and(|timeout| start.elapsed() >= timeout);
+
+    out_of_frames || out_of_steps || out_of_time
+}
+
+fn write_screenshot(machine: &mut Machine, filename: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let fb = machine.get_framebuffer();
+
+    let mut data: Vec<u8> = Vec::with_capacity(fb.len() * 3);
+    for c in fb {
+        data.push((c & 0xFF) as u8);
+        data.push(((c >> 8) & 0xFF) as u8);
+        data.push(((c >> 16) & 0xFF) as u8);
+    }
+
+    let file = File::create(filename)?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), SCREEN_WIDTH, SCREEN_HEIGHT);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&data)?;
+
+    Ok(())
+}
+
+// Decodes a PNG into 8 bit luminance for the Pocket Camera sensor
+fn load_camera_image(filename: &str) -> Result<(usize, usize, Vec<u8>), Box<dyn std::error::Error>> {
+    let mut decoder = png::Decoder::new(File::open(filename)?);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+
+    let mut reader = decoder.read_info()?;
+    let mut data = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut data)?;
+
+    let channels = info.color_type.samples();
+    let pixels = data[0..info.buffer_size()].chunks_exact(channels).map(|p| {
+        if channels < 3 {
+            p[0]
+        }
+        else {
+            ((p[0] as u32 * 299 + p[1] as u32 * 587 + p[2] as u32 * 114) / 1000) as u8
+        }
+    }).collect();
+
+    Ok((info.width as usize, info.height as usize, pixels))
+}
+
+fn get_cli_matches() -> clap::ArgMatches<'static> {
+    App::new("rust-gameboy-headless")
+        .version("0.1")
+        .author("Xavier Amado <xamado@gmail.com")
+        .about("Runs a GB ROM without a window or audio device")
+        .arg(Arg::with_name("rom")
+            .long("rom")
+            .help("Specify rom to load")
+            .required(true)
+            .takes_value(true)
+        )
+        .arg(Arg::with_name("rom-entry")
+            .long("rom-entry")
+            .help("File to load when the rom is a zip archive, the first .gb/.gbc otherwise")
+            .takes_value(true)
+        )
+        .arg(Arg::with_name("cheats")
+            .long("cheats")
+            .help("Cheat codes to load")
+            .takes_value(true)
+        )
+        .arg(Arg::with_name("patch")
+            .long("patch")
+            .help("IPS/UPS/BPS patch to apply, can be given more than once. Defaults to one named like the rom")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+        )
+        .arg(Arg::with_name("no-bootrom")
+            .long("no-bootrom")
+            .help("Avoid the bootrom and just start ROM directly")
+            .takes_value(false)
+        )
+        .arg(Arg::with_name("hardware")
+            .long("hardware")
+            .help("Force hardware version (DMG/GBC)")
+            .takes_value(true)
+        )
+        .arg(Arg::with_name("frames")
+            .long("frames")
+            .help("Stop after running this many frames")
+            .takes_value(true)
+        )
+        .arg(Arg::with_name("timeout")
+            .long("timeout")
+            .help("Stop after this many seconds of wall clock time")
+            .takes_value(true)
+        )
+        .arg(Arg::with_name("until-pc")
+            .long("until-pc")
+            .help("Succeed once PC reaches this address (hex)")
+            .takes_value(true)
+        )
+        .arg(Arg::with_name("until-serial")
+            .long("until-serial")
+            .help("Succeed once the serial output contains this text")
+            .takes_value(true)
+        )
+        .arg(Arg::with_name("fail-serial")
+            .long("fail-serial")
+            .help("Fail once the serial output contains this text")
+            .takes_value(true)
+        )
+        .arg(Arg::with_name("print-serial")
+            .long("print-serial")
+            .help("Print the serial output before exiting")
+            .takes_value(false)
+        )
+        .arg(Arg::with_name("screenshot")
+            .long("screenshot")
+            .help("Write the last frame to this PNG file before exiting")
+            .takes_value(true)
+        )
+        .arg(Arg::with_name("link-listen")
+            .long("link-listen")
+            .help("Wait for another emulator to connect a link cable (host:port or unix:path)")
+            .takes_value(true)
+            .conflicts_with("link-connect")
+        )
+        .arg(Arg::with_name("link-connect")
+            .long("link-connect")
+            .help("Connect a link cable to another emulator (host:port or unix:path)")
+            .takes_value(true)
+        )
+        .arg(Arg::with_name("renderer")
+            .long("renderer")
+            .help("PPU renderer (scanline/fifo)")
+            .takes_value(true)
+        )
+        .arg(Arg::with_name("camera-image")
+            .long("camera-image")
+            .help("PNG image the Pocket Camera sees")
+            .takes_value(true)
+        )
+        .arg(Arg::with_name("infrared")
+            .long("infrared")
+            .help("What the infrared ports see (none/loopback)")
+            .takes_value(true)
+        )
+        .get_matches()
+}
@@ -12,7 +12,7 @@ impl BootROM {
     }
 
     pub fn open(&mut self, filename : &str) {
-        let bytes = std::fs::read(&filename).unwrap();
+        let bytes = std::fs::read(filename).unwrap();
         self.data = bytes;
         
         println!("Loaded BOOTROM {}: {} bytes read.", filename, self.len());
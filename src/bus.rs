@@ -1,5 +1,5 @@
 use crate::machine::GameBoyModel;
-use crate::cpu::{CPUInterrupts};
+use crate::cpu::{CPUInterrupts, CPUSpeed};
 use crate::ppu::PPU;
 use crate::apu::APU;
 use crate::timer::Timer;
@@ -8,6 +8,9 @@ use crate::memory::Memory;
 use crate::joystick::Joystick;
 use crate::bootrom::BootROM;
 use crate::serial::Serial;
+use crate::infrared::Infrared;
+use crate::cheats::Cheats;
+use crate::debugger::Watchpoints;
 
 pub struct CPUMemoryBus<'a> {
     pub model: GameBoyModel,
@@ -22,17 +25,83 @@ pub struct CPUMemoryBus<'a> {
     pub joystick: &'a mut Joystick,
     pub timer: &'a mut Timer,
     pub serial: &'a mut Serial,
+    pub infrared: &'a mut Infrared,
     pub interrupts: &'a mut CPUInterrupts,
+    pub speed: &'a mut CPUSpeed,
+    pub cheats: &'a Cheats,
+    pub watchpoints: Option<&'a Watchpoints>,
+}
+
+// Buses the OAM DMA can conflict with the CPU on, the external bus (ROM, SRAM and WRAM) and the VRAM bus
+fn dma_bus(addr: u16) -> u8 {
+    match addr {
+        0x8000..=0x9FFF => 1,
+        _ => 0
+    }
 }
 
 impl<'a> CPUMemoryBus<'a> {
+    // While OAM DMA runs the CPU can only reach HRAM and the IO registers, OAM is locked and
+    // using the bus the DMA is reading from gets in the way of the transfer
+    fn is_dma_blocked(&self, addr: u16) -> bool {
+        addr < 0xFF00 && self.ppu.is_oam_dma_active() &&
+            ((0xFE00..=0xFEFF).contains(&addr) || dma_bus(addr) == dma_bus(self.ppu.get_oam_dma_source()))
+    }
+
+    // The bank mapped at an address, for watchpoints that only care about one
+    pub fn get_bank(&self, addr: u16) -> u16 {
+        match addr {
+            0x0000..=0x7FFF => self.rom.get_rom_bank(addr),
+            0x8000..=0x9FFF if self.model == GameBoyModel::GBC => (self.ppu.get_vram_bank() & 0x1) as u16,
+            // bank 0 of ram2 is WRAM bank 1
+            0xD000..=0xDFFF | 0xF000..=0xFDFF => self.ram2.get_selected_bank() + 1,
+            _ => 0
+        }
+    }
+
     pub fn read_byte(&self, addr: u16) -> u8 {
+        let data = self.read(addr);
+
+        if let Some(watchpoints) = self.watchpoints {
+            if watchpoints.is_watching(addr) {
+                watchpoints.check_read(addr, self.get_bank(addr), data);
+            }
+        }
+
+        data
+    }
+
+    // Reads without triggering watchpoints, for the debugger and for the CPU's opcode and operand fetches
+    pub fn peek_byte(&self, addr: u16) -> u8 {
+        self.read(addr)
+    }
+
+    pub fn write_byte(&mut self, addr: u16, data: u8) {
+        if let Some(watchpoints) = self.watchpoints {
+            if watchpoints.is_watching(addr) {
+                // the bank before the write, in case it's the write that switches it
+                watchpoints.check_write(addr, self.get_bank(addr), self.read(addr), data);
+            }
+        }
+
+        self.write(addr, data);
+    }
+
+    fn read(&self, addr: u16) -> u8 {
+        if self.is_dma_blocked(addr) {
+            // reading from the DMA bus returns whatever is being copied
+            return match addr {
+                0xFE00..=0xFEFF => 0xFF,
+                _ => self.ppu.get_oam_dma_byte()
+            };
+        }
+
         match addr {
             0x0000..=0x00FF if *self.bootrom_enabled => self.bootrom.read_byte(addr),
             0x0200..=0x08FF if *self.bootrom_enabled => self.bootrom.read_byte(addr),
 
-            // 0000-7FFF - ROM 
-            0x0000..=0x7FFF => self.rom.read_byte(addr),
+            // 0000-7FFF - ROM, with any Game Genie codes applied
+            0x0000..=0x7FFF => self.cheats.read_rom(addr, self.rom.read_byte(addr)),
 
             // 8000-9FFF - VRAM
             0x8000..=0x9FFF => self.ppu.read_byte(addr),
@@ -76,12 +145,18 @@ impl<'a> CPUMemoryBus<'a> {
             // FF40-FF4B - PPU Registers
             0xFF40..=0xFF4B => self.ppu.read_byte(addr),
 
+            // FF4D - KEY1 - Prepare Speed Switch (GBC)
+            0xFF4D if self.model == GameBoyModel::GBC => self.speed.read_byte(),
+
             // FF4F - VRAM Bank Register (GBC)
             0xFF4F if self.model == GameBoyModel::GBC => self.ppu.get_vram_bank(),
 
             // FF51-FF55 - HDMA Transfer (GBC)
             0xFF51..=0xFF55 if self.model == GameBoyModel::GBC => self.ppu.read_byte(addr),
 
+            // FF56 - RP - Infrared Port (GBC)
+            0xFF56 if self.model == GameBoyModel::GBC => self.infrared.read_byte(addr),
+
             // FF68 - FF6A - Palette Data (GBC)
             0xFF68..=0xFF6B if self.model == GameBoyModel::GBC => self.ppu.read_byte(addr),
 
@@ -99,7 +174,11 @@ impl<'a> CPUMemoryBus<'a> {
         }
     }
 
-    pub fn write_byte(&mut self, addr: u16, data: u8) {
+    fn write(&mut self, addr: u16, data: u8) {
+        if self.is_dma_blocked(addr) {
+            return;
+        }
+
         match addr {
             // 0000-7FFF - ROM 
             0x0000..=0x7FFF => self.rom.write_byte(addr, data),
@@ -146,6 +225,9 @@ impl<'a> CPUMemoryBus<'a> {
             // FF40-FF4B - PPU Registers
             0xFF40..=0xFF4B => self.ppu.write_byte(addr, data),
 
+            // FF4D - KEY1 - Prepare Speed Switch (GBC)
+            0xFF4D if self.model == GameBoyModel::GBC => self.speed.write_byte(data),
+
             // FF4F - VRAM Bank Register
             0xFF4F if self.model == GameBoyModel::GBC => self.ppu.set_vram_bank(data & 0x1),
 
@@ -155,6 +237,9 @@ impl<'a> CPUMemoryBus<'a> {
             // FF51-FF55 - HDMA Transfer (GBC)
             0xFF51..=0xFF55 if self.model == GameBoyModel::GBC => self.ppu.write_byte(addr, data),
 
+            // FF56 - RP - Infrared Port (GBC)
+            0xFF56 if self.model == GameBoyModel::GBC => self.infrared.write_byte(addr, data),
+
             // FF68 - FF6A - Palette Data (GBC)
             0xFF68..=0xFF6B if self.model == GameBoyModel::GBC => self.ppu.write_byte(addr, data),
 
@@ -0,0 +1,258 @@
+use std::fmt;
+use std::io;
+
+// GameShark code types, 01 writes to whatever WRAM bank is mapped and 90-97 pick the bank on CGB
+const GAMESHARK_WRITE: u8 = 0x01;
+const GAMESHARK_WRITE_BANK: u8 = 0x90;
+
+#[derive(Debug)]
+pub enum CheatError {
+    Io(io::Error),
+    InvalidCode(String),
+}
+
+impl fmt::Display for CheatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CheatError::Io(e) => write!(f, "failed to read cheats: {}", e),
+            CheatError::InvalidCode(code) => write!(f, "invalid cheat code: {}", code),
+        }
+    }
+}
+
+impl std::error::Error for CheatError {}
+
+impl From<io::Error> for CheatError {
+    fn from(e: io::Error) -> Self {
+        CheatError::Io(e)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CheatKind {
+    // Replaces a ROM byte as the CPU reads it. With a compare value only when the byte matches it,
+    // which is what keeps the code to the right bank
+    GameGenie { address: u16, value: u8, compare: Option<u8> },
+
+    // Writes RAM once per frame, the WRAM bank is only used on CGB
+    GameShark { bank: Option<u8>, address: u16, value: u8 },
+}
+
+#[derive(Clone, Debug)]
+pub struct Cheat {
+    pub code: String,
+    pub name: String,
+    pub kind: CheatKind,
+    pub enabled: bool,
+}
+
+pub struct Cheats {
+    cheats: Vec<Cheat>,
+    enabled: bool,
+}
+
+impl Default for Cheats {
+    fn default() -> Self {
+        Cheats::new()
+    }
+}
+
+impl Cheats {
+    pub fn new() -> Self {
+        Self {
+            cheats: vec!(),
+            enabled: true,
+        }
+    }
+
+    pub fn add(&mut self, code: &str, name: &str) -> Result<(), CheatError> {
+        self.cheats.push(Cheat {
+            code: code.to_owned(),
+            name: name.to_owned(),
+            kind: Cheats::parse_code(code)?,
+            enabled: true,
+        });
+
+        Ok(())
+    }
+
+    // One code per line followed by an optional name. Lines starting with # are comments
+    // and codes starting with ! are loaded disabled:
+    //   # Super Mario Land
+    //   00A-17B-C49 Infinite lives
+    //   !01FF10C2 Start at world 4
+    pub fn load_file(&mut self, filename: &str) -> Result<(), CheatError> {
+        let text = std::fs::read_to_string(filename)?;
+
+        for line in text.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#')) {
+            let (enabled, line) = match line.strip_prefix('!') {
+                Some(line) => (false, line),
+                None => (true, line)
+            };
+
+            let mut parts = line.splitn(2, char::is_whitespace);
+            let code = parts.next().unwrap_or("");
+            let name = parts.next().unwrap_or("").trim();
+
+            self.add(code, name)?;
+            if let Some(cheat) = self.cheats.last_mut() {
+                cheat.enabled = enabled;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn parse_code(code: &str) -> Result<CheatKind, CheatError> {
+        let invalid = || CheatError::InvalidCode(code.to_owned());
+
+        let digits: Vec<u8> = code.chars()
+            .filter(|c| *c != '-')
+            .map(|c| c.to_digit(16).map(|d| d as u8))
+            .collect::<Option<Vec<u8>>>()
+            .ok_or_else(invalid)?;
+
+        let byte = |i: usize| (digits[i] << 4) | digits[i + 1];
+
+        match digits.len() {
+            // Game Genie, ABC-DEF or ABC-DEF-GHI. AB is the value and FCDE the address with F inverted,
+            // GI rotated right by 2 and XORed with BA is the compare value, H isn't used
+            6 | 9 if code.contains('-') => {
+                let value = byte(0);
+                let address = (((digits[5] ^ 0x0F) as u16) << 12) | ((digits[2] as u16) << 8) | ((digits[3] as u16) << 4) | (digits[4] as u16);
+                let compare = if digits.len() == 9 { Some(((digits[6] << 4) | digits[8]).rotate_right(2) ^ 0xBA) } else { None };
+
+                // Game Genie sits between the cart and the console, it can only patch ROM
+                if address >= 0x8000 {
+                    return Err(invalid());
+                }
+
+                Ok(CheatKind::GameGenie { address, value, compare })
+            },
+
+            // GameShark, TTVVLLHH
+            8 => {
+                let bank = match byte(0) {
+                    GAMESHARK_WRITE => None,
+                    t if t & 0xF8 == GAMESHARK_WRITE_BANK => Some(t & 0x07),
+                    _ => return Err(invalid())
+                };
+
+                Ok(CheatKind::GameShark { bank, address: ((byte(6) as u16) << 8) | (byte(4) as u16), value: byte(2) })
+            },
+
+            _ => Err(invalid())
+        }
+    }
+
+    pub fn get_cheats(&self) -> &[Cheat] {
+        &self.cheats
+    }
+
+    pub fn set_enabled(&mut self, index: usize, enabled: bool) {
+        if let Some(cheat) = self.cheats.get_mut(index) {
+            cheat.enabled = enabled;
+        }
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        if index < self.cheats.len() {
+            self.cheats.remove(index);
+        }
+    }
+
+    // Master switch, leaves each code's own setting alone
+    pub fn set_all_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn active(&self) -> impl Iterator<Item = &CheatKind> {
+        self.cheats.iter()
+            .filter(move |c| self.enabled && c.enabled)
+            .map(|c| &c.kind)
+    }
+
+    // Game Genie codes, applied to every ROM read the CPU makes
+    pub fn read_rom(&self, address: u16, data: u8) -> u8 {
+        for cheat in self.active() {
+            if let CheatKind::GameGenie { address: a, value, compare } = *cheat {
+                if a == address && compare.is_none_or(|c| c == data) {
+                    return value;
+                }
+            }
+        }
+
+        data
+    }
+
+    // GameShark codes as (WRAM bank, address, value), applied once per frame
+    pub fn get_ram_writes(&self) -> Vec<(Option<u8>, u16, u8)> {
+        self.active()
+            .filter_map(|cheat| match *cheat {
+                CheatKind::GameShark { bank, address, value } => Some((bank, address, value)),
+                _ => None
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn game_genie() {
+        assert_eq!(Cheats::parse_code("00A-17B").unwrap(), CheatKind::GameGenie { address: 0x4A17, value: 0x00, compare: None });
+        assert_eq!(Cheats::parse_code("00A-17B-C49").unwrap(), CheatKind::GameGenie { address: 0x4A17, value: 0x00, compare: Some(0xC8) });
+        assert_eq!(Cheats::parse_code("3e1-23f-e6e").unwrap(), CheatKind::GameGenie { address: 0x0123, value: 0x3E, compare: Some(0x01) });
+    }
+
+    #[test]
+    fn game_genie_outside_rom() {
+        assert!(Cheats::parse_code("00A-170").is_err());
+    }
+
+    #[test]
+    fn gameshark() {
+        assert_eq!(Cheats::parse_code("01FF10C2").unwrap(), CheatKind::GameShark { bank: None, address: 0xC210, value: 0xFF });
+        assert_eq!(Cheats::parse_code("9263A4D0").unwrap(), CheatKind::GameShark { bank: Some(2), address: 0xD0A4, value: 0x63 });
+        assert!(Cheats::parse_code("02FF10C2").is_err());
+    }
+
+    #[test]
+    fn invalid_codes() {
+        for code in ["", "00A-17", "01FF10C", "01FF10CZ", "00A-17B-C4"] {
+            assert!(Cheats::parse_code(code).is_err(), "{}", code);
+        }
+    }
+
+    #[test]
+    fn read_rom_compare() {
+        let mut cheats = Cheats::new();
+        cheats.add("00A-17B-C49", "").unwrap();
+
+        assert_eq!(cheats.read_rom(0x4A17, 0xC8), 0x00);
+        assert_eq!(cheats.read_rom(0x4A17, 0xC9), 0xC9);
+        assert_eq!(cheats.read_rom(0x4A18, 0xC8), 0xC8);
+
+        cheats.set_all_enabled(false);
+        assert_eq!(cheats.read_rom(0x4A17, 0xC8), 0xC8);
+    }
+
+    #[test]
+    fn ram_writes() {
+        let mut cheats = Cheats::new();
+        cheats.add("01FF10C2", "").unwrap();
+        cheats.add("9263A4D0", "").unwrap();
+        cheats.add("00A-17B", "").unwrap();
+
+        assert_eq!(cheats.get_ram_writes(), [(None, 0xC210, 0xFF), (Some(2), 0xD0A4, 0x63)]);
+
+        cheats.set_enabled(0, false);
+        assert_eq!(cheats.get_ram_writes(), [(Some(2), 0xD0A4, 0x63)]);
+    }
+}
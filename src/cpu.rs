@@ -1,6 +1,7 @@
 use crate::bus::CPUMemoryBus;
 use crate::bitutils::*;
 use crate::machine::GameBoyModel;
+use crate::savestate::{StateWriter, StateReader, SaveStateError};
 
 use hashbrown::HashMap;
 
@@ -45,19 +46,75 @@ impl CPUInterrupts {
             _ => panic!("Invalid address")
         }
     }
+
+    pub fn save_state(&self, w: &mut StateWriter) {
+        w.write_bool(self.interrupts_enabled);
+        w.write_bool(self.interrupts_enable_request);
+        w.write_u8(self.flags);
+        w.write_u8(self.enabled);
+    }
+
+    pub fn load_state(&mut self, r: &mut StateReader) -> Result<(), SaveStateError> {
+        self.interrupts_enabled = r.read_bool()?;
+        self.interrupts_enable_request = r.read_bool()?;
+        self.flags = r.read_u8()?;
+        self.enabled = r.read_u8()?;
+
+        Ok(())
+    }
+}
+
+// FF4D - KEY1 - Prepare Speed Switch (GBC)
+pub struct CPUSpeed {
+    pub double_speed: bool,
+    pub switch_armed: bool,
+}
+
+impl CPUSpeed {
+    pub fn new() -> Self {
+        Self {
+            double_speed: false,
+            switch_armed: false,
+        }
+    }
+
+    pub fn read_byte(&self) -> u8 {
+        0x7E | (self.double_speed as u8) << 7 | (self.switch_armed as u8)
+    }
+
+    pub fn write_byte(&mut self, data: u8) {
+        self.switch_armed = (data & 0x1) != 0;
+    }
+
+    pub fn save_state(&self, w: &mut StateWriter) {
+        w.write_bool(self.double_speed);
+        w.write_bool(self.switch_armed);
+    }
+
+    pub fn load_state(&mut self, r: &mut StateReader) -> Result<(), SaveStateError> {
+        self.double_speed = r.read_bool()?;
+        self.switch_armed = r.read_bool()?;
+
+        Ok(())
+    }
 }
 
+// How long the CPU is stopped while switching speed, in M-cycles
+const SPEED_SWITCH_CYCLES: u16 = 2050;
+
 #[derive(PartialEq)]
 enum CPUMode {
     Normal,
     Halt,
-    Stop
+    Stop,
+    SpeedSwitch
 }
 
 #[derive(Clone)]
 pub struct Instruction {
+    // only there to make the table readable, the disassembler decodes opcodes on its own
+    #[allow(dead_code)]
     pub dissassembly: &'static str,
-    bytes: u16,
     closure: fn(InstructionContext) -> u8
 }
 
@@ -97,6 +154,34 @@ const INTERRUPT_ADDRESS : [u16; 5] = [
     0x0060
 ];
 
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CPURegister {
+    A, F, B, C, D, E, H, L,
+    AF, BC, DE, HL, SP, PC,
+}
+
+impl CPURegister {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_uppercase().as_str() {
+            "A" => Some(CPURegister::A),
+            "F" => Some(CPURegister::F),
+            "B" => Some(CPURegister::B),
+            "C" => Some(CPURegister::C),
+            "D" => Some(CPURegister::D),
+            "E" => Some(CPURegister::E),
+            "H" => Some(CPURegister::H),
+            "L" => Some(CPURegister::L),
+            "AF" => Some(CPURegister::AF),
+            "BC" => Some(CPURegister::BC),
+            "DE" => Some(CPURegister::DE),
+            "HL" => Some(CPURegister::HL),
+            "SP" => Some(CPURegister::SP),
+            "PC" => Some(CPURegister::PC),
+            _ => None
+        }
+    }
+}
+
 pub struct CPUDebugState {
     pub af: u16,
     pub bc: u16,
@@ -107,9 +192,32 @@ pub struct CPUDebugState {
     pub next_opcode: u16,
 }
 
+impl CPUDebugState {
+    pub fn get(&self, register: CPURegister) -> u16 {
+        match register {
+            CPURegister::A => self.af >> 8,
+            CPURegister::F => self.af & 0xFF,
+            CPURegister::B => self.bc >> 8,
+            CPURegister::C => self.bc & 0xFF,
+            CPURegister::D => self.de >> 8,
+            CPURegister::E => self.de & 0xFF,
+            CPURegister::H => self.hl >> 8,
+            CPURegister::L => self.hl & 0xFF,
+            CPURegister::AF => self.af,
+            CPURegister::BC => self.bc,
+            CPURegister::DE => self.de,
+            CPURegister::HL => self.hl,
+            CPURegister::SP => self.sp,
+            CPURegister::PC => self.pc,
+        }
+    }
+}
+
 struct CPUState {
     mode: CPUMode,
     next_op: u16,
+    // M-cycles left before the CPU runs again after a speed switch
+    speed_switch_cycles: u16,
 }
 
 pub struct CPU {
@@ -122,514 +230,514 @@ pub struct CPU {
 impl CPU {
     pub fn new(model: GameBoyModel) -> Self {
         let instruction_table : HashMap<u16, Instruction> = [
-            (0x0000_u16, Instruction { dissassembly: "NOP",         bytes: 1, closure: |_ctx| Self::op_nop() }),
-            (0x0010_u16, Instruction { dissassembly: "STOP",        bytes: 2, closure: |ctx| Self::op_stop(ctx.s, &ctx.bus.interrupts) }),
-            (0x0076_u16, Instruction { dissassembly: "HALT",        bytes: 1, closure: |ctx| Self::op_halt(ctx.s, &ctx.bus.interrupts) }),
-            (0x003C_u16, Instruction { dissassembly: "INC A",       bytes: 1, closure: |ctx| Self::op_inc_r(&mut ctx.r.a, &mut ctx.r.f) }),
-            (0x0004_u16, Instruction { dissassembly: "INC B",       bytes: 1, closure: |ctx| Self::op_inc_r(&mut ctx.r.b, &mut ctx.r.f) }),
-            (0x000C_u16, Instruction { dissassembly: "INC C",       bytes: 1, closure: |ctx| Self::op_inc_r(&mut ctx.r.c, &mut ctx.r.f) }),
-            (0x0014_u16, Instruction { dissassembly: "INC D",       bytes: 1, closure: |ctx| Self::op_inc_r(&mut ctx.r.d, &mut ctx.r.f) }),
-            (0x001C_u16, Instruction { dissassembly: "INC E",       bytes: 1, closure: |ctx| Self::op_inc_r(&mut ctx.r.e, &mut ctx.r.f) }),
-            (0x0024_u16, Instruction { dissassembly: "INC H",       bytes: 1, closure: |ctx| Self::op_inc_r(&mut ctx.r.h, &mut ctx.r.f) }),
-            (0x002C_u16, Instruction { dissassembly: "INC L",       bytes: 1, closure: |ctx| Self::op_inc_r(&mut ctx.r.l, &mut ctx.r.f) }),
-            (0x0003_u16, Instruction { dissassembly: "INC BC",      bytes: 1, closure: |ctx| Self::op_inc_r16(&mut ctx.r.b, &mut ctx.r.c) }),
-            (0x0013_u16, Instruction { dissassembly: "INC DE",      bytes: 1, closure: |ctx| Self::op_inc_r16(&mut ctx.r.d, &mut ctx.r.e) }),
-            (0x0023_u16, Instruction { dissassembly: "INC HL",      bytes: 1, closure: |ctx| Self::op_inc_r16(&mut ctx.r.h, &mut ctx.r.l) }),
-            (0x0033_u16, Instruction { dissassembly: "INC SP",      bytes: 1, closure: |ctx| Self::op_inc_sp(&mut ctx.r.sp) }),
-            (0x0034_u16, Instruction { dissassembly: "INC (HL)",    bytes: 1, closure: |ctx| Self::op_inc_addr(ctx.bus, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) }),
-            (0x003D_u16, Instruction { dissassembly: "DEC A",       bytes: 1, closure: |ctx| Self::op_dec_r(&mut ctx.r.a, &mut ctx.r.f) }),
-            (0x0005_u16, Instruction { dissassembly: "DEC B",       bytes: 1, closure: |ctx| Self::op_dec_r(&mut ctx.r.b, &mut ctx.r.f) }),
-            (0x000D_u16, Instruction { dissassembly: "DEC C",       bytes: 1, closure: |ctx| Self::op_dec_r(&mut ctx.r.c, &mut ctx.r.f) }),
-            (0x0015_u16, Instruction { dissassembly: "DEC D",       bytes: 1, closure: |ctx| Self::op_dec_r(&mut ctx.r.d, &mut ctx.r.f) }),
-            (0x001D_u16, Instruction { dissassembly: "DEC E",       bytes: 1, closure: |ctx| Self::op_dec_r(&mut ctx.r.e, &mut ctx.r.f) }),
-            (0x0025_u16, Instruction { dissassembly: "DEC H",       bytes: 1, closure: |ctx| Self::op_dec_r(&mut ctx.r.h, &mut ctx.r.f) }),
-            (0x002D_u16, Instruction { dissassembly: "DEC L",       bytes: 1, closure: |ctx| Self::op_dec_r(&mut ctx.r.l, &mut ctx.r.f) }),
-            (0x000B_u16, Instruction { dissassembly: "DEC BC",      bytes: 1, closure: |ctx| Self::op_dec_r16(&mut ctx.r.b, &mut ctx.r.c) }),
-            (0x001B_u16, Instruction { dissassembly: "DEC DE",      bytes: 1, closure: |ctx| Self::op_dec_r16(&mut ctx.r.d, &mut ctx.r.e) }),
-            (0x002B_u16, Instruction { dissassembly: "DEC HL",      bytes: 1, closure: |ctx| Self::op_dec_r16(&mut ctx.r.h, &mut ctx.r.l) }),
-            (0x003B_u16, Instruction { dissassembly: "DEC SP",      bytes: 1, closure: |ctx| Self::op_dec_sp(&mut ctx.r.sp) }),
-            (0x0035_u16, Instruction { dissassembly: "DEC (HL)",    bytes: 1, closure: |ctx| Self::op_dec_addr(ctx.bus, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) }),
-            (0x0087_u16, Instruction { dissassembly: "ADD A,A",     bytes: 1, closure: |ctx| { let v = ctx.r.a; Self::op_add_r(&mut ctx.r.a, v, &mut ctx.r.f) } }),
-            (0x0080_u16, Instruction { dissassembly: "ADD A,B",     bytes: 1, closure: |ctx| Self::op_add_r(&mut ctx.r.a, ctx.r.b, &mut ctx.r.f) }),
-            (0x0081_u16, Instruction { dissassembly: "ADD A,C",     bytes: 1, closure: |ctx| Self::op_add_r(&mut ctx.r.a, ctx.r.c, &mut ctx.r.f) }),
-            (0x0082_u16, Instruction { dissassembly: "ADD A,D",     bytes: 1, closure: |ctx| Self::op_add_r(&mut ctx.r.a, ctx.r.d, &mut ctx.r.f) }),
-            (0x0083_u16, Instruction { dissassembly: "ADD A,E",     bytes: 1, closure: |ctx| Self::op_add_r(&mut ctx.r.a, ctx.r.e, &mut ctx.r.f) }),
-            (0x0084_u16, Instruction { dissassembly: "ADD A,H",     bytes: 1, closure: |ctx| Self::op_add_r(&mut ctx.r.a, ctx.r.h, &mut ctx.r.f) }),
-            (0x0085_u16, Instruction { dissassembly: "ADD A,L",     bytes: 1, closure: |ctx| Self::op_add_r(&mut ctx.r.a, ctx.r.l, &mut ctx.r.f) }),
-            (0x00C6_u16, Instruction { dissassembly: "ADD A,d8",    bytes: 2, closure: |ctx| Self::op_add_d8(ctx.bus, &mut ctx.r.a, &mut ctx.r.pc, &mut ctx.r.f) }),
-            (0x0086_u16, Instruction { dissassembly: "ADD A,(HL)",  bytes: 1, closure: |ctx| { Self::op_add_addr(ctx.bus, &mut ctx.r.a, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) } }),
-            (0x0009_u16, Instruction { dissassembly: "ADD HL,BC",   bytes: 1, closure: |ctx| Self::op_add_r16(&mut ctx.r.h, &mut ctx.r.l, to_u16(ctx.r.b, ctx.r.c), &mut ctx.r.f) }),
-            (0x0019_u16, Instruction { dissassembly: "ADD HL,DE",   bytes: 1, closure: |ctx| Self::op_add_r16(&mut ctx.r.h, &mut ctx.r.l, to_u16(ctx.r.d, ctx.r.e), &mut ctx.r.f) }),
-            (0x0029_u16, Instruction { dissassembly: "ADD HL,HL",   bytes: 1, closure: |ctx| { let v = to_u16(ctx.r.h, ctx.r.l); Self::op_add_r16(&mut ctx.r.h, &mut ctx.r.l, v, &mut ctx.r.f) } }),
-            (0x0039_u16, Instruction { dissassembly: "ADD HL,SP",   bytes: 1, closure: |ctx| Self::op_add_r16(&mut ctx.r.h, &mut ctx.r.l, ctx.r.sp, &mut ctx.r.f) }),
-            (0x00E8_u16, Instruction { dissassembly: "ADD SP,s8",   bytes: 2, closure: |ctx| Self::op_add_sp_s8(ctx.bus, &mut ctx.r.sp, &mut ctx.r.pc, &mut ctx.r.f) }),
-            (0x0097_u16, Instruction { dissassembly: "SUB A",       bytes: 1, closure: |ctx| { let v = ctx.r.a; Self::op_sub_r(&mut ctx.r.a, v, &mut ctx.r.f) } }),
-            (0x0090_u16, Instruction { dissassembly: "SUB B",       bytes: 1, closure: |ctx| Self::op_sub_r(&mut ctx.r.a, ctx.r.b, &mut ctx.r.f) }),
-            (0x0091_u16, Instruction { dissassembly: "SUB C",       bytes: 1, closure: |ctx| Self::op_sub_r(&mut ctx.r.a, ctx.r.c, &mut ctx.r.f) }),
-            (0x0092_u16, Instruction { dissassembly: "SUB D",       bytes: 1, closure: |ctx| Self::op_sub_r(&mut ctx.r.a, ctx.r.d, &mut ctx.r.f) }),
-            (0x0093_u16, Instruction { dissassembly: "SUB E",       bytes: 1, closure: |ctx| Self::op_sub_r(&mut ctx.r.a, ctx.r.e, &mut ctx.r.f) }),
-            (0x0094_u16, Instruction { dissassembly: "SUB H",       bytes: 1, closure: |ctx| Self::op_sub_r(&mut ctx.r.a, ctx.r.h, &mut ctx.r.f) }),
-            (0x0095_u16, Instruction { dissassembly: "SUB L",       bytes: 1, closure: |ctx| Self::op_sub_r(&mut ctx.r.a, ctx.r.l, &mut ctx.r.f) }),
-            (0x00D6_u16, Instruction { dissassembly: "SUB d8",      bytes: 2, closure: |ctx| Self::op_sub_d8(ctx.bus, &mut ctx.r.a, &mut ctx.r.pc, &mut ctx.r.f) }),
-            (0x0096_u16, Instruction { dissassembly: "SUB (HL)",    bytes: 1, closure: |ctx| Self::op_sub_addr(ctx.bus, &mut ctx.r.a, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) }),
-            (0x008F_u16, Instruction { dissassembly: "ADC A,A",     bytes: 1, closure: |ctx| { let v = ctx.r.a; Self::op_adc_r(&mut ctx.r.a, v, &mut ctx.r.f) } }),
-            (0x0088_u16, Instruction { dissassembly: "ADC A,B",     bytes: 1, closure: |ctx| Self::op_adc_r(&mut ctx.r.a, ctx.r.b, &mut ctx.r.f) }),
-            (0x0089_u16, Instruction { dissassembly: "ADC A,C",     bytes: 1, closure: |ctx| Self::op_adc_r(&mut ctx.r.a, ctx.r.c, &mut ctx.r.f) }),
-            (0x008A_u16, Instruction { dissassembly: "ADC A,D",     bytes: 1, closure: |ctx| Self::op_adc_r(&mut ctx.r.a, ctx.r.d, &mut ctx.r.f) }),
-            (0x008B_u16, Instruction { dissassembly: "ADC A,E",     bytes: 1, closure: |ctx| Self::op_adc_r(&mut ctx.r.a, ctx.r.e, &mut ctx.r.f) }),
-            (0x008C_u16, Instruction { dissassembly: "ADC A,H",     bytes: 1, closure: |ctx| Self::op_adc_r(&mut ctx.r.a, ctx.r.h, &mut ctx.r.f) }),
-            (0x008D_u16, Instruction { dissassembly: "ADC A,L",     bytes: 1, closure: |ctx| Self::op_adc_r(&mut ctx.r.a, ctx.r.l, &mut ctx.r.f) }),
-            (0x00CE_u16, Instruction { dissassembly: "ADC A,d8",    bytes: 2, closure: |ctx| Self::op_adc_d8(ctx.bus, &mut ctx.r.a, &mut ctx.r.pc, &mut ctx.r.f) }),
-            (0x008E_u16, Instruction { dissassembly: "ADC A,(HL)",  bytes: 1, closure: |ctx| Self::op_adc_addr(ctx.bus, &mut ctx.r.a, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) }),
-            (0x009F_u16, Instruction { dissassembly: "SBC A,A",     bytes: 1, closure: |ctx| { let v = ctx.r.a; Self::op_sbc_r(&mut ctx.r.a, v, &mut ctx.r.f) } }),
-            (0x0098_u16, Instruction { dissassembly: "SBC A,B",     bytes: 1, closure: |ctx| Self::op_sbc_r(&mut ctx.r.a, ctx.r.b, &mut ctx.r.f) }),
-            (0x0099_u16, Instruction { dissassembly: "SBC A,C",     bytes: 1, closure: |ctx| Self::op_sbc_r(&mut ctx.r.a, ctx.r.c, &mut ctx.r.f) }),
-            (0x009A_u16, Instruction { dissassembly: "SBC A,D",     bytes: 1, closure: |ctx| Self::op_sbc_r(&mut ctx.r.a, ctx.r.d, &mut ctx.r.f) }),
-            (0x009B_u16, Instruction { dissassembly: "SBC A,E",     bytes: 1, closure: |ctx| Self::op_sbc_r(&mut ctx.r.a, ctx.r.e, &mut ctx.r.f) }),
-            (0x009C_u16, Instruction { dissassembly: "SBC A,H",     bytes: 1, closure: |ctx| Self::op_sbc_r(&mut ctx.r.a, ctx.r.h, &mut ctx.r.f) }),
-            (0x009D_u16, Instruction { dissassembly: "SBC A,L",     bytes: 1, closure: |ctx| Self::op_sbc_r(&mut ctx.r.a, ctx.r.l, &mut ctx.r.f) }),
-            (0x00DE_u16, Instruction { dissassembly: "SBC A,d8",    bytes: 2, closure: |ctx| Self::op_sbc_d8(ctx.bus, &mut ctx.r.a, &mut ctx.r.pc, &mut ctx.r.f) }),
-            (0x009E_u16, Instruction { dissassembly: "SBC A,(HL)",  bytes: 2, closure: |ctx| Self::op_sbc_addr(ctx.bus, &mut ctx.r.a, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) }),
-            (0x0027_u16, Instruction { dissassembly: "DAA",         bytes: 1, closure: |ctx| Self::op_daa(&mut ctx.r.a, &mut ctx.r.f) }),
-            (0x0037_u16, Instruction { dissassembly: "SCF",         bytes: 1, closure: |ctx| Self::op_scf(&mut ctx.r.f) }),
-            (0x003F_u16, Instruction { dissassembly: "CCF",         bytes: 1, closure: |ctx| Self::op_ccf(&mut ctx.r.f) }),
-            (0x00BF_u16, Instruction { dissassembly: "CP A",        bytes: 1, closure: |ctx| Self::op_cp_r(ctx.r.a, ctx.r.a, &mut ctx.r.f) }),
-            (0x00B8_u16, Instruction { dissassembly: "CP B",        bytes: 1, closure: |ctx| Self::op_cp_r(ctx.r.a, ctx.r.b, &mut ctx.r.f) }),
-            (0x00B9_u16, Instruction { dissassembly: "CP C",        bytes: 1, closure: |ctx| Self::op_cp_r(ctx.r.a, ctx.r.c, &mut ctx.r.f) }),
-            (0x00BA_u16, Instruction { dissassembly: "CP D",        bytes: 1, closure: |ctx| Self::op_cp_r(ctx.r.a, ctx.r.d, &mut ctx.r.f) }),
-            (0x00BB_u16, Instruction { dissassembly: "CP E",        bytes: 1, closure: |ctx| Self::op_cp_r(ctx.r.a, ctx.r.e, &mut ctx.r.f) }),
-            (0x00BC_u16, Instruction { dissassembly: "CP H",        bytes: 1, closure: |ctx| Self::op_cp_r(ctx.r.a, ctx.r.h, &mut ctx.r.f) }),
-            (0x00BD_u16, Instruction { dissassembly: "CP L",        bytes: 1, closure: |ctx| Self::op_cp_r(ctx.r.a, ctx.r.l, &mut ctx.r.f) }),
-            (0x00FE_u16, Instruction { dissassembly: "CP d8",       bytes: 1, closure: |ctx| Self::op_cp_d8(ctx.bus, ctx.r.a, &mut ctx.r.pc, &mut ctx.r.f) }),
-            (0x00BE_u16, Instruction { dissassembly: "CP (HL)",     bytes: 1, closure: |ctx| Self::op_cp_addr(ctx.bus, ctx.r.a, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) }),
+            (0x0000_u16, Instruction { dissassembly: "NOP",         closure: |_ctx| Self::op_nop() }),
+            (0x0010_u16, Instruction { dissassembly: "STOP",        closure: |ctx| Self::op_stop(ctx.s, ctx.bus) }),
+            (0x0076_u16, Instruction { dissassembly: "HALT",        closure: |ctx| Self::op_halt(ctx.s, ctx.bus.interrupts) }),
+            (0x003C_u16, Instruction { dissassembly: "INC A",       closure: |ctx| Self::op_inc_r(&mut ctx.r.a, &mut ctx.r.f) }),
+            (0x0004_u16, Instruction { dissassembly: "INC B",       closure: |ctx| Self::op_inc_r(&mut ctx.r.b, &mut ctx.r.f) }),
+            (0x000C_u16, Instruction { dissassembly: "INC C",       closure: |ctx| Self::op_inc_r(&mut ctx.r.c, &mut ctx.r.f) }),
+            (0x0014_u16, Instruction { dissassembly: "INC D",       closure: |ctx| Self::op_inc_r(&mut ctx.r.d, &mut ctx.r.f) }),
+            (0x001C_u16, Instruction { dissassembly: "INC E",       closure: |ctx| Self::op_inc_r(&mut ctx.r.e, &mut ctx.r.f) }),
+            (0x0024_u16, Instruction { dissassembly: "INC H",       closure: |ctx| Self::op_inc_r(&mut ctx.r.h, &mut ctx.r.f) }),
+            (0x002C_u16, Instruction { dissassembly: "INC L",       closure: |ctx| Self::op_inc_r(&mut ctx.r.l, &mut ctx.r.f) }),
+            (0x0003_u16, Instruction { dissassembly: "INC BC",      closure: |ctx| Self::op_inc_r16(&mut ctx.r.b, &mut ctx.r.c) }),
+            (0x0013_u16, Instruction { dissassembly: "INC DE",      closure: |ctx| Self::op_inc_r16(&mut ctx.r.d, &mut ctx.r.e) }),
+            (0x0023_u16, Instruction { dissassembly: "INC HL",      closure: |ctx| Self::op_inc_r16(&mut ctx.r.h, &mut ctx.r.l) }),
+            (0x0033_u16, Instruction { dissassembly: "INC SP",      closure: |ctx| Self::op_inc_sp(&mut ctx.r.sp) }),
+            (0x0034_u16, Instruction { dissassembly: "INC (HL)",    closure: |ctx| Self::op_inc_addr(ctx.bus, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) }),
+            (0x003D_u16, Instruction { dissassembly: "DEC A",       closure: |ctx| Self::op_dec_r(&mut ctx.r.a, &mut ctx.r.f) }),
+            (0x0005_u16, Instruction { dissassembly: "DEC B",       closure: |ctx| Self::op_dec_r(&mut ctx.r.b, &mut ctx.r.f) }),
+            (0x000D_u16, Instruction { dissassembly: "DEC C",       closure: |ctx| Self::op_dec_r(&mut ctx.r.c, &mut ctx.r.f) }),
+            (0x0015_u16, Instruction { dissassembly: "DEC D",       closure: |ctx| Self::op_dec_r(&mut ctx.r.d, &mut ctx.r.f) }),
+            (0x001D_u16, Instruction { dissassembly: "DEC E",       closure: |ctx| Self::op_dec_r(&mut ctx.r.e, &mut ctx.r.f) }),
+            (0x0025_u16, Instruction { dissassembly: "DEC H",       closure: |ctx| Self::op_dec_r(&mut ctx.r.h, &mut ctx.r.f) }),
+            (0x002D_u16, Instruction { dissassembly: "DEC L",       closure: |ctx| Self::op_dec_r(&mut ctx.r.l, &mut ctx.r.f) }),
+            (0x000B_u16, Instruction { dissassembly: "DEC BC",      closure: |ctx| Self::op_dec_r16(&mut ctx.r.b, &mut ctx.r.c) }),
+            (0x001B_u16, Instruction { dissassembly: "DEC DE",      closure: |ctx| Self::op_dec_r16(&mut ctx.r.d, &mut ctx.r.e) }),
+            (0x002B_u16, Instruction { dissassembly: "DEC HL",      closure: |ctx| Self::op_dec_r16(&mut ctx.r.h, &mut ctx.r.l) }),
+            (0x003B_u16, Instruction { dissassembly: "DEC SP",      closure: |ctx| Self::op_dec_sp(&mut ctx.r.sp) }),
+            (0x0035_u16, Instruction { dissassembly: "DEC (HL)",    closure: |ctx| Self::op_dec_addr(ctx.bus, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) }),
+            (0x0087_u16, Instruction { dissassembly: "ADD A,A",     closure: |ctx| { let v = ctx.r.a; Self::op_add_r(&mut ctx.r.a, v, &mut ctx.r.f) } }),
+            (0x0080_u16, Instruction { dissassembly: "ADD A,B",     closure: |ctx| Self::op_add_r(&mut ctx.r.a, ctx.r.b, &mut ctx.r.f) }),
+            (0x0081_u16, Instruction { dissassembly: "ADD A,C",     closure: |ctx| Self::op_add_r(&mut ctx.r.a, ctx.r.c, &mut ctx.r.f) }),
+            (0x0082_u16, Instruction { dissassembly: "ADD A,D",     closure: |ctx| Self::op_add_r(&mut ctx.r.a, ctx.r.d, &mut ctx.r.f) }),
+            (0x0083_u16, Instruction { dissassembly: "ADD A,E",     closure: |ctx| Self::op_add_r(&mut ctx.r.a, ctx.r.e, &mut ctx.r.f) }),
+            (0x0084_u16, Instruction { dissassembly: "ADD A,H",     closure: |ctx| Self::op_add_r(&mut ctx.r.a, ctx.r.h, &mut ctx.r.f) }),
+            (0x0085_u16, Instruction { dissassembly: "ADD A,L",     closure: |ctx| Self::op_add_r(&mut ctx.r.a, ctx.r.l, &mut ctx.r.f) }),
+            (0x00C6_u16, Instruction { dissassembly: "ADD A,d8",    closure: |ctx| Self::op_add_d8(ctx.bus, &mut ctx.r.a, &mut ctx.r.pc, &mut ctx.r.f) }),
+            (0x0086_u16, Instruction { dissassembly: "ADD A,(HL)",  closure: |ctx| { Self::op_add_addr(ctx.bus, &mut ctx.r.a, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) } }),
+            (0x0009_u16, Instruction { dissassembly: "ADD HL,BC",   closure: |ctx| Self::op_add_r16(&mut ctx.r.h, &mut ctx.r.l, to_u16(ctx.r.b, ctx.r.c), &mut ctx.r.f) }),
+            (0x0019_u16, Instruction { dissassembly: "ADD HL,DE",   closure: |ctx| Self::op_add_r16(&mut ctx.r.h, &mut ctx.r.l, to_u16(ctx.r.d, ctx.r.e), &mut ctx.r.f) }),
+            (0x0029_u16, Instruction { dissassembly: "ADD HL,HL",   closure: |ctx| { let v = to_u16(ctx.r.h, ctx.r.l); Self::op_add_r16(&mut ctx.r.h, &mut ctx.r.l, v, &mut ctx.r.f) } }),
+            (0x0039_u16, Instruction { dissassembly: "ADD HL,SP",   closure: |ctx| Self::op_add_r16(&mut ctx.r.h, &mut ctx.r.l, ctx.r.sp, &mut ctx.r.f) }),
+            (0x00E8_u16, Instruction { dissassembly: "ADD SP,s8",   closure: |ctx| Self::op_add_sp_s8(ctx.bus, &mut ctx.r.sp, &mut ctx.r.pc, &mut ctx.r.f) }),
+            (0x0097_u16, Instruction { dissassembly: "SUB A",       closure: |ctx| { let v = ctx.r.a; Self::op_sub_r(&mut ctx.r.a, v, &mut ctx.r.f) } }),
+            (0x0090_u16, Instruction { dissassembly: "SUB B",       closure: |ctx| Self::op_sub_r(&mut ctx.r.a, ctx.r.b, &mut ctx.r.f) }),
+            (0x0091_u16, Instruction { dissassembly: "SUB C",       closure: |ctx| Self::op_sub_r(&mut ctx.r.a, ctx.r.c, &mut ctx.r.f) }),
+            (0x0092_u16, Instruction { dissassembly: "SUB D",       closure: |ctx| Self::op_sub_r(&mut ctx.r.a, ctx.r.d, &mut ctx.r.f) }),
+            (0x0093_u16, Instruction { dissassembly: "SUB E",       closure: |ctx| Self::op_sub_r(&mut ctx.r.a, ctx.r.e, &mut ctx.r.f) }),
+            (0x0094_u16, Instruction { dissassembly: "SUB H",       closure: |ctx| Self::op_sub_r(&mut ctx.r.a, ctx.r.h, &mut ctx.r.f) }),
+            (0x0095_u16, Instruction { dissassembly: "SUB L",       closure: |ctx| Self::op_sub_r(&mut ctx.r.a, ctx.r.l, &mut ctx.r.f) }),
+            (0x00D6_u16, Instruction { dissassembly: "SUB d8",      closure: |ctx| Self::op_sub_d8(ctx.bus, &mut ctx.r.a, &mut ctx.r.pc, &mut ctx.r.f) }),
+            (0x0096_u16, Instruction { dissassembly: "SUB (HL)",    closure: |ctx| Self::op_sub_addr(ctx.bus, &mut ctx.r.a, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) }),
+            (0x008F_u16, Instruction { dissassembly: "ADC A,A",     closure: |ctx| { let v = ctx.r.a; Self::op_adc_r(&mut ctx.r.a, v, &mut ctx.r.f) } }),
+            (0x0088_u16, Instruction { dissassembly: "ADC A,B",     closure: |ctx| Self::op_adc_r(&mut ctx.r.a, ctx.r.b, &mut ctx.r.f) }),
+            (0x0089_u16, Instruction { dissassembly: "ADC A,C",     closure: |ctx| Self::op_adc_r(&mut ctx.r.a, ctx.r.c, &mut ctx.r.f) }),
+            (0x008A_u16, Instruction { dissassembly: "ADC A,D",     closure: |ctx| Self::op_adc_r(&mut ctx.r.a, ctx.r.d, &mut ctx.r.f) }),
+            (0x008B_u16, Instruction { dissassembly: "ADC A,E",     closure: |ctx| Self::op_adc_r(&mut ctx.r.a, ctx.r.e, &mut ctx.r.f) }),
+            (0x008C_u16, Instruction { dissassembly: "ADC A,H",     closure: |ctx| Self::op_adc_r(&mut ctx.r.a, ctx.r.h, &mut ctx.r.f) }),
+            (0x008D_u16, Instruction { dissassembly: "ADC A,L",     closure: |ctx| Self::op_adc_r(&mut ctx.r.a, ctx.r.l, &mut ctx.r.f) }),
+            (0x00CE_u16, Instruction { dissassembly: "ADC A,d8",    closure: |ctx| Self::op_adc_d8(ctx.bus, &mut ctx.r.a, &mut ctx.r.pc, &mut ctx.r.f) }),
+            (0x008E_u16, Instruction { dissassembly: "ADC A,(HL)",  closure: |ctx| Self::op_adc_addr(ctx.bus, &mut ctx.r.a, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) }),
+            (0x009F_u16, Instruction { dissassembly: "SBC A,A",     closure: |ctx| { let v = ctx.r.a; Self::op_sbc_r(&mut ctx.r.a, v, &mut ctx.r.f) } }),
+            (0x0098_u16, Instruction { dissassembly: "SBC A,B",     closure: |ctx| Self::op_sbc_r(&mut ctx.r.a, ctx.r.b, &mut ctx.r.f) }),
+            (0x0099_u16, Instruction { dissassembly: "SBC A,C",     closure: |ctx| Self::op_sbc_r(&mut ctx.r.a, ctx.r.c, &mut ctx.r.f) }),
+            (0x009A_u16, Instruction { dissassembly: "SBC A,D",     closure: |ctx| Self::op_sbc_r(&mut ctx.r.a, ctx.r.d, &mut ctx.r.f) }),
+            (0x009B_u16, Instruction { dissassembly: "SBC A,E",     closure: |ctx| Self::op_sbc_r(&mut ctx.r.a, ctx.r.e, &mut ctx.r.f) }),
+            (0x009C_u16, Instruction { dissassembly: "SBC A,H",     closure: |ctx| Self::op_sbc_r(&mut ctx.r.a, ctx.r.h, &mut ctx.r.f) }),
+            (0x009D_u16, Instruction { dissassembly: "SBC A,L",     closure: |ctx| Self::op_sbc_r(&mut ctx.r.a, ctx.r.l, &mut ctx.r.f) }),
+            (0x00DE_u16, Instruction { dissassembly: "SBC A,d8",    closure: |ctx| Self::op_sbc_d8(ctx.bus, &mut ctx.r.a, &mut ctx.r.pc, &mut ctx.r.f) }),
+            (0x009E_u16, Instruction { dissassembly: "SBC A,(HL)",  closure: |ctx| Self::op_sbc_addr(ctx.bus, &mut ctx.r.a, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) }),
+            (0x0027_u16, Instruction { dissassembly: "DAA",         closure: |ctx| Self::op_daa(&mut ctx.r.a, &mut ctx.r.f) }),
+            (0x0037_u16, Instruction { dissassembly: "SCF",         closure: |ctx| Self::op_scf(&mut ctx.r.f) }),
+            (0x003F_u16, Instruction { dissassembly: "CCF",         closure: |ctx| Self::op_ccf(&mut ctx.r.f) }),
+            (0x00BF_u16, Instruction { dissassembly: "CP A",        closure: |ctx| Self::op_cp_r(ctx.r.a, ctx.r.a, &mut ctx.r.f) }),
+            (0x00B8_u16, Instruction { dissassembly: "CP B",        closure: |ctx| Self::op_cp_r(ctx.r.a, ctx.r.b, &mut ctx.r.f) }),
+            (0x00B9_u16, Instruction { dissassembly: "CP C",        closure: |ctx| Self::op_cp_r(ctx.r.a, ctx.r.c, &mut ctx.r.f) }),
+            (0x00BA_u16, Instruction { dissassembly: "CP D",        closure: |ctx| Self::op_cp_r(ctx.r.a, ctx.r.d, &mut ctx.r.f) }),
+            (0x00BB_u16, Instruction { dissassembly: "CP E",        closure: |ctx| Self::op_cp_r(ctx.r.a, ctx.r.e, &mut ctx.r.f) }),
+            (0x00BC_u16, Instruction { dissassembly: "CP H",        closure: |ctx| Self::op_cp_r(ctx.r.a, ctx.r.h, &mut ctx.r.f) }),
+            (0x00BD_u16, Instruction { dissassembly: "CP L",        closure: |ctx| Self::op_cp_r(ctx.r.a, ctx.r.l, &mut ctx.r.f) }),
+            (0x00FE_u16, Instruction { dissassembly: "CP d8",       closure: |ctx| Self::op_cp_d8(ctx.bus, ctx.r.a, &mut ctx.r.pc, &mut ctx.r.f) }),
+            (0x00BE_u16, Instruction { dissassembly: "CP (HL)",     closure: |ctx| Self::op_cp_addr(ctx.bus, ctx.r.a, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) }),
             // LOAD instructions
-            (0x007F_u16, Instruction { dissassembly: "LD A,A",      bytes: 1, closure: |_ctx| Self::op_nop() }),
-            (0x0078_u16, Instruction { dissassembly: "LD A,B",      bytes: 1, closure: |ctx| Self::op_ld_r_r(&mut ctx.r.a, ctx.r.b) }),
-            (0x0079_u16, Instruction { dissassembly: "LD A,C",      bytes: 1, closure: |ctx| Self::op_ld_r_r(&mut ctx.r.a, ctx.r.c) }),
-            (0x007A_u16, Instruction { dissassembly: "LD A,D",      bytes: 1, closure: |ctx| Self::op_ld_r_r(&mut ctx.r.a, ctx.r.d) }),
-            (0x007B_u16, Instruction { dissassembly: "LD A,E",      bytes: 1, closure: |ctx| Self::op_ld_r_r(&mut ctx.r.a, ctx.r.e) }),
-            (0x007C_u16, Instruction { dissassembly: "LD A,H",      bytes: 1, closure: |ctx| Self::op_ld_r_r(&mut ctx.r.a, ctx.r.h) }),
-            (0x007D_u16, Instruction { dissassembly: "LD A,L",      bytes: 1, closure: |ctx| Self::op_ld_r_r(&mut ctx.r.a, ctx.r.l) }),
-            (0x0047_u16, Instruction { dissassembly: "LD B,A",      bytes: 1, closure: |ctx| Self::op_ld_r_r(&mut ctx.r.b, ctx.r.a) }),
-            (0x0040_u16, Instruction { dissassembly: "LD B,B",      bytes: 1, closure: |_ctx| Self::op_nop() }),
-            (0x0041_u16, Instruction { dissassembly: "LD B,C",      bytes: 1, closure: |ctx| Self::op_ld_r_r(&mut ctx.r.b, ctx.r.c) }),
-            (0x0042_u16, Instruction { dissassembly: "LD B,D",      bytes: 1, closure: |ctx| Self::op_ld_r_r(&mut ctx.r.b, ctx.r.d) }),
-            (0x0043_u16, Instruction { dissassembly: "LD B,E",      bytes: 1, closure: |ctx| Self::op_ld_r_r(&mut ctx.r.b, ctx.r.e) }),
-            (0x0044_u16, Instruction { dissassembly: "LD B,H",      bytes: 1, closure: |ctx| Self::op_ld_r_r(&mut ctx.r.b, ctx.r.h) }),
-            (0x0045_u16, Instruction { dissassembly: "LD B,L",      bytes: 1, closure: |ctx| Self::op_ld_r_r(&mut ctx.r.b, ctx.r.l) }),
-            (0x004F_u16, Instruction { dissassembly: "LD C,A",      bytes: 1, closure: |ctx| Self::op_ld_r_r(&mut ctx.r.c, ctx.r.a) }),
-            (0x0048_u16, Instruction { dissassembly: "LD C,B",      bytes: 1, closure: |ctx| Self::op_ld_r_r(&mut ctx.r.c, ctx.r.b) }),
-            (0x0049_u16, Instruction { dissassembly: "LD C,C",      bytes: 1, closure: |_ctx| Self::op_nop() }),
-            (0x004A_u16, Instruction { dissassembly: "LD C,D",      bytes: 1, closure: |ctx| Self::op_ld_r_r(&mut ctx.r.c, ctx.r.d) }),
-            (0x004B_u16, Instruction { dissassembly: "LD C,E",      bytes: 1, closure: |ctx| Self::op_ld_r_r(&mut ctx.r.c, ctx.r.e) }),
-            (0x004C_u16, Instruction { dissassembly: "LD C,H",      bytes: 1, closure: |ctx| Self::op_ld_r_r(&mut ctx.r.c, ctx.r.h) }),
-            (0x004D_u16, Instruction { dissassembly: "LD C,L",      bytes: 1, closure: |ctx| Self::op_ld_r_r(&mut ctx.r.c, ctx.r.l) }),
-            (0x0057_u16, Instruction { dissassembly: "LD D,A",      bytes: 1, closure: |ctx| Self::op_ld_r_r(&mut ctx.r.d, ctx.r.a) }),
-            (0x0050_u16, Instruction { dissassembly: "LD D,B",      bytes: 1, closure: |ctx| Self::op_ld_r_r(&mut ctx.r.d, ctx.r.b) }),
-            (0x0051_u16, Instruction { dissassembly: "LD D,C",      bytes: 1, closure: |ctx| Self::op_ld_r_r(&mut ctx.r.d, ctx.r.c) }),
-            (0x0052_u16, Instruction { dissassembly: "LD D,D",      bytes: 1, closure: |_ctx| Self::op_nop() }),
-            (0x0053_u16, Instruction { dissassembly: "LD D,E",      bytes: 1, closure: |ctx| Self::op_ld_r_r(&mut ctx.r.d, ctx.r.e) }),
-            (0x0054_u16, Instruction { dissassembly: "LD D,H",      bytes: 1, closure: |ctx| Self::op_ld_r_r(&mut ctx.r.d, ctx.r.h) }),
-            (0x0055_u16, Instruction { dissassembly: "LD D,L",      bytes: 1, closure: |ctx| Self::op_ld_r_r(&mut ctx.r.d, ctx.r.l) }),
-            (0x005F_u16, Instruction { dissassembly: "LD E,A",      bytes: 1, closure: |ctx| Self::op_ld_r_r(&mut ctx.r.e, ctx.r.a) }),
-            (0x0058_u16, Instruction { dissassembly: "LD E,B",      bytes: 1, closure: |ctx| Self::op_ld_r_r(&mut ctx.r.e, ctx.r.b) }),
-            (0x0059_u16, Instruction { dissassembly: "LD E,C",      bytes: 1, closure: |ctx| Self::op_ld_r_r(&mut ctx.r.e, ctx.r.c) }),
-            (0x005A_u16, Instruction { dissassembly: "LD E,D",      bytes: 1, closure: |ctx| Self::op_ld_r_r(&mut ctx.r.e, ctx.r.d) }),
-            (0x005B_u16, Instruction { dissassembly: "LD E,E",      bytes: 1, closure: |_ctx| Self::op_nop() }),
-            (0x005C_u16, Instruction { dissassembly: "LD E,H",      bytes: 1, closure: |ctx| Self::op_ld_r_r(&mut ctx.r.e, ctx.r.h) }),
-            (0x005D_u16, Instruction { dissassembly: "LD E,L",      bytes: 1, closure: |ctx| Self::op_ld_r_r(&mut ctx.r.e, ctx.r.l) }),
-            (0x0067_u16, Instruction { dissassembly: "LD H,A",      bytes: 1, closure: |ctx| Self::op_ld_r_r(&mut ctx.r.h, ctx.r.a) }),
-            (0x0060_u16, Instruction { dissassembly: "LD H,B",      bytes: 1, closure: |ctx| Self::op_ld_r_r(&mut ctx.r.h, ctx.r.b) }),
-            (0x0061_u16, Instruction { dissassembly: "LD H,C",      bytes: 1, closure: |ctx| Self::op_ld_r_r(&mut ctx.r.h, ctx.r.c) }),
-            (0x0062_u16, Instruction { dissassembly: "LD H,D",      bytes: 1, closure: |ctx| Self::op_ld_r_r(&mut ctx.r.h, ctx.r.d) }),
-            (0x0063_u16, Instruction { dissassembly: "LD H,E",      bytes: 1, closure: |ctx| Self::op_ld_r_r(&mut ctx.r.h, ctx.r.e) }),
-            (0x0064_u16, Instruction { dissassembly: "LD H,H",      bytes: 1, closure: |_ctx| Self::op_nop() }),
-            (0x0065_u16, Instruction { dissassembly: "LD H,L",      bytes: 1, closure: |ctx| Self::op_ld_r_r(&mut ctx.r.h, ctx.r.l) }),
-            (0x006F_u16, Instruction { dissassembly: "LD L,A",      bytes: 1, closure: |ctx| Self::op_ld_r_r(&mut ctx.r.l, ctx.r.a) }),
-            (0x0068_u16, Instruction { dissassembly: "LD L,B",      bytes: 1, closure: |ctx| Self::op_ld_r_r(&mut ctx.r.l, ctx.r.b) }),
-            (0x0069_u16, Instruction { dissassembly: "LD L,C",      bytes: 1, closure: |ctx| Self::op_ld_r_r(&mut ctx.r.l, ctx.r.c) }),
-            (0x006A_u16, Instruction { dissassembly: "LD L,D",      bytes: 1, closure: |ctx| Self::op_ld_r_r(&mut ctx.r.l, ctx.r.d) }),
-            (0x006B_u16, Instruction { dissassembly: "LD L,E",      bytes: 1, closure: |ctx| Self::op_ld_r_r(&mut ctx.r.l, ctx.r.e) }),
-            (0x006C_u16, Instruction { dissassembly: "LD L,H",      bytes: 1, closure: |ctx| Self::op_ld_r_r(&mut ctx.r.l, ctx.r.h) }),
-            (0x006D_u16, Instruction { dissassembly: "LD L,L",      bytes: 1, closure: |_ctx| Self::op_nop() }),
-            (0x0066_u16, Instruction { dissassembly: "LD H,(HL)",   bytes: 1, closure: |ctx| { let hl = to_u16(ctx.r.h, ctx.r.l); Self::op_ld_r_addr(ctx.bus, &mut ctx.r.h, hl) } }),
-            (0x006E_u16, Instruction { dissassembly: "LD L,(HL)",   bytes: 1, closure: |ctx| { let hl = to_u16(ctx.r.h, ctx.r.l); Self::op_ld_r_addr(ctx.bus, &mut ctx.r.l, hl) } }),
-            (0x003E_u16, Instruction { dissassembly: "LD A,d8",     bytes: 2, closure: |ctx| Self::op_ld_r_d8(ctx.bus, &mut ctx.r.a, &mut ctx.r.pc) }),
-            (0x0006_u16, Instruction { dissassembly: "LD B,d8",     bytes: 2, closure: |ctx| Self::op_ld_r_d8(ctx.bus, &mut ctx.r.b, &mut ctx.r.pc) }),
-            (0x000E_u16, Instruction { dissassembly: "LD C,d8",     bytes: 2, closure: |ctx| Self::op_ld_r_d8(ctx.bus, &mut ctx.r.c, &mut ctx.r.pc) }),
-            (0x0016_u16, Instruction { dissassembly: "LD D,d8",     bytes: 2, closure: |ctx| Self::op_ld_r_d8(ctx.bus, &mut ctx.r.d, &mut ctx.r.pc) }),
-            (0x001E_u16, Instruction { dissassembly: "LD E,d8",     bytes: 2, closure: |ctx| Self::op_ld_r_d8(ctx.bus, &mut ctx.r.e, &mut ctx.r.pc) }),
-            (0x0026_u16, Instruction { dissassembly: "LD H,d8",     bytes: 2, closure: |ctx| Self::op_ld_r_d8(ctx.bus, &mut ctx.r.h, &mut ctx.r.pc) }),
-            (0x002E_u16, Instruction { dissassembly: "LD L,d8",     bytes: 2, closure: |ctx| Self::op_ld_r_d8(ctx.bus, &mut ctx.r.l, &mut ctx.r.pc) }),
-            (0x0001_u16, Instruction { dissassembly: "LD BC,d16",   bytes: 3, closure: |ctx| Self::op_ld_r_d16(ctx.bus, &mut ctx.r.b, &mut ctx.r.c, &mut ctx.r.pc) }),
-            (0x0011_u16, Instruction { dissassembly: "LD DE,d16",   bytes: 3, closure: |ctx| Self::op_ld_r_d16(ctx.bus, &mut ctx.r.d, &mut ctx.r.e, &mut ctx.r.pc) }),
-            (0x0021_u16, Instruction { dissassembly: "LD HL,d16",   bytes: 3, closure: |ctx| Self::op_ld_r_d16(ctx.bus, &mut ctx.r.h, &mut ctx.r.l, &mut ctx.r.pc) }),
-            (0x0031_u16, Instruction { dissassembly: "LD SP,d16",   bytes: 3, closure: |ctx| Self::op_ld_sp_d16(ctx.bus, &mut ctx.r.sp, &mut ctx.r.pc) }),
-            (0x00F9_u16, Instruction { dissassembly: "LD SP,HL",    bytes: 1, closure: |ctx| Self::op_ld_sp_r16(&mut ctx.r.sp, to_u16(ctx.r.h, ctx.r.l)) }),
-            (0x00F8_u16, Instruction { dissassembly: "LD HL,SP+s8", bytes: 2, closure: |ctx| Self::op_ld_hl_sp_add_s8(ctx.bus, &mut ctx.r.h, &mut ctx.r.l, ctx.r.sp, &mut ctx.r.pc, &mut ctx.r.f) }),
-            (0x00F2_u16, Instruction { dissassembly: "LD A,(C)",    bytes: 1, closure: |ctx| Self::op_ld_r_addr(ctx.bus, &mut ctx.r.a, 0xFF00 | (ctx.r.c as u16)) }),
-            (0x000A_u16, Instruction { dissassembly: "LD A,(BC)",   bytes: 1, closure: |ctx| Self::op_ld_r_addr(ctx.bus, &mut ctx.r.a, to_u16(ctx.r.b, ctx.r.c)) }),
-            (0x001A_u16, Instruction { dissassembly: "LD A,(DE)",   bytes: 1, closure: |ctx| Self::op_ld_r_addr(ctx.bus, &mut ctx.r.a, to_u16(ctx.r.d, ctx.r.e)) }),
-            (0x007E_u16, Instruction { dissassembly: "LD A,(HL)",   bytes: 1, closure: |ctx| Self::op_ld_r_addr(ctx.bus, &mut ctx.r.a, to_u16(ctx.r.h, ctx.r.l)) }),
-            (0x0046_u16, Instruction { dissassembly: "LD B,(HL)",   bytes: 1, closure: |ctx| Self::op_ld_r_addr(ctx.bus, &mut ctx.r.b, to_u16(ctx.r.h, ctx.r.l)) }),
-            (0x004E_u16, Instruction { dissassembly: "LD C,(HL)",   bytes: 1, closure: |ctx| Self::op_ld_r_addr(ctx.bus, &mut ctx.r.c, to_u16(ctx.r.h, ctx.r.l)) }),
-            (0x0056_u16, Instruction { dissassembly: "LD D,(HL)",   bytes: 1, closure: |ctx| Self::op_ld_r_addr(ctx.bus, &mut ctx.r.d, to_u16(ctx.r.h, ctx.r.l)) }),
-            (0x005E_u16, Instruction { dissassembly: "LD E,(HL)",   bytes: 1, closure: |ctx| Self::op_ld_r_addr(ctx.bus, &mut ctx.r.e, to_u16(ctx.r.h, ctx.r.l)) }),
-            (0x00F0_u16, Instruction { dissassembly: "LD A,(d8)",   bytes: 2, closure: |ctx| Self::op_ld_r_a8(ctx.bus, &mut ctx.r.a, &mut ctx.r.pc) }),
-            (0x00FA_u16, Instruction { dissassembly: "LD A,(a16)",  bytes: 3, closure: |ctx| Self::op_ld_r_a16(ctx.bus, &mut ctx.r.a, &mut ctx.r.pc) }),
-            (0x002A_u16, Instruction { dissassembly: "LD A,(HL+)",  bytes: 1, closure: |ctx| Self::op_ld_a_mem_hl_inc(ctx.bus, &mut ctx.r.a, &mut ctx.r.h, &mut ctx.r.l) }),
-            (0x003A_u16, Instruction { dissassembly: "LD A,(HL-)",  bytes: 1, closure: |ctx| Self::op_ld_a_mem_hl_dec(ctx.bus, &mut ctx.r.a, &mut ctx.r.h, &mut ctx.r.l) }),
-            (0x00E2_u16, Instruction { dissassembly: "LD (C),A",    bytes: 1, closure: |ctx| Self::op_ld_addr_r(ctx.bus, 0xFF00 | (ctx.r.c as u16), ctx.r.a) }),
-            (0x0002_u16, Instruction { dissassembly: "LD (BC),A",   bytes: 1, closure: |ctx| Self::op_ld_addr_r(ctx.bus, to_u16(ctx.r.b, ctx.r.c), ctx.r.a) }),
-            (0x0012_u16, Instruction { dissassembly: "LD (DE),A",   bytes: 1, closure: |ctx| Self::op_ld_addr_r(ctx.bus, to_u16(ctx.r.d, ctx.r.e), ctx.r.a) }),
-            (0x0077_u16, Instruction { dissassembly: "LD (HL),A",   bytes: 1, closure: |ctx| Self::op_ld_addr_r(ctx.bus, to_u16(ctx.r.h, ctx.r.l), ctx.r.a) }),
-            (0x0070_u16, Instruction { dissassembly: "LD (HL),B",   bytes: 1, closure: |ctx| Self::op_ld_addr_r(ctx.bus, to_u16(ctx.r.h, ctx.r.l), ctx.r.b) }),
-            (0x0071_u16, Instruction { dissassembly: "LD (HL),C",   bytes: 1, closure: |ctx| Self::op_ld_addr_r(ctx.bus, to_u16(ctx.r.h, ctx.r.l), ctx.r.c) }),
-            (0x0072_u16, Instruction { dissassembly: "LD (HL),D",   bytes: 1, closure: |ctx| Self::op_ld_addr_r(ctx.bus, to_u16(ctx.r.h, ctx.r.l), ctx.r.d) }),
-            (0x0073_u16, Instruction { dissassembly: "LD (HL),E",   bytes: 1, closure: |ctx| Self::op_ld_addr_r(ctx.bus, to_u16(ctx.r.h, ctx.r.l), ctx.r.e) }),
-            (0x0074_u16, Instruction { dissassembly: "LD (HL),H",   bytes: 2, closure: |ctx| Self::op_ld_addr_r(ctx.bus, to_u16(ctx.r.h, ctx.r.l), ctx.r.h) }),
-            (0x0075_u16, Instruction { dissassembly: "LD (HL),L",   bytes: 2, closure: |ctx| Self::op_ld_addr_r(ctx.bus, to_u16(ctx.r.h, ctx.r.l), ctx.r.l) }),
-            (0x0032_u16, Instruction { dissassembly: "LD (HL-),A",  bytes: 1, closure: |ctx| Self::op_ld_addr_r_dec_hl(ctx.bus, &mut ctx.r.h, &mut ctx.r.l, ctx.r.a) }),
-            (0x0022_u16, Instruction { dissassembly: "LD (HL+),A",  bytes: 1, closure: |ctx| Self::op_ld_addr_r_inc_hl(ctx.bus, &mut ctx.r.h, &mut ctx.r.l, ctx.r.a) }),
-            (0x0036_u16, Instruction { dissassembly: "LD (HL),d8",  bytes: 1, closure: |ctx| Self::op_ld_addr_d8(ctx.bus, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.pc) }),
-            (0x00E0_u16, Instruction { dissassembly: "LD (a8),A",   bytes: 2, closure: |ctx| Self::op_ld_a8_r(ctx.bus, &mut ctx.r.pc, ctx.r.a) }),
-            (0x00EA_u16, Instruction { dissassembly: "LD (a16),A",  bytes: 3, closure: |ctx| Self::op_ld_a16_r(ctx.bus, &mut ctx.r.pc, ctx.r.a) }), 
-            (0x0008_u16, Instruction { dissassembly: "LD (a16),SP", bytes: 3, closure: |ctx| Self::op_ld_a16_r16(ctx.bus, &mut ctx.r.pc, ctx.r.sp) }),
+            (0x007F_u16, Instruction { dissassembly: "LD A,A",      closure: |_ctx| Self::op_nop() }),
+            (0x0078_u16, Instruction { dissassembly: "LD A,B",      closure: |ctx| Self::op_ld_r_r(&mut ctx.r.a, ctx.r.b) }),
+            (0x0079_u16, Instruction { dissassembly: "LD A,C",      closure: |ctx| Self::op_ld_r_r(&mut ctx.r.a, ctx.r.c) }),
+            (0x007A_u16, Instruction { dissassembly: "LD A,D",      closure: |ctx| Self::op_ld_r_r(&mut ctx.r.a, ctx.r.d) }),
+            (0x007B_u16, Instruction { dissassembly: "LD A,E",      closure: |ctx| Self::op_ld_r_r(&mut ctx.r.a, ctx.r.e) }),
+            (0x007C_u16, Instruction { dissassembly: "LD A,H",      closure: |ctx| Self::op_ld_r_r(&mut ctx.r.a, ctx.r.h) }),
+            (0x007D_u16, Instruction { dissassembly: "LD A,L",      closure: |ctx| Self::op_ld_r_r(&mut ctx.r.a, ctx.r.l) }),
+            (0x0047_u16, Instruction { dissassembly: "LD B,A",      closure: |ctx| Self::op_ld_r_r(&mut ctx.r.b, ctx.r.a) }),
+            (0x0040_u16, Instruction { dissassembly: "LD B,B",      closure: |_ctx| Self::op_nop() }),
+            (0x0041_u16, Instruction { dissassembly: "LD B,C",      closure: |ctx| Self::op_ld_r_r(&mut ctx.r.b, ctx.r.c) }),
+            (0x0042_u16, Instruction { dissassembly: "LD B,D",      closure: |ctx| Self::op_ld_r_r(&mut ctx.r.b, ctx.r.d) }),
+            (0x0043_u16, Instruction { dissassembly: "LD B,E",      closure: |ctx| Self::op_ld_r_r(&mut ctx.r.b, ctx.r.e) }),
+            (0x0044_u16, Instruction { dissassembly: "LD B,H",      closure: |ctx| Self::op_ld_r_r(&mut ctx.r.b, ctx.r.h) }),
+            (0x0045_u16, Instruction { dissassembly: "LD B,L",      closure: |ctx| Self::op_ld_r_r(&mut ctx.r.b, ctx.r.l) }),
+            (0x004F_u16, Instruction { dissassembly: "LD C,A",      closure: |ctx| Self::op_ld_r_r(&mut ctx.r.c, ctx.r.a) }),
+            (0x0048_u16, Instruction { dissassembly: "LD C,B",      closure: |ctx| Self::op_ld_r_r(&mut ctx.r.c, ctx.r.b) }),
+            (0x0049_u16, Instruction { dissassembly: "LD C,C",      closure: |_ctx| Self::op_nop() }),
+            (0x004A_u16, Instruction { dissassembly: "LD C,D",      closure: |ctx| Self::op_ld_r_r(&mut ctx.r.c, ctx.r.d) }),
+            (0x004B_u16, Instruction { dissassembly: "LD C,E",      closure: |ctx| Self::op_ld_r_r(&mut ctx.r.c, ctx.r.e) }),
+            (0x004C_u16, Instruction { dissassembly: "LD C,H",      closure: |ctx| Self::op_ld_r_r(&mut ctx.r.c, ctx.r.h) }),
+            (0x004D_u16, Instruction { dissassembly: "LD C,L",      closure: |ctx| Self::op_ld_r_r(&mut ctx.r.c, ctx.r.l) }),
+            (0x0057_u16, Instruction { dissassembly: "LD D,A",      closure: |ctx| Self::op_ld_r_r(&mut ctx.r.d, ctx.r.a) }),
+            (0x0050_u16, Instruction { dissassembly: "LD D,B",      closure: |ctx| Self::op_ld_r_r(&mut ctx.r.d, ctx.r.b) }),
+            (0x0051_u16, Instruction { dissassembly: "LD D,C",      closure: |ctx| Self::op_ld_r_r(&mut ctx.r.d, ctx.r.c) }),
+            (0x0052_u16, Instruction { dissassembly: "LD D,D",      closure: |_ctx| Self::op_nop() }),
+            (0x0053_u16, Instruction { dissassembly: "LD D,E",      closure: |ctx| Self::op_ld_r_r(&mut ctx.r.d, ctx.r.e) }),
+            (0x0054_u16, Instruction { dissassembly: "LD D,H",      closure: |ctx| Self::op_ld_r_r(&mut ctx.r.d, ctx.r.h) }),
+            (0x0055_u16, Instruction { dissassembly: "LD D,L",      closure: |ctx| Self::op_ld_r_r(&mut ctx.r.d, ctx.r.l) }),
+            (0x005F_u16, Instruction { dissassembly: "LD E,A",      closure: |ctx| Self::op_ld_r_r(&mut ctx.r.e, ctx.r.a) }),
+            (0x0058_u16, Instruction { dissassembly: "LD E,B",      closure: |ctx| Self::op_ld_r_r(&mut ctx.r.e, ctx.r.b) }),
+            (0x0059_u16, Instruction { dissassembly: "LD E,C",      closure: |ctx| Self::op_ld_r_r(&mut ctx.r.e, ctx.r.c) }),
+            (0x005A_u16, Instruction { dissassembly: "LD E,D",      closure: |ctx| Self::op_ld_r_r(&mut ctx.r.e, ctx.r.d) }),
+            (0x005B_u16, Instruction { dissassembly: "LD E,E",      closure: |_ctx| Self::op_nop() }),
+            (0x005C_u16, Instruction { dissassembly: "LD E,H",      closure: |ctx| Self::op_ld_r_r(&mut ctx.r.e, ctx.r.h) }),
+            (0x005D_u16, Instruction { dissassembly: "LD E,L",      closure: |ctx| Self::op_ld_r_r(&mut ctx.r.e, ctx.r.l) }),
+            (0x0067_u16, Instruction { dissassembly: "LD H,A",      closure: |ctx| Self::op_ld_r_r(&mut ctx.r.h, ctx.r.a) }),
+            (0x0060_u16, Instruction { dissassembly: "LD H,B",      closure: |ctx| Self::op_ld_r_r(&mut ctx.r.h, ctx.r.b) }),
+            (0x0061_u16, Instruction { dissassembly: "LD H,C",      closure: |ctx| Self::op_ld_r_r(&mut ctx.r.h, ctx.r.c) }),
+            (0x0062_u16, Instruction { dissassembly: "LD H,D",      closure: |ctx| Self::op_ld_r_r(&mut ctx.r.h, ctx.r.d) }),
+            (0x0063_u16, Instruction { dissassembly: "LD H,E",      closure: |ctx| Self::op_ld_r_r(&mut ctx.r.h, ctx.r.e) }),
+            (0x0064_u16, Instruction { dissassembly: "LD H,H",      closure: |_ctx| Self::op_nop() }),
+            (0x0065_u16, Instruction { dissassembly: "LD H,L",      closure: |ctx| Self::op_ld_r_r(&mut ctx.r.h, ctx.r.l) }),
+            (0x006F_u16, Instruction { dissassembly: "LD L,A",      closure: |ctx| Self::op_ld_r_r(&mut ctx.r.l, ctx.r.a) }),
+            (0x0068_u16, Instruction { dissassembly: "LD L,B",      closure: |ctx| Self::op_ld_r_r(&mut ctx.r.l, ctx.r.b) }),
+            (0x0069_u16, Instruction { dissassembly: "LD L,C",      closure: |ctx| Self::op_ld_r_r(&mut ctx.r.l, ctx.r.c) }),
+            (0x006A_u16, Instruction { dissassembly: "LD L,D",      closure: |ctx| Self::op_ld_r_r(&mut ctx.r.l, ctx.r.d) }),
+            (0x006B_u16, Instruction { dissassembly: "LD L,E",      closure: |ctx| Self::op_ld_r_r(&mut ctx.r.l, ctx.r.e) }),
+            (0x006C_u16, Instruction { dissassembly: "LD L,H",      closure: |ctx| Self::op_ld_r_r(&mut ctx.r.l, ctx.r.h) }),
+            (0x006D_u16, Instruction { dissassembly: "LD L,L",      closure: |_ctx| Self::op_nop() }),
+            (0x0066_u16, Instruction { dissassembly: "LD H,(HL)",   closure: |ctx| { let hl = to_u16(ctx.r.h, ctx.r.l); Self::op_ld_r_addr(ctx.bus, &mut ctx.r.h, hl) } }),
+            (0x006E_u16, Instruction { dissassembly: "LD L,(HL)",   closure: |ctx| { let hl = to_u16(ctx.r.h, ctx.r.l); Self::op_ld_r_addr(ctx.bus, &mut ctx.r.l, hl) } }),
+            (0x003E_u16, Instruction { dissassembly: "LD A,d8",     closure: |ctx| Self::op_ld_r_d8(ctx.bus, &mut ctx.r.a, &mut ctx.r.pc) }),
+            (0x0006_u16, Instruction { dissassembly: "LD B,d8",     closure: |ctx| Self::op_ld_r_d8(ctx.bus, &mut ctx.r.b, &mut ctx.r.pc) }),
+            (0x000E_u16, Instruction { dissassembly: "LD C,d8",     closure: |ctx| Self::op_ld_r_d8(ctx.bus, &mut ctx.r.c, &mut ctx.r.pc) }),
+            (0x0016_u16, Instruction { dissassembly: "LD D,d8",     closure: |ctx| Self::op_ld_r_d8(ctx.bus, &mut ctx.r.d, &mut ctx.r.pc) }),
+            (0x001E_u16, Instruction { dissassembly: "LD E,d8",     closure: |ctx| Self::op_ld_r_d8(ctx.bus, &mut ctx.r.e, &mut ctx.r.pc) }),
+            (0x0026_u16, Instruction { dissassembly: "LD H,d8",     closure: |ctx| Self::op_ld_r_d8(ctx.bus, &mut ctx.r.h, &mut ctx.r.pc) }),
+            (0x002E_u16, Instruction { dissassembly: "LD L,d8",     closure: |ctx| Self::op_ld_r_d8(ctx.bus, &mut ctx.r.l, &mut ctx.r.pc) }),
+            (0x0001_u16, Instruction { dissassembly: "LD BC,d16",   closure: |ctx| Self::op_ld_r_d16(ctx.bus, &mut ctx.r.b, &mut ctx.r.c, &mut ctx.r.pc) }),
+            (0x0011_u16, Instruction { dissassembly: "LD DE,d16",   closure: |ctx| Self::op_ld_r_d16(ctx.bus, &mut ctx.r.d, &mut ctx.r.e, &mut ctx.r.pc) }),
+            (0x0021_u16, Instruction { dissassembly: "LD HL,d16",   closure: |ctx| Self::op_ld_r_d16(ctx.bus, &mut ctx.r.h, &mut ctx.r.l, &mut ctx.r.pc) }),
+            (0x0031_u16, Instruction { dissassembly: "LD SP,d16",   closure: |ctx| Self::op_ld_sp_d16(ctx.bus, &mut ctx.r.sp, &mut ctx.r.pc) }),
+            (0x00F9_u16, Instruction { dissassembly: "LD SP,HL",    closure: |ctx| Self::op_ld_sp_r16(&mut ctx.r.sp, to_u16(ctx.r.h, ctx.r.l)) }),
+            (0x00F8_u16, Instruction { dissassembly: "LD HL,SP+s8", closure: |ctx| Self::op_ld_hl_sp_add_s8(ctx.bus, &mut ctx.r.h, &mut ctx.r.l, ctx.r.sp, &mut ctx.r.pc, &mut ctx.r.f) }),
+            (0x00F2_u16, Instruction { dissassembly: "LD A,(C)",    closure: |ctx| Self::op_ld_r_addr(ctx.bus, &mut ctx.r.a, 0xFF00 | (ctx.r.c as u16)) }),
+            (0x000A_u16, Instruction { dissassembly: "LD A,(BC)",   closure: |ctx| Self::op_ld_r_addr(ctx.bus, &mut ctx.r.a, to_u16(ctx.r.b, ctx.r.c)) }),
+            (0x001A_u16, Instruction { dissassembly: "LD A,(DE)",   closure: |ctx| Self::op_ld_r_addr(ctx.bus, &mut ctx.r.a, to_u16(ctx.r.d, ctx.r.e)) }),
+            (0x007E_u16, Instruction { dissassembly: "LD A,(HL)",   closure: |ctx| Self::op_ld_r_addr(ctx.bus, &mut ctx.r.a, to_u16(ctx.r.h, ctx.r.l)) }),
+            (0x0046_u16, Instruction { dissassembly: "LD B,(HL)",   closure: |ctx| Self::op_ld_r_addr(ctx.bus, &mut ctx.r.b, to_u16(ctx.r.h, ctx.r.l)) }),
+            (0x004E_u16, Instruction { dissassembly: "LD C,(HL)",   closure: |ctx| Self::op_ld_r_addr(ctx.bus, &mut ctx.r.c, to_u16(ctx.r.h, ctx.r.l)) }),
+            (0x0056_u16, Instruction { dissassembly: "LD D,(HL)",   closure: |ctx| Self::op_ld_r_addr(ctx.bus, &mut ctx.r.d, to_u16(ctx.r.h, ctx.r.l)) }),
+            (0x005E_u16, Instruction { dissassembly: "LD E,(HL)",   closure: |ctx| Self::op_ld_r_addr(ctx.bus, &mut ctx.r.e, to_u16(ctx.r.h, ctx.r.l)) }),
+            (0x00F0_u16, Instruction { dissassembly: "LD A,(d8)",   closure: |ctx| Self::op_ld_r_a8(ctx.bus, &mut ctx.r.a, &mut ctx.r.pc) }),
+            (0x00FA_u16, Instruction { dissassembly: "LD A,(a16)",  closure: |ctx| Self::op_ld_r_a16(ctx.bus, &mut ctx.r.a, &mut ctx.r.pc) }),
+            (0x002A_u16, Instruction { dissassembly: "LD A,(HL+)",  closure: |ctx| Self::op_ld_a_mem_hl_inc(ctx.bus, &mut ctx.r.a, &mut ctx.r.h, &mut ctx.r.l) }),
+            (0x003A_u16, Instruction { dissassembly: "LD A,(HL-)",  closure: |ctx| Self::op_ld_a_mem_hl_dec(ctx.bus, &mut ctx.r.a, &mut ctx.r.h, &mut ctx.r.l) }),
+            (0x00E2_u16, Instruction { dissassembly: "LD (C),A",    closure: |ctx| Self::op_ld_addr_r(ctx.bus, 0xFF00 | (ctx.r.c as u16), ctx.r.a) }),
+            (0x0002_u16, Instruction { dissassembly: "LD (BC),A",   closure: |ctx| Self::op_ld_addr_r(ctx.bus, to_u16(ctx.r.b, ctx.r.c), ctx.r.a) }),
+            (0x0012_u16, Instruction { dissassembly: "LD (DE),A",   closure: |ctx| Self::op_ld_addr_r(ctx.bus, to_u16(ctx.r.d, ctx.r.e), ctx.r.a) }),
+            (0x0077_u16, Instruction { dissassembly: "LD (HL),A",   closure: |ctx| Self::op_ld_addr_r(ctx.bus, to_u16(ctx.r.h, ctx.r.l), ctx.r.a) }),
+            (0x0070_u16, Instruction { dissassembly: "LD (HL),B",   closure: |ctx| Self::op_ld_addr_r(ctx.bus, to_u16(ctx.r.h, ctx.r.l), ctx.r.b) }),
+            (0x0071_u16, Instruction { dissassembly: "LD (HL),C",   closure: |ctx| Self::op_ld_addr_r(ctx.bus, to_u16(ctx.r.h, ctx.r.l), ctx.r.c) }),
+            (0x0072_u16, Instruction { dissassembly: "LD (HL),D",   closure: |ctx| Self::op_ld_addr_r(ctx.bus, to_u16(ctx.r.h, ctx.r.l), ctx.r.d) }),
+            (0x0073_u16, Instruction { dissassembly: "LD (HL),E",   closure: |ctx| Self::op_ld_addr_r(ctx.bus, to_u16(ctx.r.h, ctx.r.l), ctx.r.e) }),
+            (0x0074_u16, Instruction { dissassembly: "LD (HL),H",   closure: |ctx| Self::op_ld_addr_r(ctx.bus, to_u16(ctx.r.h, ctx.r.l), ctx.r.h) }),
+            (0x0075_u16, Instruction { dissassembly: "LD (HL),L",   closure: |ctx| Self::op_ld_addr_r(ctx.bus, to_u16(ctx.r.h, ctx.r.l), ctx.r.l) }),
+            (0x0032_u16, Instruction { dissassembly: "LD (HL-),A",  closure: |ctx| Self::op_ld_addr_r_dec_hl(ctx.bus, &mut ctx.r.h, &mut ctx.r.l, ctx.r.a) }),
+            (0x0022_u16, Instruction { dissassembly: "LD (HL+),A",  closure: |ctx| Self::op_ld_addr_r_inc_hl(ctx.bus, &mut ctx.r.h, &mut ctx.r.l, ctx.r.a) }),
+            (0x0036_u16, Instruction { dissassembly: "LD (HL),d8",  closure: |ctx| Self::op_ld_addr_d8(ctx.bus, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.pc) }),
+            (0x00E0_u16, Instruction { dissassembly: "LD (a8),A",   closure: |ctx| Self::op_ld_a8_r(ctx.bus, &mut ctx.r.pc, ctx.r.a) }),
+            (0x00EA_u16, Instruction { dissassembly: "LD (a16),A",  closure: |ctx| Self::op_ld_a16_r(ctx.bus, &mut ctx.r.pc, ctx.r.a) }), 
+            (0x0008_u16, Instruction { dissassembly: "LD (a16),SP", closure: |ctx| Self::op_ld_a16_r16(ctx.bus, &mut ctx.r.pc, ctx.r.sp) }),
             // BITWISE operations
-            (0x00A7_u16, Instruction { dissassembly: "AND A",       bytes: 1, closure: |ctx| { let v = ctx.r.a; Self::op_and_r(&mut ctx.r.a, v, &mut ctx.r.f) } }),
-            (0x00A0_u16, Instruction { dissassembly: "AND B",       bytes: 1, closure: |ctx| Self::op_and_r(&mut ctx.r.a, ctx.r.b, &mut ctx.r.f) }),
-            (0x00A1_u16, Instruction { dissassembly: "AND C",       bytes: 1, closure: |ctx| Self::op_and_r(&mut ctx.r.a, ctx.r.c, &mut ctx.r.f) }),
-            (0x00A2_u16, Instruction { dissassembly: "AND D",       bytes: 1, closure: |ctx| Self::op_and_r(&mut ctx.r.a, ctx.r.d, &mut ctx.r.f) }),
-            (0x00A3_u16, Instruction { dissassembly: "AND E",       bytes: 1, closure: |ctx| Self::op_and_r(&mut ctx.r.a, ctx.r.e, &mut ctx.r.f) }),
-            (0x00A4_u16, Instruction { dissassembly: "AND H",       bytes: 1, closure: |ctx| Self::op_and_r(&mut ctx.r.a, ctx.r.h, &mut ctx.r.f) }),
-            (0x00A5_u16, Instruction { dissassembly: "AND L",       bytes: 1, closure: |ctx| Self::op_and_r(&mut ctx.r.a, ctx.r.l, &mut ctx.r.f) }),
-            (0x00E6_u16, Instruction { dissassembly: "AND d8",      bytes: 2, closure: |ctx| Self::op_and_d8(ctx.bus, &mut ctx.r.a, &mut ctx.r.pc, &mut ctx.r.f) }),
-            (0x00A6_u16, Instruction { dissassembly: "AND (HL)",    bytes: 1, closure: |ctx| Self::op_and_addr(ctx.bus, &mut ctx.r.a, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) }),
-            (0x00B7_u16, Instruction { dissassembly: "OR A",        bytes: 1, closure: |ctx| { let v = ctx.r.a; Self::op_or_r(&mut ctx.r.a, v, &mut ctx.r.f) } }),
-            (0x00B0_u16, Instruction { dissassembly: "OR B",        bytes: 1, closure: |ctx| Self::op_or_r(&mut ctx.r.a, ctx.r.b, &mut ctx.r.f) }),
-            (0x00B1_u16, Instruction { dissassembly: "OR C",        bytes: 1, closure: |ctx| Self::op_or_r(&mut ctx.r.a, ctx.r.c, &mut ctx.r.f) }),
-            (0x00B2_u16, Instruction { dissassembly: "OR D",        bytes: 1, closure: |ctx| Self::op_or_r(&mut ctx.r.a, ctx.r.d, &mut ctx.r.f) }),
-            (0x00B3_u16, Instruction { dissassembly: "OR E",        bytes: 1, closure: |ctx| Self::op_or_r(&mut ctx.r.a, ctx.r.e, &mut ctx.r.f) }),
-            (0x00B4_u16, Instruction { dissassembly: "OR H",        bytes: 1, closure: |ctx| Self::op_or_r(&mut ctx.r.a, ctx.r.h, &mut ctx.r.f) }),
-            (0x00B5_u16, Instruction { dissassembly: "OR L",        bytes: 1, closure: |ctx| Self::op_or_r(&mut ctx.r.a, ctx.r.l, &mut ctx.r.f) }),
-            (0x00F6_u16, Instruction { dissassembly: "OR d8",       bytes: 2, closure: |ctx| Self::op_or_d8(ctx.bus, &mut ctx.r.a, &mut ctx.r.pc, &mut ctx.r.f) }),
-            (0x00B6_u16, Instruction { dissassembly: "OR (HL)",     bytes: 1, closure: |ctx| Self::op_or_addr(ctx.bus, &mut ctx.r.a, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) }),
-            (0x00AF_u16, Instruction { dissassembly: "XOR A",       bytes: 1, closure: |ctx| { let v = ctx.r.a; Self::op_xor_r(&mut ctx.r.a, v, &mut ctx.r.f) } }),
-            (0x00A8_u16, Instruction { dissassembly: "XOR B",       bytes: 1, closure: |ctx| Self::op_xor_r(&mut ctx.r.a, ctx.r.b, &mut ctx.r.f) }),
-            (0x00A9_u16, Instruction { dissassembly: "XOR C",       bytes: 1, closure: |ctx| Self::op_xor_r(&mut ctx.r.a, ctx.r.c, &mut ctx.r.f) }),
-            (0x00AA_u16, Instruction { dissassembly: "XOR D",       bytes: 1, closure: |ctx| Self::op_xor_r(&mut ctx.r.a, ctx.r.d, &mut ctx.r.f) }),
-            (0x00AB_u16, Instruction { dissassembly: "XOR E",       bytes: 1, closure: |ctx| Self::op_xor_r(&mut ctx.r.a, ctx.r.e, &mut ctx.r.f) }),
-            (0x00AC_u16, Instruction { dissassembly: "XOR H",       bytes: 1, closure: |ctx| Self::op_xor_r(&mut ctx.r.a, ctx.r.h, &mut ctx.r.f) }),
-            (0x00AD_u16, Instruction { dissassembly: "XOR L",       bytes: 1, closure: |ctx| Self::op_xor_r(&mut ctx.r.a, ctx.r.l, &mut ctx.r.f) }),
-            (0x00EE_u16, Instruction { dissassembly: "XOR d8",      bytes: 2, closure: |ctx| Self::op_xor_d8(ctx.bus, &mut ctx.r.a, &mut ctx.r.pc, &mut ctx.r.f) }),
-            (0x00AE_u16, Instruction { dissassembly: "XOR (HL)",    bytes: 1, closure: |ctx| Self::op_xor_addr(ctx.bus, &mut ctx.r.a, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) }),
-            (0x002F_u16, Instruction { dissassembly: "CPL",         bytes: 1, closure: |ctx| Self::op_cpl(&mut ctx.r.a, &mut ctx.r.f) }),
-            (0x0017_u16, Instruction { dissassembly: "RLA",         bytes: 1, closure: |ctx| Self::op_rla(&mut ctx.r.a, &mut ctx.r.f) }), 
-            (0x001F_u16, Instruction { dissassembly: "RRA",         bytes: 1, closure: |ctx| Self::op_rra(&mut ctx.r.a, &mut ctx.r.f) }), 
-            (0x0007_u16, Instruction { dissassembly: "RLCA",        bytes: 1, closure: |ctx| Self::op_rlca(&mut ctx.r.a, &mut ctx.r.f) }),
-            (0x000F_u16, Instruction { dissassembly: "RRCA",        bytes: 1, closure: |ctx| Self::op_rrca(&mut ctx.r.a, &mut ctx.r.f) }),
+            (0x00A7_u16, Instruction { dissassembly: "AND A",       closure: |ctx| { let v = ctx.r.a; Self::op_and_r(&mut ctx.r.a, v, &mut ctx.r.f) } }),
+            (0x00A0_u16, Instruction { dissassembly: "AND B",       closure: |ctx| Self::op_and_r(&mut ctx.r.a, ctx.r.b, &mut ctx.r.f) }),
+            (0x00A1_u16, Instruction { dissassembly: "AND C",       closure: |ctx| Self::op_and_r(&mut ctx.r.a, ctx.r.c, &mut ctx.r.f) }),
+            (0x00A2_u16, Instruction { dissassembly: "AND D",       closure: |ctx| Self::op_and_r(&mut ctx.r.a, ctx.r.d, &mut ctx.r.f) }),
+            (0x00A3_u16, Instruction { dissassembly: "AND E",       closure: |ctx| Self::op_and_r(&mut ctx.r.a, ctx.r.e, &mut ctx.r.f) }),
+            (0x00A4_u16, Instruction { dissassembly: "AND H",       closure: |ctx| Self::op_and_r(&mut ctx.r.a, ctx.r.h, &mut ctx.r.f) }),
+            (0x00A5_u16, Instruction { dissassembly: "AND L",       closure: |ctx| Self::op_and_r(&mut ctx.r.a, ctx.r.l, &mut ctx.r.f) }),
+            (0x00E6_u16, Instruction { dissassembly: "AND d8",      closure: |ctx| Self::op_and_d8(ctx.bus, &mut ctx.r.a, &mut ctx.r.pc, &mut ctx.r.f) }),
+            (0x00A6_u16, Instruction { dissassembly: "AND (HL)",    closure: |ctx| Self::op_and_addr(ctx.bus, &mut ctx.r.a, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) }),
+            (0x00B7_u16, Instruction { dissassembly: "OR A",        closure: |ctx| { let v = ctx.r.a; Self::op_or_r(&mut ctx.r.a, v, &mut ctx.r.f) } }),
+            (0x00B0_u16, Instruction { dissassembly: "OR B",        closure: |ctx| Self::op_or_r(&mut ctx.r.a, ctx.r.b, &mut ctx.r.f) }),
+            (0x00B1_u16, Instruction { dissassembly: "OR C",        closure: |ctx| Self::op_or_r(&mut ctx.r.a, ctx.r.c, &mut ctx.r.f) }),
+            (0x00B2_u16, Instruction { dissassembly: "OR D",        closure: |ctx| Self::op_or_r(&mut ctx.r.a, ctx.r.d, &mut ctx.r.f) }),
+            (0x00B3_u16, Instruction { dissassembly: "OR E",        closure: |ctx| Self::op_or_r(&mut ctx.r.a, ctx.r.e, &mut ctx.r.f) }),
+            (0x00B4_u16, Instruction { dissassembly: "OR H",        closure: |ctx| Self::op_or_r(&mut ctx.r.a, ctx.r.h, &mut ctx.r.f) }),
+            (0x00B5_u16, Instruction { dissassembly: "OR L",        closure: |ctx| Self::op_or_r(&mut ctx.r.a, ctx.r.l, &mut ctx.r.f) }),
+            (0x00F6_u16, Instruction { dissassembly: "OR d8",       closure: |ctx| Self::op_or_d8(ctx.bus, &mut ctx.r.a, &mut ctx.r.pc, &mut ctx.r.f) }),
+            (0x00B6_u16, Instruction { dissassembly: "OR (HL)",     closure: |ctx| Self::op_or_addr(ctx.bus, &mut ctx.r.a, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) }),
+            (0x00AF_u16, Instruction { dissassembly: "XOR A",       closure: |ctx| { let v = ctx.r.a; Self::op_xor_r(&mut ctx.r.a, v, &mut ctx.r.f) } }),
+            (0x00A8_u16, Instruction { dissassembly: "XOR B",       closure: |ctx| Self::op_xor_r(&mut ctx.r.a, ctx.r.b, &mut ctx.r.f) }),
+            (0x00A9_u16, Instruction { dissassembly: "XOR C",       closure: |ctx| Self::op_xor_r(&mut ctx.r.a, ctx.r.c, &mut ctx.r.f) }),
+            (0x00AA_u16, Instruction { dissassembly: "XOR D",       closure: |ctx| Self::op_xor_r(&mut ctx.r.a, ctx.r.d, &mut ctx.r.f) }),
+            (0x00AB_u16, Instruction { dissassembly: "XOR E",       closure: |ctx| Self::op_xor_r(&mut ctx.r.a, ctx.r.e, &mut ctx.r.f) }),
+            (0x00AC_u16, Instruction { dissassembly: "XOR H",       closure: |ctx| Self::op_xor_r(&mut ctx.r.a, ctx.r.h, &mut ctx.r.f) }),
+            (0x00AD_u16, Instruction { dissassembly: "XOR L",       closure: |ctx| Self::op_xor_r(&mut ctx.r.a, ctx.r.l, &mut ctx.r.f) }),
+            (0x00EE_u16, Instruction { dissassembly: "XOR d8",      closure: |ctx| Self::op_xor_d8(ctx.bus, &mut ctx.r.a, &mut ctx.r.pc, &mut ctx.r.f) }),
+            (0x00AE_u16, Instruction { dissassembly: "XOR (HL)",    closure: |ctx| Self::op_xor_addr(ctx.bus, &mut ctx.r.a, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) }),
+            (0x002F_u16, Instruction { dissassembly: "CPL",         closure: |ctx| Self::op_cpl(&mut ctx.r.a, &mut ctx.r.f) }),
+            (0x0017_u16, Instruction { dissassembly: "RLA",         closure: |ctx| Self::op_rla(&mut ctx.r.a, &mut ctx.r.f) }), 
+            (0x001F_u16, Instruction { dissassembly: "RRA",         closure: |ctx| Self::op_rra(&mut ctx.r.a, &mut ctx.r.f) }), 
+            (0x0007_u16, Instruction { dissassembly: "RLCA",        closure: |ctx| Self::op_rlca(&mut ctx.r.a, &mut ctx.r.f) }),
+            (0x000F_u16, Instruction { dissassembly: "RRCA",        closure: |ctx| Self::op_rrca(&mut ctx.r.a, &mut ctx.r.f) }),
             // FLOW CONTROL
-            (0x00E9_u16, Instruction { dissassembly: "JP HL",       bytes: 1, closure: |ctx| Self::op_jp_v16(&mut ctx.r.pc, to_u16(ctx.r.h, ctx.r.l)) }),
-            (0x00C3_u16, Instruction { dissassembly: "JP a16",      bytes: 3, closure: |ctx| Self::op_jp_a16(ctx.bus, &mut ctx.r.pc, true) }),
-            (0x00C2_u16, Instruction { dissassembly: "JP NZ,a16",   bytes: 3, closure: |ctx| Self::op_jp_a16(ctx.bus, &mut ctx.r.pc, !get_flag2(ctx.r.f, FLAG_Z)) }),
-            (0x00CA_u16, Instruction { dissassembly: "JP Z,a16",    bytes: 3, closure: |ctx| Self::op_jp_a16(ctx.bus, &mut ctx.r.pc, get_flag2(ctx.r.f, FLAG_Z)) }),
-            (0x00D2_u16, Instruction { dissassembly: "JP NC,a16",   bytes: 3, closure: |ctx| Self::op_jp_a16(ctx.bus, &mut ctx.r.pc, !get_flag2(ctx.r.f, FLAG_C)) }),
-            (0x00DA_u16, Instruction { dissassembly: "JP C,a16",    bytes: 3, closure: |ctx| Self::op_jp_a16(ctx.bus, &mut ctx.r.pc, get_flag2(ctx.r.f, FLAG_C)) }),
-            (0x0018_u16, Instruction { dissassembly: "JR s8",       bytes: 2, closure: |ctx| Self::op_jr_s8(ctx.bus, &mut ctx.r.pc, true) }),
-            (0x0020_u16, Instruction { dissassembly: "JR NZ,s8",    bytes: 2, closure: |ctx| Self::op_jr_s8(ctx.bus, &mut ctx.r.pc, !get_flag2(ctx.r.f, FLAG_Z)) }),
-            (0x0028_u16, Instruction { dissassembly: "JR Z,s8",     bytes: 2, closure: |ctx| Self::op_jr_s8(ctx.bus, &mut ctx.r.pc, get_flag2(ctx.r.f, FLAG_Z)) }),
-            (0x0030_u16, Instruction { dissassembly: "JR NC,s8",    bytes: 2, closure: |ctx| Self::op_jr_s8(ctx.bus, &mut ctx.r.pc, !get_flag2(ctx.r.f, FLAG_C)) }),
-            (0x0038_u16, Instruction { dissassembly: "JR C,s8",     bytes: 2, closure: |ctx| Self::op_jr_s8(ctx.bus, &mut ctx.r.pc, get_flag2(ctx.r.f, FLAG_C)) }),
-            (0x00CD_u16, Instruction { dissassembly: "CALL a16",    bytes: 3, closure: |ctx| Self::op_call_a16(ctx.bus, &mut ctx.r.pc, &mut ctx.r.sp, true) }),
-            (0x00C4_u16, Instruction { dissassembly: "CALL NZ,a16", bytes: 3, closure: |ctx| Self::op_call_a16(ctx.bus, &mut ctx.r.pc, &mut ctx.r.sp, !get_flag2(ctx.r.f, FLAG_Z)) }),
-            (0x00CC_u16, Instruction { dissassembly: "CALL Z,a16",  bytes: 3, closure: |ctx| Self::op_call_a16(ctx.bus, &mut ctx.r.pc, &mut ctx.r.sp, get_flag2(ctx.r.f, FLAG_Z)) }),
-            (0x00D4_u16, Instruction { dissassembly: "CALL NC,a16", bytes: 3, closure: |ctx| Self::op_call_a16(ctx.bus, &mut ctx.r.pc, &mut ctx.r.sp, !get_flag2(ctx.r.f, FLAG_C)) }),
-            (0x00DC_u16, Instruction { dissassembly: "CALL C,a16",  bytes: 3, closure: |ctx| Self::op_call_a16(ctx.bus, &mut ctx.r.pc, &mut ctx.r.sp, get_flag2(ctx.r.f, FLAG_C)) }),
-            (0x00C9_u16, Instruction { dissassembly: "RET",         bytes: 1, closure: |ctx| Self::op_ret(ctx.bus, &mut ctx.r.pc, &mut ctx.r.sp, true) }),
-            (0x00C0_u16, Instruction { dissassembly: "RET NZ",      bytes: 1, closure: |ctx| Self::op_ret(ctx.bus, &mut ctx.r.pc, &mut ctx.r.sp, !get_flag2(ctx.r.f, FLAG_Z)) }),
-            (0x00C8_u16, Instruction { dissassembly: "RET Z",       bytes: 1, closure: |ctx| Self::op_ret(ctx.bus, &mut ctx.r.pc, &mut ctx.r.sp, get_flag2(ctx.r.f, FLAG_Z)) }),
-            (0x00D0_u16, Instruction { dissassembly: "RET NC",      bytes: 1, closure: |ctx| Self::op_ret(ctx.bus, &mut ctx.r.pc, &mut ctx.r.sp, !get_flag2(ctx.r.f, FLAG_C)) }),
-            (0x00D8_u16, Instruction { dissassembly: "RET C",       bytes: 1, closure: |ctx| Self::op_ret(ctx.bus, &mut ctx.r.pc, &mut ctx.r.sp, get_flag2(ctx.r.f, FLAG_C)) }),
-            (0x00D9_u16, Instruction { dissassembly: "RETI",        bytes: 1, closure: |ctx| Self::op_reti(ctx.bus, &mut ctx.r.pc, &mut ctx.r.sp) }),
-            (0x00F5_u16, Instruction { dissassembly: "PUSH AF",     bytes: 1, closure: |ctx| Self::op_push_r16(ctx.bus, &mut ctx.r.sp, ctx.r.a, ctx.r.f) }),
-            (0x00C5_u16, Instruction { dissassembly: "PUSH BC",     bytes: 1, closure: |ctx| Self::op_push_r16(ctx.bus, &mut ctx.r.sp, ctx.r.b, ctx.r.c) }),
-            (0x00D5_u16, Instruction { dissassembly: "PUSH DE",     bytes: 1, closure: |ctx| Self::op_push_r16(ctx.bus, &mut ctx.r.sp, ctx.r.d, ctx.r.e) }),
-            (0x00E5_u16, Instruction { dissassembly: "PUSH HL",     bytes: 1, closure: |ctx| Self::op_push_r16(ctx.bus, &mut ctx.r.sp, ctx.r.h, ctx.r.l) }),
-            (0x00F1_u16, Instruction { dissassembly: "POP AF",      bytes: 1, closure: |ctx| Self::op_pop_af(ctx.bus, &mut ctx.r.sp, &mut ctx.r.a, &mut ctx.r.f) }),
-            (0x00C1_u16, Instruction { dissassembly: "POP BC",      bytes: 1, closure: |ctx| Self::op_pop_r16(ctx.bus, &mut ctx.r.sp, &mut ctx.r.b, &mut ctx.r.c) }),
-            (0x00D1_u16, Instruction { dissassembly: "POP DE",      bytes: 1, closure: |ctx| Self::op_pop_r16(ctx.bus, &mut ctx.r.sp, &mut ctx.r.d, &mut ctx.r.e) }),
-            (0x00E1_u16, Instruction { dissassembly: "POP HL",      bytes: 1, closure: |ctx| Self::op_pop_r16(ctx.bus, &mut ctx.r.sp, &mut ctx.r.h, &mut ctx.r.l) }),
-            (0x00C7_u16, Instruction { dissassembly: "RST 0",       bytes: 1, closure: |ctx| Self::op_rst_n(ctx.bus, 0, &mut ctx.r.pc, &mut ctx.r.sp) }),
-            (0x00CF_u16, Instruction { dissassembly: "RST 1",       bytes: 1, closure: |ctx| Self::op_rst_n(ctx.bus, 1, &mut ctx.r.pc, &mut ctx.r.sp) }),
-            (0x00D7_u16, Instruction { dissassembly: "RST 2",       bytes: 1, closure: |ctx| Self::op_rst_n(ctx.bus, 2, &mut ctx.r.pc, &mut ctx.r.sp) }),
-            (0x00DF_u16, Instruction { dissassembly: "RST 3",       bytes: 1, closure: |ctx| Self::op_rst_n(ctx.bus, 3, &mut ctx.r.pc, &mut ctx.r.sp) }),
-            (0x00E7_u16, Instruction { dissassembly: "RST 4",       bytes: 1, closure: |ctx| Self::op_rst_n(ctx.bus, 4, &mut ctx.r.pc, &mut ctx.r.sp) }),
-            (0x00EF_u16, Instruction { dissassembly: "RST 5",       bytes: 1, closure: |ctx| Self::op_rst_n(ctx.bus, 5, &mut ctx.r.pc, &mut ctx.r.sp) }),
-            (0x00F7_u16, Instruction { dissassembly: "RST 6",       bytes: 1, closure: |ctx| Self::op_rst_n(ctx.bus, 6, &mut ctx.r.pc, &mut ctx.r.sp) }),
-            (0x00FF_u16, Instruction { dissassembly: "RST 7",       bytes: 1, closure: |ctx| Self::op_rst_n(ctx.bus, 7, &mut ctx.r.pc, &mut ctx.r.sp) }),
-            (0x00F3_u16, Instruction { dissassembly: "DI",          bytes: 1, closure: |ctx| Self::op_di(&mut ctx.bus.interrupts) }),
-            (0x00FB_u16, Instruction { dissassembly: "EI",          bytes: 1, closure: |ctx| Self::op_ei(&mut ctx.bus.interrupts) }),
+            (0x00E9_u16, Instruction { dissassembly: "JP HL",       closure: |ctx| Self::op_jp_v16(&mut ctx.r.pc, to_u16(ctx.r.h, ctx.r.l)) }),
+            (0x00C3_u16, Instruction { dissassembly: "JP a16",      closure: |ctx| Self::op_jp_a16(ctx.bus, &mut ctx.r.pc, true) }),
+            (0x00C2_u16, Instruction { dissassembly: "JP NZ,a16",   closure: |ctx| Self::op_jp_a16(ctx.bus, &mut ctx.r.pc, !get_flag2(ctx.r.f, FLAG_Z)) }),
+            (0x00CA_u16, Instruction { dissassembly: "JP Z,a16",    closure: |ctx| Self::op_jp_a16(ctx.bus, &mut ctx.r.pc, get_flag2(ctx.r.f, FLAG_Z)) }),
+            (0x00D2_u16, Instruction { dissassembly: "JP NC,a16",   closure: |ctx| Self::op_jp_a16(ctx.bus, &mut ctx.r.pc, !get_flag2(ctx.r.f, FLAG_C)) }),
+            (0x00DA_u16, Instruction { dissassembly: "JP C,a16",    closure: |ctx| Self::op_jp_a16(ctx.bus, &mut ctx.r.pc, get_flag2(ctx.r.f, FLAG_C)) }),
+            (0x0018_u16, Instruction { dissassembly: "JR s8",       closure: |ctx| Self::op_jr_s8(ctx.bus, &mut ctx.r.pc, true) }),
+            (0x0020_u16, Instruction { dissassembly: "JR NZ,s8",    closure: |ctx| Self::op_jr_s8(ctx.bus, &mut ctx.r.pc, !get_flag2(ctx.r.f, FLAG_Z)) }),
+            (0x0028_u16, Instruction { dissassembly: "JR Z,s8",     closure: |ctx| Self::op_jr_s8(ctx.bus, &mut ctx.r.pc, get_flag2(ctx.r.f, FLAG_Z)) }),
+            (0x0030_u16, Instruction { dissassembly: "JR NC,s8",    closure: |ctx| Self::op_jr_s8(ctx.bus, &mut ctx.r.pc, !get_flag2(ctx.r.f, FLAG_C)) }),
+            (0x0038_u16, Instruction { dissassembly: "JR C,s8",     closure: |ctx| Self::op_jr_s8(ctx.bus, &mut ctx.r.pc, get_flag2(ctx.r.f, FLAG_C)) }),
+            (0x00CD_u16, Instruction { dissassembly: "CALL a16",    closure: |ctx| Self::op_call_a16(ctx.bus, &mut ctx.r.pc, &mut ctx.r.sp, true) }),
+            (0x00C4_u16, Instruction { dissassembly: "CALL NZ,a16", closure: |ctx| Self::op_call_a16(ctx.bus, &mut ctx.r.pc, &mut ctx.r.sp, !get_flag2(ctx.r.f, FLAG_Z)) }),
+            (0x00CC_u16, Instruction { dissassembly: "CALL Z,a16",  closure: |ctx| Self::op_call_a16(ctx.bus, &mut ctx.r.pc, &mut ctx.r.sp, get_flag2(ctx.r.f, FLAG_Z)) }),
+            (0x00D4_u16, Instruction { dissassembly: "CALL NC,a16", closure: |ctx| Self::op_call_a16(ctx.bus, &mut ctx.r.pc, &mut ctx.r.sp, !get_flag2(ctx.r.f, FLAG_C)) }),
+            (0x00DC_u16, Instruction { dissassembly: "CALL C,a16",  closure: |ctx| Self::op_call_a16(ctx.bus, &mut ctx.r.pc, &mut ctx.r.sp, get_flag2(ctx.r.f, FLAG_C)) }),
+            (0x00C9_u16, Instruction { dissassembly: "RET",         closure: |ctx| Self::op_ret(ctx.bus, &mut ctx.r.pc, &mut ctx.r.sp, true) }),
+            (0x00C0_u16, Instruction { dissassembly: "RET NZ",      closure: |ctx| Self::op_ret(ctx.bus, &mut ctx.r.pc, &mut ctx.r.sp, !get_flag2(ctx.r.f, FLAG_Z)) }),
+            (0x00C8_u16, Instruction { dissassembly: "RET Z",       closure: |ctx| Self::op_ret(ctx.bus, &mut ctx.r.pc, &mut ctx.r.sp, get_flag2(ctx.r.f, FLAG_Z)) }),
+            (0x00D0_u16, Instruction { dissassembly: "RET NC",      closure: |ctx| Self::op_ret(ctx.bus, &mut ctx.r.pc, &mut ctx.r.sp, !get_flag2(ctx.r.f, FLAG_C)) }),
+            (0x00D8_u16, Instruction { dissassembly: "RET C",       closure: |ctx| Self::op_ret(ctx.bus, &mut ctx.r.pc, &mut ctx.r.sp, get_flag2(ctx.r.f, FLAG_C)) }),
+            (0x00D9_u16, Instruction { dissassembly: "RETI",        closure: |ctx| Self::op_reti(ctx.bus, &mut ctx.r.pc, &mut ctx.r.sp) }),
+            (0x00F5_u16, Instruction { dissassembly: "PUSH AF",     closure: |ctx| Self::op_push_r16(ctx.bus, &mut ctx.r.sp, ctx.r.a, ctx.r.f) }),
+            (0x00C5_u16, Instruction { dissassembly: "PUSH BC",     closure: |ctx| Self::op_push_r16(ctx.bus, &mut ctx.r.sp, ctx.r.b, ctx.r.c) }),
+            (0x00D5_u16, Instruction { dissassembly: "PUSH DE",     closure: |ctx| Self::op_push_r16(ctx.bus, &mut ctx.r.sp, ctx.r.d, ctx.r.e) }),
+            (0x00E5_u16, Instruction { dissassembly: "PUSH HL",     closure: |ctx| Self::op_push_r16(ctx.bus, &mut ctx.r.sp, ctx.r.h, ctx.r.l) }),
+            (0x00F1_u16, Instruction { dissassembly: "POP AF",      closure: |ctx| Self::op_pop_af(ctx.bus, &mut ctx.r.sp, &mut ctx.r.a, &mut ctx.r.f) }),
+            (0x00C1_u16, Instruction { dissassembly: "POP BC",      closure: |ctx| Self::op_pop_r16(ctx.bus, &mut ctx.r.sp, &mut ctx.r.b, &mut ctx.r.c) }),
+            (0x00D1_u16, Instruction { dissassembly: "POP DE",      closure: |ctx| Self::op_pop_r16(ctx.bus, &mut ctx.r.sp, &mut ctx.r.d, &mut ctx.r.e) }),
+            (0x00E1_u16, Instruction { dissassembly: "POP HL",      closure: |ctx| Self::op_pop_r16(ctx.bus, &mut ctx.r.sp, &mut ctx.r.h, &mut ctx.r.l) }),
+            (0x00C7_u16, Instruction { dissassembly: "RST 0",       closure: |ctx| Self::op_rst_n(ctx.bus, 0, &mut ctx.r.pc, &mut ctx.r.sp) }),
+            (0x00CF_u16, Instruction { dissassembly: "RST 1",       closure: |ctx| Self::op_rst_n(ctx.bus, 1, &mut ctx.r.pc, &mut ctx.r.sp) }),
+            (0x00D7_u16, Instruction { dissassembly: "RST 2",       closure: |ctx| Self::op_rst_n(ctx.bus, 2, &mut ctx.r.pc, &mut ctx.r.sp) }),
+            (0x00DF_u16, Instruction { dissassembly: "RST 3",       closure: |ctx| Self::op_rst_n(ctx.bus, 3, &mut ctx.r.pc, &mut ctx.r.sp) }),
+            (0x00E7_u16, Instruction { dissassembly: "RST 4",       closure: |ctx| Self::op_rst_n(ctx.bus, 4, &mut ctx.r.pc, &mut ctx.r.sp) }),
+            (0x00EF_u16, Instruction { dissassembly: "RST 5",       closure: |ctx| Self::op_rst_n(ctx.bus, 5, &mut ctx.r.pc, &mut ctx.r.sp) }),
+            (0x00F7_u16, Instruction { dissassembly: "RST 6",       closure: |ctx| Self::op_rst_n(ctx.bus, 6, &mut ctx.r.pc, &mut ctx.r.sp) }),
+            (0x00FF_u16, Instruction { dissassembly: "RST 7",       closure: |ctx| Self::op_rst_n(ctx.bus, 7, &mut ctx.r.pc, &mut ctx.r.sp) }),
+            (0x00F3_u16, Instruction { dissassembly: "DI",          closure: |ctx| Self::op_di(ctx.bus.interrupts) }),
+            (0x00FB_u16, Instruction { dissassembly: "EI",          closure: |ctx| Self::op_ei(ctx.bus.interrupts) }),
             
             // 16 bit opcodes
-            (0xCB07_u16, Instruction { dissassembly: "RLC A",       bytes: 2, closure: |ctx| Self::op_rlc_r(&mut ctx.r.a, &mut ctx.r.f) }),
-            (0xCB00_u16, Instruction { dissassembly: "RLC B",       bytes: 2, closure: |ctx| Self::op_rlc_r(&mut ctx.r.b, &mut ctx.r.f) }),
-            (0xCB01_u16, Instruction { dissassembly: "RLC C",       bytes: 2, closure: |ctx| Self::op_rlc_r(&mut ctx.r.c, &mut ctx.r.f) }),
-            (0xCB02_u16, Instruction { dissassembly: "RLC D",       bytes: 2, closure: |ctx| Self::op_rlc_r(&mut ctx.r.d, &mut ctx.r.f) }),
-            (0xCB03_u16, Instruction { dissassembly: "RLC E",       bytes: 2, closure: |ctx| Self::op_rlc_r(&mut ctx.r.e, &mut ctx.r.f) }),
-            (0xCB04_u16, Instruction { dissassembly: "RLC H",       bytes: 2, closure: |ctx| Self::op_rlc_r(&mut ctx.r.h, &mut ctx.r.f) }),
-            (0xCB05_u16, Instruction { dissassembly: "RLC L",       bytes: 2, closure: |ctx| Self::op_rlc_r(&mut ctx.r.l, &mut ctx.r.f) }),
-            (0xCB06_u16, Instruction { dissassembly: "RLC (HL)",    bytes: 2, closure: |ctx| Self::op_rlc_addr(ctx.bus, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) }),
-            (0xCB0F_u16, Instruction { dissassembly: "RRC A",       bytes: 2, closure: |ctx| Self::op_rrc_r(&mut ctx.r.a, &mut ctx.r.f) }),
-            (0xCB08_u16, Instruction { dissassembly: "RRC B",       bytes: 2, closure: |ctx| Self::op_rrc_r(&mut ctx.r.b, &mut ctx.r.f) }),
-            (0xCB09_u16, Instruction { dissassembly: "RRC C",       bytes: 2, closure: |ctx| Self::op_rrc_r(&mut ctx.r.c, &mut ctx.r.f) }),
-            (0xCB0A_u16, Instruction { dissassembly: "RRC D",       bytes: 2, closure: |ctx| Self::op_rrc_r(&mut ctx.r.d, &mut ctx.r.f) }),
-            (0xCB0B_u16, Instruction { dissassembly: "RRC E",       bytes: 2, closure: |ctx| Self::op_rrc_r(&mut ctx.r.e, &mut ctx.r.f) }),
-            (0xCB0C_u16, Instruction { dissassembly: "RRC H",       bytes: 2, closure: |ctx| Self::op_rrc_r(&mut ctx.r.h, &mut ctx.r.f) }),
-            (0xCB0D_u16, Instruction { dissassembly: "RRC L",       bytes: 2, closure: |ctx| Self::op_rrc_r(&mut ctx.r.l, &mut ctx.r.f) }),
-            (0xCB0E_u16, Instruction { dissassembly: "RRC (HL)",    bytes: 2, closure: |ctx| Self::op_rrc_addr(ctx.bus, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) }),
-            (0xCB17_u16, Instruction { dissassembly: "RL A",        bytes: 2, closure: |ctx| Self::op_rl_r(&mut ctx.r.a, &mut ctx.r.f) }),
-            (0xCB10_u16, Instruction { dissassembly: "RL B",        bytes: 2, closure: |ctx| Self::op_rl_r(&mut ctx.r.b, &mut ctx.r.f) }),
-            (0xCB11_u16, Instruction { dissassembly: "RL C",        bytes: 2, closure: |ctx| Self::op_rl_r(&mut ctx.r.c, &mut ctx.r.f) }),
-            (0xCB12_u16, Instruction { dissassembly: "RL D",        bytes: 2, closure: |ctx| Self::op_rl_r(&mut ctx.r.d, &mut ctx.r.f) }),
-            (0xCB13_u16, Instruction { dissassembly: "RL E",        bytes: 2, closure: |ctx| Self::op_rl_r(&mut ctx.r.e, &mut ctx.r.f) }),
-            (0xCB14_u16, Instruction { dissassembly: "RL H",        bytes: 2, closure: |ctx| Self::op_rl_r(&mut ctx.r.h, &mut ctx.r.f) }),
-            (0xCB15_u16, Instruction { dissassembly: "RL L",        bytes: 2, closure: |ctx| Self::op_rl_r(&mut ctx.r.l, &mut ctx.r.f) }),
-            (0xCB16_u16, Instruction { dissassembly: "RL (HL)",     bytes: 2, closure: |ctx| Self::op_rl_addr(ctx.bus, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) }),
-            (0xCB1F_u16, Instruction { dissassembly: "RR A",        bytes: 2, closure: |ctx| Self::op_rr_r(&mut ctx.r.a, &mut ctx.r.f) }),
-            (0xCB18_u16, Instruction { dissassembly: "RR B",        bytes: 2, closure: |ctx| Self::op_rr_r(&mut ctx.r.b, &mut ctx.r.f) }),
-            (0xCB19_u16, Instruction { dissassembly: "RR C",        bytes: 2, closure: |ctx| Self::op_rr_r(&mut ctx.r.c, &mut ctx.r.f) }),
-            (0xCB1A_u16, Instruction { dissassembly: "RR D",        bytes: 2, closure: |ctx| Self::op_rr_r(&mut ctx.r.d, &mut ctx.r.f) }),
-            (0xCB1B_u16, Instruction { dissassembly: "RR E",        bytes: 2, closure: |ctx| Self::op_rr_r(&mut ctx.r.e, &mut ctx.r.f) }),
-            (0xCB1C_u16, Instruction { dissassembly: "RR H",        bytes: 2, closure: |ctx| Self::op_rr_r(&mut ctx.r.h, &mut ctx.r.f) }),
-            (0xCB1D_u16, Instruction { dissassembly: "RR L",        bytes: 2, closure: |ctx| Self::op_rr_r(&mut ctx.r.l, &mut ctx.r.f) }),
-            (0xCB1E_u16, Instruction { dissassembly: "RR (HL)",     bytes: 2, closure: |ctx| Self::op_rr_addr(ctx.bus, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) }),
-            (0xCB27_u16, Instruction { dissassembly: "SLA A",       bytes: 2, closure: |ctx| Self::op_sla_r(&mut ctx.r.a, &mut ctx.r.f) }),
-            (0xCB20_u16, Instruction { dissassembly: "SLA B",       bytes: 2, closure: |ctx| Self::op_sla_r(&mut ctx.r.b, &mut ctx.r.f) }),
-            (0xCB21_u16, Instruction { dissassembly: "SLA C",       bytes: 2, closure: |ctx| Self::op_sla_r(&mut ctx.r.c, &mut ctx.r.f) }),
-            (0xCB22_u16, Instruction { dissassembly: "SLA D",       bytes: 2, closure: |ctx| Self::op_sla_r(&mut ctx.r.d, &mut ctx.r.f) }),
-            (0xCB23_u16, Instruction { dissassembly: "SLA E",       bytes: 2, closure: |ctx| Self::op_sla_r(&mut ctx.r.e, &mut ctx.r.f) }),
-            (0xCB24_u16, Instruction { dissassembly: "SLA H",       bytes: 2, closure: |ctx| Self::op_sla_r(&mut ctx.r.h, &mut ctx.r.f) }),
-            (0xCB25_u16, Instruction { dissassembly: "SLA L",       bytes: 2, closure: |ctx| Self::op_sla_r(&mut ctx.r.l, &mut ctx.r.f) }),
-            (0xCB26_u16, Instruction { dissassembly: "SLA (HL)",    bytes: 2, closure: |ctx| Self::op_sla_addr(ctx.bus, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) }),
-            (0xCB3F_u16, Instruction { dissassembly: "SRL A",       bytes: 2, closure: |ctx| Self::op_srl_r(&mut ctx.r.a, &mut ctx.r.f) }),
-            (0xCB38_u16, Instruction { dissassembly: "SRL B",       bytes: 2, closure: |ctx| Self::op_srl_r(&mut ctx.r.b, &mut ctx.r.f) }),
-            (0xCB39_u16, Instruction { dissassembly: "SRL C",       bytes: 2, closure: |ctx| Self::op_srl_r(&mut ctx.r.c, &mut ctx.r.f) }),
-            (0xCB3A_u16, Instruction { dissassembly: "SRL D",       bytes: 2, closure: |ctx| Self::op_srl_r(&mut ctx.r.d, &mut ctx.r.f) }),
-            (0xCB3B_u16, Instruction { dissassembly: "SRL E",       bytes: 2, closure: |ctx| Self::op_srl_r(&mut ctx.r.e, &mut ctx.r.f) }),
-            (0xCB3C_u16, Instruction { dissassembly: "SRL H",       bytes: 2, closure: |ctx| Self::op_srl_r(&mut ctx.r.h, &mut ctx.r.f) }),
-            (0xCB3D_u16, Instruction { dissassembly: "SRL L",       bytes: 2, closure: |ctx| Self::op_srl_r(&mut ctx.r.l, &mut ctx.r.f) }),
-            (0xCB3E_u16, Instruction { dissassembly: "SRL (HL)",    bytes: 2, closure: |ctx| Self::op_srl_addr(ctx.bus, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) }),
-            (0xCB2F_u16, Instruction { dissassembly: "SRA A",       bytes: 2, closure: |ctx| Self::op_sra_r(&mut ctx.r.a, &mut ctx.r.f) }),
-            (0xCB28_u16, Instruction { dissassembly: "SRA B",       bytes: 2, closure: |ctx| Self::op_sra_r(&mut ctx.r.b, &mut ctx.r.f) }),
-            (0xCB29_u16, Instruction { dissassembly: "SRA C",       bytes: 2, closure: |ctx| Self::op_sra_r(&mut ctx.r.c, &mut ctx.r.f) }),
-            (0xCB2A_u16, Instruction { dissassembly: "SRA D",       bytes: 2, closure: |ctx| Self::op_sra_r(&mut ctx.r.d, &mut ctx.r.f) }),
-            (0xCB2B_u16, Instruction { dissassembly: "SRA E",       bytes: 2, closure: |ctx| Self::op_sra_r(&mut ctx.r.e, &mut ctx.r.f) }),
-            (0xCB2C_u16, Instruction { dissassembly: "SRA H",       bytes: 2, closure: |ctx| Self::op_sra_r(&mut ctx.r.h, &mut ctx.r.f) }),
-            (0xCB2D_u16, Instruction { dissassembly: "SRA L",       bytes: 2, closure: |ctx| Self::op_sra_r(&mut ctx.r.l, &mut ctx.r.f) }),
-            (0xCB2E_u16, Instruction { dissassembly: "SRA (HL)",    bytes: 2, closure: |ctx| Self::op_sra_addr(ctx.bus, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) }),
+            (0xCB07_u16, Instruction { dissassembly: "RLC A",       closure: |ctx| Self::op_rlc_r(&mut ctx.r.a, &mut ctx.r.f) }),
+            (0xCB00_u16, Instruction { dissassembly: "RLC B",       closure: |ctx| Self::op_rlc_r(&mut ctx.r.b, &mut ctx.r.f) }),
+            (0xCB01_u16, Instruction { dissassembly: "RLC C",       closure: |ctx| Self::op_rlc_r(&mut ctx.r.c, &mut ctx.r.f) }),
+            (0xCB02_u16, Instruction { dissassembly: "RLC D",       closure: |ctx| Self::op_rlc_r(&mut ctx.r.d, &mut ctx.r.f) }),
+            (0xCB03_u16, Instruction { dissassembly: "RLC E",       closure: |ctx| Self::op_rlc_r(&mut ctx.r.e, &mut ctx.r.f) }),
+            (0xCB04_u16, Instruction { dissassembly: "RLC H",       closure: |ctx| Self::op_rlc_r(&mut ctx.r.h, &mut ctx.r.f) }),
+            (0xCB05_u16, Instruction { dissassembly: "RLC L",       closure: |ctx| Self::op_rlc_r(&mut ctx.r.l, &mut ctx.r.f) }),
+            (0xCB06_u16, Instruction { dissassembly: "RLC (HL)",    closure: |ctx| Self::op_rlc_addr(ctx.bus, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) }),
+            (0xCB0F_u16, Instruction { dissassembly: "RRC A",       closure: |ctx| Self::op_rrc_r(&mut ctx.r.a, &mut ctx.r.f) }),
+            (0xCB08_u16, Instruction { dissassembly: "RRC B",       closure: |ctx| Self::op_rrc_r(&mut ctx.r.b, &mut ctx.r.f) }),
+            (0xCB09_u16, Instruction { dissassembly: "RRC C",       closure: |ctx| Self::op_rrc_r(&mut ctx.r.c, &mut ctx.r.f) }),
+            (0xCB0A_u16, Instruction { dissassembly: "RRC D",       closure: |ctx| Self::op_rrc_r(&mut ctx.r.d, &mut ctx.r.f) }),
+            (0xCB0B_u16, Instruction { dissassembly: "RRC E",       closure: |ctx| Self::op_rrc_r(&mut ctx.r.e, &mut ctx.r.f) }),
+            (0xCB0C_u16, Instruction { dissassembly: "RRC H",       closure: |ctx| Self::op_rrc_r(&mut ctx.r.h, &mut ctx.r.f) }),
+            (0xCB0D_u16, Instruction { dissassembly: "RRC L",       closure: |ctx| Self::op_rrc_r(&mut ctx.r.l, &mut ctx.r.f) }),
+            (0xCB0E_u16, Instruction { dissassembly: "RRC (HL)",    closure: |ctx| Self::op_rrc_addr(ctx.bus, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) }),
+            (0xCB17_u16, Instruction { dissassembly: "RL A",        closure: |ctx| Self::op_rl_r(&mut ctx.r.a, &mut ctx.r.f) }),
+            (0xCB10_u16, Instruction { dissassembly: "RL B",        closure: |ctx| Self::op_rl_r(&mut ctx.r.b, &mut ctx.r.f) }),
+            (0xCB11_u16, Instruction { dissassembly: "RL C",        closure: |ctx| Self::op_rl_r(&mut ctx.r.c, &mut ctx.r.f) }),
+            (0xCB12_u16, Instruction { dissassembly: "RL D",        closure: |ctx| Self::op_rl_r(&mut ctx.r.d, &mut ctx.r.f) }),
+            (0xCB13_u16, Instruction { dissassembly: "RL E",        closure: |ctx| Self::op_rl_r(&mut ctx.r.e, &mut ctx.r.f) }),
+            (0xCB14_u16, Instruction { dissassembly: "RL H",        closure: |ctx| Self::op_rl_r(&mut ctx.r.h, &mut ctx.r.f) }),
+            (0xCB15_u16, Instruction { dissassembly: "RL L",        closure: |ctx| Self::op_rl_r(&mut ctx.r.l, &mut ctx.r.f) }),
+            (0xCB16_u16, Instruction { dissassembly: "RL (HL)",     closure: |ctx| Self::op_rl_addr(ctx.bus, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) }),
+            (0xCB1F_u16, Instruction { dissassembly: "RR A",        closure: |ctx| Self::op_rr_r(&mut ctx.r.a, &mut ctx.r.f) }),
+            (0xCB18_u16, Instruction { dissassembly: "RR B",        closure: |ctx| Self::op_rr_r(&mut ctx.r.b, &mut ctx.r.f) }),
+            (0xCB19_u16, Instruction { dissassembly: "RR C",        closure: |ctx| Self::op_rr_r(&mut ctx.r.c, &mut ctx.r.f) }),
+            (0xCB1A_u16, Instruction { dissassembly: "RR D",        closure: |ctx| Self::op_rr_r(&mut ctx.r.d, &mut ctx.r.f) }),
+            (0xCB1B_u16, Instruction { dissassembly: "RR E",        closure: |ctx| Self::op_rr_r(&mut ctx.r.e, &mut ctx.r.f) }),
+            (0xCB1C_u16, Instruction { dissassembly: "RR H",        closure: |ctx| Self::op_rr_r(&mut ctx.r.h, &mut ctx.r.f) }),
+            (0xCB1D_u16, Instruction { dissassembly: "RR L",        closure: |ctx| Self::op_rr_r(&mut ctx.r.l, &mut ctx.r.f) }),
+            (0xCB1E_u16, Instruction { dissassembly: "RR (HL)",     closure: |ctx| Self::op_rr_addr(ctx.bus, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) }),
+            (0xCB27_u16, Instruction { dissassembly: "SLA A",       closure: |ctx| Self::op_sla_r(&mut ctx.r.a, &mut ctx.r.f) }),
+            (0xCB20_u16, Instruction { dissassembly: "SLA B",       closure: |ctx| Self::op_sla_r(&mut ctx.r.b, &mut ctx.r.f) }),
+            (0xCB21_u16, Instruction { dissassembly: "SLA C",       closure: |ctx| Self::op_sla_r(&mut ctx.r.c, &mut ctx.r.f) }),
+            (0xCB22_u16, Instruction { dissassembly: "SLA D",       closure: |ctx| Self::op_sla_r(&mut ctx.r.d, &mut ctx.r.f) }),
+            (0xCB23_u16, Instruction { dissassembly: "SLA E",       closure: |ctx| Self::op_sla_r(&mut ctx.r.e, &mut ctx.r.f) }),
+            (0xCB24_u16, Instruction { dissassembly: "SLA H",       closure: |ctx| Self::op_sla_r(&mut ctx.r.h, &mut ctx.r.f) }),
+            (0xCB25_u16, Instruction { dissassembly: "SLA L",       closure: |ctx| Self::op_sla_r(&mut ctx.r.l, &mut ctx.r.f) }),
+            (0xCB26_u16, Instruction { dissassembly: "SLA (HL)",    closure: |ctx| Self::op_sla_addr(ctx.bus, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) }),
+            (0xCB3F_u16, Instruction { dissassembly: "SRL A",       closure: |ctx| Self::op_srl_r(&mut ctx.r.a, &mut ctx.r.f) }),
+            (0xCB38_u16, Instruction { dissassembly: "SRL B",       closure: |ctx| Self::op_srl_r(&mut ctx.r.b, &mut ctx.r.f) }),
+            (0xCB39_u16, Instruction { dissassembly: "SRL C",       closure: |ctx| Self::op_srl_r(&mut ctx.r.c, &mut ctx.r.f) }),
+            (0xCB3A_u16, Instruction { dissassembly: "SRL D",       closure: |ctx| Self::op_srl_r(&mut ctx.r.d, &mut ctx.r.f) }),
+            (0xCB3B_u16, Instruction { dissassembly: "SRL E",       closure: |ctx| Self::op_srl_r(&mut ctx.r.e, &mut ctx.r.f) }),
+            (0xCB3C_u16, Instruction { dissassembly: "SRL H",       closure: |ctx| Self::op_srl_r(&mut ctx.r.h, &mut ctx.r.f) }),
+            (0xCB3D_u16, Instruction { dissassembly: "SRL L",       closure: |ctx| Self::op_srl_r(&mut ctx.r.l, &mut ctx.r.f) }),
+            (0xCB3E_u16, Instruction { dissassembly: "SRL (HL)",    closure: |ctx| Self::op_srl_addr(ctx.bus, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) }),
+            (0xCB2F_u16, Instruction { dissassembly: "SRA A",       closure: |ctx| Self::op_sra_r(&mut ctx.r.a, &mut ctx.r.f) }),
+            (0xCB28_u16, Instruction { dissassembly: "SRA B",       closure: |ctx| Self::op_sra_r(&mut ctx.r.b, &mut ctx.r.f) }),
+            (0xCB29_u16, Instruction { dissassembly: "SRA C",       closure: |ctx| Self::op_sra_r(&mut ctx.r.c, &mut ctx.r.f) }),
+            (0xCB2A_u16, Instruction { dissassembly: "SRA D",       closure: |ctx| Self::op_sra_r(&mut ctx.r.d, &mut ctx.r.f) }),
+            (0xCB2B_u16, Instruction { dissassembly: "SRA E",       closure: |ctx| Self::op_sra_r(&mut ctx.r.e, &mut ctx.r.f) }),
+            (0xCB2C_u16, Instruction { dissassembly: "SRA H",       closure: |ctx| Self::op_sra_r(&mut ctx.r.h, &mut ctx.r.f) }),
+            (0xCB2D_u16, Instruction { dissassembly: "SRA L",       closure: |ctx| Self::op_sra_r(&mut ctx.r.l, &mut ctx.r.f) }),
+            (0xCB2E_u16, Instruction { dissassembly: "SRA (HL)",    closure: |ctx| Self::op_sra_addr(ctx.bus, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) }),
             
-            (0xCB37_u16, Instruction { dissassembly: "SWAP A",      bytes: 2, closure: |ctx| Self::op_swap_r(&mut ctx.r.a, &mut ctx.r.f) }),
-            (0xCB30_u16, Instruction { dissassembly: "SWAP B",      bytes: 2, closure: |ctx| Self::op_swap_r(&mut ctx.r.b, &mut ctx.r.f) }),
-            (0xCB31_u16, Instruction { dissassembly: "SWAP C",      bytes: 2, closure: |ctx| Self::op_swap_r(&mut ctx.r.c, &mut ctx.r.f) }),
-            (0xCB32_u16, Instruction { dissassembly: "SWAP D",      bytes: 2, closure: |ctx| Self::op_swap_r(&mut ctx.r.d, &mut ctx.r.f) }),
-            (0xCB33_u16, Instruction { dissassembly: "SWAP E",      bytes: 2, closure: |ctx| Self::op_swap_r(&mut ctx.r.e, &mut ctx.r.f) }),
-            (0xCB34_u16, Instruction { dissassembly: "SWAP H",      bytes: 2, closure: |ctx| Self::op_swap_r(&mut ctx.r.h, &mut ctx.r.f) }),
-            (0xCB35_u16, Instruction { dissassembly: "SWAP L",      bytes: 2, closure: |ctx| Self::op_swap_r(&mut ctx.r.l, &mut ctx.r.f) }),
-            (0xCB36_u16, Instruction { dissassembly: "SWAP (HL)",   bytes: 2, closure: |ctx| Self::op_swap_addr(ctx.bus, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) }), // func: CPU::op_swap_mem_hl }),
-
-            (0xCB47_u16, Instruction { dissassembly: "BIT 0,A",     bytes: 2, closure: |ctx| Self::op_bitn_r(0, ctx.r.a, &mut ctx.r.f) }),
-            (0xCB40_u16, Instruction { dissassembly: "BIT 0,B",     bytes: 2, closure: |ctx| Self::op_bitn_r(0, ctx.r.b, &mut ctx.r.f) }),
-            (0xCB41_u16, Instruction { dissassembly: "BIT 0,C",     bytes: 2, closure: |ctx| Self::op_bitn_r(0, ctx.r.c, &mut ctx.r.f) }),
-            (0xCB42_u16, Instruction { dissassembly: "BIT 0,D",     bytes: 2, closure: |ctx| Self::op_bitn_r(0, ctx.r.d, &mut ctx.r.f) }),
-            (0xCB43_u16, Instruction { dissassembly: "BIT 0,E",     bytes: 2, closure: |ctx| Self::op_bitn_r(0, ctx.r.e, &mut ctx.r.f) }),
-            (0xCB44_u16, Instruction { dissassembly: "BIT 0,H",     bytes: 2, closure: |ctx| Self::op_bitn_r(0, ctx.r.h, &mut ctx.r.f) }),
-            (0xCB45_u16, Instruction { dissassembly: "BIT 0,L",     bytes: 2, closure: |ctx| Self::op_bitn_r(0, ctx.r.l, &mut ctx.r.f) }),
-            (0xCB4F_u16, Instruction { dissassembly: "BIT 1,A",     bytes: 2, closure: |ctx| Self::op_bitn_r(1, ctx.r.a, &mut ctx.r.f) }),
-            (0xCB48_u16, Instruction { dissassembly: "BIT 1,B",     bytes: 2, closure: |ctx| Self::op_bitn_r(1, ctx.r.b, &mut ctx.r.f) }),
-            (0xCB49_u16, Instruction { dissassembly: "BIT 1,C",     bytes: 2, closure: |ctx| Self::op_bitn_r(1, ctx.r.c, &mut ctx.r.f) }),
-            (0xCB4A_u16, Instruction { dissassembly: "BIT 1,D",     bytes: 2, closure: |ctx| Self::op_bitn_r(1, ctx.r.d, &mut ctx.r.f) }),
-            (0xCB4B_u16, Instruction { dissassembly: "BIT 1,E",     bytes: 2, closure: |ctx| Self::op_bitn_r(1, ctx.r.e, &mut ctx.r.f) }),
-            (0xCB4C_u16, Instruction { dissassembly: "BIT 1,H",     bytes: 2, closure: |ctx| Self::op_bitn_r(1, ctx.r.h, &mut ctx.r.f) }),
-            (0xCB4D_u16, Instruction { dissassembly: "BIT 1,L",     bytes: 2, closure: |ctx| Self::op_bitn_r(1, ctx.r.l, &mut ctx.r.f) }),
-            (0xCB57_u16, Instruction { dissassembly: "BIT 2,A",     bytes: 2, closure: |ctx| Self::op_bitn_r(2, ctx.r.a, &mut ctx.r.f) }),
-            (0xCB50_u16, Instruction { dissassembly: "BIT 2,B",     bytes: 2, closure: |ctx| Self::op_bitn_r(2, ctx.r.b, &mut ctx.r.f) }),
-            (0xCB51_u16, Instruction { dissassembly: "BIT 2,C",     bytes: 2, closure: |ctx| Self::op_bitn_r(2, ctx.r.c, &mut ctx.r.f) }),
-            (0xCB52_u16, Instruction { dissassembly: "BIT 2,D",     bytes: 2, closure: |ctx| Self::op_bitn_r(2, ctx.r.d, &mut ctx.r.f) }),
-            (0xCB53_u16, Instruction { dissassembly: "BIT 2,E",     bytes: 2, closure: |ctx| Self::op_bitn_r(2, ctx.r.e, &mut ctx.r.f) }),
-            (0xCB54_u16, Instruction { dissassembly: "BIT 2,H",     bytes: 2, closure: |ctx| Self::op_bitn_r(2, ctx.r.h, &mut ctx.r.f) }),
-            (0xCB55_u16, Instruction { dissassembly: "BIT 2,L",     bytes: 2, closure: |ctx| Self::op_bitn_r(2, ctx.r.l, &mut ctx.r.f) }),
-            (0xCB5F_u16, Instruction { dissassembly: "BIT 3,A",     bytes: 2, closure: |ctx| Self::op_bitn_r(3, ctx.r.a, &mut ctx.r.f) }),
-            (0xCB58_u16, Instruction { dissassembly: "BIT 3,B",     bytes: 2, closure: |ctx| Self::op_bitn_r(3, ctx.r.b, &mut ctx.r.f) }),
-            (0xCB59_u16, Instruction { dissassembly: "BIT 3,C",     bytes: 2, closure: |ctx| Self::op_bitn_r(3, ctx.r.c, &mut ctx.r.f) }),
-            (0xCB5A_u16, Instruction { dissassembly: "BIT 3,D",     bytes: 2, closure: |ctx| Self::op_bitn_r(3, ctx.r.d, &mut ctx.r.f) }),
-            (0xCB5B_u16, Instruction { dissassembly: "BIT 3,E",     bytes: 2, closure: |ctx| Self::op_bitn_r(3, ctx.r.e, &mut ctx.r.f) }),
-            (0xCB5C_u16, Instruction { dissassembly: "BIT 3,H",     bytes: 2, closure: |ctx| Self::op_bitn_r(3, ctx.r.h, &mut ctx.r.f) }),
-            (0xCB5D_u16, Instruction { dissassembly: "BIT 3,L",     bytes: 2, closure: |ctx| Self::op_bitn_r(3, ctx.r.l, &mut ctx.r.f) }),
-            (0xCB67_u16, Instruction { dissassembly: "BIT 4,A",     bytes: 2, closure: |ctx| Self::op_bitn_r(4, ctx.r.a, &mut ctx.r.f) }),
-            (0xCB60_u16, Instruction { dissassembly: "BIT 4,B",     bytes: 2, closure: |ctx| Self::op_bitn_r(4, ctx.r.b, &mut ctx.r.f) }),
-            (0xCB61_u16, Instruction { dissassembly: "BIT 4,C",     bytes: 2, closure: |ctx| Self::op_bitn_r(4, ctx.r.c, &mut ctx.r.f) }),
-            (0xCB62_u16, Instruction { dissassembly: "BIT 4,D",     bytes: 2, closure: |ctx| Self::op_bitn_r(4, ctx.r.d, &mut ctx.r.f) }),
-            (0xCB63_u16, Instruction { dissassembly: "BIT 4,E",     bytes: 2, closure: |ctx| Self::op_bitn_r(4, ctx.r.e, &mut ctx.r.f) }),
-            (0xCB64_u16, Instruction { dissassembly: "BIT 4,H",     bytes: 2, closure: |ctx| Self::op_bitn_r(4, ctx.r.h, &mut ctx.r.f) }),
-            (0xCB65_u16, Instruction { dissassembly: "BIT 4,L",     bytes: 2, closure: |ctx| Self::op_bitn_r(4, ctx.r.l, &mut ctx.r.f) }),
-            (0xCB6F_u16, Instruction { dissassembly: "BIT 5,A",     bytes: 2, closure: |ctx| Self::op_bitn_r(5, ctx.r.a, &mut ctx.r.f) }),
-            (0xCB68_u16, Instruction { dissassembly: "BIT 5,B",     bytes: 2, closure: |ctx| Self::op_bitn_r(5, ctx.r.b, &mut ctx.r.f) }),
-            (0xCB69_u16, Instruction { dissassembly: "BIT 5,C",     bytes: 2, closure: |ctx| Self::op_bitn_r(5, ctx.r.c, &mut ctx.r.f) }),
-            (0xCB6A_u16, Instruction { dissassembly: "BIT 5,D",     bytes: 2, closure: |ctx| Self::op_bitn_r(5, ctx.r.d, &mut ctx.r.f) }),
-            (0xCB6B_u16, Instruction { dissassembly: "BIT 5,E",     bytes: 2, closure: |ctx| Self::op_bitn_r(5, ctx.r.e, &mut ctx.r.f) }),
-            (0xCB6C_u16, Instruction { dissassembly: "BIT 5,H",     bytes: 2, closure: |ctx| Self::op_bitn_r(5, ctx.r.h, &mut ctx.r.f) }),
-            (0xCB6D_u16, Instruction { dissassembly: "BIT 5,L",     bytes: 2, closure: |ctx| Self::op_bitn_r(5, ctx.r.l, &mut ctx.r.f) }),
-            (0xCB77_u16, Instruction { dissassembly: "BIT 6,A",     bytes: 2, closure: |ctx| Self::op_bitn_r(6, ctx.r.a, &mut ctx.r.f) }),
-            (0xCB70_u16, Instruction { dissassembly: "BIT 6,B",     bytes: 2, closure: |ctx| Self::op_bitn_r(6, ctx.r.b, &mut ctx.r.f) }),
-            (0xCB71_u16, Instruction { dissassembly: "BIT 6,C",     bytes: 2, closure: |ctx| Self::op_bitn_r(6, ctx.r.c, &mut ctx.r.f) }),
-            (0xCB72_u16, Instruction { dissassembly: "BIT 6,D",     bytes: 2, closure: |ctx| Self::op_bitn_r(6, ctx.r.d, &mut ctx.r.f) }),
-            (0xCB73_u16, Instruction { dissassembly: "BIT 6,E",     bytes: 2, closure: |ctx| Self::op_bitn_r(6, ctx.r.e, &mut ctx.r.f) }),
-            (0xCB74_u16, Instruction { dissassembly: "BIT 6,H",     bytes: 2, closure: |ctx| Self::op_bitn_r(6, ctx.r.h, &mut ctx.r.f) }),
-            (0xCB75_u16, Instruction { dissassembly: "BIT 6,L",     bytes: 2, closure: |ctx| Self::op_bitn_r(6, ctx.r.l, &mut ctx.r.f) }),
-            (0xCB7F_u16, Instruction { dissassembly: "BIT 7,A",     bytes: 2, closure: |ctx| Self::op_bitn_r(7, ctx.r.a, &mut ctx.r.f) }),
-            (0xCB78_u16, Instruction { dissassembly: "BIT 7,B",     bytes: 2, closure: |ctx| Self::op_bitn_r(7, ctx.r.b, &mut ctx.r.f) }),
-            (0xCB79_u16, Instruction { dissassembly: "BIT 7,C",     bytes: 2, closure: |ctx| Self::op_bitn_r(7, ctx.r.c, &mut ctx.r.f) }),
-            (0xCB7A_u16, Instruction { dissassembly: "BIT 7,D",     bytes: 2, closure: |ctx| Self::op_bitn_r(7, ctx.r.d, &mut ctx.r.f) }),
-            (0xCB7B_u16, Instruction { dissassembly: "BIT 7,E",     bytes: 2, closure: |ctx| Self::op_bitn_r(7, ctx.r.e, &mut ctx.r.f) }),
-            (0xCB7C_u16, Instruction { dissassembly: "BIT 7,H",     bytes: 2, closure: |ctx| Self::op_bitn_r(7, ctx.r.h, &mut ctx.r.f) }),
-            (0xCB7D_u16, Instruction { dissassembly: "BIT 7,L",     bytes: 2, closure: |ctx| Self::op_bitn_r(7, ctx.r.l, &mut ctx.r.f) }),
-            (0xCB46_u16, Instruction { dissassembly: "BIT 0,(HL)",  bytes: 2, closure: |ctx| Self::op_bitn_addr(ctx.bus, 0, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) }),
-            (0xCB4E_u16, Instruction { dissassembly: "BIT 1,(HL)",  bytes: 2, closure: |ctx| Self::op_bitn_addr(ctx.bus, 1, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) }),
-            (0xCB56_u16, Instruction { dissassembly: "BIT 2,(HL)",  bytes: 2, closure: |ctx| Self::op_bitn_addr(ctx.bus, 2, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) }),
-            (0xCB5E_u16, Instruction { dissassembly: "BIT 3,(HL)",  bytes: 2, closure: |ctx| Self::op_bitn_addr(ctx.bus, 3, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) }),
-            (0xCB66_u16, Instruction { dissassembly: "BIT 4,(HL)",  bytes: 2, closure: |ctx| Self::op_bitn_addr(ctx.bus, 4, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) }),
-            (0xCB6E_u16, Instruction { dissassembly: "BIT 5,(HL)",  bytes: 2, closure: |ctx| Self::op_bitn_addr(ctx.bus, 5, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) }),
-            (0xCB76_u16, Instruction { dissassembly: "BIT 6,(HL)",  bytes: 2, closure: |ctx| Self::op_bitn_addr(ctx.bus, 6, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) }),
-            (0xCB7E_u16, Instruction { dissassembly: "BIT 7,(HL)",  bytes: 2, closure: |ctx| Self::op_bitn_addr(ctx.bus, 7, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) }),
-
-            (0xCBC7_u16, Instruction { dissassembly: "SET 0,A",     bytes: 2, closure: |ctx| Self::op_setn_r(0, &mut ctx.r.a) }),
-            (0xCBC0_u16, Instruction { dissassembly: "SET 0,B",     bytes: 2, closure: |ctx| Self::op_setn_r(0, &mut ctx.r.b) }),
-            (0xCBC1_u16, Instruction { dissassembly: "SET 0,C",     bytes: 2, closure: |ctx| Self::op_setn_r(0, &mut ctx.r.c) }),
-            (0xCBC2_u16, Instruction { dissassembly: "SET 0,D",     bytes: 2, closure: |ctx| Self::op_setn_r(0, &mut ctx.r.d) }),
-            (0xCBC3_u16, Instruction { dissassembly: "SET 0,E",     bytes: 2, closure: |ctx| Self::op_setn_r(0, &mut ctx.r.e) }),
-            (0xCBC4_u16, Instruction { dissassembly: "SET 0,H",     bytes: 2, closure: |ctx| Self::op_setn_r(0, &mut ctx.r.h) }),
-            (0xCBC5_u16, Instruction { dissassembly: "SET 0,L",     bytes: 2, closure: |ctx| Self::op_setn_r(0, &mut ctx.r.l) }),
-            (0xCBCF_u16, Instruction { dissassembly: "SET 1,A",     bytes: 2, closure: |ctx| Self::op_setn_r(1, &mut ctx.r.a) }),
-            (0xCBC8_u16, Instruction { dissassembly: "SET 1,B",     bytes: 2, closure: |ctx| Self::op_setn_r(1, &mut ctx.r.b) }),
-            (0xCBC9_u16, Instruction { dissassembly: "SET 1,C",     bytes: 2, closure: |ctx| Self::op_setn_r(1, &mut ctx.r.c) }),
-            (0xCBCA_u16, Instruction { dissassembly: "SET 1,D",     bytes: 2, closure: |ctx| Self::op_setn_r(1, &mut ctx.r.d) }),
-            (0xCBCB_u16, Instruction { dissassembly: "SET 1,E",     bytes: 2, closure: |ctx| Self::op_setn_r(1, &mut ctx.r.e) }),
-            (0xCBCC_u16, Instruction { dissassembly: "SET 1,H",     bytes: 2, closure: |ctx| Self::op_setn_r(1, &mut ctx.r.h) }),
-            (0xCBCD_u16, Instruction { dissassembly: "SET 1,L",     bytes: 2, closure: |ctx| Self::op_setn_r(1, &mut ctx.r.l) }),
-            (0xCBD7_u16, Instruction { dissassembly: "SET 2,A",     bytes: 2, closure: |ctx| Self::op_setn_r(2, &mut ctx.r.a) }),
-            (0xCBD0_u16, Instruction { dissassembly: "SET 2,B",     bytes: 2, closure: |ctx| Self::op_setn_r(2, &mut ctx.r.b) }),
-            (0xCBD1_u16, Instruction { dissassembly: "SET 2,C",     bytes: 2, closure: |ctx| Self::op_setn_r(2, &mut ctx.r.c) }),
-            (0xCBD2_u16, Instruction { dissassembly: "SET 2,D",     bytes: 2, closure: |ctx| Self::op_setn_r(2, &mut ctx.r.d) }),
-            (0xCBD3_u16, Instruction { dissassembly: "SET 2,E",     bytes: 2, closure: |ctx| Self::op_setn_r(2, &mut ctx.r.e) }),
-            (0xCBD4_u16, Instruction { dissassembly: "SET 2,H",     bytes: 2, closure: |ctx| Self::op_setn_r(2, &mut ctx.r.h) }),
-            (0xCBD5_u16, Instruction { dissassembly: "SET 2,L",     bytes: 2, closure: |ctx| Self::op_setn_r(2, &mut ctx.r.l) }),
-            (0xCBDF_u16, Instruction { dissassembly: "SET 3,A",     bytes: 2, closure: |ctx| Self::op_setn_r(3, &mut ctx.r.a) }),
-            (0xCBD8_u16, Instruction { dissassembly: "SET 3,B",     bytes: 2, closure: |ctx| Self::op_setn_r(3, &mut ctx.r.b) }),
-            (0xCBD9_u16, Instruction { dissassembly: "SET 3,C",     bytes: 2, closure: |ctx| Self::op_setn_r(3, &mut ctx.r.c) }),
-            (0xCBDA_u16, Instruction { dissassembly: "SET 3,D",     bytes: 2, closure: |ctx| Self::op_setn_r(3, &mut ctx.r.d) }),
-            (0xCBDB_u16, Instruction { dissassembly: "SET 3,E",     bytes: 2, closure: |ctx| Self::op_setn_r(3, &mut ctx.r.e) }),
-            (0xCBDC_u16, Instruction { dissassembly: "SET 3,H",     bytes: 2, closure: |ctx| Self::op_setn_r(3, &mut ctx.r.h) }),
-            (0xCBDD_u16, Instruction { dissassembly: "SET 3,L",     bytes: 2, closure: |ctx| Self::op_setn_r(3, &mut ctx.r.l) }),
-            (0xCBE7_u16, Instruction { dissassembly: "SET 4,A",     bytes: 2, closure: |ctx| Self::op_setn_r(4, &mut ctx.r.a) }),
-            (0xCBE0_u16, Instruction { dissassembly: "SET 4,B",     bytes: 2, closure: |ctx| Self::op_setn_r(4, &mut ctx.r.b) }),
-            (0xCBE1_u16, Instruction { dissassembly: "SET 4,C",     bytes: 2, closure: |ctx| Self::op_setn_r(4, &mut ctx.r.c) }),
-            (0xCBE2_u16, Instruction { dissassembly: "SET 4,D",     bytes: 2, closure: |ctx| Self::op_setn_r(4, &mut ctx.r.d) }),
-            (0xCBE3_u16, Instruction { dissassembly: "SET 4,E",     bytes: 2, closure: |ctx| Self::op_setn_r(4, &mut ctx.r.e) }),
-            (0xCBE4_u16, Instruction { dissassembly: "SET 4,H",     bytes: 2, closure: |ctx| Self::op_setn_r(4, &mut ctx.r.h) }),
-            (0xCBE5_u16, Instruction { dissassembly: "SET 4,L",     bytes: 2, closure: |ctx| Self::op_setn_r(4, &mut ctx.r.l) }),
-            (0xCBEF_u16, Instruction { dissassembly: "SET 5,A",     bytes: 2, closure: |ctx| Self::op_setn_r(5, &mut ctx.r.a) }),
-            (0xCBE8_u16, Instruction { dissassembly: "SET 5,B",     bytes: 2, closure: |ctx| Self::op_setn_r(5, &mut ctx.r.b) }),
-            (0xCBE9_u16, Instruction { dissassembly: "SET 5,C",     bytes: 2, closure: |ctx| Self::op_setn_r(5, &mut ctx.r.c) }),
-            (0xCBEA_u16, Instruction { dissassembly: "SET 5,D",     bytes: 2, closure: |ctx| Self::op_setn_r(5, &mut ctx.r.d) }),
-            (0xCBEB_u16, Instruction { dissassembly: "SET 5,E",     bytes: 2, closure: |ctx| Self::op_setn_r(5, &mut ctx.r.e) }),
-            (0xCBEC_u16, Instruction { dissassembly: "SET 5,H",     bytes: 2, closure: |ctx| Self::op_setn_r(5, &mut ctx.r.h) }),
-            (0xCBED_u16, Instruction { dissassembly: "SET 5,L",     bytes: 2, closure: |ctx| Self::op_setn_r(5, &mut ctx.r.l) }),
-            (0xCBF7_u16, Instruction { dissassembly: "SET 6,A",     bytes: 2, closure: |ctx| Self::op_setn_r(6, &mut ctx.r.a) }),
-            (0xCBF0_u16, Instruction { dissassembly: "SET 6,B",     bytes: 2, closure: |ctx| Self::op_setn_r(6, &mut ctx.r.b) }),
-            (0xCBF1_u16, Instruction { dissassembly: "SET 6,C",     bytes: 2, closure: |ctx| Self::op_setn_r(6, &mut ctx.r.c) }),
-            (0xCBF2_u16, Instruction { dissassembly: "SET 6,D",     bytes: 2, closure: |ctx| Self::op_setn_r(6, &mut ctx.r.d) }),
-            (0xCBF3_u16, Instruction { dissassembly: "SET 6,E",     bytes: 2, closure: |ctx| Self::op_setn_r(6, &mut ctx.r.e) }),
-            (0xCBF4_u16, Instruction { dissassembly: "SET 6,H",     bytes: 2, closure: |ctx| Self::op_setn_r(6, &mut ctx.r.h) }),
-            (0xCBF5_u16, Instruction { dissassembly: "SET 6,L",     bytes: 2, closure: |ctx| Self::op_setn_r(6, &mut ctx.r.l) }),
-            (0xCBFF_u16, Instruction { dissassembly: "SET 7,A",     bytes: 2, closure: |ctx| Self::op_setn_r(7, &mut ctx.r.a) }),
-            (0xCBF8_u16, Instruction { dissassembly: "SET 7,B",     bytes: 2, closure: |ctx| Self::op_setn_r(7, &mut ctx.r.b) }),
-            (0xCBF9_u16, Instruction { dissassembly: "SET 7,C",     bytes: 2, closure: |ctx| Self::op_setn_r(7, &mut ctx.r.c) }),
-            (0xCBFA_u16, Instruction { dissassembly: "SET 7,D",     bytes: 2, closure: |ctx| Self::op_setn_r(7, &mut ctx.r.d) }),
-            (0xCBFB_u16, Instruction { dissassembly: "SET 7,E",     bytes: 2, closure: |ctx| Self::op_setn_r(7, &mut ctx.r.e) }),
-            (0xCBFC_u16, Instruction { dissassembly: "SET 7,H",     bytes: 2, closure: |ctx| Self::op_setn_r(7, &mut ctx.r.h) }),
-            (0xCBFD_u16, Instruction { dissassembly: "SET 7,L",     bytes: 2, closure: |ctx| Self::op_setn_r(7, &mut ctx.r.l) }),
-            (0xCB87_u16, Instruction { dissassembly: "RES 0,A",     bytes: 2, closure: |ctx| Self::op_resn_r(0, &mut ctx.r.a) }),
-            (0xCB80_u16, Instruction { dissassembly: "RES 0,B",     bytes: 2, closure: |ctx| Self::op_resn_r(0, &mut ctx.r.b) }),
-            (0xCB81_u16, Instruction { dissassembly: "RES 0,C",     bytes: 2, closure: |ctx| Self::op_resn_r(0, &mut ctx.r.c) }),
-            (0xCB82_u16, Instruction { dissassembly: "RES 0,D",     bytes: 2, closure: |ctx| Self::op_resn_r(0, &mut ctx.r.d) }),
-            (0xCB83_u16, Instruction { dissassembly: "RES 0,E",     bytes: 2, closure: |ctx| Self::op_resn_r(0, &mut ctx.r.e) }),
-            (0xCB84_u16, Instruction { dissassembly: "RES 0,H",     bytes: 2, closure: |ctx| Self::op_resn_r(0, &mut ctx.r.h) }),
-            (0xCB85_u16, Instruction { dissassembly: "RES 0,L",     bytes: 2, closure: |ctx| Self::op_resn_r(0, &mut ctx.r.l) }),
-            (0xCB8F_u16, Instruction { dissassembly: "RES 1,A",     bytes: 2, closure: |ctx| Self::op_resn_r(1, &mut ctx.r.a) }),
-            (0xCB88_u16, Instruction { dissassembly: "RES 1,B",     bytes: 2, closure: |ctx| Self::op_resn_r(1, &mut ctx.r.b) }),
-            (0xCB89_u16, Instruction { dissassembly: "RES 1,C",     bytes: 2, closure: |ctx| Self::op_resn_r(1, &mut ctx.r.c) }),
-            (0xCB8A_u16, Instruction { dissassembly: "RES 1,D",     bytes: 2, closure: |ctx| Self::op_resn_r(1, &mut ctx.r.d) }),
-            (0xCB8B_u16, Instruction { dissassembly: "RES 1,E",     bytes: 2, closure: |ctx| Self::op_resn_r(1, &mut ctx.r.e) }),
-            (0xCB8C_u16, Instruction { dissassembly: "RES 1,H",     bytes: 2, closure: |ctx| Self::op_resn_r(1, &mut ctx.r.h) }),
-            (0xCB8D_u16, Instruction { dissassembly: "RES 1,L",     bytes: 2, closure: |ctx| Self::op_resn_r(1, &mut ctx.r.l) }),
-            (0xCB97_u16, Instruction { dissassembly: "RES 2,A",     bytes: 2, closure: |ctx| Self::op_resn_r(2, &mut ctx.r.a) }),
-            (0xCB90_u16, Instruction { dissassembly: "RES 2,B",     bytes: 2, closure: |ctx| Self::op_resn_r(2, &mut ctx.r.b) }),
-            (0xCB91_u16, Instruction { dissassembly: "RES 2,C",     bytes: 2, closure: |ctx| Self::op_resn_r(2, &mut ctx.r.c) }),
-            (0xCB92_u16, Instruction { dissassembly: "RES 2,D",     bytes: 2, closure: |ctx| Self::op_resn_r(2, &mut ctx.r.d) }),
-            (0xCB93_u16, Instruction { dissassembly: "RES 2,E",     bytes: 2, closure: |ctx| Self::op_resn_r(2, &mut ctx.r.e) }),
-            (0xCB94_u16, Instruction { dissassembly: "RES 2,H",     bytes: 2, closure: |ctx| Self::op_resn_r(2, &mut ctx.r.h) }),
-            (0xCB95_u16, Instruction { dissassembly: "RES 2,L",     bytes: 2, closure: |ctx| Self::op_resn_r(2, &mut ctx.r.l) }),
-            (0xCB9F_u16, Instruction { dissassembly: "RES 3,A",     bytes: 2, closure: |ctx| Self::op_resn_r(3, &mut ctx.r.a) }),
-            (0xCB98_u16, Instruction { dissassembly: "RES 3,B",     bytes: 2, closure: |ctx| Self::op_resn_r(3, &mut ctx.r.b) }),
-            (0xCB99_u16, Instruction { dissassembly: "RES 3,C",     bytes: 2, closure: |ctx| Self::op_resn_r(3, &mut ctx.r.c) }),
-            (0xCB9A_u16, Instruction { dissassembly: "RES 3,D",     bytes: 2, closure: |ctx| Self::op_resn_r(3, &mut ctx.r.d) }),
-            (0xCB9B_u16, Instruction { dissassembly: "RES 3,E",     bytes: 2, closure: |ctx| Self::op_resn_r(3, &mut ctx.r.e) }),
-            (0xCB9C_u16, Instruction { dissassembly: "RES 3,H",     bytes: 2, closure: |ctx| Self::op_resn_r(3, &mut ctx.r.h) }),
-            (0xCB9D_u16, Instruction { dissassembly: "RES 3,L",     bytes: 2, closure: |ctx| Self::op_resn_r(3, &mut ctx.r.l) }),
-            (0xCBA7_u16, Instruction { dissassembly: "RES 4,A",     bytes: 2, closure: |ctx| Self::op_resn_r(4, &mut ctx.r.a) }),
-            (0xCBA0_u16, Instruction { dissassembly: "RES 4,B",     bytes: 2, closure: |ctx| Self::op_resn_r(4, &mut ctx.r.b) }),
-            (0xCBA1_u16, Instruction { dissassembly: "RES 4,C",     bytes: 2, closure: |ctx| Self::op_resn_r(4, &mut ctx.r.c) }),
-            (0xCBA2_u16, Instruction { dissassembly: "RES 4,D",     bytes: 2, closure: |ctx| Self::op_resn_r(4, &mut ctx.r.d) }),
-            (0xCBA3_u16, Instruction { dissassembly: "RES 4,E",     bytes: 2, closure: |ctx| Self::op_resn_r(4, &mut ctx.r.e) }),
-            (0xCBA4_u16, Instruction { dissassembly: "RES 4,H",     bytes: 2, closure: |ctx| Self::op_resn_r(4, &mut ctx.r.h) }),
-            (0xCBA5_u16, Instruction { dissassembly: "RES 4,L",     bytes: 2, closure: |ctx| Self::op_resn_r(4, &mut ctx.r.l) }),
-            (0xCBAF_u16, Instruction { dissassembly: "RES 5,A",     bytes: 2, closure: |ctx| Self::op_resn_r(5, &mut ctx.r.a) }),
-            (0xCBA8_u16, Instruction { dissassembly: "RES 5,B",     bytes: 2, closure: |ctx| Self::op_resn_r(5, &mut ctx.r.b) }),
-            (0xCBA9_u16, Instruction { dissassembly: "RES 5,C",     bytes: 2, closure: |ctx| Self::op_resn_r(5, &mut ctx.r.c) }),
-            (0xCBAA_u16, Instruction { dissassembly: "RES 5,D",     bytes: 2, closure: |ctx| Self::op_resn_r(5, &mut ctx.r.d) }),
-            (0xCBAB_u16, Instruction { dissassembly: "RES 5,E",     bytes: 2, closure: |ctx| Self::op_resn_r(5, &mut ctx.r.e) }),
-            (0xCBAC_u16, Instruction { dissassembly: "RES 5,H",     bytes: 2, closure: |ctx| Self::op_resn_r(5, &mut ctx.r.h) }),
-            (0xCBAD_u16, Instruction { dissassembly: "RES 5,L",     bytes: 2, closure: |ctx| Self::op_resn_r(5, &mut ctx.r.l) }),
-            (0xCBB7_u16, Instruction { dissassembly: "RES 6,A",     bytes: 2, closure: |ctx| Self::op_resn_r(6, &mut ctx.r.a) }),
-            (0xCBB0_u16, Instruction { dissassembly: "RES 6,B",     bytes: 2, closure: |ctx| Self::op_resn_r(6, &mut ctx.r.b) }),
-            (0xCBB1_u16, Instruction { dissassembly: "RES 6,C",     bytes: 2, closure: |ctx| Self::op_resn_r(6, &mut ctx.r.c) }),
-            (0xCBB2_u16, Instruction { dissassembly: "RES 6,D",     bytes: 2, closure: |ctx| Self::op_resn_r(6, &mut ctx.r.d) }),
-            (0xCBB3_u16, Instruction { dissassembly: "RES 6,E",     bytes: 2, closure: |ctx| Self::op_resn_r(6, &mut ctx.r.e) }),
-            (0xCBB4_u16, Instruction { dissassembly: "RES 6,H",     bytes: 2, closure: |ctx| Self::op_resn_r(6, &mut ctx.r.h) }),
-            (0xCBB5_u16, Instruction { dissassembly: "RES 6,L",     bytes: 2, closure: |ctx| Self::op_resn_r(6, &mut ctx.r.l) }),
-            (0xCBBF_u16, Instruction { dissassembly: "RES 7,A",     bytes: 2, closure: |ctx| Self::op_resn_r(7, &mut ctx.r.a) }),
-            (0xCBB8_u16, Instruction { dissassembly: "RES 7,B",     bytes: 2, closure: |ctx| Self::op_resn_r(7, &mut ctx.r.b) }),
-            (0xCBB9_u16, Instruction { dissassembly: "RES 7,C",     bytes: 2, closure: |ctx| Self::op_resn_r(7, &mut ctx.r.c) }),
-            (0xCBBA_u16, Instruction { dissassembly: "RES 7,D",     bytes: 2, closure: |ctx| Self::op_resn_r(7, &mut ctx.r.d) }),
-            (0xCBBB_u16, Instruction { dissassembly: "RES 7,E",     bytes: 2, closure: |ctx| Self::op_resn_r(7, &mut ctx.r.e) }),
-            (0xCBBC_u16, Instruction { dissassembly: "RES 7,H",     bytes: 2, closure: |ctx| Self::op_resn_r(7, &mut ctx.r.h) }),
-            (0xCBBD_u16, Instruction { dissassembly: "RES 7,L",     bytes: 2, closure: |ctx| Self::op_resn_r(7, &mut ctx.r.l) }),
-            (0xCB86_u16, Instruction { dissassembly: "RES 0,(HL)",  bytes: 2, closure: |ctx| Self::op_resn_addr(ctx.bus, 0, to_u16(ctx.r.h, ctx.r.l)) }),
-            (0xCB8E_u16, Instruction { dissassembly: "RES 1,(HL)",  bytes: 2, closure: |ctx| Self::op_resn_addr(ctx.bus, 1, to_u16(ctx.r.h, ctx.r.l)) }),
-            (0xCB96_u16, Instruction { dissassembly: "RES 2,(HL)",  bytes: 2, closure: |ctx| Self::op_resn_addr(ctx.bus, 2, to_u16(ctx.r.h, ctx.r.l)) }),
-            (0xCB9E_u16, Instruction { dissassembly: "RES 3,(HL)",  bytes: 2, closure: |ctx| Self::op_resn_addr(ctx.bus, 3, to_u16(ctx.r.h, ctx.r.l)) }),
-            (0xCBA6_u16, Instruction { dissassembly: "RES 4,(HL)",  bytes: 2, closure: |ctx| Self::op_resn_addr(ctx.bus, 4, to_u16(ctx.r.h, ctx.r.l)) }),
-            (0xCBAE_u16, Instruction { dissassembly: "RES 5,(HL)",  bytes: 2, closure: |ctx| Self::op_resn_addr(ctx.bus, 5, to_u16(ctx.r.h, ctx.r.l)) }),
-            (0xCBB6_u16, Instruction { dissassembly: "RES 6,(HL)",  bytes: 2, closure: |ctx| Self::op_resn_addr(ctx.bus, 6, to_u16(ctx.r.h, ctx.r.l)) }),
-            (0xCBBE_u16, Instruction { dissassembly: "RES 7,(HL)",  bytes: 2, closure: |ctx| Self::op_resn_addr(ctx.bus, 7, to_u16(ctx.r.h, ctx.r.l)) }),
-            (0xCBC6_u16, Instruction { dissassembly: "SET 0,(HL)",  bytes: 2, closure: |ctx| Self::op_setn_addr(ctx.bus, 0, to_u16(ctx.r.h, ctx.r.l)) }),
-            (0xCBCE_u16, Instruction { dissassembly: "SET 1,(HL)",  bytes: 2, closure: |ctx| Self::op_setn_addr(ctx.bus, 1, to_u16(ctx.r.h, ctx.r.l)) }),
-            (0xCBD6_u16, Instruction { dissassembly: "SET 2,(HL)",  bytes: 2, closure: |ctx| Self::op_setn_addr(ctx.bus, 2, to_u16(ctx.r.h, ctx.r.l)) }),
-            (0xCBDE_u16, Instruction { dissassembly: "SET 3,(HL)",  bytes: 2, closure: |ctx| Self::op_setn_addr(ctx.bus, 3, to_u16(ctx.r.h, ctx.r.l)) }),
-            (0xCBE6_u16, Instruction { dissassembly: "SET 4,(HL)",  bytes: 2, closure: |ctx| Self::op_setn_addr(ctx.bus, 4, to_u16(ctx.r.h, ctx.r.l)) }),
-            (0xCBEE_u16, Instruction { dissassembly: "SET 5,(HL)",  bytes: 2, closure: |ctx| Self::op_setn_addr(ctx.bus, 5, to_u16(ctx.r.h, ctx.r.l)) }),
-            (0xCBF6_u16, Instruction { dissassembly: "SET 6,(HL)",  bytes: 2, closure: |ctx| Self::op_setn_addr(ctx.bus, 6, to_u16(ctx.r.h, ctx.r.l)) }),
-            (0xCBFE_u16, Instruction { dissassembly: "SET 7,(HL)",  bytes: 2, closure: |ctx| Self::op_setn_addr(ctx.bus, 7, to_u16(ctx.r.h, ctx.r.l)) }),
+            (0xCB37_u16, Instruction { dissassembly: "SWAP A",      closure: |ctx| Self::op_swap_r(&mut ctx.r.a, &mut ctx.r.f) }),
+            (0xCB30_u16, Instruction { dissassembly: "SWAP B",      closure: |ctx| Self::op_swap_r(&mut ctx.r.b, &mut ctx.r.f) }),
+            (0xCB31_u16, Instruction { dissassembly: "SWAP C",      closure: |ctx| Self::op_swap_r(&mut ctx.r.c, &mut ctx.r.f) }),
+            (0xCB32_u16, Instruction { dissassembly: "SWAP D",      closure: |ctx| Self::op_swap_r(&mut ctx.r.d, &mut ctx.r.f) }),
+            (0xCB33_u16, Instruction { dissassembly: "SWAP E",      closure: |ctx| Self::op_swap_r(&mut ctx.r.e, &mut ctx.r.f) }),
+            (0xCB34_u16, Instruction { dissassembly: "SWAP H",      closure: |ctx| Self::op_swap_r(&mut ctx.r.h, &mut ctx.r.f) }),
+            (0xCB35_u16, Instruction { dissassembly: "SWAP L",      closure: |ctx| Self::op_swap_r(&mut ctx.r.l, &mut ctx.r.f) }),
+            (0xCB36_u16, Instruction { dissassembly: "SWAP (HL)",   closure: |ctx| Self::op_swap_addr(ctx.bus, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) }), // func: CPU::op_swap_mem_hl }),
+
+            (0xCB47_u16, Instruction { dissassembly: "BIT 0,A",     closure: |ctx| Self::op_bitn_r(0, ctx.r.a, &mut ctx.r.f) }),
+            (0xCB40_u16, Instruction { dissassembly: "BIT 0,B",     closure: |ctx| Self::op_bitn_r(0, ctx.r.b, &mut ctx.r.f) }),
+            (0xCB41_u16, Instruction { dissassembly: "BIT 0,C",     closure: |ctx| Self::op_bitn_r(0, ctx.r.c, &mut ctx.r.f) }),
+            (0xCB42_u16, Instruction { dissassembly: "BIT 0,D",     closure: |ctx| Self::op_bitn_r(0, ctx.r.d, &mut ctx.r.f) }),
+            (0xCB43_u16, Instruction { dissassembly: "BIT 0,E",     closure: |ctx| Self::op_bitn_r(0, ctx.r.e, &mut ctx.r.f) }),
+            (0xCB44_u16, Instruction { dissassembly: "BIT 0,H",     closure: |ctx| Self::op_bitn_r(0, ctx.r.h, &mut ctx.r.f) }),
+            (0xCB45_u16, Instruction { dissassembly: "BIT 0,L",     closure: |ctx| Self::op_bitn_r(0, ctx.r.l, &mut ctx.r.f) }),
+            (0xCB4F_u16, Instruction { dissassembly: "BIT 1,A",     closure: |ctx| Self::op_bitn_r(1, ctx.r.a, &mut ctx.r.f) }),
+            (0xCB48_u16, Instruction { dissassembly: "BIT 1,B",     closure: |ctx| Self::op_bitn_r(1, ctx.r.b, &mut ctx.r.f) }),
+            (0xCB49_u16, Instruction { dissassembly: "BIT 1,C",     closure: |ctx| Self::op_bitn_r(1, ctx.r.c, &mut ctx.r.f) }),
+            (0xCB4A_u16, Instruction { dissassembly: "BIT 1,D",     closure: |ctx| Self::op_bitn_r(1, ctx.r.d, &mut ctx.r.f) }),
+            (0xCB4B_u16, Instruction { dissassembly: "BIT 1,E",     closure: |ctx| Self::op_bitn_r(1, ctx.r.e, &mut ctx.r.f) }),
+            (0xCB4C_u16, Instruction { dissassembly: "BIT 1,H",     closure: |ctx| Self::op_bitn_r(1, ctx.r.h, &mut ctx.r.f) }),
+            (0xCB4D_u16, Instruction { dissassembly: "BIT 1,L",     closure: |ctx| Self::op_bitn_r(1, ctx.r.l, &mut ctx.r.f) }),
+            (0xCB57_u16, Instruction { dissassembly: "BIT 2,A",     closure: |ctx| Self::op_bitn_r(2, ctx.r.a, &mut ctx.r.f) }),
+            (0xCB50_u16, Instruction { dissassembly: "BIT 2,B",     closure: |ctx| Self::op_bitn_r(2, ctx.r.b, &mut ctx.r.f) }),
+            (0xCB51_u16, Instruction { dissassembly: "BIT 2,C",     closure: |ctx| Self::op_bitn_r(2, ctx.r.c, &mut ctx.r.f) }),
+            (0xCB52_u16, Instruction { dissassembly: "BIT 2,D",     closure: |ctx| Self::op_bitn_r(2, ctx.r.d, &mut ctx.r.f) }),
+            (0xCB53_u16, Instruction { dissassembly: "BIT 2,E",     closure: |ctx| Self::op_bitn_r(2, ctx.r.e, &mut ctx.r.f) }),
+            (0xCB54_u16, Instruction { dissassembly: "BIT 2,H",     closure: |ctx| Self::op_bitn_r(2, ctx.r.h, &mut ctx.r.f) }),
+            (0xCB55_u16, Instruction { dissassembly: "BIT 2,L",     closure: |ctx| Self::op_bitn_r(2, ctx.r.l, &mut ctx.r.f) }),
+            (0xCB5F_u16, Instruction { dissassembly: "BIT 3,A",     closure: |ctx| Self::op_bitn_r(3, ctx.r.a, &mut ctx.r.f) }),
+            (0xCB58_u16, Instruction { dissassembly: "BIT 3,B",     closure: |ctx| Self::op_bitn_r(3, ctx.r.b, &mut ctx.r.f) }),
+            (0xCB59_u16, Instruction { dissassembly: "BIT 3,C",     closure: |ctx| Self::op_bitn_r(3, ctx.r.c, &mut ctx.r.f) }),
+            (0xCB5A_u16, Instruction { dissassembly: "BIT 3,D",     closure: |ctx| Self::op_bitn_r(3, ctx.r.d, &mut ctx.r.f) }),
+            (0xCB5B_u16, Instruction { dissassembly: "BIT 3,E",     closure: |ctx| Self::op_bitn_r(3, ctx.r.e, &mut ctx.r.f) }),
+            (0xCB5C_u16, Instruction { dissassembly: "BIT 3,H",     closure: |ctx| Self::op_bitn_r(3, ctx.r.h, &mut ctx.r.f) }),
+            (0xCB5D_u16, Instruction { dissassembly: "BIT 3,L",     closure: |ctx| Self::op_bitn_r(3, ctx.r.l, &mut ctx.r.f) }),
+            (0xCB67_u16, Instruction { dissassembly: "BIT 4,A",     closure: |ctx| Self::op_bitn_r(4, ctx.r.a, &mut ctx.r.f) }),
+            (0xCB60_u16, Instruction { dissassembly: "BIT 4,B",     closure: |ctx| Self::op_bitn_r(4, ctx.r.b, &mut ctx.r.f) }),
+            (0xCB61_u16, Instruction { dissassembly: "BIT 4,C",     closure: |ctx| Self::op_bitn_r(4, ctx.r.c, &mut ctx.r.f) }),
+            (0xCB62_u16, Instruction { dissassembly: "BIT 4,D",     closure: |ctx| Self::op_bitn_r(4, ctx.r.d, &mut ctx.r.f) }),
+            (0xCB63_u16, Instruction { dissassembly: "BIT 4,E",     closure: |ctx| Self::op_bitn_r(4, ctx.r.e, &mut ctx.r.f) }),
+            (0xCB64_u16, Instruction { dissassembly: "BIT 4,H",     closure: |ctx| Self::op_bitn_r(4, ctx.r.h, &mut ctx.r.f) }),
+            (0xCB65_u16, Instruction { dissassembly: "BIT 4,L",     closure: |ctx| Self::op_bitn_r(4, ctx.r.l, &mut ctx.r.f) }),
+            (0xCB6F_u16, Instruction { dissassembly: "BIT 5,A",     closure: |ctx| Self::op_bitn_r(5, ctx.r.a, &mut ctx.r.f) }),
+            (0xCB68_u16, Instruction { dissassembly: "BIT 5,B",     closure: |ctx| Self::op_bitn_r(5, ctx.r.b, &mut ctx.r.f) }),
+            (0xCB69_u16, Instruction { dissassembly: "BIT 5,C",     closure: |ctx| Self::op_bitn_r(5, ctx.r.c, &mut ctx.r.f) }),
+            (0xCB6A_u16, Instruction { dissassembly: "BIT 5,D",     closure: |ctx| Self::op_bitn_r(5, ctx.r.d, &mut ctx.r.f) }),
+            (0xCB6B_u16, Instruction { dissassembly: "BIT 5,E",     closure: |ctx| Self::op_bitn_r(5, ctx.r.e, &mut ctx.r.f) }),
+            (0xCB6C_u16, Instruction { dissassembly: "BIT 5,H",     closure: |ctx| Self::op_bitn_r(5, ctx.r.h, &mut ctx.r.f) }),
+            (0xCB6D_u16, Instruction { dissassembly: "BIT 5,L",     closure: |ctx| Self::op_bitn_r(5, ctx.r.l, &mut ctx.r.f) }),
+            (0xCB77_u16, Instruction { dissassembly: "BIT 6,A",     closure: |ctx| Self::op_bitn_r(6, ctx.r.a, &mut ctx.r.f) }),
+            (0xCB70_u16, Instruction { dissassembly: "BIT 6,B",     closure: |ctx| Self::op_bitn_r(6, ctx.r.b, &mut ctx.r.f) }),
+            (0xCB71_u16, Instruction { dissassembly: "BIT 6,C",     closure: |ctx| Self::op_bitn_r(6, ctx.r.c, &mut ctx.r.f) }),
+            (0xCB72_u16, Instruction { dissassembly: "BIT 6,D",     closure: |ctx| Self::op_bitn_r(6, ctx.r.d, &mut ctx.r.f) }),
+            (0xCB73_u16, Instruction { dissassembly: "BIT 6,E",     closure: |ctx| Self::op_bitn_r(6, ctx.r.e, &mut ctx.r.f) }),
+            (0xCB74_u16, Instruction { dissassembly: "BIT 6,H",     closure: |ctx| Self::op_bitn_r(6, ctx.r.h, &mut ctx.r.f) }),
+            (0xCB75_u16, Instruction { dissassembly: "BIT 6,L",     closure: |ctx| Self::op_bitn_r(6, ctx.r.l, &mut ctx.r.f) }),
+            (0xCB7F_u16, Instruction { dissassembly: "BIT 7,A",     closure: |ctx| Self::op_bitn_r(7, ctx.r.a, &mut ctx.r.f) }),
+            (0xCB78_u16, Instruction { dissassembly: "BIT 7,B",     closure: |ctx| Self::op_bitn_r(7, ctx.r.b, &mut ctx.r.f) }),
+            (0xCB79_u16, Instruction { dissassembly: "BIT 7,C",     closure: |ctx| Self::op_bitn_r(7, ctx.r.c, &mut ctx.r.f) }),
+            (0xCB7A_u16, Instruction { dissassembly: "BIT 7,D",     closure: |ctx| Self::op_bitn_r(7, ctx.r.d, &mut ctx.r.f) }),
+            (0xCB7B_u16, Instruction { dissassembly: "BIT 7,E",     closure: |ctx| Self::op_bitn_r(7, ctx.r.e, &mut ctx.r.f) }),
+            (0xCB7C_u16, Instruction { dissassembly: "BIT 7,H",     closure: |ctx| Self::op_bitn_r(7, ctx.r.h, &mut ctx.r.f) }),
+            (0xCB7D_u16, Instruction { dissassembly: "BIT 7,L",     closure: |ctx| Self::op_bitn_r(7, ctx.r.l, &mut ctx.r.f) }),
+            (0xCB46_u16, Instruction { dissassembly: "BIT 0,(HL)",  closure: |ctx| Self::op_bitn_addr(ctx.bus, 0, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) }),
+            (0xCB4E_u16, Instruction { dissassembly: "BIT 1,(HL)",  closure: |ctx| Self::op_bitn_addr(ctx.bus, 1, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) }),
+            (0xCB56_u16, Instruction { dissassembly: "BIT 2,(HL)",  closure: |ctx| Self::op_bitn_addr(ctx.bus, 2, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) }),
+            (0xCB5E_u16, Instruction { dissassembly: "BIT 3,(HL)",  closure: |ctx| Self::op_bitn_addr(ctx.bus, 3, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) }),
+            (0xCB66_u16, Instruction { dissassembly: "BIT 4,(HL)",  closure: |ctx| Self::op_bitn_addr(ctx.bus, 4, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) }),
+            (0xCB6E_u16, Instruction { dissassembly: "BIT 5,(HL)",  closure: |ctx| Self::op_bitn_addr(ctx.bus, 5, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) }),
+            (0xCB76_u16, Instruction { dissassembly: "BIT 6,(HL)",  closure: |ctx| Self::op_bitn_addr(ctx.bus, 6, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) }),
+            (0xCB7E_u16, Instruction { dissassembly: "BIT 7,(HL)",  closure: |ctx| Self::op_bitn_addr(ctx.bus, 7, to_u16(ctx.r.h, ctx.r.l), &mut ctx.r.f) }),
+
+            (0xCBC7_u16, Instruction { dissassembly: "SET 0,A",     closure: |ctx| Self::op_setn_r(0, &mut ctx.r.a) }),
+            (0xCBC0_u16, Instruction { dissassembly: "SET 0,B",     closure: |ctx| Self::op_setn_r(0, &mut ctx.r.b) }),
+            (0xCBC1_u16, Instruction { dissassembly: "SET 0,C",     closure: |ctx| Self::op_setn_r(0, &mut ctx.r.c) }),
+            (0xCBC2_u16, Instruction { dissassembly: "SET 0,D",     closure: |ctx| Self::op_setn_r(0, &mut ctx.r.d) }),
+            (0xCBC3_u16, Instruction { dissassembly: "SET 0,E",     closure: |ctx| Self::op_setn_r(0, &mut ctx.r.e) }),
+            (0xCBC4_u16, Instruction { dissassembly: "SET 0,H",     closure: |ctx| Self::op_setn_r(0, &mut ctx.r.h) }),
+            (0xCBC5_u16, Instruction { dissassembly: "SET 0,L",     closure: |ctx| Self::op_setn_r(0, &mut ctx.r.l) }),
+            (0xCBCF_u16, Instruction { dissassembly: "SET 1,A",     closure: |ctx| Self::op_setn_r(1, &mut ctx.r.a) }),
+            (0xCBC8_u16, Instruction { dissassembly: "SET 1,B",     closure: |ctx| Self::op_setn_r(1, &mut ctx.r.b) }),
+            (0xCBC9_u16, Instruction { dissassembly: "SET 1,C",     closure: |ctx| Self::op_setn_r(1, &mut ctx.r.c) }),
+            (0xCBCA_u16, Instruction { dissassembly: "SET 1,D",     closure: |ctx| Self::op_setn_r(1, &mut ctx.r.d) }),
+            (0xCBCB_u16, Instruction { dissassembly: "SET 1,E",     closure: |ctx| Self::op_setn_r(1, &mut ctx.r.e) }),
+            (0xCBCC_u16, Instruction { dissassembly: "SET 1,H",     closure: |ctx| Self::op_setn_r(1, &mut ctx.r.h) }),
+            (0xCBCD_u16, Instruction { dissassembly: "SET 1,L",     closure: |ctx| Self::op_setn_r(1, &mut ctx.r.l) }),
+            (0xCBD7_u16, Instruction { dissassembly: "SET 2,A",     closure: |ctx| Self::op_setn_r(2, &mut ctx.r.a) }),
+            (0xCBD0_u16, Instruction { dissassembly: "SET 2,B",     closure: |ctx| Self::op_setn_r(2, &mut ctx.r.b) }),
+            (0xCBD1_u16, Instruction { dissassembly: "SET 2,C",     closure: |ctx| Self::op_setn_r(2, &mut ctx.r.c) }),
+            (0xCBD2_u16, Instruction { dissassembly: "SET 2,D",     closure: |ctx| Self::op_setn_r(2, &mut ctx.r.d) }),
+            (0xCBD3_u16, Instruction { dissassembly: "SET 2,E",     closure: |ctx| Self::op_setn_r(2, &mut ctx.r.e) }),
+            (0xCBD4_u16, Instruction { dissassembly: "SET 2,H",     closure: |ctx| Self::op_setn_r(2, &mut ctx.r.h) }),
+            (0xCBD5_u16, Instruction { dissassembly: "SET 2,L",     closure: |ctx| Self::op_setn_r(2, &mut ctx.r.l) }),
+            (0xCBDF_u16, Instruction { dissassembly: "SET 3,A",     closure: |ctx| Self::op_setn_r(3, &mut ctx.r.a) }),
+            (0xCBD8_u16, Instruction { dissassembly: "SET 3,B",     closure: |ctx| Self::op_setn_r(3, &mut ctx.r.b) }),
+            (0xCBD9_u16, Instruction { dissassembly: "SET 3,C",     closure: |ctx| Self::op_setn_r(3, &mut ctx.r.c) }),
+            (0xCBDA_u16, Instruction { dissassembly: "SET 3,D",     closure: |ctx| Self::op_setn_r(3, &mut ctx.r.d) }),
+            (0xCBDB_u16, Instruction { dissassembly: "SET 3,E",     closure: |ctx| Self::op_setn_r(3, &mut ctx.r.e) }),
+            (0xCBDC_u16, Instruction { dissassembly: "SET 3,H",     closure: |ctx| Self::op_setn_r(3, &mut ctx.r.h) }),
+            (0xCBDD_u16, Instruction { dissassembly: "SET 3,L",     closure: |ctx| Self::op_setn_r(3, &mut ctx.r.l) }),
+            (0xCBE7_u16, Instruction { dissassembly: "SET 4,A",     closure: |ctx| Self::op_setn_r(4, &mut ctx.r.a) }),
+            (0xCBE0_u16, Instruction { dissassembly: "SET 4,B",     closure: |ctx| Self::op_setn_r(4, &mut ctx.r.b) }),
+            (0xCBE1_u16, Instruction { dissassembly: "SET 4,C",     closure: |ctx| Self::op_setn_r(4, &mut ctx.r.c) }),
+            (0xCBE2_u16, Instruction { dissassembly: "SET 4,D",     closure: |ctx| Self::op_setn_r(4, &mut ctx.r.d) }),
+            (0xCBE3_u16, Instruction { dissassembly: "SET 4,E",     closure: |ctx| Self::op_setn_r(4, &mut ctx.r.e) }),
+            (0xCBE4_u16, Instruction { dissassembly: "SET 4,H",     closure: |ctx| Self::op_setn_r(4, &mut ctx.r.h) }),
+            (0xCBE5_u16, Instruction { dissassembly: "SET 4,L",     closure: |ctx| Self::op_setn_r(4, &mut ctx.r.l) }),
+            (0xCBEF_u16, Instruction { dissassembly: "SET 5,A",     closure: |ctx| Self::op_setn_r(5, &mut ctx.r.a) }),
+            (0xCBE8_u16, Instruction { dissassembly: "SET 5,B",     closure: |ctx| Self::op_setn_r(5, &mut ctx.r.b) }),
+            (0xCBE9_u16, Instruction { dissassembly: "SET 5,C",     closure: |ctx| Self::op_setn_r(5, &mut ctx.r.c) }),
+            (0xCBEA_u16, Instruction { dissassembly: "SET 5,D",     closure: |ctx| Self::op_setn_r(5, &mut ctx.r.d) }),
+            (0xCBEB_u16, Instruction { dissassembly: "SET 5,E",     closure: |ctx| Self::op_setn_r(5, &mut ctx.r.e) }),
+            (0xCBEC_u16, Instruction { dissassembly: "SET 5,H",     closure: |ctx| Self::op_setn_r(5, &mut ctx.r.h) }),
+            (0xCBED_u16, Instruction { dissassembly: "SET 5,L",     closure: |ctx| Self::op_setn_r(5, &mut ctx.r.l) }),
+            (0xCBF7_u16, Instruction { dissassembly: "SET 6,A",     closure: |ctx| Self::op_setn_r(6, &mut ctx.r.a) }),
+            (0xCBF0_u16, Instruction { dissassembly: "SET 6,B",     closure: |ctx| Self::op_setn_r(6, &mut ctx.r.b) }),
+            (0xCBF1_u16, Instruction { dissassembly: "SET 6,C",     closure: |ctx| Self::op_setn_r(6, &mut ctx.r.c) }),
+            (0xCBF2_u16, Instruction { dissassembly: "SET 6,D",     closure: |ctx| Self::op_setn_r(6, &mut ctx.r.d) }),
+            (0xCBF3_u16, Instruction { dissassembly: "SET 6,E",     closure: |ctx| Self::op_setn_r(6, &mut ctx.r.e) }),
+            (0xCBF4_u16, Instruction { dissassembly: "SET 6,H",     closure: |ctx| Self::op_setn_r(6, &mut ctx.r.h) }),
+            (0xCBF5_u16, Instruction { dissassembly: "SET 6,L",     closure: |ctx| Self::op_setn_r(6, &mut ctx.r.l) }),
+            (0xCBFF_u16, Instruction { dissassembly: "SET 7,A",     closure: |ctx| Self::op_setn_r(7, &mut ctx.r.a) }),
+            (0xCBF8_u16, Instruction { dissassembly: "SET 7,B",     closure: |ctx| Self::op_setn_r(7, &mut ctx.r.b) }),
+            (0xCBF9_u16, Instruction { dissassembly: "SET 7,C",     closure: |ctx| Self::op_setn_r(7, &mut ctx.r.c) }),
+            (0xCBFA_u16, Instruction { dissassembly: "SET 7,D",     closure: |ctx| Self::op_setn_r(7, &mut ctx.r.d) }),
+            (0xCBFB_u16, Instruction { dissassembly: "SET 7,E",     closure: |ctx| Self::op_setn_r(7, &mut ctx.r.e) }),
+            (0xCBFC_u16, Instruction { dissassembly: "SET 7,H",     closure: |ctx| Self::op_setn_r(7, &mut ctx.r.h) }),
+            (0xCBFD_u16, Instruction { dissassembly: "SET 7,L",     closure: |ctx| Self::op_setn_r(7, &mut ctx.r.l) }),
+            (0xCB87_u16, Instruction { dissassembly: "RES 0,A",     closure: |ctx| Self::op_resn_r(0, &mut ctx.r.a) }),
+            (0xCB80_u16, Instruction { dissassembly: "RES 0,B",     closure: |ctx| Self::op_resn_r(0, &mut ctx.r.b) }),
+            (0xCB81_u16, Instruction { dissassembly: "RES 0,C",     closure: |ctx| Self::op_resn_r(0, &mut ctx.r.c) }),
+            (0xCB82_u16, Instruction { dissassembly: "RES 0,D",     closure: |ctx| Self::op_resn_r(0, &mut ctx.r.d) }),
+            (0xCB83_u16, Instruction { dissassembly: "RES 0,E",     closure: |ctx| Self::op_resn_r(0, &mut ctx.r.e) }),
+            (0xCB84_u16, Instruction { dissassembly: "RES 0,H",     closure: |ctx| Self::op_resn_r(0, &mut ctx.r.h) }),
+            (0xCB85_u16, Instruction { dissassembly: "RES 0,L",     closure: |ctx| Self::op_resn_r(0, &mut ctx.r.l) }),
+            (0xCB8F_u16, Instruction { dissassembly: "RES 1,A",     closure: |ctx| Self::op_resn_r(1, &mut ctx.r.a) }),
+            (0xCB88_u16, Instruction { dissassembly: "RES 1,B",     closure: |ctx| Self::op_resn_r(1, &mut ctx.r.b) }),
+            (0xCB89_u16, Instruction { dissassembly: "RES 1,C",     closure: |ctx| Self::op_resn_r(1, &mut ctx.r.c) }),
+            (0xCB8A_u16, Instruction { dissassembly: "RES 1,D",     closure: |ctx| Self::op_resn_r(1, &mut ctx.r.d) }),
+            (0xCB8B_u16, Instruction { dissassembly: "RES 1,E",     closure: |ctx| Self::op_resn_r(1, &mut ctx.r.e) }),
+            (0xCB8C_u16, Instruction { dissassembly: "RES 1,H",     closure: |ctx| Self::op_resn_r(1, &mut ctx.r.h) }),
+            (0xCB8D_u16, Instruction { dissassembly: "RES 1,L",     closure: |ctx| Self::op_resn_r(1, &mut ctx.r.l) }),
+            (0xCB97_u16, Instruction { dissassembly: "RES 2,A",     closure: |ctx| Self::op_resn_r(2, &mut ctx.r.a) }),
+            (0xCB90_u16, Instruction { dissassembly: "RES 2,B",     closure: |ctx| Self::op_resn_r(2, &mut ctx.r.b) }),
+            (0xCB91_u16, Instruction { dissassembly: "RES 2,C",     closure: |ctx| Self::op_resn_r(2, &mut ctx.r.c) }),
+            (0xCB92_u16, Instruction { dissassembly: "RES 2,D",     closure: |ctx| Self::op_resn_r(2, &mut ctx.r.d) }),
+            (0xCB93_u16, Instruction { dissassembly: "RES 2,E",     closure: |ctx| Self::op_resn_r(2, &mut ctx.r.e) }),
+            (0xCB94_u16, Instruction { dissassembly: "RES 2,H",     closure: |ctx| Self::op_resn_r(2, &mut ctx.r.h) }),
+            (0xCB95_u16, Instruction { dissassembly: "RES 2,L",     closure: |ctx| Self::op_resn_r(2, &mut ctx.r.l) }),
+            (0xCB9F_u16, Instruction { dissassembly: "RES 3,A",     closure: |ctx| Self::op_resn_r(3, &mut ctx.r.a) }),
+            (0xCB98_u16, Instruction { dissassembly: "RES 3,B",     closure: |ctx| Self::op_resn_r(3, &mut ctx.r.b) }),
+            (0xCB99_u16, Instruction { dissassembly: "RES 3,C",     closure: |ctx| Self::op_resn_r(3, &mut ctx.r.c) }),
+            (0xCB9A_u16, Instruction { dissassembly: "RES 3,D",     closure: |ctx| Self::op_resn_r(3, &mut ctx.r.d) }),
+            (0xCB9B_u16, Instruction { dissassembly: "RES 3,E",     closure: |ctx| Self::op_resn_r(3, &mut ctx.r.e) }),
+            (0xCB9C_u16, Instruction { dissassembly: "RES 3,H",     closure: |ctx| Self::op_resn_r(3, &mut ctx.r.h) }),
+            (0xCB9D_u16, Instruction { dissassembly: "RES 3,L",     closure: |ctx| Self::op_resn_r(3, &mut ctx.r.l) }),
+            (0xCBA7_u16, Instruction { dissassembly: "RES 4,A",     closure: |ctx| Self::op_resn_r(4, &mut ctx.r.a) }),
+            (0xCBA0_u16, Instruction { dissassembly: "RES 4,B",     closure: |ctx| Self::op_resn_r(4, &mut ctx.r.b) }),
+            (0xCBA1_u16, Instruction { dissassembly: "RES 4,C",     closure: |ctx| Self::op_resn_r(4, &mut ctx.r.c) }),
+            (0xCBA2_u16, Instruction { dissassembly: "RES 4,D",     closure: |ctx| Self::op_resn_r(4, &mut ctx.r.d) }),
+            (0xCBA3_u16, Instruction { dissassembly: "RES 4,E",     closure: |ctx| Self::op_resn_r(4, &mut ctx.r.e) }),
+            (0xCBA4_u16, Instruction { dissassembly: "RES 4,H",     closure: |ctx| Self::op_resn_r(4, &mut ctx.r.h) }),
+            (0xCBA5_u16, Instruction { dissassembly: "RES 4,L",     closure: |ctx| Self::op_resn_r(4, &mut ctx.r.l) }),
+            (0xCBAF_u16, Instruction { dissassembly: "RES 5,A",     closure: |ctx| Self::op_resn_r(5, &mut ctx.r.a) }),
+            (0xCBA8_u16, Instruction { dissassembly: "RES 5,B",     closure: |ctx| Self::op_resn_r(5, &mut ctx.r.b) }),
+            (0xCBA9_u16, Instruction { dissassembly: "RES 5,C",     closure: |ctx| Self::op_resn_r(5, &mut ctx.r.c) }),
+            (0xCBAA_u16, Instruction { dissassembly: "RES 5,D",     closure: |ctx| Self::op_resn_r(5, &mut ctx.r.d) }),
+            (0xCBAB_u16, Instruction { dissassembly: "RES 5,E",     closure: |ctx| Self::op_resn_r(5, &mut ctx.r.e) }),
+            (0xCBAC_u16, Instruction { dissassembly: "RES 5,H",     closure: |ctx| Self::op_resn_r(5, &mut ctx.r.h) }),
+            (0xCBAD_u16, Instruction { dissassembly: "RES 5,L",     closure: |ctx| Self::op_resn_r(5, &mut ctx.r.l) }),
+            (0xCBB7_u16, Instruction { dissassembly: "RES 6,A",     closure: |ctx| Self::op_resn_r(6, &mut ctx.r.a) }),
+            (0xCBB0_u16, Instruction { dissassembly: "RES 6,B",     closure: |ctx| Self::op_resn_r(6, &mut ctx.r.b) }),
+            (0xCBB1_u16, Instruction { dissassembly: "RES 6,C",     closure: |ctx| Self::op_resn_r(6, &mut ctx.r.c) }),
+            (0xCBB2_u16, Instruction { dissassembly: "RES 6,D",     closure: |ctx| Self::op_resn_r(6, &mut ctx.r.d) }),
+            (0xCBB3_u16, Instruction { dissassembly: "RES 6,E",     closure: |ctx| Self::op_resn_r(6, &mut ctx.r.e) }),
+            (0xCBB4_u16, Instruction { dissassembly: "RES 6,H",     closure: |ctx| Self::op_resn_r(6, &mut ctx.r.h) }),
+            (0xCBB5_u16, Instruction { dissassembly: "RES 6,L",     closure: |ctx| Self::op_resn_r(6, &mut ctx.r.l) }),
+            (0xCBBF_u16, Instruction { dissassembly: "RES 7,A",     closure: |ctx| Self::op_resn_r(7, &mut ctx.r.a) }),
+            (0xCBB8_u16, Instruction { dissassembly: "RES 7,B",     closure: |ctx| Self::op_resn_r(7, &mut ctx.r.b) }),
+            (0xCBB9_u16, Instruction { dissassembly: "RES 7,C",     closure: |ctx| Self::op_resn_r(7, &mut ctx.r.c) }),
+            (0xCBBA_u16, Instruction { dissassembly: "RES 7,D",     closure: |ctx| Self::op_resn_r(7, &mut ctx.r.d) }),
+            (0xCBBB_u16, Instruction { dissassembly: "RES 7,E",     closure: |ctx| Self::op_resn_r(7, &mut ctx.r.e) }),
+            (0xCBBC_u16, Instruction { dissassembly: "RES 7,H",     closure: |ctx| Self::op_resn_r(7, &mut ctx.r.h) }),
+            (0xCBBD_u16, Instruction { dissassembly: "RES 7,L",     closure: |ctx| Self::op_resn_r(7, &mut ctx.r.l) }),
+            (0xCB86_u16, Instruction { dissassembly: "RES 0,(HL)",  closure: |ctx| Self::op_resn_addr(ctx.bus, 0, to_u16(ctx.r.h, ctx.r.l)) }),
+            (0xCB8E_u16, Instruction { dissassembly: "RES 1,(HL)",  closure: |ctx| Self::op_resn_addr(ctx.bus, 1, to_u16(ctx.r.h, ctx.r.l)) }),
+            (0xCB96_u16, Instruction { dissassembly: "RES 2,(HL)",  closure: |ctx| Self::op_resn_addr(ctx.bus, 2, to_u16(ctx.r.h, ctx.r.l)) }),
+            (0xCB9E_u16, Instruction { dissassembly: "RES 3,(HL)",  closure: |ctx| Self::op_resn_addr(ctx.bus, 3, to_u16(ctx.r.h, ctx.r.l)) }),
+            (0xCBA6_u16, Instruction { dissassembly: "RES 4,(HL)",  closure: |ctx| Self::op_resn_addr(ctx.bus, 4, to_u16(ctx.r.h, ctx.r.l)) }),
+            (0xCBAE_u16, Instruction { dissassembly: "RES 5,(HL)",  closure: |ctx| Self::op_resn_addr(ctx.bus, 5, to_u16(ctx.r.h, ctx.r.l)) }),
+            (0xCBB6_u16, Instruction { dissassembly: "RES 6,(HL)",  closure: |ctx| Self::op_resn_addr(ctx.bus, 6, to_u16(ctx.r.h, ctx.r.l)) }),
+            (0xCBBE_u16, Instruction { dissassembly: "RES 7,(HL)",  closure: |ctx| Self::op_resn_addr(ctx.bus, 7, to_u16(ctx.r.h, ctx.r.l)) }),
+            (0xCBC6_u16, Instruction { dissassembly: "SET 0,(HL)",  closure: |ctx| Self::op_setn_addr(ctx.bus, 0, to_u16(ctx.r.h, ctx.r.l)) }),
+            (0xCBCE_u16, Instruction { dissassembly: "SET 1,(HL)",  closure: |ctx| Self::op_setn_addr(ctx.bus, 1, to_u16(ctx.r.h, ctx.r.l)) }),
+            (0xCBD6_u16, Instruction { dissassembly: "SET 2,(HL)",  closure: |ctx| Self::op_setn_addr(ctx.bus, 2, to_u16(ctx.r.h, ctx.r.l)) }),
+            (0xCBDE_u16, Instruction { dissassembly: "SET 3,(HL)",  closure: |ctx| Self::op_setn_addr(ctx.bus, 3, to_u16(ctx.r.h, ctx.r.l)) }),
+            (0xCBE6_u16, Instruction { dissassembly: "SET 4,(HL)",  closure: |ctx| Self::op_setn_addr(ctx.bus, 4, to_u16(ctx.r.h, ctx.r.l)) }),
+            (0xCBEE_u16, Instruction { dissassembly: "SET 5,(HL)",  closure: |ctx| Self::op_setn_addr(ctx.bus, 5, to_u16(ctx.r.h, ctx.r.l)) }),
+            (0xCBF6_u16, Instruction { dissassembly: "SET 6,(HL)",  closure: |ctx| Self::op_setn_addr(ctx.bus, 6, to_u16(ctx.r.h, ctx.r.l)) }),
+            (0xCBFE_u16, Instruction { dissassembly: "SET 7,(HL)",  closure: |ctx| Self::op_setn_addr(ctx.bus, 7, to_u16(ctx.r.h, ctx.r.l)) }),
         ].iter().cloned().collect();
 
         Self {
@@ -645,6 +753,7 @@ impl CPU {
             },
             state: CPUState {
                 mode: CPUMode::Normal,
+                speed_switch_cycles: 0,
                 next_op: 0x0000,
             },            
         }
@@ -692,7 +801,95 @@ impl CPU {
         }
     }
 
+    pub fn set_register(&mut self, register: CPURegister, value: u16) {
+        let r = &mut self.registers;
+        let (high, low) = ((value >> 8) as u8, value as u8);
+
+        match register {
+            CPURegister::A => r.a = low,
+            // the lower nibble of F always reads as 0
+            CPURegister::F => r.f = low & 0xF0,
+            CPURegister::B => r.b = low,
+            CPURegister::C => r.c = low,
+            CPURegister::D => r.d = low,
+            CPURegister::E => r.e = low,
+            CPURegister::H => r.h = low,
+            CPURegister::L => r.l = low,
+            CPURegister::AF => { r.a = high; r.f = low & 0xF0; },
+            CPURegister::BC => { r.b = high; r.c = low; },
+            CPURegister::DE => { r.d = high; r.e = low; },
+            CPURegister::HL => { r.h = high; r.l = low; },
+            CPURegister::SP => r.sp = value,
+            CPURegister::PC => r.pc = value,
+        }
+    }
+
+    pub fn save_state(&self, w: &mut StateWriter) {
+        let r = &self.registers;
+
+        w.write_u8(r.a);
+        w.write_u8(r.f);
+        w.write_u8(r.b);
+        w.write_u8(r.c);
+        w.write_u8(r.d);
+        w.write_u8(r.e);
+        w.write_u8(r.h);
+        w.write_u8(r.l);
+        w.write_u16(r.sp);
+        w.write_u16(r.pc);
+
+        w.write_u8(match self.state.mode {
+            CPUMode::Normal => 0,
+            CPUMode::Halt => 1,
+            CPUMode::Stop => 2,
+            CPUMode::SpeedSwitch => 3,
+        });
+        w.write_u16(self.state.next_op);
+        w.write_u16(self.state.speed_switch_cycles);
+    }
+
+    pub fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        let r = &mut self.registers;
+
+        r.a = reader.read_u8()?;
+        r.f = reader.read_u8()?;
+        r.b = reader.read_u8()?;
+        r.c = reader.read_u8()?;
+        r.d = reader.read_u8()?;
+        r.e = reader.read_u8()?;
+        r.h = reader.read_u8()?;
+        r.l = reader.read_u8()?;
+        r.sp = reader.read_u16()?;
+        r.pc = reader.read_u16()?;
+
+        self.state.mode = match reader.read_u8()? {
+            0 => CPUMode::Normal,
+            1 => CPUMode::Halt,
+            2 => CPUMode::Stop,
+            3 => CPUMode::SpeedSwitch,
+            _ => return Err(SaveStateError::InvalidData("CPU mode"))
+        };
+        self.state.next_op = reader.read_u16()?;
+        self.state.speed_switch_cycles = reader.read_u16()?;
+
+        if self.state.mode == CPUMode::SpeedSwitch && self.state.speed_switch_cycles == 0 {
+            return Err(SaveStateError::InvalidData("CPU speed switch"));
+        }
+
+        Ok(())
+    }
+
     pub fn tick(&mut self, bus: &mut CPUMemoryBus) -> u8 {
+        // the CPU sits out the speed switch one M-cycle at a time, so everything else keeps running
+        if self.state.mode == CPUMode::SpeedSwitch {
+            self.state.speed_switch_cycles -= 1;
+            if self.state.speed_switch_cycles == 0 {
+                self.state.mode = CPUMode::Normal;
+            }
+
+            return 1;
+        }
+
         let mut cycles = 0;
 
         cycles += self.dispatch_interrupts(bus);
@@ -721,8 +918,9 @@ impl CPU {
         if cycles == 0 { 1 } else { cycles } 
     }
 
+    // Opcodes and operands are fetched with peek_byte, read watchpoints only watch data
     fn read_next_instruction(&mut self, bus: &mut CPUMemoryBus, advance_pc: bool) -> u16 {
-        let b1 = bus.read_byte(self.registers.pc);
+        let b1 = bus.peek_byte(self.registers.pc);
         if advance_pc {
             self.registers.pc += 1;
         }
@@ -731,7 +929,7 @@ impl CPU {
             b1 as u16
         }
         else {
-            let b2: u8 = bus.read_byte(self.registers.pc);
+            let b2: u8 = bus.peek_byte(if advance_pc { self.registers.pc } else { self.registers.pc.wrapping_add(1) });
             if advance_pc {
                 self.registers.pc += 1;
             }
@@ -794,7 +992,7 @@ impl CPU {
     }
 
     fn read_byte_from_pc(bus: &mut CPUMemoryBus, pc: &mut u16) -> u8 {
-        let b = bus.read_byte(*pc);
+        let b = bus.peek_byte(*pc);
         *pc += 1;
         
         b
@@ -815,9 +1013,22 @@ impl CPU {
         1
     }
     
-    fn op_stop(state: &mut CPUState, interrupts: &CPUInterrupts) -> u8 {
+    fn op_stop(state: &mut CPUState, bus: &mut CPUMemoryBus) -> u8 {
+        // on GBC, STOP with the switch armed in KEY1 toggles double speed instead of stopping, DIV is reset too.
+        // The CPU is then stopped for a while before it runs at the new speed
+        if bus.model == GameBoyModel::GBC && bus.speed.switch_armed {
+            bus.speed.double_speed = !bus.speed.double_speed;
+            bus.speed.switch_armed = false;
+            bus.timer.write_byte(0xFF04, 0);
+
+            state.mode = CPUMode::SpeedSwitch;
+            state.speed_switch_cycles = SPEED_SWITCH_CYCLES;
+
+            return 1;
+        }
+
         // TODO: P10-P13 should be LOW
-        if interrupts.enabled == 0 {
+        if bus.interrupts.enabled == 0 {
             state.mode = CPUMode::Stop;
         }
     
@@ -1,32 +1,70 @@
+use std::io;
+
+use crate::bus::CPUMemoryBus;
 use crate::cpu::CPU;
-use crate::ppu::PPU;
+use crate::disassembler::{self, Symbols};
 
-pub struct Breakpoint {
-    address: u16
-}
+pub use self::repl::DebuggerRepl;
+pub use self::gdb::GdbServer;
+pub use self::breakpoint::Breakpoint;
+pub use self::expression::Expression;
+pub use self::watchpoint::{Watchpoint, Watchpoints, WatchKind, WatchCondition};
+mod repl;
+mod gdb;
+mod breakpoint;
+mod expression;
+mod watchpoint;
+
+// RET, RET cc and RETI
+const RET_OPCODES: [u16; 6] = [0xC9, 0xC0, 0xC8, 0xD0, 0xD8, 0xD9];
 
-pub struct Watchpoint {
-    address: u16,
-    value: u8
+// Why the machine stopped, gdb reports its breakpoints and watchpoints differently from a step or a pause
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum StopReason {
+    Other,
+    Breakpoint,
+    // the kind of watchpoint and the address that was accessed
+    Watchpoint(WatchKind, u16),
 }
 
 struct DebuggerState {
     stopped: bool,
+    stop_reason: StopReason,
+    // the opcode that was about to run the last time we looked, so the one that just ran
+    last_opcode: u16,
+    // one-off stop for "next", the address after the call being stepped over
+    run_until: Option<u16>,
+    // stop for "finish", once a RET leaves the stack above this
+    run_until_return: Option<u16>,
 }
 
 pub struct Debugger {
     breakpoints: Vec<Breakpoint>,
-    watchpoints: Vec<Watchpoint>,
+    next_breakpoint_id: usize,
+    watchpoints: Watchpoints,
+    symbols: Symbols,
     state: DebuggerState,
 }
 
+impl Default for Debugger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Debugger {
     pub fn new() -> Self {
         Self {
             breakpoints: vec!(),
-            watchpoints: vec!(),
+            next_breakpoint_id: 0,
+            watchpoints: Watchpoints::default(),
+            symbols: Symbols::new(),
             state: DebuggerState {
-                stopped: false
+                stopped: false,
+                stop_reason: StopReason::Other,
+                last_opcode: 0,
+                run_until: None,
+                run_until_return: None,
             },
         }
     }
@@ -35,56 +73,188 @@ impl Debugger {
         self.state.stopped
     }
 
+    pub fn get_stop_reason(&self) -> StopReason {
+        if self.state.stopped { self.state.stop_reason } else { StopReason::Other }
+    }
+
     pub fn resume(&mut self) {
         self.state.stopped = false;
     }
 
-    pub fn stop(&mut self, cpu: &CPU, ppu: &PPU) {
-        self.print_trace(cpu, ppu);
+    pub fn stop(&mut self, cpu: &CPU, bus: &CPUMemoryBus) {
+        self.print_trace(cpu, bus);
         self.state.stopped = true;
+        self.state.stop_reason = StopReason::Other;
+        self.state.run_until = None;
+        self.state.run_until_return = None;
+    }
+
+    // Resumes until PC gets to the address
+    pub fn run_until(&mut self, addr: u16) {
+        self.state.run_until = Some(addr);
+        self.resume();
+    }
+
+    // Resumes until the function we are in returns
+    pub fn run_until_return(&mut self, cpu: &CPU) {
+        let cpu_state = cpu.get_debug_state();
+
+        // single steps don't go through process, so what runs next may have moved on since
+        self.state.last_opcode = cpu_state.next_opcode;
+        self.state.run_until_return = Some(cpu_state.sp);
+        self.resume();
+    }
+
+    // Returns the id the breakpoint is known by from now on
+    pub fn add_breakpoint(&mut self, mut breakpoint: Breakpoint) -> usize {
+        let id = self.next_breakpoint_id;
+        self.next_breakpoint_id += 1;
+
+        breakpoint.id = id;
+        self.breakpoints.push(breakpoint);
+        id
+    }
+
+    pub fn remove_breakpoint(&mut self, id: usize) -> bool {
+        match self.breakpoints.iter().position(|b| b.id == id) {
+            Some(index) => {
+                self.breakpoints.remove(index);
+                true
+            },
+            None => false
+        }
+    }
+
+    pub fn get_breakpoints(&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
+
+    pub fn get_breakpoint_mut(&mut self, id: usize) -> Option<&mut Breakpoint> {
+        self.breakpoints.iter_mut().find(|b| b.id == id)
+    }
+
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) -> usize {
+        self.watchpoints.add(watchpoint)
     }
 
-    pub fn add_breakpoint(&mut self, addr: u16) {
-        self.breakpoints.push(Breakpoint {
-            address: addr
-        });
+    pub fn remove_watchpoint(&mut self, id: usize) -> bool {
+        self.watchpoints.remove(id)
     }
 
-    pub fn add_watchpoint(&mut self, addr: u16) {
-        self.watchpoints.push(Watchpoint {
-            address: addr,
-            value: 0
-        });
+    pub fn get_watchpoints(&self) -> &Watchpoints {
+        &self.watchpoints
     }
 
-    pub fn process(&mut self, cpu: &CPU, ppu: &PPU) {
+    // Labels from an RGBDS or WLA .sym file, for traces and disassembly
+    pub fn load_symbols(&mut self, filename: &str) -> io::Result<usize> {
+        self.symbols.load_file(filename)
+    }
+
+    pub fn get_symbols(&self) -> &Symbols {
+        &self.symbols
+    }
+
+    // Prints what the watchpoints caught while running the instruction at pc, returns the kind and address of the first hit
+    pub fn report_watch_hits(&self, pc: u16) -> Option<(WatchKind, u16)> {
+        let hits = self.watchpoints.take_hits();
+
+        let mut first = None;
+
+        for hit in &hits {
+            let watchpoint = match self.watchpoints.get().iter().find(|w| w.id == hit.id) {
+                Some(watchpoint) => watchpoint,
+                None => continue
+            };
+
+            first.get_or_insert((watchpoint.kind, hit.address));
+
+            let address = match watchpoint.bank {
+                Some(_) => format!("{:02X}:{:04X}", hit.bank, hit.address),
+                None => format!("{:04X}", hit.address)
+            };
+
+            if hit.write {
+                println!("Watchpoint {} ({}): write at PC={:04X} [{}] {:02X} -> {:02X}", hit.id, watchpoint, pc, address, hit.old, hit.new);
+            }
+            else {
+                println!("Watchpoint {} ({}): read at PC={:04X} [{}] = {:02X}", hit.id, watchpoint, pc, address, hit.new);
+            }
+        }
+
+        first
+    }
+
+    // pc is where the instruction that just ran started. Memory in conditions is read through the bus,
+    // without going through the watchpoints
+    pub fn process(&mut self, pc: u16, cpu: &CPU, bus: &CPUMemoryBus) {
         let cpu_state = cpu.get_debug_state();
 
-        for b in &self.breakpoints {
-            if b.address == cpu_state.pc {
-                self.print_trace(&cpu, ppu);
-                self.state.stopped = true;
-                break;
+        let executed = self.state.last_opcode;
+        self.state.last_opcode = cpu_state.next_opcode;
+
+        if let Some((kind, address)) = self.report_watch_hits(pc) {
+            self.stop(cpu, bus);
+            self.state.stop_reason = StopReason::Watchpoint(kind, address);
+            return;
+        }
+
+        if self.state.run_until == Some(cpu_state.pc) {
+            self.stop(cpu, bus);
+            return;
+        }
+
+        if let Some(sp) = self.state.run_until_return {
+            if RET_OPCODES.contains(&executed) && cpu_state.sp > sp {
+                self.stop(cpu, bus);
+                return;
             }
         }
 
-        // for w in &mut self.watchpoints {
-        //     let v = bus.read_byte(w.address);
-        //     if v != w.value {
-        //         w.value = v;
+        let bank = bus.get_bank(cpu_state.pc);
+        let mut stop_at = None;
+
+        for b in self.breakpoints.iter_mut() {
+            if !b.matches(cpu_state.pc, bank, &cpu_state, bus) {
+                continue;
+            }
+
+            b.hits += 1;
+            if b.hits <= b.ignore_count {
+                continue;
+            }
+
+            match &b.log {
+                Some(message) => println!("{}", Breakpoint::format_message(message, &cpu_state, bus)),
+                None => stop_at = stop_at.or(Some((b.id, b.hits, b.temporary)))
+            }
+        }
 
-        //         println!("@{:06X} Watch: {:#06X} = {:#04X}", cpu_state.pc, w.address, v);
-        //     }
-        // }
+        if let Some((id, hits, temporary)) = stop_at {
+            println!("Breakpoint {} at {:02X}:{:04X}, hit {} times", id, bank, cpu_state.pc, hits);
+
+            if temporary {
+                self.remove_breakpoint(id);
+            }
+
+            self.stop(cpu, bus);
+            self.state.stop_reason = StopReason::Breakpoint;
+        }
     }
 
-    pub fn print_trace(&self, cpu: &CPU, ppu: &PPU) {
+    pub fn print_trace(&self, cpu: &CPU, bus: &CPUMemoryBus) {
         let cpu_state = cpu.get_debug_state();
-        let ppu_state = ppu.get_debug_state();
+        let ppu_state = bus.ppu.get_debug_state();
+
+        let rom_bank = bus.get_bank(0x4000);
+        let instruction = disassembler::disassemble(|a| bus.peek_byte(a), rom_bank, cpu_state.pc, &self.symbols);
 
-        println!("@{:#06X} {} | AF: {:#06X} | BC: {:#06X} | DE: {:#06X} | HL: {:#06X} | LY: {} | STAT: {:#04X} | LCDC: {:#04X} | CNT: {}", 
+        if let Some(label) = self.symbols.get(rom_bank, cpu_state.pc) {
+            println!("{}:", label);
+        }
+
+        println!("@{:#06X} {:<16} | AF: {:#06X} | BC: {:#06X} | DE: {:#06X} | HL: {:#06X} | LY: {} | STAT: {:#04X} | LCDC: {:#04X} | CNT: {}", 
             cpu_state.pc, 
-            cpu_state.next_opcode, 
+            instruction.text, 
             cpu_state.af, 
             cpu_state.bc, 
             cpu_state.de, 
@@ -96,3 +266,37 @@ impl Debugger {
         );
     }
 }
+
+// 0x or $ for hex, decimal otherwise
+pub fn parse_number(s: &str) -> Result<u16, String> {
+    let s = s.trim();
+
+    let value = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")).or_else(|| s.strip_prefix('$')) {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => s.parse::<u16>()
+    };
+
+    value.map_err(|_| format!("Invalid number {}", s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn breakpoint_ids() {
+        let mut debugger = Debugger::new();
+        let ids: Vec<usize> = [0x100, 0x200, 0x300].iter().map(|a| debugger.add_breakpoint(Breakpoint::new(*a))).collect();
+        assert_eq!(ids, [0, 1, 2]);
+
+        // removing one leaves the others where they were, and its id isn't given out again
+        assert!(debugger.remove_breakpoint(1));
+        assert!(!debugger.remove_breakpoint(1));
+        assert_eq!(debugger.get_breakpoint_mut(2).map(|b| b.address), Some(0x300));
+        assert!(debugger.get_breakpoint_mut(1).is_none());
+        assert_eq!(debugger.add_breakpoint(Breakpoint::new(0x400)), 3);
+
+        let breakpoints: Vec<(usize, u16)> = debugger.get_breakpoints().iter().map(|b| (b.id, b.address)).collect();
+        assert_eq!(breakpoints, [(0, 0x100), (2, 0x300), (3, 0x400)]);
+    }
+}
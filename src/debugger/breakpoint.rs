@@ -0,0 +1,202 @@
+use std::fmt;
+
+use crate::bus::CPUMemoryBus;
+use crate::cpu::CPUDebugState;
+use crate::debugger::{Expression, parse_number};
+
+#[derive(Clone, Debug)]
+pub struct Breakpoint {
+    // given by the debugger when it's added, and never reused so removing one doesn't renumber the rest
+    pub id: usize,
+    pub address: u16,
+    // ROM bank for 0000-7FFF, VRAM bank for 8000-9FFF and WRAM bank for D000-DFFF, any bank if not set
+    pub bank: Option<u16>,
+    pub condition: Option<Expression>,
+    // hits to let through before stopping
+    pub ignore_count: u32,
+    // removed the first time it stops
+    pub temporary: bool,
+    // logpoints print the message and keep going, {expression} is replaced with its value
+    pub log: Option<String>,
+    pub hits: u32,
+}
+
+impl Breakpoint {
+    pub fn new(address: u16) -> Self {
+        Self {
+            id: 0,
+            address,
+            bank: None,
+            condition: None,
+            ignore_count: 0,
+            temporary: false,
+            log: None,
+            hits: 0,
+        }
+    }
+
+    // [bank:]address [if <condition>] [ignore <count>] [temp] [log <message>], the message takes the rest of the line:
+    //   0x0150
+    //   3:0x4123 if A == 0 && [FF44] > 100
+    //   0x0150 ignore 10 temp
+    //   0x2000 log switching to bank {A}
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut words = spec.split_whitespace();
+
+        let location = words.next().ok_or("Missing breakpoint address")?;
+        let (bank, address) = match location.split_once(':') {
+            Some((bank, address)) => (Some(parse_number(bank)?), address),
+            None => (None, location)
+        };
+
+        let mut breakpoint = Breakpoint::new(parse_number(address)?);
+        breakpoint.bank = bank;
+
+        let words: Vec<&str> = words.collect();
+        let mut i = 0;
+
+        while i < words.len() {
+            match words[i] {
+                "if" => {
+                    let end = words[i + 1..].iter()
+                        .position(|w| ["ignore", "temp", "log"].contains(w))
+                        .map_or(words.len(), |p| i + 1 + p);
+
+                    breakpoint.condition = Some(Expression::parse(&words[i + 1..end].join(" "))?);
+                    i = end;
+                },
+
+                "ignore" => {
+                    let count = words.get(i + 1).ok_or("Missing ignore count")?;
+                    breakpoint.ignore_count = count.parse().map_err(|_| format!("Invalid ignore count {}", count))?;
+                    i += 2;
+                },
+
+                "temp" => {
+                    breakpoint.temporary = true;
+                    i += 1;
+                },
+
+                "log" => {
+                    let message = words[i + 1..].join(" ");
+                    Breakpoint::check_message(&message)?;
+                    breakpoint.log = Some(message);
+                    break;
+                },
+
+                word => return Err(format!("Unexpected {} in breakpoint {}", word, spec.trim()))
+            }
+        }
+
+        Ok(breakpoint)
+    }
+
+    // Catches bad {expressions} when the logpoint is added rather than every time it's hit
+    fn check_message(message: &str) -> Result<(), String> {
+        for part in message.split('{').skip(1) {
+            let (expression, _) = part.split_once('}').ok_or_else(|| format!("Missing }} in {}", message))?;
+            Expression::parse(expression)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn format_message(message: &str, cpu: &CPUDebugState, bus: &CPUMemoryBus) -> String {
+        let mut parts = message.split('{');
+        let mut text = parts.next().unwrap_or("").to_owned();
+
+        for part in parts {
+            let (expression, rest) = part.split_once('}').unwrap_or((part, ""));
+
+            match Expression::parse(expression) {
+                Ok(e) => {
+                    let value = e.evaluate(cpu, bus);
+                    text += &if value > 0xFF { format!("{:04X}", value) } else { format!("{:02X}", value) };
+                },
+                Err(_) => text += expression
+            }
+
+            text += rest;
+        }
+
+        text
+    }
+
+    pub fn matches(&self, address: u16, bank: u16, cpu: &CPUDebugState, bus: &CPUMemoryBus) -> bool {
+        self.address == address
+            && self.bank.is_none_or(|b| b == bank)
+            && self.condition.as_ref().is_none_or(|c| c.evaluate(cpu, bus) != 0)
+    }
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(bank) = self.bank {
+            write!(f, "{:02X}:", bank)?;
+        }
+
+        write!(f, "{:#06x}", self.address)?;
+
+        if let Some(condition) = &self.condition {
+            write!(f, " if {}", condition)?;
+        }
+
+        if self.ignore_count > 0 {
+            write!(f, " ignore {}", self.ignore_count)?;
+        }
+
+        if self.temporary {
+            write!(f, " temp")?;
+        }
+
+        if let Some(log) = &self.log {
+            write!(f, " log {}", log)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_address() {
+        let b = Breakpoint::parse("0x0150").unwrap();
+        assert_eq!((b.address, b.bank, b.ignore_count, b.temporary), (0x0150, None, 0, false));
+        assert!(b.condition.is_none() && b.log.is_none());
+
+        let b = Breakpoint::parse("3:$4123").unwrap();
+        assert_eq!((b.address, b.bank), (0x4123, Some(3)));
+    }
+
+    #[test]
+    fn parse_options() {
+        let b = Breakpoint::parse("3:0x4123 if A == 0 && [FF44] > 100 ignore 2 temp log bank {A}").unwrap();
+        assert_eq!((b.address, b.bank, b.ignore_count, b.temporary), (0x4123, Some(3), 2, true));
+        assert_eq!(b.condition.unwrap().to_string(), "A == 0 && [FF44] > 100");
+        assert_eq!(b.log.as_deref(), Some("bank {A}"));
+
+        // any order, and the message takes the rest of the line
+        let b = Breakpoint::parse("0x2000 temp if A ignore 1 log if {A} temp").unwrap();
+        assert_eq!((b.ignore_count, b.temporary), (1, true));
+        assert_eq!(b.condition.unwrap().to_string(), "A");
+        assert_eq!(b.log.as_deref(), Some("if {A} temp"));
+    }
+
+    #[test]
+    fn parse_errors() {
+        for spec in ["", "x", "0x150 foo", "0x150 if", "0x150 if A ==", "0x150 ignore", "0x150 ignore x", "0x150 log {A", "0x150 log {A ==}"] {
+            assert!(Breakpoint::parse(spec).is_err(), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn display_round_trip() {
+        for spec in ["0x0150", "03:0x4123 if A == 0 && [FF44] > 100", "0x0150 ignore 10 temp", "0x2000 log switching to bank {A}"] {
+            let b = Breakpoint::parse(spec).unwrap();
+            assert_eq!(b.to_string(), spec);
+        }
+    }
+}
@@ -0,0 +1,293 @@
+use std::fmt;
+
+use crate::bus::CPUMemoryBus;
+use crate::cpu::{CPUDebugState, CPURegister};
+use crate::debugger::parse_number;
+
+// Operators from the lowest precedence to the highest
+const OPERATORS: [&[&str]; 4] = [
+    &["||"],
+    &["&&"],
+    &["==", "!=", "<=", ">=", "<", ">"],
+    &["&"],
+];
+
+#[derive(Clone, Debug)]
+enum Node {
+    Number(u16),
+    Register(CPURegister),
+    // mask of the flag in F
+    Flag(u16),
+    Memory(Box<Node>),
+    Not(Box<Node>),
+    Binary(&'static str, Box<Node>, Box<Node>),
+}
+
+#[derive(Clone, PartialEq, Debug)]
+enum Token {
+    Word(String),
+    Op(&'static str),
+    Open(char),
+    Close(char),
+}
+
+// Conditions and logpoint arguments, over registers, flags (zf, nf, hf, cf) and memory:
+//   A == 0 && [FF44] > 100
+//   [HL] & 0x80 || !zf
+// Numbers are decimal unless prefixed with 0x or $, addresses in brackets are always hex
+#[derive(Clone, Debug)]
+pub struct Expression {
+    source: String,
+    root: Node,
+}
+
+impl Expression {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let tokens = Expression::tokenize(source)?;
+
+        let mut parser = Parser { tokens: &tokens, pos: 0 };
+        let root = parser.parse_binary(0)?;
+
+        if let Some(token) = parser.tokens.get(parser.pos) {
+            return Err(format!("Unexpected {:?} in {}", token, source));
+        }
+
+        Ok(Self { source: source.trim().to_owned(), root })
+    }
+
+    fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+        let mut tokens = vec!();
+        let mut chars = source.char_indices().peekable();
+
+        while let Some((i, c)) = chars.next() {
+            match c {
+                c if c.is_whitespace() => {},
+                '(' | '[' => tokens.push(Token::Open(c)),
+                ')' | ']' => tokens.push(Token::Close(c)),
+
+                c if c.is_ascii_alphanumeric() || c == '$' || c == '_' => {
+                    let mut end = i + c.len_utf8();
+                    while let Some(&(j, c)) = chars.peek() {
+                        if !c.is_ascii_alphanumeric() && c != '_' {
+                            break;
+                        }
+
+                        end = j + c.len_utf8();
+                        chars.next();
+                    }
+
+                    tokens.push(Token::Word(source[i..end].to_owned()));
+                },
+
+                _ => {
+                    // longest operator first, so <= isn't read as <
+                    let op = OPERATORS.iter().flat_map(|ops| ops.iter())
+                        .chain(["!"].iter())
+                        .filter(|op| source[i..].starts_with(**op))
+                        .max_by_key(|op| op.len())
+                        .ok_or_else(|| format!("Unexpected {} in {}", c, source))?;
+
+                    for _ in 1..op.len() {
+                        chars.next();
+                    }
+
+                    tokens.push(Token::Op(op));
+                }
+            }
+        }
+
+        Ok(tokens)
+    }
+
+    pub fn evaluate(&self, cpu: &CPUDebugState, bus: &CPUMemoryBus) -> u16 {
+        Expression::evaluate_node(&self.root, cpu, bus)
+    }
+
+    fn evaluate_node(node: &Node, cpu: &CPUDebugState, bus: &CPUMemoryBus) -> u16 {
+        match node {
+            Node::Number(v) => *v,
+            Node::Register(r) => cpu.get(*r),
+            Node::Flag(mask) => (cpu.af & mask != 0) as u16,
+            Node::Memory(address) => bus.peek_byte(Expression::evaluate_node(address, cpu, bus)) as u16,
+            Node::Not(node) => (Expression::evaluate_node(node, cpu, bus) == 0) as u16,
+            Node::Binary(op, left, right) => {
+                let left = Expression::evaluate_node(left, cpu, bus);
+
+                // && and || don't look at the right side when they don't need to, so [..] reads stay cheap
+                match *op {
+                    "&&" if left == 0 => return 0,
+                    "||" if left != 0 => return 1,
+                    _ => {}
+                }
+
+                let right = Expression::evaluate_node(right, cpu, bus);
+                match *op {
+                    "&&" | "||" => (right != 0) as u16,
+                    "==" => (left == right) as u16,
+                    "!=" => (left != right) as u16,
+                    "<=" => (left <= right) as u16,
+                    ">=" => (left >= right) as u16,
+                    "<" => (left < right) as u16,
+                    ">" => (left > right) as u16,
+                    _ => left & right,
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn next(&mut self) -> Option<&'a Token> {
+        let token = self.tokens.get(self.pos);
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, token: Token) -> Result<(), String> {
+        match self.next() {
+            Some(t) if *t == token => Ok(()),
+            Some(t) => Err(format!("Expected {:?}, found {:?}", token, t)),
+            None => Err(format!("Expected {:?} at the end", token))
+        }
+    }
+
+    fn parse_binary(&mut self, level: usize) -> Result<Node, String> {
+        if level == OPERATORS.len() {
+            return self.parse_operand(false);
+        }
+
+        let mut left = self.parse_binary(level + 1)?;
+
+        while let Some(Token::Op(op)) = self.tokens.get(self.pos) {
+            if !OPERATORS[level].contains(op) {
+                break;
+            }
+
+            self.pos += 1;
+            let right = self.parse_binary(level + 1)?;
+            left = Node::Binary(op, Box::new(left), Box::new(right));
+        }
+
+        Ok(left)
+    }
+
+    fn parse_operand(&mut self, in_brackets: bool) -> Result<Node, String> {
+        match self.next() {
+            Some(Token::Op("!")) => Ok(Node::Not(Box::new(self.parse_operand(in_brackets)?))),
+
+            Some(Token::Open('(')) => {
+                let node = self.parse_binary(0)?;
+                self.expect(Token::Close(')'))?;
+                Ok(node)
+            },
+
+            Some(Token::Open('[')) => {
+                let node = self.parse_operand(true)?;
+                self.expect(Token::Close(']'))?;
+                Ok(Node::Memory(Box::new(node)))
+            },
+
+            Some(Token::Word(word)) => {
+                if let Some(register) = CPURegister::from_name(word) {
+                    return Ok(Node::Register(register));
+                }
+
+                let flag = match word.to_ascii_lowercase().as_str() {
+                    "zf" => Some(0x80),
+                    "nf" => Some(0x40),
+                    "hf" => Some(0x20),
+                    "cf" => Some(0x10),
+                    _ => None
+                };
+
+                if let Some(mask) = flag {
+                    return Ok(Node::Flag(mask));
+                }
+
+                let value = if in_brackets {
+                    let hex = word.trim_start_matches("0x").trim_start_matches("0X").trim_start_matches('$');
+                    u16::from_str_radix(hex, 16).map_err(|_| format!("Invalid address {}", word))?
+                }
+                else {
+                    parse_number(word)?
+                };
+
+                Ok(Node::Number(value))
+            },
+
+            Some(t) => Err(format!("Expected a value, found {:?}", t)),
+            None => Err("Unexpected end of expression".to_owned())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // fully bracketed, so the tests see how the parser grouped things
+    fn tree(node: &Node) -> String {
+        match node {
+            Node::Number(v) => format!("{:#x}", v),
+            Node::Register(r) => format!("{:?}", r),
+            Node::Flag(mask) => format!("flag{:02X}", mask),
+            Node::Memory(address) => format!("[{}]", tree(address)),
+            Node::Not(node) => format!("!{}", tree(node)),
+            Node::Binary(op, left, right) => format!("({} {} {})", tree(left), op, tree(right)),
+        }
+    }
+
+    fn parse(source: &str) -> String {
+        tree(&Expression::parse(source).unwrap().root)
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(parse("A == 0 && [FF44] > 100"), "((A == 0x0) && ([0xff44] > 0x64))");
+        assert_eq!(parse("[HL] & 0x80 || !zf"), "(([HL] & 0x80) || !flag80)");
+        assert_eq!(parse("A || B && C"), "(A || (B && C))");
+        assert_eq!(parse("A & 1 == 1"), "((A & 0x1) == 0x1)");
+        assert_eq!(parse("A == B != C"), "((A == B) != C)");
+        assert_eq!(parse("(A || B) && C"), "((A || B) && C)");
+        assert_eq!(parse("!(A == 1)"), "!(A == 0x1)");
+    }
+
+    #[test]
+    fn operators() {
+        assert_eq!(parse("A <= 3"), "(A <= 0x3)");
+        assert_eq!(parse("A>=$10"), "(A >= 0x10)");
+        assert_eq!(parse("A<B"), "(A < B)");
+        assert_eq!(parse("zf && !cf"), "(flag80 && !flag10)");
+    }
+
+    #[test]
+    fn memory_is_hex() {
+        assert_eq!(parse("[C000]"), "[0xc000]");
+        assert_eq!(parse("[$C000]"), "[0xc000]");
+        assert_eq!(parse("[0x10]"), "[0x10]");
+        assert_eq!(parse("10"), "0xa");
+    }
+
+    #[test]
+    fn errors() {
+        for source in ["", "A ==", "(A", "[HL", "A # 1", "A B", "[G000]", "A && )"] {
+            assert!(Expression::parse(source).is_err(), "{}", source);
+        }
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(Expression::parse("  A == 0 ").unwrap().to_string(), "A == 0");
+    }
+}
@@ -0,0 +1,597 @@
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+
+use crate::cpu::CPURegister;
+use crate::debugger::{Breakpoint, Watchpoint, WatchKind, StopReason};
+use crate::machine::Machine;
+
+// GDB numbers the registers in the order of the target description
+const REGISTERS: [CPURegister; 6] = [
+    CPURegister::AF, CPURegister::BC, CPURegister::DE, CPURegister::HL, CPURegister::SP, CPURegister::PC,
+];
+
+const TARGET_XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <feature name="org.rust-gameboy.sm83">
+    <flags id="sm83_flags" size="1">
+      <field name="C" start="4" end="4"/>
+      <field name="H" start="5" end="5"/>
+      <field name="N" start="6" end="6"/>
+      <field name="Z" start="7" end="7"/>
+    </flags>
+    <reg name="af" bitsize="16" type="uint16" regnum="0"/>
+    <reg name="bc" bitsize="16" type="uint16"/>
+    <reg name="de" bitsize="16" type="uint16"/>
+    <reg name="hl" bitsize="16" type="data_ptr"/>
+    <reg name="sp" bitsize="16" type="data_ptr"/>
+    <reg name="pc" bitsize="16" type="code_ptr"/>
+  </feature>
+</target>
+"#;
+
+const SIGINT: u8 = 2;
+const SIGTRAP: u8 = 5;
+
+// Lets gdb, lldb and IDEs that speak the GDB Remote Serial Protocol attach over TCP.
+// Like the REPL it is polled by the frontend, so the machine keeps running between packets
+pub struct GdbServer {
+    listener: TcpListener,
+    stream: Option<TcpStream>,
+    buffer: Vec<u8>,
+    no_ack: bool,
+    // gdb is waiting for the machine to stop after a continue
+    running: bool,
+}
+
+impl GdbServer {
+    pub fn listen(address: &str) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+
+        println!("Waiting for GDB on {}", listener.local_addr()?);
+
+        Ok(Self {
+            listener,
+            stream: None,
+            buffer: vec!(),
+            no_ack: false,
+            running: false,
+        })
+    }
+
+    pub fn poll(&mut self, machine: &mut Machine) {
+        if self.stream.is_none() {
+            self.accept(machine);
+        }
+
+        if self.stream.is_none() {
+            return;
+        }
+
+        if let Err(e) = self.process(machine) {
+            println!("GDB connection closed: {}", e);
+            self.disconnect(machine);
+        }
+    }
+
+    fn accept(&mut self, machine: &mut Machine) {
+        match self.listener.accept() {
+            Ok((stream, peer)) => {
+                if stream.set_nonblocking(true).is_err() {
+                    return;
+                }
+
+                println!("GDB connected from {}", peer);
+
+                // gdb expects the target to be stopped when it attaches
+                machine.debugger_pause();
+
+                self.stream = Some(stream);
+                self.buffer.clear();
+                self.no_ack = false;
+                self.running = false;
+            },
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {},
+            Err(e) => println!("Failed to accept GDB connection: {}", e),
+        }
+    }
+
+    fn disconnect(&mut self, machine: &mut Machine) {
+        self.stream = None;
+        self.running = false;
+
+        if machine.is_stopped() {
+            machine.debugger_continue();
+        }
+    }
+
+    fn process(&mut self, machine: &mut Machine) -> io::Result<()> {
+        self.receive()?;
+
+        while let Some(packet) = self.next_packet()? {
+            match packet {
+                // Ctrl-C
+                None => {
+                    machine.debugger_pause();
+                    self.stop_reply(SIGINT, StopReason::Other)?;
+                },
+
+                Some(packet) => {
+                    if !self.handle(machine, &packet)? {
+                        self.disconnect(machine);
+                        return Ok(());
+                    }
+                }
+            }
+        }
+
+        // a breakpoint, watchpoint or the REPL stopped the machine
+        if self.running && machine.is_stopped() {
+            let reason = machine.get_debugger_mut().map_or(StopReason::Other, |d| d.get_stop_reason());
+            self.stop_reply(SIGTRAP, reason)?;
+        }
+
+        Ok(())
+    }
+
+    fn receive(&mut self) -> io::Result<()> {
+        let stream = self.stream.as_mut().ok_or_else(|| io::Error::from(io::ErrorKind::NotConnected))?;
+        let mut data = [0; 1024];
+
+        loop {
+            match stream.read(&mut data) {
+                Ok(0) => return Err(io::Error::from(io::ErrorKind::UnexpectedEof)),
+                Ok(n) => self.buffer.extend_from_slice(&data[..n]),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    // Packets are $data#checksum. Returns Some(None) for an interrupt
+    fn next_packet(&mut self) -> io::Result<Option<Option<String>>> {
+        loop {
+            match self.buffer.first() {
+                None => return Ok(None),
+
+                Some(0x03) => {
+                    self.buffer.remove(0);
+                    return Ok(Some(None));
+                },
+
+                Some(b'$') => {
+                    let end = match self.buffer.iter().position(|&b| b == b'#') {
+                        Some(end) if self.buffer.len() >= end + 3 => end,
+                        _ => return Ok(None)
+                    };
+
+                    let data: Vec<u8> = self.buffer.drain(..end + 3).collect();
+                    let body = &data[1..end];
+                    let checksum = std::str::from_utf8(&data[end + 1..]).ok().and_then(|c| u8::from_str_radix(c, 16).ok());
+
+                    if !self.no_ack {
+                        let ack: &[u8] = if checksum == Some(GdbServer::checksum(body)) { b"+" } else { b"-" };
+                        self.write(ack)?;
+
+                        if ack == b"-" {
+                            continue;
+                        }
+                    }
+
+                    return Ok(Some(Some(String::from_utf8_lossy(body).into_owned())));
+                },
+
+                // acks and anything else between packets
+                Some(_) => {
+                    self.buffer.remove(0);
+                }
+            }
+        }
+    }
+
+    fn checksum(data: &[u8]) -> u8 {
+        data.iter().fold(0u8, |sum, b| sum.wrapping_add(*b))
+    }
+
+    fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        let stream = self.stream.as_mut().ok_or_else(|| io::Error::from(io::ErrorKind::NotConnected))?;
+
+        // the socket only needs to be non-blocking for reads
+        stream.set_nonblocking(false)?;
+        let result = stream.write_all(data);
+        stream.set_nonblocking(true)?;
+
+        result
+    }
+
+    fn send(&mut self, data: &str) -> io::Result<()> {
+        let data = GdbServer::escape(data);
+        let packet = format!("${}#{:02x}", data, GdbServer::checksum(data.as_bytes()));
+        self.write(packet.as_bytes())
+    }
+
+    // $, # and } can't appear in a packet and * starts a run, they go as } and the character XOR 0x20.
+    // Only the target description can have any of them, everything else is hex
+    fn escape(data: &str) -> String {
+        let mut escaped = String::with_capacity(data.len());
+
+        for c in data.chars() {
+            if "$#}*".contains(c) {
+                escaped.push('}');
+                escaped.push((c as u8 ^ 0x20) as char);
+            }
+            else {
+                escaped.push(c);
+            }
+        }
+
+        escaped
+    }
+
+    fn stop_reply(&mut self, signal: u8, reason: StopReason) -> io::Result<()> {
+        self.running = false;
+        self.send(&GdbServer::stop_packet(signal, reason))
+    }
+
+    // swbreak tells gdb the stop was one of its Z0 breakpoints, which we advertise in qSupported.
+    // Watchpoint stops carry the address so gdb can tell which Z2-Z4 it was
+    fn stop_packet(signal: u8, reason: StopReason) -> String {
+        match reason {
+            StopReason::Breakpoint => format!("T{:02x}swbreak:;", signal),
+            StopReason::Watchpoint(kind, address) => {
+                let name = match kind {
+                    WatchKind::Read => "rwatch",
+                    WatchKind::Access => "awatch",
+                    WatchKind::Write | WatchKind::Change => "watch",
+                };
+
+                format!("T{:02x}{}:{:x};", signal, name, address)
+            },
+            StopReason::Other => format!("S{:02x}", signal),
+        }
+    }
+
+    // Returns false when gdb detaches
+    fn handle(&mut self, machine: &mut Machine, packet: &str) -> io::Result<bool> {
+        let (command, args) = packet.split_at(packet.chars().next().map_or(0, char::len_utf8));
+
+        match command {
+            "?" => self.send(&format!("S{:02x}", SIGTRAP))?,
+
+            "g" => {
+                let registers = machine.get_registers();
+                let data: String = REGISTERS.iter().map(|r| GdbServer::encode_u16(registers.get(*r))).collect();
+                self.send(&data)?;
+            },
+
+            "G" => {
+                let values: Option<Vec<u16>> = (0..REGISTERS.len())
+                    .map(|i| args.get(i * 4..i * 4 + 4).and_then(GdbServer::decode_u16))
+                    .collect();
+
+                match values {
+                    Some(values) => {
+                        for (register, value) in REGISTERS.iter().zip(values) {
+                            machine.set_register(*register, value);
+                        }
+                        self.send("OK")?;
+                    },
+                    None => self.send("E01")?
+                }
+            },
+
+            "p" => {
+                let registers = machine.get_registers();
+                match usize::from_str_radix(args, 16).ok().and_then(|i| REGISTERS.get(i)) {
+                    Some(register) => self.send(&GdbServer::encode_u16(registers.get(*register)))?,
+                    None => self.send("E01")?
+                }
+            },
+
+            "P" => {
+                let register = args.split_once('=')
+                    .and_then(|(i, v)| Some((REGISTERS.get(usize::from_str_radix(i, 16).ok()?)?, GdbServer::decode_u16(v)?)));
+
+                match register {
+                    Some((register, value)) => {
+                        machine.set_register(*register, value);
+                        self.send("OK")?;
+                    },
+                    None => self.send("E01")?
+                }
+            },
+
+            "m" => {
+                match GdbServer::parse_range(args) {
+                    Some((address, length)) => {
+                        let data: String = (0..length).map(|i| format!("{:02x}", machine.read_memory(address.wrapping_add(i)))).collect();
+                        self.send(&data)?;
+                    },
+                    None => self.send("E01")?
+                }
+            },
+
+            "M" => {
+                let write = args.split_once(':').and_then(|(range, data)| {
+                    let (address, length) = GdbServer::parse_range(range)?;
+                    let bytes = (0..length as usize)
+                        .map(|i| data.get(i * 2..i * 2 + 2).and_then(|b| u8::from_str_radix(b, 16).ok()))
+                        .collect::<Option<Vec<u8>>>()?;
+
+                    Some((address, bytes))
+                });
+
+                match write {
+                    Some((address, bytes)) => {
+                        for (i, b) in bytes.iter().enumerate() {
+                            machine.write_memory(address.wrapping_add(i as u16), *b);
+                        }
+                        self.send("OK")?;
+                    },
+                    None => self.send("E01")?
+                }
+            },
+
+            "s" => {
+                machine.debugger_pause();
+                machine.debugger_step();
+                self.stop_reply(SIGTRAP, StopReason::Other)?;
+            },
+
+            "c" => {
+                if machine.is_stopped() {
+                    machine.debugger_continue();
+                }
+
+                // the reply goes out once the machine stops
+                self.running = true;
+            },
+
+            "Z" | "z" => {
+                let reply = match GdbServer::set_breakpoint(machine, args, command == "Z") {
+                    Some(true) => "OK",
+                    Some(false) => "",
+                    None => "E01"
+                };
+
+                self.send(reply)?;
+            },
+
+            "D" => {
+                self.send("OK")?;
+                return Ok(false);
+            },
+
+            "k" => return Ok(false),
+
+            "H" => self.send("OK")?,
+
+            "q" | "Q" => self.query(packet)?,
+
+            // anything we don't support gets an empty reply
+            _ => self.send("")?
+        }
+
+        Ok(true)
+    }
+
+    fn query(&mut self, packet: &str) -> io::Result<()> {
+        if packet.starts_with("qSupported") {
+            return self.send("PacketSize=4000;qXfer:features:read+;QStartNoAckMode+;swbreak+");
+        }
+
+        if packet == "QStartNoAckMode" {
+            self.send("OK")?;
+            self.no_ack = true;
+            return Ok(());
+        }
+
+        if let Some(range) = packet.strip_prefix("qXfer:features:read:target.xml:") {
+            let reply = match range.split_once(',') {
+                Some((offset, length)) => {
+                    let offset = usize::from_str_radix(offset, 16).unwrap_or(usize::MAX).min(TARGET_XML.len());
+                    let length = usize::from_str_radix(length, 16).unwrap_or(0);
+                    let end = offset.saturating_add(length).min(TARGET_XML.len());
+
+                    // m means there is more to read, l that this is the last part
+                    format!("{}{}", if end < TARGET_XML.len() { 'm' } else { 'l' }, &TARGET_XML[offset..end])
+                },
+                None => "E01".to_owned()
+            };
+
+            return self.send(&reply);
+        }
+
+        match packet {
+            "qAttached" => self.send("1"),
+            "qC" => self.send("QC1"),
+            "qfThreadInfo" => self.send("m1"),
+            "qsThreadInfo" => self.send("l"),
+            _ => self.send("")
+        }
+    }
+
+    // Z0/Z1 are breakpoints and Z2-Z4 write, read and access watchpoints. Returns Some(false) if the type isn't supported
+    fn set_breakpoint(machine: &mut Machine, args: &str, insert: bool) -> Option<bool> {
+        let mut parts = args.split(',');
+        let kind = parts.next()?;
+        let address = u16::from_str_radix(parts.next()?, 16).ok()?;
+        let length = parts.next().and_then(|l| u16::from_str_radix(l, 16).ok()).unwrap_or(1).max(1);
+
+        let debugger = machine.get_debugger_mut()?;
+
+        let watch = match kind {
+            "0" | "1" => None,
+            "2" => Some(WatchKind::Write),
+            "3" => Some(WatchKind::Read),
+            "4" => Some(WatchKind::Access),
+            _ => return Some(false)
+        };
+
+        match watch {
+            None if insert => {
+                debugger.add_breakpoint(Breakpoint::new(address));
+            },
+
+            None => {
+                let id = debugger.get_breakpoints().iter()
+                    .find(|b| b.address == address && b.bank.is_none() && b.condition.is_none() && b.log.is_none())?.id;
+                debugger.remove_breakpoint(id);
+            },
+
+            Some(kind) => {
+                let end = address.wrapping_add(length - 1);
+
+                if insert {
+                    debugger.add_watchpoint(Watchpoint { id: 0, kind, start: address, end, bank: None, condition: None });
+                }
+                else {
+                    let id = debugger.get_watchpoints().get().iter()
+                        .find(|w| w.kind == kind && w.start == address && w.end == end && w.bank.is_none() && w.condition.is_none())?.id;
+                    debugger.remove_watchpoint(id);
+                }
+            }
+        }
+
+        Some(true)
+    }
+
+    fn parse_range(range: &str) -> Option<(u16, u16)> {
+        let (address, length) = range.split_once(',')?;
+        Some((u16::from_str_radix(address, 16).ok()?, u16::from_str_radix(length, 16).ok()?))
+    }
+
+    // registers go over the wire in target byte order
+    fn encode_u16(value: u16) -> String {
+        format!("{:02x}{:02x}", value & 0xFF, value >> 8)
+    }
+
+    fn decode_u16(data: &str) -> Option<u16> {
+        let low = u8::from_str_radix(data.get(0..2)?, 16).ok()?;
+        let high = u8::from_str_radix(data.get(2..4)?, 16).ok()?;
+
+        Some(((high as u16) << 8) | (low as u16))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A server with gdb on the other end of a loopback connection
+    fn connect() -> (GdbServer, TcpStream) {
+        let mut server = GdbServer::listen("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(server.listener.local_addr().unwrap()).unwrap();
+
+        let (stream, _) = server.listener.accept().unwrap();
+        stream.set_nonblocking(true).unwrap();
+        server.stream = Some(stream);
+
+        (server, client)
+    }
+
+    fn read_bytes(client: &mut TcpStream, count: usize) -> Vec<u8> {
+        let mut acks = vec![0; count];
+        client.read_exact(&mut acks).unwrap();
+        acks
+    }
+
+    #[test]
+    fn checksum() {
+        assert_eq!(GdbServer::checksum(b""), 0x00);
+        assert_eq!(GdbServer::checksum(b"OK"), 0x9A);
+        assert_eq!(GdbServer::checksum(b"qSupported"), 0x37);
+        // wraps around
+        assert_eq!(GdbServer::checksum(&[0xFF, 0x02]), 0x01);
+    }
+
+    #[test]
+    fn escape() {
+        assert_eq!(GdbServer::escape("OK"), "OK");
+        assert_eq!(GdbServer::escape("a$b#c}d*e"), "a}\x04b}\x03c}]d}\x0ae");
+        assert_eq!(GdbServer::escape(TARGET_XML), TARGET_XML);
+    }
+
+    #[test]
+    fn packets() {
+        let (mut server, mut client) = connect();
+
+        // acks from gdb are skipped, a good checksum gets a +, a bad one a - and is dropped
+        server.buffer.extend_from_slice(b"+$g#67$m0,1#00$?#3f");
+
+        assert_eq!(server.next_packet().unwrap(), Some(Some("g".to_owned())));
+        assert_eq!(server.next_packet().unwrap(), Some(Some("?".to_owned())));
+        assert_eq!(server.next_packet().unwrap(), None);
+        assert_eq!(read_bytes(&mut client, 3), b"+-+");
+    }
+
+    #[test]
+    fn partial_packets() {
+        let (mut server, mut client) = connect();
+
+        server.buffer.extend_from_slice(b"$qC#b");
+        assert_eq!(server.next_packet().unwrap(), None);
+
+        server.buffer.extend_from_slice(b"4\x03");
+        assert_eq!(server.next_packet().unwrap(), Some(Some("qC".to_owned())));
+
+        // Ctrl-C comes on its own, outside of any packet
+        assert_eq!(server.next_packet().unwrap(), Some(None));
+        assert_eq!(read_bytes(&mut client, 1), b"+");
+    }
+
+    #[test]
+    fn no_ack_mode() {
+        let (mut server, _client) = connect();
+        server.no_ack = true;
+
+        // with no acks the checksum isn't checked either
+        server.buffer.extend_from_slice(b"$g#00");
+        assert_eq!(server.next_packet().unwrap(), Some(Some("g".to_owned())));
+    }
+
+    #[test]
+    fn send() {
+        let (mut server, mut client) = connect();
+        server.send("T05swbreak:;").unwrap();
+        server.send("l}").unwrap();
+
+        let expected = format!("$T05swbreak:;#{:02x}$l}}]#{:02x}", GdbServer::checksum(b"T05swbreak:;"), GdbServer::checksum(b"l}]"));
+        assert_eq!(read_bytes(&mut client, expected.len()), expected.as_bytes());
+    }
+
+    #[test]
+    fn stop_packets() {
+        assert_eq!(GdbServer::stop_packet(SIGINT, StopReason::Other), "S02");
+        assert_eq!(GdbServer::stop_packet(SIGTRAP, StopReason::Breakpoint), "T05swbreak:;");
+        assert_eq!(GdbServer::stop_packet(SIGTRAP, StopReason::Watchpoint(WatchKind::Write, 0xC000)), "T05watch:c000;");
+        assert_eq!(GdbServer::stop_packet(SIGTRAP, StopReason::Watchpoint(WatchKind::Read, 0xFF44)), "T05rwatch:ff44;");
+        assert_eq!(GdbServer::stop_packet(SIGTRAP, StopReason::Watchpoint(WatchKind::Access, 0x8000)), "T05awatch:8000;");
+    }
+
+    #[test]
+    fn target_xml() {
+        let (mut server, mut client) = connect();
+
+        // a length that runs past the end, or past usize, is cut to what's left
+        server.query("qXfer:features:read:target.xml:0,ffffffffffffffff").unwrap();
+        let reply = format!("l{}", TARGET_XML);
+        let expected = format!("${}#{:02x}", reply, GdbServer::checksum(reply.as_bytes()));
+        assert_eq!(read_bytes(&mut client, expected.len()), expected.as_bytes());
+
+        server.query("qXfer:features:read:target.xml:5,3").unwrap();
+        let reply = format!("m{}", &TARGET_XML[5..8]);
+        let expected = format!("${}#{:02x}", reply, GdbServer::checksum(reply.as_bytes()));
+        assert_eq!(read_bytes(&mut client, expected.len()), expected.as_bytes());
+    }
+
+    #[test]
+    fn registers() {
+        assert_eq!(GdbServer::encode_u16(0x1234), "3412");
+        assert_eq!(GdbServer::decode_u16("3412"), Some(0x1234));
+        assert_eq!(GdbServer::decode_u16("341"), None);
+        assert_eq!(GdbServer::parse_range("c000,10"), Some((0xC000, 0x10)));
+        assert_eq!(GdbServer::parse_range("c000"), None);
+    }
+}
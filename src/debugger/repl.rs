@@ -0,0 +1,357 @@
+use std::io::{self, BufRead, Write};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+use crate::cpu::CPURegister;
+use crate::machine::Machine;
+use crate::debugger::{Debugger, Breakpoint, Watchpoint, Expression, parse_number};
+
+const PROMPT: &str = "(gb) ";
+const DISASSEMBLY_LINES: usize = 10;
+
+const PPU_REGISTERS: [(&str, u16); 11] = [
+    ("LCDC", 0xFF40), ("STAT", 0xFF41), ("SCY", 0xFF42), ("SCX", 0xFF43), ("LY", 0xFF44), ("LYC", 0xFF45),
+    ("BGP", 0xFF47), ("OBP0", 0xFF48), ("OBP1", 0xFF49), ("WY", 0xFF4A), ("WX", 0xFF4B),
+];
+
+const APU_REGISTERS: [(&str, u16); 21] = [
+    ("NR10", 0xFF10), ("NR11", 0xFF11), ("NR12", 0xFF12), ("NR13", 0xFF13), ("NR14", 0xFF14),
+    ("NR21", 0xFF16), ("NR22", 0xFF17), ("NR23", 0xFF18), ("NR24", 0xFF19),
+    ("NR30", 0xFF1A), ("NR31", 0xFF1B), ("NR32", 0xFF1C), ("NR33", 0xFF1D), ("NR34", 0xFF1E),
+    ("NR41", 0xFF20), ("NR42", 0xFF21), ("NR43", 0xFF22), ("NR44", 0xFF23),
+    ("NR50", 0xFF24), ("NR51", 0xFF25), ("NR52", 0xFF26),
+];
+
+const TIMER_REGISTERS: [(&str, u16); 4] = [
+    ("DIV", 0xFF04), ("TIMA", 0xFF05), ("TMA", 0xFF06), ("TAC", 0xFF07),
+];
+
+const HELP: &str = "\
+step [n]            run n instructions (s)
+next                step over calls (n)
+finish              run until the current function returns
+continue            resume (c)
+pause               stop the machine
+break <spec>        add a breakpoint (b), [bank:]addr [if <cond>] [ignore <n>] [temp] [log <message>]
+tbreak <spec>       add a breakpoint that is removed once it stops
+condition <n> [c]   set or clear the condition of breakpoint n
+ignore <n> <count>  let breakpoint n through count more times
+delete <n>          remove breakpoint n (d)
+watch <spec>        add a watchpoint, [r|w|rw|c] [bank:]start[-end] [== != < > & value]
+unwatch <n>         remove watchpoint n
+info <what>         breakpoints, watchpoints, registers, ppu, apu or timer (i)
+x/<n><b|w> <addr>   examine n bytes or words
+set <reg>=<value>   change a register, also set [<addr>]=<value> for memory
+disas [b:addr] [n]  disassemble n instructions, from PC by default
+quit                exit the emulator (q)
+Numbers are decimal unless prefixed with 0x or $, an empty line repeats the last command";
+
+// GDB style command line for the debugger. Lines are read from stdin on their own thread
+// so the frontend keeps running, and are handled whenever it polls
+pub struct DebuggerRepl {
+    lines: Receiver<String>,
+    last_line: String,
+}
+
+impl Default for DebuggerRepl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DebuggerRepl {
+    pub fn new() -> Self {
+        let (sender, lines) = mpsc::channel();
+
+        thread::spawn(move || {
+            for line in io::stdin().lock().lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        DebuggerRepl::prompt();
+
+        Self {
+            lines,
+            last_line: String::new(),
+        }
+    }
+
+    fn prompt() {
+        print!("{}", PROMPT);
+        io::stdout().flush().ok();
+    }
+
+    // Runs any commands typed since the last call, returns true once asked to quit
+    pub fn poll(&mut self, machine: &mut Machine) -> bool {
+        loop {
+            let line = match self.lines.try_recv() {
+                Ok(line) => line,
+                Err(TryRecvError::Empty) => return false,
+                // stdin was closed, keep running without a prompt
+                Err(TryRecvError::Disconnected) => return false,
+            };
+
+            let line = if line.trim().is_empty() { self.last_line.clone() } else { line };
+            self.last_line = line.clone();
+
+            match self.execute(machine, line.trim()) {
+                Ok(true) => return true,
+                Ok(false) => {},
+                Err(e) => println!("{}", e),
+            }
+
+            DebuggerRepl::prompt();
+        }
+    }
+
+    fn execute(&mut self, machine: &mut Machine, line: &str) -> Result<bool, String> {
+        let mut args = line.split_whitespace();
+        let command = match args.next() {
+            Some(command) => command,
+            None => return Ok(false)
+        };
+        let args: Vec<&str> = args.collect();
+
+        // x/16b, the format is glued to the command
+        if let Some(format) = command.strip_prefix("x/").or_else(|| if command == "x" { Some("") } else { None }) {
+            let address = parse_number(args.first().ok_or("usage: x/<n><b|w> <addr>")?)?;
+            return DebuggerRepl::examine(machine, format, address).map(|_| false);
+        }
+
+        match command {
+            "s" | "step" => {
+                let count = match args.first() {
+                    Some(n) => parse_number(n)?,
+                    None => 1
+                };
+
+                machine.debugger_pause();
+                for _ in 0..count {
+                    machine.debugger_step();
+                }
+            },
+
+            "n" | "next" => {
+                machine.debugger_pause();
+                machine.debugger_next();
+            },
+
+            "finish" => {
+                machine.debugger_pause();
+                machine.debugger_finish();
+            },
+
+            "c" | "continue" => {
+                if machine.is_stopped() {
+                    machine.debugger_continue();
+                }
+            },
+
+            "pause" => machine.debugger_pause(),
+
+            "b" | "break" | "tbreak" => {
+                let mut breakpoint = Breakpoint::parse(&args.join(" "))?;
+                breakpoint.temporary |= command == "tbreak";
+
+                let description = breakpoint.to_string();
+                let id = DebuggerRepl::debugger(machine)?.add_breakpoint(breakpoint);
+                println!("Breakpoint {}: {}", id, description);
+            },
+
+            "condition" => {
+                let id = parse_number(args.first().ok_or("usage: condition <n> [condition]")?)?;
+                let condition = match args.len() {
+                    1 => None,
+                    _ => Some(Expression::parse(&args[1..].join(" "))?)
+                };
+
+                DebuggerRepl::breakpoint(machine, id)?.condition = condition;
+            },
+
+            "ignore" => {
+                let id = parse_number(args.first().ok_or("usage: ignore <n> <count>")?)?;
+                let count = parse_number(args.get(1).ok_or("usage: ignore <n> <count>")?)?;
+
+                // counts from now, like GDB
+                let breakpoint = DebuggerRepl::breakpoint(machine, id)?;
+                breakpoint.ignore_count = breakpoint.hits + count as u32;
+            },
+
+            "d" | "delete" => {
+                let id = parse_number(args.first().ok_or("usage: delete <n>")?)?;
+                if !DebuggerRepl::debugger(machine)?.remove_breakpoint(id as usize) {
+                    return Err(format!("No breakpoint {}", id));
+                }
+            },
+
+            "watch" => {
+                let watchpoint = Watchpoint::parse(&args.join(" "))?;
+                let description = watchpoint.to_string();
+                let id = DebuggerRepl::debugger(machine)?.add_watchpoint(watchpoint);
+                println!("Watchpoint {}: {}", id, description);
+            },
+
+            "unwatch" => {
+                let id = parse_number(args.first().ok_or("usage: unwatch <n>")?)?;
+                if !DebuggerRepl::debugger(machine)?.remove_watchpoint(id as usize) {
+                    return Err(format!("No watchpoint {}", id));
+                }
+            },
+
+            "i" | "info" => DebuggerRepl::info(machine, args.first().copied().unwrap_or(""))?,
+
+            "set" => DebuggerRepl::set(machine, &args.concat())?,
+
+            "disas" | "disassemble" => {
+                let (bank, address) = match args.first().map(|a| a.split_once(':')) {
+                    Some(Some((bank, address))) => (Some(parse_number(bank)?), parse_number(address)?),
+                    Some(None) => (None, parse_number(args[0])?),
+                    None => (None, machine.get_registers().pc)
+                };
+
+                let count = match args.get(1) {
+                    Some(n) => parse_number(n)? as usize,
+                    None => DISASSEMBLY_LINES
+                };
+
+                DebuggerRepl::disassemble(machine, bank, address, count);
+            },
+
+            "h" | "help" => println!("{}", HELP),
+
+            "q" | "quit" => return Ok(true),
+
+            _ => return Err(format!("Unknown command {}, try help", command))
+        }
+
+        Ok(false)
+    }
+
+    fn debugger(machine: &mut Machine) -> Result<&mut Debugger, String> {
+        machine.get_debugger_mut().ok_or_else(|| "No debugger attached".to_owned())
+    }
+
+    fn breakpoint(machine: &mut Machine, id: u16) -> Result<&mut Breakpoint, String> {
+        DebuggerRepl::debugger(machine)?.get_breakpoint_mut(id as usize).ok_or_else(|| format!("No breakpoint {}", id))
+    }
+
+    fn info(machine: &mut Machine, what: &str) -> Result<(), String> {
+        match what {
+            "b" | "break" | "breakpoints" => {
+                let breakpoints = DebuggerRepl::debugger(machine)?.get_breakpoints();
+                if breakpoints.is_empty() {
+                    println!("No breakpoints");
+                }
+
+                for breakpoint in breakpoints {
+                    println!("{:<3} {} (hit {} times)", breakpoint.id, breakpoint, breakpoint.hits);
+                }
+            },
+
+            "w" | "watch" | "watchpoints" => {
+                let watchpoints = DebuggerRepl::debugger(machine)?.get_watchpoints().get();
+                if watchpoints.is_empty() {
+                    println!("No watchpoints");
+                }
+
+                for watchpoint in watchpoints {
+                    println!("{:<3} {}", watchpoint.id, watchpoint);
+                }
+            },
+
+            "r" | "registers" => {
+                let r = machine.get_registers();
+                let f = r.af as u8;
+
+                println!("AF={:04X} BC={:04X} DE={:04X} HL={:04X} SP={:04X} PC={:04X}", r.af, r.bc, r.de, r.hl, r.sp, r.pc);
+                println!("Z={} N={} H={} C={} IE={:02X} IF={:02X}",
+                    (f >> 7) & 1, (f >> 6) & 1, (f >> 5) & 1, (f >> 4) & 1,
+                    machine.read_memory(0xFFFF), machine.read_memory(0xFF0F));
+            },
+
+            "ppu" => DebuggerRepl::print_registers(machine, &PPU_REGISTERS),
+
+            "apu" => {
+                DebuggerRepl::print_registers(machine, &APU_REGISTERS);
+
+                let wave: Vec<String> = (0xFF30..=0xFF3F).map(|a| format!("{:02X}", machine.read_memory(a))).collect();
+                println!("WAVE {}", wave.join(""));
+            },
+
+            "timer" => DebuggerRepl::print_registers(machine, &TIMER_REGISTERS),
+
+            _ => return Err("usage: info breakpoints|watchpoints|registers|ppu|apu|timer".to_owned())
+        }
+
+        Ok(())
+    }
+
+    fn print_registers(machine: &mut Machine, registers: &[(&str, u16)]) {
+        for line in registers.chunks(6) {
+            let values: Vec<String> = line.iter().map(|(name, a)| format!("{}={:02X}", name, machine.read_memory(*a))).collect();
+            println!("{}", values.join(" "));
+        }
+    }
+
+    fn examine(machine: &mut Machine, format: &str, address: u16) -> Result<(), String> {
+        let digits = format.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+        let count = if digits.is_empty() { 1 } else { digits.parse::<u16>().map_err(|_| format!("Invalid count {}", digits))? };
+
+        let (size, per_line) = match &format[digits.len()..] {
+            "" | "b" => (1, 16),
+            "w" => (2, 8),
+            f => return Err(format!("Invalid format {}, use b or w", f))
+        };
+
+        for line in 0..count.div_ceil(per_line) {
+            let start = address.wrapping_add((line * per_line).wrapping_mul(size));
+            let items = per_line.min(count - line * per_line);
+
+            let values: Vec<String> = (0..items).map(|i| {
+                let a = start.wrapping_add(i * size);
+                match size {
+                    1 => format!("{:02X}", machine.read_memory(a)),
+                    _ => format!("{:04X}", ((machine.read_memory(a.wrapping_add(1)) as u16) << 8) | (machine.read_memory(a) as u16))
+                }
+            }).collect();
+
+            println!("{:04X}: {}", start, values.join(" "));
+        }
+
+        Ok(())
+    }
+
+    // set a=0x12, set hl = 0xC000 or set [0xC000]=0x12
+    fn set(machine: &mut Machine, assignment: &str) -> Result<(), String> {
+        let mut parts = assignment.splitn(2, '=');
+        let target = parts.next().unwrap_or("");
+        let value = parse_number(parts.next().ok_or("usage: set <reg>=<value> or set [<addr>]=<value>")?)?;
+
+        if let Some(address) = target.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+            machine.write_memory(parse_number(address)?, value as u8);
+        }
+        else {
+            let register = CPURegister::from_name(target).ok_or_else(|| format!("Unknown register {}", target))?;
+            machine.set_register(register, value);
+        }
+
+        Ok(())
+    }
+
+    fn disassemble(machine: &mut Machine, bank: Option<u16>, address: u16, count: usize) {
+        let pc = machine.get_registers().pc;
+        let mut address = address;
+
+        for _ in 0..count {
+            let instruction = machine.disassemble(address, bank);
+            let bytes: Vec<String> = instruction.bytes.iter().map(|b| format!("{:02X}", b)).collect();
+
+            println!("{} {:04X}: {:<9} {}", if address == pc { "=>" } else { "  " }, address, bytes.join(" "), instruction.text);
+
+            address = address.wrapping_add(instruction.len());
+        }
+    }
+}
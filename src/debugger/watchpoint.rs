@@ -0,0 +1,319 @@
+use std::cell::RefCell;
+use std::fmt;
+
+use crate::debugger::parse_number;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum WatchKind {
+    Read,
+    Write,
+    // reads and writes
+    Access,
+    // writes that store a different value than what was there
+    Change,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum WatchCondition {
+    Equal(u8),
+    NotEqual(u8),
+    Less(u8),
+    Greater(u8),
+    // any of the bits set
+    Mask(u8),
+}
+
+impl WatchCondition {
+    fn matches(&self, value: u8) -> bool {
+        match *self {
+            WatchCondition::Equal(v) => value == v,
+            WatchCondition::NotEqual(v) => value != v,
+            WatchCondition::Less(v) => value < v,
+            WatchCondition::Greater(v) => value > v,
+            WatchCondition::Mask(v) => value & v != 0,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Watchpoint {
+    // given when it's added, and never reused so removing one doesn't renumber the rest
+    pub id: usize,
+    pub kind: WatchKind,
+    pub start: u16,
+    pub end: u16,
+    // ROM bank for 0000-7FFF, VRAM bank for 8000-9FFF and WRAM bank for D000-DFFF
+    pub bank: Option<u16>,
+    // checked against the value read, or the one written
+    pub condition: Option<WatchCondition>,
+}
+
+impl Watchpoint {
+    // [r|w|rw|c] [bank:]start[-end] [== != < > & value], writes are watched by default:
+    //   0xC000
+    //   r 2:0x4000-0x7FFF
+    //   c 0xFF40 & 0x80
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let spec = spec.trim();
+
+        let (kind, rest) = match spec.split_once(char::is_whitespace) {
+            Some((kind, rest)) => match kind {
+                "r" | "read" => (WatchKind::Read, rest),
+                "w" | "write" => (WatchKind::Write, rest),
+                "rw" | "access" => (WatchKind::Access, rest),
+                "c" | "change" => (WatchKind::Change, rest),
+                _ => (WatchKind::Write, spec)
+            },
+            None => (WatchKind::Write, spec)
+        };
+
+        let (location, condition) = match rest.find(|c| "=!<>&".contains(c)) {
+            Some(i) => (&rest[..i], Some(Watchpoint::parse_condition(&rest[i..])?)),
+            None => (rest, None)
+        };
+
+        let (bank, range) = match location.split_once(':') {
+            Some((bank, range)) => (Some(parse_number(bank)?), range),
+            None => (None, location)
+        };
+
+        let (start, end) = match range.split_once('-') {
+            Some((start, end)) => (parse_number(start)?, parse_number(end)?),
+            None => {
+                let address = parse_number(range)?;
+                (address, address)
+            }
+        };
+
+        if end < start {
+            return Err(format!("Invalid range {}", range.trim()));
+        }
+
+        Ok(Self { id: 0, kind, start, end, bank, condition })
+    }
+
+    fn parse_condition(condition: &str) -> Result<WatchCondition, String> {
+        let condition = condition.trim();
+
+        let (op, value) = ["==", "!=", "<", ">", "&"].iter()
+            .find_map(|op| condition.strip_prefix(op).map(|value| (*op, value)))
+            .ok_or_else(|| format!("Invalid condition {}", condition))?;
+
+        let value = parse_number(value)?;
+        if value > 0xFF {
+            return Err(format!("Invalid condition {}, values are a byte", condition));
+        }
+
+        let value = value as u8;
+        Ok(match op {
+            "==" => WatchCondition::Equal(value),
+            "!=" => WatchCondition::NotEqual(value),
+            "<" => WatchCondition::Less(value),
+            ">" => WatchCondition::Greater(value),
+            _ => WatchCondition::Mask(value),
+        })
+    }
+
+    fn contains(&self, address: u16) -> bool {
+        (self.start..=self.end).contains(&address)
+    }
+
+    fn matches(&self, write: bool, address: u16, bank: u16, old: u8, new: u8) -> bool {
+        let kind = match self.kind {
+            WatchKind::Read => !write,
+            WatchKind::Write => write,
+            WatchKind::Access => true,
+            WatchKind::Change => write && old != new,
+        };
+
+        kind && self.contains(address)
+            && self.bank.is_none_or(|b| b == bank)
+            && self.condition.is_none_or(|c| c.matches(new))
+    }
+}
+
+impl fmt::Display for Watchpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = match self.kind {
+            WatchKind::Read => "r",
+            WatchKind::Write => "w",
+            WatchKind::Access => "rw",
+            WatchKind::Change => "c",
+        };
+
+        write!(f, "{} ", kind)?;
+
+        if let Some(bank) = self.bank {
+            write!(f, "{:02X}:", bank)?;
+        }
+
+        write!(f, "{:#06x}", self.start)?;
+
+        if self.end != self.start {
+            write!(f, "-{:#06x}", self.end)?;
+        }
+
+        match self.condition {
+            Some(WatchCondition::Equal(v)) => write!(f, " == {:#04x}", v),
+            Some(WatchCondition::NotEqual(v)) => write!(f, " != {:#04x}", v),
+            Some(WatchCondition::Less(v)) => write!(f, " < {:#04x}", v),
+            Some(WatchCondition::Greater(v)) => write!(f, " > {:#04x}", v),
+            Some(WatchCondition::Mask(v)) => write!(f, " & {:#04x}", v),
+            None => Ok(())
+        }
+    }
+}
+
+pub struct WatchHit {
+    pub id: usize,
+    pub write: bool,
+    pub address: u16,
+    pub bank: u16,
+    pub old: u8,
+    pub new: u8,
+}
+
+// The CPU bus checks every access against these. Reads go through a shared borrow,
+// so hits are collected on the side and picked up by the debugger after the instruction
+#[derive(Default)]
+pub struct Watchpoints {
+    list: Vec<Watchpoint>,
+    next_id: usize,
+    hits: RefCell<Vec<WatchHit>>,
+}
+
+impl Watchpoints {
+    // Returns the id the watchpoint is known by from now on
+    pub fn add(&mut self, mut watchpoint: Watchpoint) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        watchpoint.id = id;
+        self.list.push(watchpoint);
+        id
+    }
+
+    pub fn remove(&mut self, id: usize) -> bool {
+        match self.list.iter().position(|w| w.id == id) {
+            Some(index) => {
+                self.list.remove(index);
+                true
+            },
+            None => false
+        }
+    }
+
+    pub fn get(&self) -> &[Watchpoint] {
+        &self.list
+    }
+
+    // Lets the bus skip the old value and bank lookups for everything that isn't watched
+    pub fn is_watching(&self, address: u16) -> bool {
+        self.list.iter().any(|w| w.contains(address))
+    }
+
+    pub fn check_read(&self, address: u16, bank: u16, value: u8) {
+        self.check(false, address, bank, value, value);
+    }
+
+    pub fn check_write(&self, address: u16, bank: u16, old: u8, new: u8) {
+        self.check(true, address, bank, old, new);
+    }
+
+    fn check(&self, write: bool, address: u16, bank: u16, old: u8, new: u8) {
+        for w in &self.list {
+            if w.matches(write, address, bank, old, new) {
+                self.hits.borrow_mut().push(WatchHit { id: w.id, write, address, bank, old, new });
+            }
+        }
+    }
+
+    pub fn take_hits(&self) -> Vec<WatchHit> {
+        self.hits.replace(vec!())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let w = Watchpoint::parse("0xC000").unwrap();
+        assert_eq!((w.kind, w.start, w.end, w.bank, w.condition), (WatchKind::Write, 0xC000, 0xC000, None, None));
+
+        let w = Watchpoint::parse("r 2:0x4000-0x7FFF").unwrap();
+        assert_eq!((w.kind, w.start, w.end, w.bank, w.condition), (WatchKind::Read, 0x4000, 0x7FFF, Some(2), None));
+
+        let w = Watchpoint::parse("c 0xFF40 & 0x80").unwrap();
+        assert_eq!((w.kind, w.start, w.end, w.bank, w.condition), (WatchKind::Change, 0xFF40, 0xFF40, None, Some(WatchCondition::Mask(0x80))));
+
+        let w = Watchpoint::parse("access 0xD000 != 0").unwrap();
+        assert_eq!((w.kind, w.condition), (WatchKind::Access, Some(WatchCondition::NotEqual(0))));
+    }
+
+    #[test]
+    fn parse_errors() {
+        for spec in ["", "r", "0xC010-0xC000", "0xC000 == 0x100", "0xC000 =< 1", "x 0xC000"] {
+            assert!(Watchpoint::parse(spec).is_err(), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn display_round_trip() {
+        for spec in ["w 0xc000", "r 02:0x4000-0x7fff", "c 0xff40 & 0x80", "rw 0xd000 < 0x10"] {
+            let w = Watchpoint::parse(spec).unwrap();
+            assert_eq!(w.to_string(), spec);
+            assert_eq!(Watchpoint::parse(&w.to_string()).unwrap().to_string(), spec);
+        }
+    }
+
+    #[test]
+    fn hits() {
+        let mut watchpoints = Watchpoints::default();
+        watchpoints.add(Watchpoint::parse("r 0xC000-0xC0FF").unwrap());
+        watchpoints.add(Watchpoint::parse("c 0xC010").unwrap());
+        watchpoints.add(Watchpoint::parse("w 2:0xD000 > 0x10").unwrap());
+
+        assert!(watchpoints.is_watching(0xC080));
+        assert!(!watchpoints.is_watching(0xC100));
+
+        watchpoints.check_read(0xC010, 0, 0x12);
+        watchpoints.check_write(0xC010, 0, 0x12, 0x12);
+        watchpoints.check_write(0xC010, 0, 0x12, 0x13);
+        watchpoints.check_write(0xD000, 1, 0x00, 0x20);
+        watchpoints.check_write(0xD000, 2, 0x00, 0x08);
+        watchpoints.check_write(0xD000, 2, 0x00, 0x20);
+
+        let hits: Vec<(usize, bool, u16, u8, u8)> = watchpoints.take_hits().iter()
+            .map(|h| (h.id, h.write, h.address, h.old, h.new))
+            .collect();
+
+        assert_eq!(hits, [(0, false, 0xC010, 0x12, 0x12), (1, true, 0xC010, 0x12, 0x13), (2, true, 0xD000, 0x00, 0x20)]);
+        assert!(watchpoints.take_hits().is_empty());
+    }
+
+    #[test]
+    fn remove() {
+        let mut watchpoints = Watchpoints::default();
+        let ids: Vec<usize> = ["0xC000", "0xC001", "0xC002"].iter().map(|s| watchpoints.add(Watchpoint::parse(s).unwrap())).collect();
+        assert_eq!(ids, [0, 1, 2]);
+
+        assert!(!watchpoints.remove(3));
+        assert!(watchpoints.remove(0));
+        assert!(!watchpoints.remove(0));
+        assert!(!watchpoints.is_watching(0xC000));
+
+        // the others keep their ids, and a new one doesn't take the removed id
+        assert_eq!(watchpoints.add(Watchpoint::parse("0xC003").unwrap()), 3);
+        assert!(watchpoints.remove(2));
+        assert!(!watchpoints.is_watching(0xC002));
+
+        watchpoints.check_write(0xC003, 0, 0, 1);
+        let hits: Vec<usize> = watchpoints.take_hits().iter().map(|h| h.id).collect();
+        assert_eq!(hits, [3]);
+
+        let list: Vec<(usize, u16)> = watchpoints.get().iter().map(|w| (w.id, w.start)).collect();
+        assert_eq!(list, [(1, 0xC001), (3, 0xC003)]);
+    }
+}
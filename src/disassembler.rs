@@ -0,0 +1,390 @@
+use std::io;
+
+use hashbrown::HashMap;
+
+use crate::debugger::parse_number;
+use crate::rom::ROM;
+
+const R: [&str; 8] = ["B", "C", "D", "E", "H", "L", "(HL)", "A"];
+const RP: [&str; 4] = ["BC", "DE", "HL", "SP"];
+const RP2: [&str; 4] = ["BC", "DE", "HL", "AF"];
+const CC: [&str; 4] = ["NZ", "Z", "NC", "C"];
+const ALU: [&str; 8] = ["ADD A,", "ADC A,", "SUB ", "SBC A,", "AND ", "XOR ", "OR ", "CP "];
+const ROT: [&str; 8] = ["RLC", "RRC", "RL", "RR", "SLA", "SRA", "SWAP", "SRL"];
+
+// Labels from the .sym files RGBDS (rgblink -n) and WLA DX write, one "bank:address name" per line:
+//   00:0150 Main
+//   0001:4000 Bank1Start
+// Comments start with ; and WLA's [section] headers are skipped, only [labels] is read
+#[derive(Default)]
+pub struct Symbols {
+    labels: HashMap<(u16, u16), String>,
+    // RAM labels are looked up by address alone, the bank they were given in isn't necessarily the one mapped
+    by_address: HashMap<u16, String>,
+}
+
+impl Symbols {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn load_file(&mut self, filename: &str) -> io::Result<usize> {
+        let text = std::fs::read_to_string(filename)?;
+        Ok(self.parse(&text))
+    }
+
+    // Returns how many labels were read, lines that don't look like one are ignored
+    pub fn parse(&mut self, text: &str) -> usize {
+        let mut count = 0;
+        let mut in_labels = true;
+
+        for line in text.lines() {
+            let line = line.split(';').next().unwrap_or("").trim();
+
+            if line.starts_with('[') {
+                in_labels = line == "[labels]";
+                continue;
+            }
+
+            if !in_labels {
+                continue;
+            }
+
+            let mut parts = line.split_whitespace();
+            let (location, name) = match (parts.next(), parts.next()) {
+                (Some(location), Some(name)) => (location, name),
+                _ => continue
+            };
+
+            let address = location.split_once(':')
+                .and_then(|(bank, address)| Some((u16::from_str_radix(bank, 16).ok()?, u16::from_str_radix(address, 16).ok()?)));
+
+            if let Some((bank, address)) = address {
+                self.add(bank, address, name);
+                count += 1;
+            }
+        }
+
+        count
+    }
+
+    pub fn add(&mut self, bank: u16, address: u16, name: &str) {
+        self.labels.insert((bank, address), name.to_owned());
+        self.by_address.entry(address).or_insert_with(|| name.to_owned());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    // rom_bank is the one mapped at 4000-7FFF
+    pub fn get(&self, rom_bank: u16, address: u16) -> Option<&str> {
+        let label = match address {
+            0x0000..=0x3FFF => self.labels.get(&(0, address)),
+            0x4000..=0x7FFF => self.labels.get(&(rom_bank, address)),
+            _ => self.by_address.get(&address)
+        };
+
+        label.map(String::as_str)
+    }
+}
+
+pub struct Disassembly {
+    pub address: u16,
+    pub bytes: Vec<u8>,
+    pub text: String,
+}
+
+impl Disassembly {
+    pub fn len(&self) -> u16 {
+        self.bytes.len() as u16
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+}
+
+// Decodes the instruction at address. Operands are read with read, and addresses that have
+// a label are shown by name, rom_bank says which bank is mapped at 4000-7FFF
+pub fn disassemble(read: impl Fn(u16) -> u8, rom_bank: u16, address: u16, symbols: &Symbols) -> Disassembly {
+    let op = read(address);
+    let mut bytes = vec!(op);
+    let mut operand = |n: u16| -> u16 {
+        let v = read(address.wrapping_add(n));
+        bytes.push(v);
+        v as u16
+    };
+
+    let name = |target: u16| -> String {
+        match symbols.get(rom_bank, target) {
+            Some(label) => label.to_owned(),
+            None => format!("${:04X}", target)
+        }
+    };
+
+    let (x, y, z) = (op >> 6, ((op >> 3) & 7) as usize, (op & 7) as usize);
+    let (p, q) = (y >> 1, y & 1);
+
+    let text = match (x, z) {
+        (0, 0) => match y {
+            0 => "NOP".to_owned(),
+            1 => format!("LD ({}),SP", name(operand(1) | (operand(2) << 8))),
+            2 => { operand(1); "STOP".to_owned() },
+            _ => {
+                let offset = operand(1) as u8 as i8;
+                let target = address.wrapping_add(2).wrapping_add(offset as u16);
+
+                match y {
+                    3 => format!("JR {}", name(target)),
+                    _ => format!("JR {},{}", CC[y - 4], name(target)),
+                }
+            }
+        },
+
+        (0, 1) if q == 0 => format!("LD {},{}", RP[p], name(operand(1) | (operand(2) << 8))),
+        (0, 1) => format!("ADD HL,{}", RP[p]),
+
+        (0, 2) => {
+            let pointer = ["(BC)", "(DE)", "(HL+)", "(HL-)"][p];
+            if q == 0 { format!("LD {},A", pointer) } else { format!("LD A,{}", pointer) }
+        },
+
+        (0, 3) => format!("{} {}", if q == 0 { "INC" } else { "DEC" }, RP[p]),
+        (0, 4) => format!("INC {}", R[y]),
+        (0, 5) => format!("DEC {}", R[y]),
+        (0, 6) => format!("LD {},${:02X}", R[y], operand(1)),
+        (0, _) => ["RLCA", "RRCA", "RLA", "RRA", "DAA", "CPL", "SCF", "CCF"][y].to_owned(),
+
+        (1, 6) if y == 6 => "HALT".to_owned(),
+        (1, _) => format!("LD {},{}", R[y], R[z]),
+
+        (2, _) => format!("{}{}", ALU[y], R[z]),
+
+        (_, 0) => match y {
+            0..=3 => format!("RET {}", CC[y]),
+            4 => format!("LDH ({}),A", name(0xFF00 | operand(1))),
+            5 => format!("ADD SP,{}", operand(1) as u8 as i8),
+            6 => format!("LDH A,({})", name(0xFF00 | operand(1))),
+            _ => {
+                let offset = operand(1) as u8 as i8;
+                if offset < 0 { format!("LD HL,SP-{}", -(offset as i16)) } else { format!("LD HL,SP+{}", offset) }
+            }
+        },
+
+        (_, 1) if q == 0 => format!("POP {}", RP2[p]),
+        (_, 1) => ["RET", "RETI", "JP HL", "LD SP,HL"][p].to_owned(),
+
+        (_, 2) => match y {
+            0..=3 => format!("JP {},{}", CC[y], name(operand(1) | (operand(2) << 8))),
+            4 => "LD (C),A".to_owned(),
+            5 => format!("LD ({}),A", name(operand(1) | (operand(2) << 8))),
+            6 => "LD A,(C)".to_owned(),
+            _ => format!("LD A,({})", name(operand(1) | (operand(2) << 8))),
+        },
+
+        (_, 3) => match y {
+            0 => format!("JP {}", name(operand(1) | (operand(2) << 8))),
+            1 => {
+                let cb = operand(1) as u8;
+                let (x, y, z) = (cb >> 6, ((cb >> 3) & 7) as usize, (cb & 7) as usize);
+
+                match x {
+                    0 => format!("{} {}", ROT[y], R[z]),
+                    1 => format!("BIT {},{}", y, R[z]),
+                    2 => format!("RES {},{}", y, R[z]),
+                    _ => format!("SET {},{}", y, R[z]),
+                }
+            },
+            6 => "DI".to_owned(),
+            7 => "EI".to_owned(),
+            _ => format!("DB ${:02X}", op),
+        },
+
+        (_, 4) if y < 4 => format!("CALL {},{}", CC[y], name(operand(1) | (operand(2) << 8))),
+        (_, 5) if q == 0 => format!("PUSH {}", RP2[p]),
+        (_, 5) if p == 0 => format!("CALL {}", name(operand(1) | (operand(2) << 8))),
+        (_, 6) => format!("{}${:02X}", ALU[y], operand(1)),
+        (_, 7) => format!("RST ${:02X}", y * 8),
+
+        // the opcodes the SM83 doesn't have
+        _ => format!("DB ${:02X}", op),
+    };
+
+    Disassembly { address, bytes, text }
+}
+
+// A listing of length bytes from start, with a line for each label:
+//   Main:
+//     00:0150  3E 12      LD A,$12
+pub fn listing(read: impl Fn(u16) -> u8, rom_bank: u16, start: u16, length: u32, symbols: &Symbols) -> String {
+    let mut text = String::new();
+    let mut offset: u32 = 0;
+
+    while offset < length {
+        let address = start.wrapping_add(offset as u16);
+
+        if let Some(label) = symbols.get(rom_bank, address) {
+            text += &format!("{}:\n", label);
+        }
+
+        let d = disassemble(&read, rom_bank, address, symbols);
+        let bytes: Vec<String> = d.bytes.iter().map(|b| format!("{:02X}", b)).collect();
+        let bank = if address < 0x4000 { 0 } else { rom_bank };
+
+        text += &format!("  {:02X}:{:04X}  {:<9}  {}\n", bank, address, bytes.join(" "), d.text);
+        offset += d.len() as u32;
+    }
+
+    text
+}
+
+// The listing --disassemble prints, range is bank:start:length. The bank is the one at 4000-7FFF,
+// 0000-3FFF is always bank 0
+pub fn rom_listing(rom: &ROM, range: &str, symbols: &Symbols) -> Result<String, String> {
+    let parts: Vec<&str> = range.split(':').collect();
+    let (bank, start, length) = match parts[..] {
+        [bank, start, length] => (parse_number(bank)?, parse_number(start)?, parse_number(length)?),
+        _ => return Err(format!("Expected bank:start:length, got {}", range))
+    };
+
+    if bank >= rom.get_bank_count() {
+        return Err(format!("Bank {} is past the end of the ROM, it has {} banks", bank, rom.get_bank_count()));
+    }
+
+    if start as u32 + length as u32 > 0x8000 {
+        return Err(format!("{:04X}-{:04X} is outside of ROM", start, start as u32 + length as u32 - 1));
+    }
+
+    let read = |address: u16| rom.read_bank_byte(if address < 0x4000 { 0 } else { bank }, address);
+    Ok(listing(read, bank, start, length as u32, symbols))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(bytes: &[u8], address: u16, symbols: &Symbols) -> (String, usize) {
+        let read = |a: u16| bytes.get(a.wrapping_sub(address) as usize).copied().unwrap_or(0);
+        let d = disassemble(read, 1, address, symbols);
+        (d.text, d.bytes.len())
+    }
+
+    fn text(bytes: &[u8]) -> String {
+        decode(bytes, 0x0150, &Symbols::new()).0
+    }
+
+    #[test]
+    fn rgbds_symbols() {
+        let mut symbols = Symbols::new();
+        let count = symbols.parse("; File generated by rgblink\n00:0150 Main\n01:4000 Bank1Start\n00:c000 wBuffer ; comment\nnot a label\n");
+
+        assert_eq!(count, 3);
+        assert_eq!(symbols.get(0, 0x0150), Some("Main"));
+        assert_eq!(symbols.get(1, 0x4000), Some("Bank1Start"));
+        assert_eq!(symbols.get(2, 0x4000), None);
+        // RAM labels ignore the bank
+        assert_eq!(symbols.get(5, 0xC000), Some("wBuffer"));
+    }
+
+    #[test]
+    fn wla_symbols() {
+        let mut symbols = Symbols::new();
+        let count = symbols.parse("[labels]\n0000:0150 Main\n0002:4000 Bank2Start\n\n[definitions]\n00000010 SOME_CONSTANT\n");
+
+        assert_eq!(count, 2);
+        assert_eq!(symbols.get(7, 0x0150), Some("Main"));
+        assert_eq!(symbols.get(2, 0x4000), Some("Bank2Start"));
+        assert!(symbols.get(0, 0x0010).is_none());
+    }
+
+    #[test]
+    fn first_ram_label_wins() {
+        let mut symbols = Symbols::new();
+        symbols.parse("00:c000 wFirst\n01:c000 wSecond\n");
+
+        assert_eq!(symbols.get(0, 0xC000), Some("wFirst"));
+    }
+
+    #[test]
+    fn instructions() {
+        assert_eq!(text(&[0x00]), "NOP");
+        assert_eq!(text(&[0x3E, 0x12]), "LD A,$12");
+        assert_eq!(text(&[0x21, 0x34, 0x12]), "LD HL,$1234");
+        assert_eq!(text(&[0x08, 0x00, 0xC0]), "LD ($C000),SP");
+        assert_eq!(text(&[0x2A]), "LD A,(HL+)");
+        assert_eq!(text(&[0x76]), "HALT");
+        assert_eq!(text(&[0x7E]), "LD A,(HL)");
+        assert_eq!(text(&[0xAF]), "XOR A");
+        assert_eq!(text(&[0xFE, 0x90]), "CP $90");
+        assert_eq!(text(&[0xE0, 0x40]), "LDH ($FF40),A");
+        assert_eq!(text(&[0xF8, 0xFE]), "LD HL,SP-2");
+        assert_eq!(text(&[0xE8, 0x05]), "ADD SP,5");
+        assert_eq!(text(&[0xC2, 0x00, 0x02]), "JP NZ,$0200");
+        assert_eq!(text(&[0xCD, 0x00, 0x40]), "CALL $4000");
+        assert_eq!(text(&[0xFF]), "RST $38");
+        assert_eq!(text(&[0xD3]), "DB $D3");
+    }
+
+    #[test]
+    fn cb_instructions() {
+        assert_eq!(text(&[0xCB, 0x37]), "SWAP A");
+        assert_eq!(text(&[0xCB, 0x7E]), "BIT 7,(HL)");
+        assert_eq!(text(&[0xCB, 0x80]), "RES 0,B");
+        assert_eq!(text(&[0xCB, 0xFF]), "SET 7,A");
+    }
+
+    #[test]
+    fn lengths() {
+        let symbols = Symbols::new();
+        assert_eq!(decode(&[0x00], 0x0150, &symbols).1, 1);
+        assert_eq!(decode(&[0x10, 0x00], 0x0150, &symbols).1, 2);
+        assert_eq!(decode(&[0xCB, 0x37], 0x0150, &symbols).1, 2);
+        assert_eq!(decode(&[0xC3, 0x50, 0x01], 0x0150, &symbols).1, 3);
+    }
+
+    #[test]
+    fn relative_jumps() {
+        let symbols = Symbols::new();
+        assert_eq!(decode(&[0x18, 0xFE], 0x0150, &symbols).0, "JR $0150");
+        assert_eq!(decode(&[0x20, 0x10], 0x0150, &symbols).0, "JR NZ,$0162");
+    }
+
+    #[test]
+    fn labels() {
+        let mut symbols = Symbols::new();
+        symbols.parse("00:0150 Main\n01:4000 Bank1Start\n02:4000 Bank2Start\n00:ff40 rLCDC\n");
+
+        assert_eq!(decode(&[0x18, 0xFE], 0x0150, &symbols).0, "JR Main");
+        assert_eq!(decode(&[0xE0, 0x40], 0x0150, &symbols).0, "LDH (rLCDC),A");
+        // disassemble is given bank 1 as the one at 4000-7FFF
+        assert_eq!(decode(&[0xCD, 0x00, 0x40], 0x0150, &symbols).0, "CALL Bank1Start");
+    }
+
+    #[test]
+    fn listing_format() {
+        let mut symbols = Symbols::new();
+        symbols.parse("00:0150 Main\n");
+
+        let bytes = [0x3E, 0x12, 0x00];
+        let read = |a: u16| bytes.get(a.wrapping_sub(0x0150) as usize).copied().unwrap_or(0);
+
+        assert_eq!(listing(read, 1, 0x0150, 3, &symbols), "Main:\n  00:0150  3E 12      LD A,$12\n  00:0152  00         NOP\n");
+    }
+
+    #[test]
+    fn rom_listing_banks() {
+        let mut bytes = vec![0; 0x10000];
+        bytes[0x147] = 0x01;
+        bytes[0x148] = 0x01;
+        bytes[0xC000] = 0x3C;
+
+        let rom = ROM::from_bytes(&bytes).unwrap();
+        let symbols = Symbols::new();
+
+        assert_eq!(rom_listing(&rom, "3:0x4000:1", &symbols).unwrap(), "  03:4000  3C         INC A\n");
+        assert!(rom_listing(&rom, "4:0x4000:1", &symbols).is_err());
+        assert!(rom_listing(&rom, "1:0x7FFF:2", &symbols).is_err());
+        assert!(rom_listing(&rom, "1:0x4000", &symbols).is_err());
+    }
+}
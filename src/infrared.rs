@@ -0,0 +1,112 @@
+use crate::savestate::{StateWriter, StateReader, SaveStateError};
+
+const RP_LED: u8 = 1;
+const RP_SIGNAL: u8 = 1 << 1;
+const RP_READ_ENABLE: u8 = 0xC0;
+
+// Whatever the IR LED is pointing at, used by the CGB RP port and by carts with an IR port (HuC1/HuC3)
+pub trait InfraredPeer {
+    // Our LED was switched on or off
+    fn set_led(&mut self, _on: bool) {}
+
+    // Whether there is light reaching our sensor
+    fn is_receiving(&self) -> bool;
+}
+
+// Nothing in front of the sensor
+pub struct NoLight;
+
+impl InfraredPeer for NoLight {
+    fn is_receiving(&self) -> bool {
+        false
+    }
+}
+
+// The sensor sees our own LED, like pointing it at a mirror
+pub struct Loopback {
+    led: bool,
+}
+
+impl Loopback {
+    pub fn new() -> Self {
+        Self {
+            led: false,
+        }
+    }
+}
+
+impl Default for Loopback {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InfraredPeer for Loopback {
+    fn set_led(&mut self, on: bool) {
+        self.led = on;
+    }
+
+    fn is_receiving(&self) -> bool {
+        self.led
+    }
+}
+
+// FF56 - RP - Infrared Communications Port (CGB)
+pub struct Infrared {
+    control: u8,
+    peer: Box<dyn InfraredPeer>,
+}
+
+impl Default for Infrared {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Infrared {
+    pub fn new() -> Self {
+        Self {
+            control: 0,
+            peer: Box::new(NoLight),
+        }
+    }
+
+    pub fn set_peer(&mut self, peer: Box<dyn InfraredPeer>) {
+        self.peer = peer;
+        self.peer.set_led(self.control & RP_LED != 0);
+    }
+
+    pub fn save_state(&self, w: &mut StateWriter) {
+        w.write_u8(self.control);
+    }
+
+    pub fn load_state(&mut self, r: &mut StateReader) -> Result<(), SaveStateError> {
+        self.control = r.read_u8()? & (RP_READ_ENABLE | RP_LED);
+        self.peer.set_led(self.control & RP_LED != 0);
+
+        Ok(())
+    }
+
+    pub fn read_byte(&self, address: u16) -> u8 {
+        match address {
+            // the signal bit reads 0 while light is received, but only with reading enabled
+            0xFF56 => {
+                let receiving = self.control & RP_READ_ENABLE == RP_READ_ENABLE && self.peer.is_receiving();
+                0x3C | self.control | if receiving { 0 } else { RP_SIGNAL }
+            },
+
+            _ => unreachable!()
+        }
+    }
+
+    pub fn write_byte(&mut self, address: u16, data: u8) {
+        match address {
+            0xFF56 => {
+                self.control = data & (RP_READ_ENABLE | RP_LED);
+                self.peer.set_led(data & RP_LED != 0);
+            },
+
+            _ => unreachable!()
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use crate::cpu::{Interrupts, CPUInterrupts};
+use crate::savestate::{StateWriter, StateReader, SaveStateError};
 
 #[allow(unused)]
 pub enum JoystickButton {
@@ -17,6 +18,12 @@ pub struct Joystick {
     data: u8,
 }
 
+impl Default for Joystick {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Joystick {
     pub fn new() -> Self {
         Self {
@@ -37,6 +44,18 @@ impl Joystick {
         interrupts.raise_interrupt(Interrupts::Joypad);
     }
 
+    pub fn save_state(&self, w: &mut StateWriter) {
+        w.write_u8(self.state);
+        w.write_u8(self.data);
+    }
+
+    pub fn load_state(&mut self, r: &mut StateReader) -> Result<(), SaveStateError> {
+        self.state = r.read_u8()?;
+        self.data = r.read_u8()?;
+
+        Ok(())
+    }
+
     pub fn read_byte(&self, _address: u16) -> u8 {
         if self.data & (1 << 5) == 0 { // select button keys
             0xC0 | self.data & 0x30 | self.state & 0x0F
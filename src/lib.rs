@@ -0,0 +1,37 @@
+#![deny(clippy::all)]
+#![forbid(unsafe_code)]
+#![allow(clippy::upper_case_acronyms)]
+
+mod cpu;
+mod memory;
+mod ppu;
+mod bus;
+mod screen;
+mod timer;
+mod bitutils;
+mod bootrom;
+mod apu;
+mod savestate;
+
+pub mod machine;
+pub mod rom;
+pub mod joystick;
+pub mod debugger;
+pub mod serial;
+pub mod infrared;
+pub mod cheats;
+pub mod disassembler;
+
+pub use machine::{Machine, GameBoyModel};
+pub use rom::{ROM, CartridgeHeader, Destination, ROMError};
+pub use joystick::JoystickButton;
+pub use debugger::Debugger;
+pub use savestate::SaveStateError;
+pub use cpu::{CPUDebugState, CPURegister};
+pub use serial::LinkPeer;
+pub use infrared::InfraredPeer;
+pub use cheats::{Cheats, CheatError};
+pub use ppu::PPURenderer;
+
+pub const SCREEN_WIDTH: u32 = 160;
+pub const SCREEN_HEIGHT: u32 = 144;
@@ -2,17 +2,50 @@ use std::fmt;
 
 use crate::bus::{CPUMemoryBus, PPUMemoryBus};
 use crate::memory::Memory;
-use crate::cpu::{CPU, CPUInterrupts};
-use crate::rom::ROM;
+use crate::cpu::{CPU, CPUInterrupts, CPUSpeed, CPUDebugState, CPURegister};
+use crate::rom::{ROM, ROMError};
 use crate::bootrom::BootROM;
-use crate::ppu::PPU;
+use crate::ppu::{PPU, PPURenderer};
 use crate::apu::APU;
 use crate::screen::Screen;
 use crate::joystick::Joystick;
 use crate::timer::Timer;
-use crate::serial::Serial;
+use crate::serial::{Serial, LinkPeer};
+use crate::infrared::{Infrared, InfraredPeer};
 use crate::debugger::Debugger;
+use crate::disassembler::{self, Disassembly, Symbols};
+use crate::cheats::Cheats;
 use crate::joystick::JoystickButton;
+use crate::savestate::{StateWriter, StateReader, SaveStateError, SAVE_STATE_MAGIC, SAVE_STATE_VERSION};
+
+// CALL and CALL cc, "next" runs over these instead of stepping into them
+const CALL_OPCODES: [u16; 5] = [0xCD, 0xC4, 0xCC, 0xD4, 0xDC];
+const RST_OPCODES: [u16; 8] = [0xC7, 0xCF, 0xD7, 0xDF, 0xE7, 0xEF, 0xF7, 0xFF];
+
+// The bus the CPU sees, built from the machine's fields so the CPU itself can still be borrowed
+macro_rules! cpu_bus {
+    ($m:expr, $watchpoints:expr) => {
+        CPUMemoryBus {
+            bootrom_enabled: &mut $m.bootrom_enabled,
+            model: $m.model,
+            ppu: &mut $m.ppu,
+            apu: &mut $m.apu,
+            ram1: &mut $m.ram1,
+            ram2: &mut $m.ram2,
+            hram: &mut $m.hram,
+            bootrom: &mut $m.bootrom,
+            rom: &mut $m.rom,
+            joystick: &mut $m.joystick,
+            serial: &mut $m.serial,
+            infrared: &mut $m.infrared,
+            timer: &mut $m.timer,
+            interrupts: &mut $m.interrupts,
+            speed: &mut $m.speed,
+            cheats: &$m.cheats,
+            watchpoints: $watchpoints,
+        }
+    };
+}
 
 #[derive(Clone, Copy, PartialEq, Debug)]
 pub enum GameBoyModel {
@@ -41,8 +74,11 @@ pub struct Machine {
     joystick: Joystick,
     timer: Timer,
     serial: Serial,
+    infrared: Infrared,
     debugger: Option<Box<Debugger>>,
+    cheats: Cheats,
     interrupts: CPUInterrupts,
+    speed: CPUSpeed,
 }
 
 impl Machine {
@@ -59,7 +95,8 @@ impl Machine {
             bootrom: BootROM::new(),
             timer: Timer::new(),
             interrupts: CPUInterrupts::new(),
-            ppu: PPU::new(model),
+            speed: CPUSpeed::new(),
+            ppu: PPU::new(model, PPURenderer::Scanline),
             apu: APU::new(),
             ram1: Memory::new(0xC000, 0x1000, 1),
             ram2: match model {
@@ -69,12 +106,18 @@ impl Machine {
             hram: Memory::new(0xFF80, 0x7F, 1),
             rom,
             joystick: Joystick::new(),
-            serial: Serial::new(),
+            serial: Serial::new(model),
+            infrared: Infrared::new(),
             screen: Screen::new(model),
             debugger: None,
+            cheats: Cheats::new(),
         }
     }
  
+    pub fn from_rom_bytes(bytes: &[u8], force_model: Option<GameBoyModel>) -> Result<Self, ROMError> {
+        Ok(Self::new(ROM::from_bytes(bytes)?, force_model))
+    }
+ 
     pub fn start(&mut self, skip_bootrom: bool) {
         self.bootrom_enabled = !skip_bootrom;
         if !skip_bootrom {
@@ -93,6 +136,11 @@ impl Machine {
         self.ppu.set_initial_state(skip_bootrom);
     }
 
+    // Picks how the PPU draws, this resets the PPU so it has to be done before starting the machine
+    pub fn set_renderer(&mut self, renderer: PPURenderer) {
+        self.ppu = PPU::new(self.model, renderer);
+    }
+
     pub fn stop(&mut self) {
         self.rom.close();
     }
@@ -109,14 +157,170 @@ impl Machine {
         self.screen.get_framebuffer()
     }
 
+    pub fn get_frame_count(&self) -> u64 {
+        self.screen.get_frame_count()
+    }
+
+    pub fn get_registers(&self) -> CPUDebugState {
+        self.cpu.get_debug_state()
+    }
+
+    pub fn set_register(&mut self, register: CPURegister, value: u16) {
+        self.cpu.set_register(register, value);
+    }
+
+    // Decodes the instruction at address, labelled with the debugger's symbols. With a bank, 4000-7FFF
+    // is read from that ROM bank instead of the one mapped
+    pub fn disassemble(&mut self, address: u16, bank: Option<u16>) -> Disassembly {
+        let empty = Symbols::new();
+        let symbols = self.debugger.as_deref().map_or(&empty, Debugger::get_symbols);
+
+        let bus = cpu_bus!(self, None);
+        let rom_bank = bank.unwrap_or_else(|| bus.get_bank(0x4000));
+
+        disassembler::disassemble(|a| match (a, bank) {
+            (0x4000..=0x7FFF, Some(bank)) => bus.rom.read_bank_byte(bank, a),
+            _ => bus.peek_byte(a)
+        }, rom_bank, address, symbols)
+    }
+
+    // Reads and writes through the same bus the CPU uses, so banking and IO registers behave as they would for the game.
+    // Watchpoints are left out, they are there to catch the game
+    pub fn read_memory(&mut self, address: u16) -> u8 {
+        cpu_bus!(self, None).read_byte(address)
+    }
+
+    pub fn write_memory(&mut self, address: u16, value: u8) {
+        cpu_bus!(self, None).write_byte(address, value);
+    }
+
+    // Keeps what the game sends through the link port so get_serial_output can return it
+    pub fn set_serial_capture(&mut self, capture: bool) {
+        self.serial.set_capture(capture);
+    }
+
+    pub fn get_serial_output(&self) -> &[u8] {
+        self.serial.get_output()
+    }
+
+    // Plugs something into the link port, by default nothing is connected
+    pub fn set_link_peer(&mut self, peer: Box<dyn LinkPeer>) {
+        self.serial.set_peer(peer);
+    }
+
+    // Points the CGB infrared port at something, by default it sees no light
+    pub fn set_infrared_peer(&mut self, peer: Box<dyn InfraredPeer>) {
+        self.infrared.set_peer(peer);
+    }
+
+    // Same for the IR port on HuC1/HuC3 carts
+    pub fn set_cartridge_infrared_peer(&mut self, peer: Box<dyn InfraredPeer>) {
+        self.rom.set_infrared_peer(peer);
+    }
+
     pub fn inject_input(&mut self, b : JoystickButton, is_pressed: bool) {
         self.joystick.inject(&mut self.interrupts, b, is_pressed);
     }
 
+    // Tilts carts with an accelerometer (MBC7), in g. Positive x tilts right and positive y tilts the top towards the player
+    pub fn set_tilt(&mut self, x: f32, y: f32) {
+        self.rom.set_tilt(x, y);
+    }
+
+    // Sets what the Pocket Camera sees, pixels are 8 bit luminance and get scaled to the sensor size
+    pub fn set_camera_image(&mut self, width: usize, height: usize, pixels: &[u8]) {
+        self.rom.set_camera_image(width, height, pixels);
+    }
+
+    pub fn get_cheats_mut(&mut self) -> &mut Cheats {
+        &mut self.cheats
+    }
+
     pub fn get_audio_buffer(&mut self) -> Vec<i16> {
         self.apu.consume_audio_samples()
     }
     
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut w = StateWriter::new();
+
+        w.write_bytes(SAVE_STATE_MAGIC);
+        w.write_u16(SAVE_STATE_VERSION);
+        w.write_u8(self.model as u8);
+        self.save_components(&mut w);
+
+        w.into_bytes()
+    }
+
+    pub fn load_state(&mut self, data: &[u8]) -> Result<(), SaveStateError> {
+        let mut r = StateReader::new(data);
+
+        let mut magic = [0; 4];
+        r.read_bytes(&mut magic)?;
+        if &magic != SAVE_STATE_MAGIC {
+            return Err(SaveStateError::InvalidMagic);
+        }
+
+        let version = r.read_u16()?;
+        if version != SAVE_STATE_VERSION {
+            return Err(SaveStateError::UnsupportedVersion(version));
+        }
+
+        if r.read_u8()? != self.model as u8 {
+            return Err(SaveStateError::ModelMismatch);
+        }
+
+        // a bad blob may leave the machine half restored, so keep a snapshot
+        // around to put back if anything fails
+        let mut backup = StateWriter::new();
+        self.save_components(&mut backup);
+        
+        if let Err(e) = self.load_components(&mut r) {
+            let backup = backup.into_bytes();
+            self.load_components(&mut StateReader::new(&backup)).expect("Failed to restore machine state");
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    fn save_components(&self, w: &mut StateWriter) {
+        w.write_bool(self.bootrom_enabled);
+
+        self.cpu.save_state(w);
+        self.interrupts.save_state(w);
+        self.speed.save_state(w);
+        self.timer.save_state(w);
+        self.serial.save_state(w);
+        self.infrared.save_state(w);
+        self.ppu.save_state(w);
+        self.apu.save_state(w);
+        self.ram1.save_state(w);
+        self.ram2.save_state(w);
+        self.hram.save_state(w);
+        self.joystick.save_state(w);
+        self.rom.save_state(w);
+    }
+
+    fn load_components(&mut self, r: &mut StateReader) -> Result<(), SaveStateError> {
+        self.bootrom_enabled = r.read_bool()?;
+
+        self.cpu.load_state(r)?;
+        self.interrupts.load_state(r)?;
+        self.speed.load_state(r)?;
+        self.timer.load_state(r)?;
+        self.serial.load_state(r)?;
+        self.infrared.load_state(r)?;
+        self.ppu.load_state(r)?;
+        self.apu.load_state(r)?;
+        self.ram1.load_state(r)?;
+        self.ram2.load_state(r)?;
+        self.hram.load_state(r)?;
+        self.joystick.load_state(r)?;
+        self.rom.load_state(r)?;
+
+        Ok(())
+    }
+
     pub fn step(&mut self) {
         if let Some(debugger) = &mut self.debugger {
             if debugger.is_stopped() {
@@ -124,10 +328,26 @@ impl Machine {
             }
         }
 
+        if self.serial.is_waiting_for_peer() {
+            return;
+        }
+
+        let pc = self.cpu.get_debug_state().pc;
         self.tick();
 
         if let Some(debugger) = &mut self.debugger {
-            debugger.process(&self.cpu, &self.ppu);
+            debugger.process(pc, &self.cpu, &cpu_bus!(self, None));
+        }
+    }
+
+    // Runs the machine until the PPU finishes a frame, the debugger stops it or a link cable peer needs to catch up
+    pub fn run_frame(&mut self) {
+        loop {
+            self.step();
+
+            if self.is_vblank() || self.is_stopped() || self.serial.is_waiting_for_peer() {
+                break;
+            }
         }
     }
 
@@ -140,25 +360,30 @@ impl Machine {
     }
 
     fn tick(&mut self) {
-        let cpu_cycles = self.cpu.tick(&mut CPUMemoryBus {
-            bootrom_enabled: &mut self.bootrom_enabled,
-            model: self.model,
-            ppu: &mut self.ppu,
-            apu: &mut self.apu,
-            ram1: &mut self.ram1,
-            ram2: &mut self.ram2,
-            hram: &mut self.hram,
-            bootrom: &mut self.bootrom,
-            rom: &mut self.rom,
-            joystick: &mut self.joystick,
-            serial: &mut self.serial,
-            timer: &mut self.timer,
-            interrupts: &mut self.interrupts
-        });
+        let frame = self.screen.get_frame_count();
+
+        // while HDMA is copying the CPU just idles
+        let cpu_cycles = if self.ppu.is_cpu_stalled() { 1 } else { self.cpu.tick(&mut cpu_bus!(self, self.debugger.as_deref().map(Debugger::get_watchpoints))) };
         let clocks = cpu_cycles * 4;
+        let double_speed = self.speed.double_speed;
 
-        for _ in 0..clocks {
+        for i in 0..clocks {
+            // timer, serial and OAM DMA run off the CPU clock
             self.timer.tick(&mut self.interrupts);
+            self.serial.tick(&mut self.interrupts);
+
+            if i % 4 == 0 {
+                self.ppu.tick_oam_dma(&PPUMemoryBus {
+                    rom: &mut self.rom,
+                    ram1: &mut self.ram1,
+                    ram2: &mut self.ram2,
+                });
+            }
+
+            // in double speed the CPU clock runs twice as fast, PPU and APU only see every other clock
+            if double_speed && i % 2 == 1 {
+                continue;
+            }
             
             self.ppu.tick(&mut PPUMemoryBus {
                 rom: &mut self.rom,
@@ -168,6 +393,32 @@ impl Machine {
 
             self.apu.tick();
         }
+
+        // the cartridge clock doesn't speed up with the CPU
+        self.rom.tick(if double_speed { clocks as u32 / 2 } else { clocks as u32 });
+
+        if self.screen.get_frame_count() != frame {
+            self.apply_ram_cheats();
+        }
+    }
+
+    // GameShark codes keep writing their value every frame
+    fn apply_ram_cheats(&mut self) {
+        for (bank, address, value) in self.cheats.get_ram_writes() {
+            match address {
+                0xA000..=0xBFFF => self.rom.write_byte(address, value),
+                0xC000..=0xCFFF => self.ram1.write_byte(address, value),
+
+                // like SVBK, bank 0 is bank 1
+                0xD000..=0xDFFF => match bank {
+                    Some(bank) if self.model == GameBoyModel::GBC => self.ram2.write_bank_byte(bank.max(1) - 1, address, value),
+                    _ => self.ram2.write_byte(address, value)
+                },
+
+                0xFF80..=0xFFFE => self.hram.write_byte(address, value),
+                _ => {}
+            }
+        }
     }
 
     pub fn attach_debugger(&mut self, debugger: Debugger) {
@@ -180,16 +431,114 @@ impl Machine {
                 debugger.resume();
             }
             else {
-                debugger.stop(&self.cpu, &self.ppu);
+                debugger.stop(&self.cpu, &cpu_bus!(self, None));
+            }
+        }
+    }
+
+    pub fn get_debugger_mut(&mut self) -> Option<&mut Debugger> {
+        self.debugger.as_deref_mut()
+    }
+
+    pub fn debugger_pause(&mut self) {
+        if let Some(debugger) = &mut self.debugger {
+            if !debugger.is_stopped() {
+                debugger.stop(&self.cpu, &cpu_bus!(self, None));
             }
         }
     }
 
     pub fn debugger_step(&mut self) {
+        let pc = self.cpu.get_debug_state().pc;
         self.tick();
 
         if let Some(debugger) = &self.debugger {
-            debugger.print_trace(&self.cpu, &self.ppu);
+            debugger.report_watch_hits(pc);
+            debugger.print_trace(&self.cpu, &cpu_bus!(self, None));
+        }
+    }
+
+    // Like step but runs a whole CALL or RST, stopping at the instruction after it
+    pub fn debugger_next(&mut self) {
+        // the prefetched opcode isn't there until the CPU has run once, so read it
+        let pc = self.cpu.get_debug_state().pc;
+        let op = self.read_memory(pc) as u16;
+
+        let length = if CALL_OPCODES.contains(&op) { 3 }
+            else if RST_OPCODES.contains(&op) { 1 }
+            else { 0 };
+
+        match &mut self.debugger {
+            Some(debugger) if length > 0 => debugger.run_until(pc.wrapping_add(length)),
+            _ => self.debugger_step()
+        }
+    }
+
+    // Runs until the current function returns
+    pub fn debugger_finish(&mut self) {
+        if let Some(debugger) = &mut self.debugger {
+            debugger.run_until_return(&self.cpu);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_machine() -> Machine {
+        let mut machine = Machine::from_rom_bytes(&[0; 0x8000], Some(GameBoyModel::GBC)).unwrap();
+        machine.start(true);
+        machine
+    }
+
+    fn tick_oam_dma(machine: &mut Machine, cycles: u32) {
+        for _ in 0..cycles {
+            machine.ppu.tick_oam_dma(&PPUMemoryBus {
+                rom: &mut machine.rom,
+                ram1: &mut machine.ram1,
+                ram2: &mut machine.ram2,
+            });
         }
     }
+
+    #[test]
+    fn oam_dma_bus_conflicts() {
+        let mut machine = new_machine();
+        machine.write_memory(0xC000, 0x11);
+        machine.write_memory(0xC105, 0x42);
+        machine.write_memory(0x8000, 0x77);
+        machine.write_memory(0xFF80, 0x99);
+
+        machine.write_memory(0xFF46, 0xC1);
+        tick_oam_dma(&mut machine, 7);
+
+        // the external bus returns the byte being copied, OAM is locked
+        assert_eq!(machine.read_memory(0xC000), 0x42);
+        assert_eq!(machine.read_memory(0x0000), 0x42);
+        assert_eq!(machine.read_memory(0xFE00), 0xFF);
+        machine.write_memory(0xC000, 0x22);
+
+        // HRAM, IO and the other bus are still there
+        assert_eq!(machine.read_memory(0xFF80), 0x99);
+        assert_eq!(machine.read_memory(0xFF46), 0xC1);
+        assert_eq!(machine.read_memory(0x8000), 0x77);
+
+        tick_oam_dma(&mut machine, 154);
+        assert_eq!(machine.read_memory(0xC000), 0x11);
+        assert_eq!(machine.read_memory(0xFE05), 0x42);
+    }
+
+    #[test]
+    fn oam_dma_from_vram_conflicts() {
+        let mut machine = new_machine();
+        machine.write_memory(0x8000, 0x77);
+        machine.write_memory(0xC000, 0x11);
+
+        // reading VRAM gets in the way, WRAM doesn't
+        machine.write_memory(0xFF46, 0x80);
+        tick_oam_dma(&mut machine, 2);
+        assert_eq!(machine.read_memory(0x9000), 0x77);
+        assert_eq!(machine.read_memory(0xC000), 0x11);
+    }
 }
@@ -5,34 +5,25 @@ use beryllium::*;
 use pixels::{PixelsBuilder, SurfaceTexture, wgpu};
 use std::time::{Duration, Instant};
 use std::thread::sleep;
+use std::path::PathBuf;
+use std::fs::File;
 use clap::{Arg, App};
 
-mod cpu;
-mod rom;
-mod memory;
-mod machine;
-mod ppu;
-mod bus;
-mod screen;
-mod joystick;
-mod timer;
-mod bitutils;
-mod serial;
-mod debugger;
-mod bootrom;
-mod apu;
-
-use machine::{Machine, GameBoyModel};
-use joystick::JoystickButton;
-use debugger::Debugger;
-use rom::ROM;
+use rust_gameboy::serial::{ByteLogger, Loopback, LinkCable};
+use rust_gameboy::infrared;
+use rust_gameboy::disassembler::{self, Symbols};
+use rust_gameboy::debugger::{DebuggerRepl, GdbServer, Breakpoint, Watchpoint};
+use rust_gameboy::{Machine, GameBoyModel, PPURenderer, JoystickButton, Debugger, ROM, SCREEN_WIDTH, SCREEN_HEIGHT};
 
 const WINDOW_TITLE: &str = "rust-gameboy";
-const BUFFER_WIDTH: u32 = 160;
-const BUFFER_HEIGHT: u32 = 144;
+const BUFFER_WIDTH: u32 = SCREEN_WIDTH;
+const BUFFER_HEIGHT: u32 = SCREEN_HEIGHT;
 const WINDOW_WIDTH: u32 = BUFFER_WIDTH * 4;
 const WINDOW_HEIGHT: u32 = BUFFER_HEIGHT * 4;
 
+// How fast the tilt follows the arrow keys, as a fraction of the remaining distance per frame
+const TILT_KEYS_SPEED: f32 = 0.2;
+
 #[derive(Debug, Copy, Clone)]
 struct Color {
     r: u8,
@@ -46,10 +37,47 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Parse CLI args
     let cli_matches = get_cli_matches();
     let opt_rom_file = cli_matches.value_of("rom").unwrap();
+    let opt_rom_entry = cli_matches.value_of("rom-entry");
+    let opt_cheats = cli_matches.value_of("cheats");
+    let opt_patches: Vec<&str> = cli_matches.values_of("patch").map(|v| v.collect()).unwrap_or_default();
     let opt_no_bootrom = cli_matches.occurrences_of("no-bootrom") > 0;
-    let opt_breakpoints = cli_matches.value_of("breakpoints").unwrap_or("");
+    let opt_breakpoints: Vec<&str> = cli_matches.values_of("breakpoint").map(|v| v.collect()).unwrap_or_default();
     let opt_watchpoints = cli_matches.value_of("watchpoints").unwrap_or("");
+    let opt_debug = cli_matches.occurrences_of("debug") > 0;
+    let opt_gdb = cli_matches.value_of("gdb");
+    let opt_symbols = cli_matches.value_of("symbols");
+    let opt_disassemble = cli_matches.value_of("disassemble");
     let opt_hardware = cli_matches.value_of("hardware").unwrap_or("");
+    let opt_serial = cli_matches.value_of("serial").unwrap_or("");
+    let opt_renderer = cli_matches.value_of("renderer").unwrap_or("");
+    let opt_link_listen = cli_matches.value_of("link-listen");
+    let opt_link_connect = cli_matches.value_of("link-connect");
+    let opt_infrared = cli_matches.value_of("infrared").unwrap_or("");
+    let opt_camera_image = cli_matches.value_of("camera-image");
+    let opt_tilt_keys = cli_matches.value_of("tilt").unwrap_or("") == "keys";
+
+    // Labels for traces and disassembly
+    let symbols_path = PathBuf::from(opt_rom_file).with_extension("sym");
+    let symbols_file = match opt_symbols {
+        Some(filename) => Some(filename.to_owned()),
+        None if symbols_path.exists() => Some(symbols_path.to_string_lossy().into_owned()),
+        None => None
+    };
+
+    // Just print a listing of the ROM, without starting the emulator
+    if let Some(range) = opt_disassemble {
+        let bytes = ROM::read_file(opt_rom_file, opt_rom_entry)?;
+        let bytes = ROM::apply_patches(bytes, opt_rom_file, &opt_patches)?;
+        let rom = ROM::from_bytes(&bytes)?;
+
+        let mut symbols = Symbols::new();
+        if let Some(filename) = &symbols_file {
+            symbols.load_file(filename)?;
+        }
+
+        print!("{}", disassembler::rom_listing(&rom, range, &symbols)?);
+        return Ok(());
+    }
     
     let sdl = SDL::init(InitFlags::default())?;
     let mut window = sdl.create_raw_window(WINDOW_TITLE, WindowPosition::Centered, WINDOW_WIDTH, WINDOW_HEIGHT, 0)?;
@@ -81,22 +109,22 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("device name: {}", device_name);
 
     let mut debugger = Debugger::new();
+
+    if let Some(filename) = &symbols_file {
+        let count = debugger.load_symbols(filename)?;
+        println!("Loaded {} symbols from {}", count, filename);
+    }
     
     // Add breakpoints
-    if !opt_breakpoints.is_empty() {
-        let breakpoints = opt_breakpoints.split(',');
-        for bp in breakpoints {
-            let addr = u16::from_str_radix(&bp[2..6], 16)?;
-            debugger.add_breakpoint(addr);
-        }
+    for bp in opt_breakpoints {
+        debugger.add_breakpoint(Breakpoint::parse(bp)?);
     }
 
     // Add watchpoints
     if !opt_watchpoints.is_empty() {
         let watchpoints = opt_watchpoints.split(',');
         for wp in watchpoints {
-            let addr = u16::from_str_radix(&wp[2..6], 16)?;
-            debugger.add_watchpoint(addr);
+            debugger.add_watchpoint(Watchpoint::parse(wp)?);
         }
     }
     
@@ -108,12 +136,70 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     };
 
     let mut rom = ROM::new();
-    rom.open(opt_rom_file);
+    rom.open(opt_rom_file, opt_rom_entry, &opt_patches)?;
 
     let mut machine = Machine::new(rom, hw);
+
+    if opt_renderer == "fifo" {
+        machine.set_renderer(PPURenderer::PixelFifo);
+    }
+
     machine.start(opt_no_bootrom);
     machine.attach_debugger(debugger);
 
+    // Debugger commands on stdin
+    let mut repl = if opt_debug { Some(DebuggerRepl::new()) } else { None };
+
+    // Remote debugging with gdb, only on localhost
+    let mut gdb = match opt_gdb {
+        Some(port) => Some(GdbServer::listen(&format!("127.0.0.1:{}", port))?),
+        None => None
+    };
+
+    // What's plugged into the link port
+    match opt_serial {
+        "log" => machine.set_link_peer(Box::new(ByteLogger)),
+        "loopback" => machine.set_link_peer(Box::new(Loopback)),
+        _ => {}
+    }
+
+    // Cheat codes, F6 switches them on and off
+    let cheats_path = PathBuf::from(opt_rom_file).with_extension("cht");
+    if let Some(filename) = opt_cheats {
+        machine.get_cheats_mut().load_file(filename)?;
+    }
+    else if cheats_path.exists() {
+        machine.get_cheats_mut().load_file(&cheats_path.to_string_lossy())?;
+    }
+
+    // What the Pocket Camera sees
+    if let Some(filename) = opt_camera_image {
+        let (width, height, pixels) = load_camera_image(filename)?;
+        machine.set_camera_image(width, height, &pixels);
+    }
+
+    // What the IR ports see, the CGB one and the one on HuC carts
+    if opt_infrared == "loopback" {
+        machine.set_infrared_peer(Box::new(infrared::Loopback::new()));
+        machine.set_cartridge_infrared_peer(Box::new(infrared::Loopback::new()));
+    }
+
+    // Link cable to another emulator
+    if let Some(address) = opt_link_listen {
+        println!("Waiting for link cable connection on {}", address);
+        machine.set_link_peer(Box::new(LinkCable::listen(address)?));
+    }
+    else if let Some(address) = opt_link_connect {
+        machine.set_link_peer(Box::new(LinkCable::connect(address)?));
+    }
+
+    let mut state_slot: u8 = 0;
+
+    // Accelerometer carts are tilted with the mouse, or with the arrow keys if asked to
+    let mut window_size = (WINDOW_WIDTH, WINDOW_HEIGHT);
+    let mut tilt = (0.0f32, 0.0f32);
+    let mut tilt_target = (0.0f32, 0.0f32);
+
     let mut instant = Instant::now();
     let frame_time: f32 = 1.0 / 60.0;
 
@@ -163,7 +249,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 key: KeyInfo { keycode: key, .. },
                 is_pressed: value,
                 ..
-            })) if key == Keycode::LEFT => {
+            })) if key == Keycode::LEFT && !opt_tilt_keys => {
                 machine.inject_input(JoystickButton::Left, value);
             }
 
@@ -171,7 +257,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 key: KeyInfo { keycode: key, .. },
                 is_pressed: value,
                 ..
-            })) if key == Keycode::RIGHT => {
+            })) if key == Keycode::RIGHT && !opt_tilt_keys => {
                 machine.inject_input(JoystickButton::Right, value);
             }
 
@@ -179,7 +265,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 key: KeyInfo { keycode: key, .. },
                 is_pressed: value,
                 ..
-            })) if key == Keycode::UP => {
+            })) if key == Keycode::UP && !opt_tilt_keys => {
                 machine.inject_input(JoystickButton::Up, value);
             }
 
@@ -187,10 +273,30 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 key: KeyInfo { keycode: key, .. },
                 is_pressed: value,
                 ..
-            })) if key == Keycode::DOWN => {
+            })) if key == Keycode::DOWN && !opt_tilt_keys => {
                 machine.inject_input(JoystickButton::Down, value);
             }
 
+            // Tilt
+            Some(Event::Keyboard(KeyboardEvent {
+                key: KeyInfo { keycode: key, .. },
+                is_pressed: value,
+                ..
+            })) if opt_tilt_keys && (key == Keycode::LEFT || key == Keycode::RIGHT || key == Keycode::UP || key == Keycode::DOWN) => {
+                let amount = if value { 1.0 } else { 0.0 };
+
+                if key == Keycode::LEFT { tilt_target.0 = -amount; }
+                else if key == Keycode::RIGHT { tilt_target.0 = amount; }
+                else if key == Keycode::UP { tilt_target.1 = -amount; }
+                else { tilt_target.1 = amount; }
+            }
+
+            Some(Event::MouseMotion(MouseMotionEvent { x_pos, y_pos, .. })) if !opt_tilt_keys => {
+                // the middle of the window is flat, the edges are 1g
+                tilt.0 = (x_pos as f32 / window_size.0 as f32) * 2.0 - 1.0;
+                tilt.1 = (y_pos as f32 / window_size.1 as f32) * 2.0 - 1.0;
+            }
+
             Some(Event::Keyboard(KeyboardEvent {
                 key: KeyInfo { keycode: key, .. },
                 is_pressed: value,
@@ -207,28 +313,94 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 machine.debugger_continue();
             }
 
+            Some(Event::Keyboard(KeyboardEvent {
+                key: KeyInfo { keycode: key, .. },
+                is_pressed: true,
+                ..
+            })) if key == Keycode::F6 => {
+                let cheats = machine.get_cheats_mut();
+                cheats.set_all_enabled(!cheats.is_enabled());
+                println!("Cheats {}", if cheats.is_enabled() { "enabled" } else { "disabled" });
+            }
+
+            // Save states
+            Some(Event::Keyboard(KeyboardEvent {
+                key: KeyInfo { keycode: key, .. },
+                is_pressed: true,
+                ..
+            })) if key >= Keycode::_0 && key <= Keycode::_9 => {
+                state_slot = (key.0 - Keycode::_0.0) as u8;
+                println!("Selected save state slot {}", state_slot);
+            }
+
+            Some(Event::Keyboard(KeyboardEvent {
+                key: KeyInfo { keycode: key, .. },
+                is_pressed: true,
+                ..
+            })) if key == Keycode::F2 => {
+                let path = get_state_path(opt_rom_file, state_slot);
+
+                match std::fs::write(&path, machine.save_state()) {
+                    Ok(_) => println!("Saved state to {}", path.display()),
+                    Err(e) => println!("Failed to save state {}: {}", path.display(), e)
+                }
+            }
+
+            Some(Event::Keyboard(KeyboardEvent {
+                key: KeyInfo { keycode: key, .. },
+                is_pressed: true,
+                ..
+            })) if key == Keycode::F4 => {
+                let path = get_state_path(opt_rom_file, state_slot);
+
+                match std::fs::read(&path) {
+                    Ok(data) => match machine.load_state(&data) {
+                        Ok(_) => println!("Loaded state from {}", path.display()),
+                        Err(e) => println!("Failed to load state {}: {}", path.display(), e)
+                    },
+                    Err(e) => println!("Failed to read state {}: {}", path.display(), e)
+                }
+            }
+
             // Resize the window
             Some(Event::Window(WindowEvent {
                 event: WindowEventEnum::Resized { w, h },
                 ..
-            })) => pixels.resize(w as u32, h as u32),
+            })) => {
+                window_size = (w, h);
+                pixels.resize(w, h);
+            }
 
             _ => (),
         }
 
-        // process logic
-        'emulator_loop: loop {
-            machine.step();
+        if opt_tilt_keys {
+            tilt.0 += (tilt_target.0 - tilt.0) * TILT_KEYS_SPEED;
+            tilt.1 += (tilt_target.1 - tilt.1) * TILT_KEYS_SPEED;
+        }
 
-            if machine.is_vblank() {
-                break 'emulator_loop;
-            }    
+        machine.set_tilt(tilt.0, tilt.1);
 
-            if machine.is_stopped() {
-                break 'emulator_loop;
+        if let Some(repl) = &mut repl {
+            if repl.poll(&mut machine) {
+                break 'game_loop;
             }
         }
 
+        if let Some(gdb) = &mut gdb {
+            gdb.poll(&mut machine);
+        }
+
+        // process logic
+        machine.run_frame();
+
+        // While the debugger has the machine stopped keep showing the last frame
+        if machine.is_stopped() {
+            pixels.render()?;
+            sleep(Duration::from_secs_f32(frame_time));
+            continue;
+        }
+
         if machine.is_vblank() {
             // Queue audio samples first
             let audio_buffer = machine.get_audio_buffer();
@@ -273,6 +445,36 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+// Save state slots live next to the .sav file, as <rom>.ss0 - <rom>.ss9
+fn get_state_path(rom_file: &str, slot: u8) -> PathBuf {
+    let mut path = PathBuf::from(rom_file);
+    path.set_extension(format!("ss{}", slot));
+
+    path
+}
+
+// Decodes a PNG into 8 bit luminance for the Pocket Camera sensor
+fn load_camera_image(filename: &str) -> Result<(usize, usize, Vec<u8>), Box<dyn std::error::Error>> {
+    let mut decoder = png::Decoder::new(File::open(filename)?);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+
+    let mut reader = decoder.read_info()?;
+    let mut data = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut data)?;
+
+    let channels = info.color_type.samples();
+    let pixels = data[0..info.buffer_size()].chunks_exact(channels).map(|p| {
+        if channels < 3 {
+            p[0]
+        }
+        else {
+            ((p[0] as u32 * 299 + p[1] as u32 * 587 + p[2] as u32 * 114) / 1000) as u8
+        }
+    }).collect();
+
+    Ok((info.width as usize, info.height as usize, pixels))
+}
+
 fn get_cli_matches() -> clap::ArgMatches<'static> {
     App::new("rust-gameboy")
         .version("0.1")
@@ -284,6 +486,23 @@ fn get_cli_matches() -> clap::ArgMatches<'static> {
             .required(true)
             .takes_value(true)
         )
+        .arg(Arg::with_name("rom-entry")
+            .long("rom-entry")
+            .help("File to load when the rom is a zip archive, the first .gb/.gbc otherwise")
+            .takes_value(true)
+        )
+        .arg(Arg::with_name("cheats")
+            .long("cheats")
+            .help("Cheat codes to load, defaults to a .cht file named like the rom")
+            .takes_value(true)
+        )
+        .arg(Arg::with_name("patch")
+            .long("patch")
+            .help("IPS/UPS/BPS patch to apply, can be given more than once. Defaults to one named like the rom")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+        )
         .arg(Arg::with_name("no-bootrom")
             .long("no-bootrom")
             .help("Avoid the bootrom and just start ROM directly")
@@ -294,16 +513,74 @@ fn get_cli_matches() -> clap::ArgMatches<'static> {
             .help("Force hardware version (DMG/GBC)")
             .takes_value(true)
         )
-        .arg(Arg::with_name("breakpoints")
-            .long("breakpoints")
+        .arg(Arg::with_name("breakpoint")
+            .long("breakpoint")
             .short("bp")
-            .help("Comma separated list of breakpoint addresses")
+            .help("Breakpoint to add, can be given more than once. [bank:]addr [if <cond>] [ignore <n>] [temp] [log <message>]")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+        )
+        .arg(Arg::with_name("debug")
+            .long("debug")
+            .help("Read debugger commands from stdin, type help for a list")
+            .takes_value(false)
+        )
+        .arg(Arg::with_name("gdb")
+            .long("gdb")
+            .help("Listen for a GDB remote debugger on this port")
+            .takes_value(true)
+        )
+        .arg(Arg::with_name("symbols")
+            .long("symbols")
+            .help("RGBDS or WLA .sym file to label addresses with, defaults to one named like the rom")
+            .takes_value(true)
+        )
+        .arg(Arg::with_name("disassemble")
+            .long("disassemble")
+            .help("Print a listing of the rom and exit, bank:start:length")
             .takes_value(true)
         )
         .arg(Arg::with_name("watchpoints")
             .long("watchpoints")
             .short("wp")
-            .help("Comma separated list of memory addresses to watch")
+            .help("Comma separated list of watchpoints, [r|w|rw|c] [bank:]start[-end] [== != < > & value]")
+            .takes_value(true)
+        )
+        .arg(Arg::with_name("serial")
+            .long("serial")
+            .help("Link port peer (none/log/loopback)")
+            .takes_value(true)
+        )
+        .arg(Arg::with_name("link-listen")
+            .long("link-listen")
+            .help("Wait for another emulator to connect a link cable (host:port or unix:path)")
+            .takes_value(true)
+            .conflicts_with("link-connect")
+        )
+        .arg(Arg::with_name("link-connect")
+            .long("link-connect")
+            .help("Connect a link cable to another emulator (host:port or unix:path)")
+            .takes_value(true)
+        )
+        .arg(Arg::with_name("renderer")
+            .long("renderer")
+            .help("PPU renderer (scanline/fifo)")
+            .takes_value(true)
+        )
+        .arg(Arg::with_name("camera-image")
+            .long("camera-image")
+            .help("PNG image the Pocket Camera sees")
+            .takes_value(true)
+        )
+        .arg(Arg::with_name("infrared")
+            .long("infrared")
+            .help("What the infrared ports see (none/loopback)")
+            .takes_value(true)
+        )
+        .arg(Arg::with_name("tilt")
+            .long("tilt")
+            .help("Tilt sensor input for MBC7 carts (mouse/keys), keys uses the arrow keys instead of the D-pad")
             .takes_value(true)
         )
         .get_matches()
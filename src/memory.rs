@@ -1,3 +1,5 @@
+use crate::savestate::{StateWriter, StateReader, SaveStateError};
+
 
 struct MemoryRegisters {
     pub ff70: u8,
@@ -41,6 +43,10 @@ impl Memory {
         }
     }
 
+    pub fn get_selected_bank(&self) -> u16 {
+        self.state.selected_bank
+    }
+
     pub fn read_register(&self, addr: u16) -> u8 {
         match addr {
             0xFF70 => {
@@ -62,11 +68,37 @@ impl Memory {
         }
     }
 
+    pub fn save_state(&self, w: &mut StateWriter) {
+        w.write_vec(&self.data);
+        w.write_u16(self.state.selected_bank);
+        w.write_u8(self.registers.ff70);
+    }
+
+    pub fn load_state(&mut self, r: &mut StateReader) -> Result<(), SaveStateError> {
+        r.read_vec_into(&mut self.data)?;
+
+        let bank = r.read_u16()?;
+        if bank >= self.banks as u16 {
+            return Err(SaveStateError::InvalidData("WRAM bank"));
+        }
+
+        self.state.selected_bank = bank;
+        self.registers.ff70 = r.read_u8()?;
+
+        Ok(())
+    }
+
     pub fn read_byte(&self, address: u16) -> u8 {
         let addr: u16 = (self.state.selected_bank * self.bank_size) + (address - self.base_addr);
         self.data[addr as usize]
     }
 
+    // Writes to a bank that may not be the one selected
+    pub fn write_bank_byte(&mut self, bank: u8, address: u16, value: u8) {
+        let addr: u16 = ((bank % self.banks) as u16 * self.bank_size) + (address - self.base_addr);
+        self.data[addr as usize] = value;
+    }
+
     pub fn write_byte(&mut self, address: u16, value: u8) {
         let addr: u16 = (self.state.selected_bank * self.bank_size) + (address - self.base_addr);
         self.data[addr as usize] = value;
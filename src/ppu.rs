@@ -5,6 +5,9 @@ use crate::screen::Screen;
 use crate::cpu::{Interrupts, CPUInterrupts};
 use crate::bitutils::*;
 use crate::machine::GameBoyModel;
+use crate::savestate::{StateWriter, StateReader, SaveStateError};
+use self::fifo::PixelFifo;
+mod fifo;
 
 const MAX_SCANLINES: u8 = 154;
 const VBLANK_LINE: u8 = 144;
@@ -52,6 +55,19 @@ struct OAMAttributes {
     bank: u8
 }
 
+impl OAMAttributes {
+    fn from_flags(flags: u8) -> Self {
+        Self {
+            priority: flags & (1 << 7) != 0,
+            flip_y: flags & (1 << 6) != 0,
+            flip_x: flags & (1 << 5) != 0,
+            palette: get_bit(flags, 4),
+            bank: get_bit(flags, 3),
+            cgb_palette: flags & 0x07,
+        }
+    }
+}
+
 #[derive(Copy, Clone)]
 struct OAMEntry {
     y: u8,
@@ -60,6 +76,7 @@ struct OAMEntry {
     flags: OAMAttributes
 }
 
+#[allow(unused)]
 struct TileAttributes {
     palette: u8,
     bank: u8,
@@ -68,6 +85,14 @@ struct TileAttributes {
     priority: bool
 }
 
+// How mode 3 draws the line. The scanline renderer draws it all at once with a fixed length,
+// the pixel FIFO one emulates the fetcher dot by dot
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum PPURenderer {
+    Scanline,
+    PixelFifo,
+}
+
 pub struct PPUDebugState {
     pub ly: u8,
     pub stat: u8,
@@ -95,6 +120,10 @@ struct PPURegisters {
     cgb_obj_palette_data: [u8; 64],
     dma_oam_active: bool,
     dma_oam_source: u8,
+    dma_oam_address: u16,
+    dma_oam_index: u8,
+    dma_oam_start_delay: u8,
+    dma_oam_byte: u8,
     hdma_active: bool,
     hdma_source: u16,
     hdma_destination: u16,
@@ -107,10 +136,14 @@ struct PPUState {
     mode: PPUMode,
     line_cycles: u16,
     trigger_stat_quirk: bool,
+    hdma_block_pending: bool,
+    hdma_stall_cycles: u16,
 }
 
 pub struct PPU {
     hardware_model: GameBoyModel,
+    renderer: PPURenderer,
+    fifo: PixelFifo,
     registers: PPURegisters,
     state: PPUState,
     vram: [u8; 0x4000],
@@ -121,15 +154,19 @@ pub struct PPU {
 // TODO: Unify state.mode and the lcdc register...
 
 impl PPU {
-    pub fn new(model: GameBoyModel) -> Self {
+    pub fn new(model: GameBoyModel, renderer: PPURenderer) -> Self {
         Self {
             hardware_model: model,
+            renderer,
+            fifo: PixelFifo::new(),
             vram: [0; 0x4000],
             oam: [0; 0x100],
             state: PPUState {
                 mode: PPUMode::ReadOAM,
                 line_cycles: 0,
                 trigger_stat_quirk: false,
+                hdma_block_pending: false,
+                hdma_stall_cycles: 0,
             },
             registers: PPURegisters {
                 lcdc: 0x00,
@@ -151,6 +188,10 @@ impl PPU {
                 cgb_obj_palette_data: [0xFF; 64],
                 dma_oam_active: false,
                 dma_oam_source: 0,
+                dma_oam_address: 0,
+                dma_oam_index: 0,
+                dma_oam_start_delay: 0,
+                dma_oam_byte: 0xFF,
                 hdma_active: false,
                 hdma_source: 0,
                 hdma_destination: 0,
@@ -186,6 +227,104 @@ impl PPU {
         }
     }
     
+    pub fn save_state(&self, w: &mut StateWriter) {
+        let r = &self.registers;
+
+        w.write_u8(r.lcdc);
+        w.write_u8(r.stat);
+        w.write_u8(r.scy);
+        w.write_u8(r.scx);
+        w.write_u8(r.ly);
+        w.write_u8(r.lyc);
+        w.write_u8(r.wpx);
+        w.write_u8(r.wpy);
+        w.write_u8(r.bg_palette);
+        w.write_u8(r.obj_palette0);
+        w.write_u8(r.obj_palette1);
+        w.write_u8(r.cgb_bg_palette_index);
+        w.write_bool(r.cgb_bg_palette_autoincrement);
+        w.write_bytes(&r.cgb_bg_palette_data);
+        w.write_u8(r.cgb_obj_palette_index);
+        w.write_bool(r.cgb_obj_palette_autoincrement);
+        w.write_bytes(&r.cgb_obj_palette_data);
+        w.write_bool(r.dma_oam_active);
+        w.write_u8(r.dma_oam_source);
+        w.write_u16(r.dma_oam_address);
+        w.write_u8(r.dma_oam_index);
+        w.write_u8(r.dma_oam_start_delay);
+        w.write_u8(r.dma_oam_byte);
+        w.write_bool(r.hdma_active);
+        w.write_u16(r.hdma_source);
+        w.write_u16(r.hdma_destination);
+        w.write_u8(r.hdma_mode);
+        w.write_u8(r.hdma_length);
+        w.write_u16(r.vram_bank);
+
+        w.write_u8(self.state.mode as u8);
+        w.write_u16(self.state.line_cycles);
+        w.write_bool(self.state.trigger_stat_quirk);
+        w.write_bool(self.state.hdma_block_pending);
+        w.write_u16(self.state.hdma_stall_cycles);
+
+        self.fifo.save_state(w);
+
+        w.write_bytes(&self.vram);
+        w.write_bytes(&self.oam);
+    }
+
+    pub fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        let r = &mut self.registers;
+
+        r.lcdc = reader.read_u8()?;
+        r.stat = reader.read_u8()?;
+        r.scy = reader.read_u8()?;
+        r.scx = reader.read_u8()?;
+        r.ly = reader.read_u8()?;
+        r.lyc = reader.read_u8()?;
+        r.wpx = reader.read_u8()?;
+        r.wpy = reader.read_u8()?;
+        r.bg_palette = reader.read_u8()?;
+        r.obj_palette0 = reader.read_u8()?;
+        r.obj_palette1 = reader.read_u8()?;
+        r.cgb_bg_palette_index = reader.read_u8()? & 0x3F;
+        r.cgb_bg_palette_autoincrement = reader.read_bool()?;
+        reader.read_bytes(&mut r.cgb_bg_palette_data)?;
+        r.cgb_obj_palette_index = reader.read_u8()? & 0x3F;
+        r.cgb_obj_palette_autoincrement = reader.read_bool()?;
+        reader.read_bytes(&mut r.cgb_obj_palette_data)?;
+        r.dma_oam_active = reader.read_bool()?;
+        r.dma_oam_source = reader.read_u8()?;
+        r.dma_oam_address = reader.read_u16()?;
+        r.dma_oam_index = reader.read_u8()?.min(0xA0);
+        r.dma_oam_start_delay = reader.read_u8()?;
+        r.dma_oam_byte = reader.read_u8()?;
+        r.hdma_active = reader.read_bool()?;
+        r.hdma_source = reader.read_u16()?;
+        r.hdma_destination = reader.read_u16()? & 0x1FF0;
+        r.hdma_mode = reader.read_u8()?;
+        r.hdma_length = reader.read_u8()?;
+        r.vram_bank = reader.read_u16()? & 0x1;
+
+        self.state.mode = match reader.read_u8()? {
+            0 => PPUMode::HBlank,
+            1 => PPUMode::VBlank,
+            2 => PPUMode::ReadOAM,
+            3 => PPUMode::ReadVRAM,
+            _ => return Err(SaveStateError::InvalidData("PPU mode"))
+        };
+        self.state.line_cycles = reader.read_u16()?;
+        self.state.trigger_stat_quirk = reader.read_bool()?;
+        self.state.hdma_block_pending = reader.read_bool()?;
+        self.state.hdma_stall_cycles = reader.read_u16()?;
+
+        self.fifo.load_state(reader)?;
+
+        reader.read_bytes(&mut self.vram)?;
+        reader.read_bytes(&mut self.oam)?;
+
+        Ok(())
+    }
+    
     pub fn set_vram_bank(&mut self, bank: u8) {
         match self.hardware_model {
             GameBoyModel::GBC => self.registers.vram_bank = bank as u16,
@@ -208,9 +347,17 @@ impl PPU {
         self.oam[addr as usize] = data;
     }
 
+    // The CPU is halted while HDMA copies a block
+    pub fn is_cpu_stalled(&self) -> bool {
+        self.state.hdma_stall_cycles > 0
+    }
+
     pub fn tick(&mut self, bus: &mut PPUMemoryBus, interrupts: &mut CPUInterrupts, screen: &mut Screen) {
-        // Do HDMA or DMA transfers to VRAM/OAM memory
-        self.handle_dma(bus);
+        if self.state.hdma_stall_cycles > 0 {
+            self.state.hdma_stall_cycles -= 1;
+        }
+
+        // Do HDMA transfers to VRAM memory
         self.handle_hdma(bus);
         
         // HACK: In DMG writing anything to STAT while in HBLANK or VBLANK causes bit 1 of the IF register (0xFF0F) to be set
@@ -233,6 +380,24 @@ impl PPU {
                 // wait for 80 cycles, then go to mode VRAM READ MODE
                 if self.state.line_cycles == 80 {
                     self.set_mode(interrupts, PPUMode::ReadVRAM);
+
+                    if self.renderer == PPURenderer::PixelFifo {
+                        self.fifo_start_line();
+                    }
+                }
+            },
+
+            // VRAM ACCESS - Mode 3, its length depends on scrolling, the window and objects
+            PPUMode::ReadVRAM if self.renderer == PPURenderer::PixelFifo => {
+                if self.fifo_tick() {
+                    let line = self.fifo_end_line();
+                    screen.set_scanline(self.registers.ly, &line);
+
+                    if get_flag2(self.registers.stat, STATBits::Mode0HBlankCheckEnable as u8) {
+                        interrupts.raise_interrupt(Interrupts::LCDStat);
+                    }
+
+                    self.set_mode(interrupts, PPUMode::HBlank);
                 }
             },
 
@@ -292,21 +457,71 @@ impl PPU {
         }
     }
 
-    fn handle_dma(&mut self, bus: &PPUMemoryBus) {
-        // in theory dma copy takes a while... in fact:
-        // This copy needs 160 × 4 + 4 clocks to
-        // complete in both double speed and single speeds modes. The copy starts after the 4 setup clocks,
-        // and a new byte is copied every 4 clocks.
+    pub fn is_oam_dma_active(&self) -> bool {
+        self.registers.dma_oam_active
+    }
+
+    // Address the OAM DMA is reading from and the last byte it copied, the CPU sees that byte
+    // when it reads from the same bus during the transfer
+    pub fn get_oam_dma_source(&self) -> u16 {
+        self.registers.dma_oam_address
+    }
+
+    pub fn get_oam_dma_byte(&self) -> u8 {
+        self.registers.dma_oam_byte
+    }
+
+    // OAM DMA runs off the CPU clock, this is called once per M-cycle.
+    // The copy needs 160 × 4 + 4 clocks, it starts after the 4 setup clocks and a new byte
+    // is copied every 4 clocks. Restarting it keeps the old transfer running during the setup
+    pub fn tick_oam_dma(&mut self, bus: &PPUMemoryBus) {
         if self.registers.dma_oam_active {
-            self.do_dma_transfer(bus, self.registers.dma_oam_source);
-            self.registers.dma_oam_active = false;
+            let address = self.registers.dma_oam_address;
+            let b = match address {
+                0x8000..=0x9FFF => self.read_vram(address, self.registers.vram_bank),
+                _ => bus.read_byte(address)
+            };
+
+            self.oam[self.registers.dma_oam_index as usize] = b;
+            self.registers.dma_oam_byte = b;
+            self.registers.dma_oam_address = address.wrapping_add(1);
+            self.registers.dma_oam_index += 1;
+
+            if self.registers.dma_oam_index == 0xA0 {
+                self.registers.dma_oam_active = false;
+            }
+        }
+
+        if self.registers.dma_oam_start_delay > 0 {
+            self.registers.dma_oam_start_delay -= 1;
+
+            if self.registers.dma_oam_start_delay == 0 {
+                self.registers.dma_oam_active = true;
+                self.registers.dma_oam_address = (self.registers.dma_oam_source as u16) << 8;
+                self.registers.dma_oam_index = 0;
+            }
         }
     }
 
     fn handle_hdma(&mut self, bus: &mut PPUMemoryBus) {
-        if self.registers.hdma_active && self.registers.hdma_mode == 0 {
-            while self.registers.hdma_length != 0xFF {
-                self.hdma_copy_block(bus);
+        if !self.registers.hdma_active {
+            return;
+        }
+
+        match self.registers.hdma_mode {
+            // General purpose DMA, everything is copied at once
+            0 => {
+                while self.registers.hdma_active {
+                    self.hdma_copy_block(bus);
+                }
+            },
+
+            // HBlank DMA, one block every time the PPU enters HBlank
+            _ => {
+                if self.state.hdma_block_pending {
+                    self.state.hdma_block_pending = false;
+                    self.hdma_copy_block(bus);
+                }
             }
         }
     }
@@ -317,9 +532,12 @@ impl PPU {
             self.write_vram(0x8000 + self.registers.hdma_destination, self.registers.vram_bank, b);
 
             self.registers.hdma_source = self.registers.hdma_source.wrapping_add(1);
-            self.registers.hdma_destination = self.registers.hdma_destination.wrapping_add(1);
+            self.registers.hdma_destination = self.registers.hdma_destination.wrapping_add(1) & 0x1FFF;
         }
 
+        // each block takes 32 clocks, and the CPU can't run while it's copied
+        self.state.hdma_stall_cycles += 32;
+
         self.registers.hdma_length = self.registers.hdma_length.wrapping_sub(1);
 
         if self.registers.hdma_length == 0xFF {
@@ -333,8 +551,9 @@ impl PPU {
 
         match self.state.mode {
             PPUMode::HBlank => {
+                // the copy itself happens on the next tick, where the bus is available
                 if self.registers.hdma_active && self.registers.hdma_mode == 1 {
-                    // self.hdma_copy_block(bus, registers);
+                    self.state.hdma_block_pending = true;
                 }
             },
 
@@ -453,7 +672,7 @@ impl PPU {
                     row = height - row - 1;
                 }
 
-                let obj_tile_data = self.read_tile_data(tile_data_base_address, obj.flags.bank, obj.tile, row as u8);
+                let obj_tile_data = self.read_tile_data(tile_data_base_address, obj.flags.bank, obj.tile, row);
 
                 for p in 0..8 {
                     if x.wrapping_add(p) >= 160 {
@@ -490,7 +709,7 @@ impl PPU {
             }
         }
 
-        screen.set_scanline(self.registers.ly as u8, &bg_buffer);
+        screen.set_scanline(self.registers.ly, &bg_buffer);
     }
     
     fn draw_background(&self, color_buffer: &mut [u16; 160], bg_attribs: &mut [u8; 160]) {
@@ -510,7 +729,7 @@ impl PPU {
 
         for x in start_tile_col..end_tile_col {
             // read tile number from tile map
-            let tile_address = bg_tile_map_address + (((TILES_PER_ROW as u16 * (start_tile_row % TILES_PER_ROW) as u16) + (x % TILES_PER_COL) as u16) as u16);
+            let tile_address = bg_tile_map_address + ((TILES_PER_ROW as u16 * (start_tile_row % TILES_PER_ROW) as u16) + (x % TILES_PER_COL) as u16);
             let tile_number: u8 = self.read_vram(tile_address, 0);
 
             // read tile attributes
@@ -532,9 +751,9 @@ impl PPU {
                 }
             };
 
-            let mut pixel_col = x as u16 * TILE_WIDTH as u16;
-            
             for i in 0..TILE_WIDTH {
+                let pixel_col = x as u16 * TILE_WIDTH as u16 + i as u16;
+
                 if pixel_col >= scx && pixel_col <= scx + 160 && pixel_idx < 160 {
                     let color_idx = match tile_attribs.flip_x {
                         false => tile_row_data[i as usize] & 0x03,
@@ -559,8 +778,6 @@ impl PPU {
 
                     pixel_idx += 1;
                 }
-                
-                pixel_col += 1;
             }
 
             if pixel_idx >= 160 {
@@ -585,7 +802,7 @@ impl PPU {
 
             for x in 0..=20 {
                 // read tile number from tile map
-                let tile_address = window_tile_map_address + (((TILES_PER_ROW as u16 * (start_tile_row % TILES_PER_ROW) as u16) + (x % TILES_PER_COL) as u16) as u16);
+                let tile_address = window_tile_map_address + ((TILES_PER_ROW as u16 * (start_tile_row % TILES_PER_ROW) as u16) + (x % TILES_PER_COL) as u16);
                 let tile_number: u8 = self.read_vram(tile_address, 0);
 
                 // read tile attributes
@@ -593,7 +810,7 @@ impl PPU {
                 
                 // read tile data
                 let tile_index: u8 = if addressing_mode != 0 { tile_number } else { ((tile_number as i16) + 128) as u8 };
-                let tile_row_data = self.read_tile_data(tile_data_base_address, tile_attribs.bank, tile_index, pixel_row as u8);
+                let tile_row_data = self.read_tile_data(tile_data_base_address, tile_attribs.bank, tile_index, pixel_row);
 
                 for i in 0..TILE_WIDTH {
                     if pixel_col < 160 {
@@ -662,36 +879,19 @@ impl PPU {
     }
 
     fn read_oam_entry(&self, idx: u8) -> OAMEntry {
-        let y = self.oam[(idx as u16 * 4) as usize];
-        let x = self.oam[(idx  as u16 * 4 + 1) as usize];
-        let tile = self.oam[(idx as u16 * 4 + 2) as usize];
-        let flags = self.oam[(idx as u16 * 4 + 3) as usize];
+        // the PPU can't read OAM while DMA is writing to it
+        let oam_byte = |offset: u16| if self.registers.dma_oam_active { 0xFF } else { self.oam[(idx as u16 * 4 + offset) as usize] };
+
+        let y = oam_byte(0);
+        let x = oam_byte(1);
+        let tile = oam_byte(2);
+        let flags = oam_byte(3);
 
         OAMEntry {
             y,
             x,
             tile, 
-            flags: OAMAttributes {
-                priority: flags & (1 << 7) != 0,
-                flip_y: flags & (1 << 6) != 0,
-                flip_x: flags & (1 << 5) != 0,
-                palette: get_bit(flags, 4),
-                bank: get_bit(flags, 3),
-                cgb_palette: flags & 0x07,
-            }
-        }
-    }
-
-    fn do_dma_transfer(&mut self, bus: &PPUMemoryBus, data: u8) {
-        let addr: u16 = (data as u16) << 8;
-        let mut data: [u8; 0xA0] = [0; 0xA0];
-        
-        for (i, datum) in data.iter_mut().enumerate() {
-            *datum = bus.read_byte(addr + (i as u16));
-        }
-
-        for (i, datum) in data.iter().enumerate() {
-            self.oam[i as usize] = *datum;
+            flags: OAMAttributes::from_flags(flags)
         }
     }
 
@@ -755,8 +955,8 @@ impl PPU {
             // FF45 LYC - LY Compare (R/W)
             0xFF45 => self.registers.lyc,
 
-            // FF46 - OAM DMA - OAM DMA Transfer and Start Address (W)
-            0xFF46 => 0,
+            // FF46 - OAM DMA - OAM DMA Transfer and Start Address (R/W)
+            0xFF46 => self.registers.dma_oam_source,
 
             // FF47 - BGP - BG Palette Data (R/W)
             0xFF47 => self.registers.bg_palette,
@@ -777,11 +977,12 @@ impl PPU {
             // FF52 HDMA2 - DMA Source, Low
             // FF53 HDMA3 - DMA Destination, High
             // FF54 HDMA4 - DMA Destination, Low
-            0xFF51 | 0xFF52 | 0xFF53 | 0xFF54 => 0xFF,
+            0xFF51..=0xFF54 => 0xFF,
 
             // FF55 HDMA5 - DMA Length/Mode/Start
-            // 0xFF55 => (((!self.hdma_active) as u8) << 7) | self.hdma_length & 0x7F,
-            0xFF55 => self.registers.hdma_length,
+            // bit 7 is clear while a transfer is active, then the remaining length (minus one) follows.
+            // Reads 0xFF once a transfer finished
+            0xFF55 => (((!self.registers.hdma_active) as u8) << 7) | (self.registers.hdma_length & 0x7F),
             
             // FF68 BCPS/BGPI - Background Palette Index (CGB)
             0xFF68 => {
@@ -867,8 +1068,8 @@ impl PPU {
 
             // FF46 - OAM DMA - OAM DMA Transfer and Start Address (W)
             0xFF46 => {
-                self.registers.dma_oam_active = true;
                 self.registers.dma_oam_source = data;
+                self.registers.dma_oam_start_delay = 1;
             },
 
             // FF47 - BGP - BG Palette Data (R/W)
@@ -908,19 +1109,21 @@ impl PPU {
 
             // FF55 HDMA5 - DMA Length/Mode/Start
             0xFF55 => {
-                if self.registers.hdma_active {
-                    if get_bit(data, 7) == 0 {
-                        self.registers.hdma_active = false;
-                        self.registers.hdma_mode = 1;
-                    }
-                    self.registers.hdma_length = data & 0x7F;
+                // writing bit 7 clear during an HBlank transfer cancels it, the remaining length can still be read
+                if self.registers.hdma_active && get_bit(data, 7) == 0 {
+                    self.registers.hdma_active = false;
+                    self.state.hdma_block_pending = false;
+                    return;
                 }
-                else {
-                    self.registers.hdma_active = true;
 
-                    self.registers.hdma_mode = get_bit(data, 7);
-                    self.registers.hdma_length = data & 0x7F;
-                }
+                self.registers.hdma_active = true;
+                self.registers.hdma_mode = get_bit(data, 7);
+                self.registers.hdma_length = data & 0x7F;
+
+                // starting an HBlank transfer while already in HBlank, or with the LCD off, copies a block right away.
+                // With the LCD off there won't be any more HBlanks until it's turned back on
+                let lcd_enabled = get_flag2(self.registers.lcdc, LCDCBits::LCDEnable as u8);
+                self.state.hdma_block_pending = self.registers.hdma_mode == 1 && (!lcd_enabled || self.state.mode == PPUMode::HBlank);
             }
 
             // FF68 BCPS/BGPI - Background Palette Index (CGB)
@@ -969,4 +1172,216 @@ impl PPU {
             _ => panic!("Invalid read")
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rom::ROM;
+    use crate::memory::Memory;
+
+    // What the PPU needs around it to be ticked on its own
+    struct TestBus {
+        rom: ROM,
+        ram1: Memory,
+        ram2: Memory,
+        interrupts: CPUInterrupts,
+        screen: Screen,
+    }
+
+    impl TestBus {
+        fn new() -> Self {
+            let mut ram1 = Memory::new(0xC000, 0x1000, 1);
+            for i in 0..0x1000 {
+                ram1.write_byte(0xC000 + i, (i as u8).wrapping_add(1));
+            }
+
+            Self {
+                rom: ROM::new(),
+                ram1,
+                ram2: Memory::new(0xD000, 0x7000, 7),
+                interrupts: CPUInterrupts::new(),
+                screen: Screen::new(GameBoyModel::GBC),
+            }
+        }
+
+        fn tick(&mut self, ppu: &mut PPU, dots: u32) {
+            for _ in 0..dots {
+                let mut bus = PPUMemoryBus {
+                    rom: &mut self.rom,
+                    ram1: &mut self.ram1,
+                    ram2: &mut self.ram2,
+                };
+
+                ppu.tick(&mut bus, &mut self.interrupts, &mut self.screen);
+            }
+        }
+
+        // OAM DMA runs once per M-cycle
+        fn tick_oam_dma(&mut self, ppu: &mut PPU, cycles: u32) {
+            for _ in 0..cycles {
+                ppu.tick_oam_dma(&PPUMemoryBus {
+                    rom: &mut self.rom,
+                    ram1: &mut self.ram1,
+                    ram2: &mut self.ram2,
+                });
+            }
+        }
+    }
+
+    fn new_ppu() -> PPU {
+        let mut ppu = PPU::new(GameBoyModel::GBC, PPURenderer::Scanline);
+        ppu.registers.lcdc = 0x91;
+        ppu
+    }
+
+    // Copies from C000 to 8000
+    fn start_hdma(ppu: &mut PPU, control: u8) {
+        ppu.write_byte(0xFF51, 0xC0);
+        ppu.write_byte(0xFF52, 0x00);
+        ppu.write_byte(0xFF53, 0x80);
+        ppu.write_byte(0xFF54, 0x00);
+        ppu.write_byte(0xFF55, control);
+    }
+
+    fn copied(ppu: &PPU) -> usize {
+        (0..0x100).take_while(|i| ppu.read_vram(0x8000 + i, 0) == (*i as u8).wrapping_add(1)).count()
+    }
+
+    #[test]
+    fn general_purpose_hdma() {
+        let mut test = TestBus::new();
+        let mut ppu = new_ppu();
+
+        start_hdma(&mut ppu, 0x03);
+        test.tick(&mut ppu, 1);
+        assert_eq!(copied(&ppu), 0x40);
+        assert_eq!(ppu.read_byte(0xFF55), 0xFF);
+
+        // the CPU waits for the whole copy
+        assert_eq!(ppu.state.hdma_stall_cycles, 4 * 32);
+        assert!(ppu.is_cpu_stalled());
+    }
+
+    #[test]
+    fn hblank_hdma() {
+        let mut test = TestBus::new();
+        let mut ppu = new_ppu();
+
+        start_hdma(&mut ppu, 0x82);
+        assert_eq!(ppu.read_byte(0xFF55), 0x02);
+
+        // nothing until the first HBlank, the block is copied on the dot after it starts
+        test.tick(&mut ppu, 252);
+        assert!(ppu.state.mode == PPUMode::HBlank);
+        assert_eq!(copied(&ppu), 0);
+        test.tick(&mut ppu, 1);
+        assert_eq!(copied(&ppu), 0x10);
+        assert_eq!(ppu.read_byte(0xFF55), 0x01);
+        assert!(ppu.is_cpu_stalled());
+
+        // one block per HBlank
+        test.tick(&mut ppu, 455);
+        assert_eq!(copied(&ppu), 0x10);
+        test.tick(&mut ppu, 1);
+        assert_eq!(copied(&ppu), 0x20);
+
+        test.tick(&mut ppu, 456);
+        assert_eq!(copied(&ppu), 0x30);
+        assert_eq!(ppu.read_byte(0xFF55), 0xFF);
+
+        test.tick(&mut ppu, 456);
+        assert_eq!(copied(&ppu), 0x30);
+    }
+
+    #[test]
+    fn hblank_hdma_cancel() {
+        let mut test = TestBus::new();
+        let mut ppu = new_ppu();
+
+        start_hdma(&mut ppu, 0x83);
+        test.tick(&mut ppu, 253);
+        assert_eq!(copied(&ppu), 0x10);
+
+        // the remaining length can still be read after cancelling
+        ppu.write_byte(0xFF55, 0x00);
+        assert_eq!(ppu.read_byte(0xFF55), 0x82);
+
+        test.tick(&mut ppu, 456 * 2);
+        assert_eq!(copied(&ppu), 0x10);
+    }
+
+    #[test]
+    fn hblank_hdma_lcd_off() {
+        let mut test = TestBus::new();
+        let mut ppu = new_ppu();
+        ppu.registers.lcdc = 0x00;
+
+        // a block right away, then nothing until the LCD is back on
+        start_hdma(&mut ppu, 0x81);
+        test.tick(&mut ppu, 1);
+        assert_eq!(copied(&ppu), 0x10);
+
+        test.tick(&mut ppu, 456);
+        assert_eq!(copied(&ppu), 0x10);
+        assert_eq!(ppu.read_byte(0xFF55), 0x00);
+    }
+
+    #[test]
+    fn oam_dma_timing() {
+        let mut test = TestBus::new();
+        let mut ppu = new_ppu();
+
+        // a setup cycle, then a byte every cycle
+        ppu.write_byte(0xFF46, 0xC1);
+        test.tick_oam_dma(&mut ppu, 1);
+        assert!(ppu.is_oam_dma_active());
+        assert_eq!(ppu.oam[0], 0x00);
+
+        test.tick_oam_dma(&mut ppu, 10);
+        assert_eq!(ppu.oam[9], 0x0A);
+        assert_eq!(ppu.oam[10], 0x00);
+        assert_eq!((ppu.get_oam_dma_source(), ppu.get_oam_dma_byte()), (0xC10A, 0x0A));
+
+        test.tick_oam_dma(&mut ppu, 149);
+        assert!(ppu.is_oam_dma_active());
+        test.tick_oam_dma(&mut ppu, 1);
+        assert!(!ppu.is_oam_dma_active());
+        assert_eq!((ppu.oam[0], ppu.oam[0x9F], ppu.oam[0xA0]), (0x01, 0xA0, 0x00));
+    }
+
+    #[test]
+    fn oam_dma_restart() {
+        let mut test = TestBus::new();
+        let mut ppu = new_ppu();
+
+        ppu.write_byte(0xFF46, 0xC1);
+        test.tick_oam_dma(&mut ppu, 11);
+
+        // the old transfer keeps going during the setup cycle of the new one
+        ppu.write_byte(0xFF46, 0xC2);
+        test.tick_oam_dma(&mut ppu, 1);
+        assert_eq!(ppu.oam[10], 0x0B);
+        assert_eq!(ppu.get_oam_dma_source(), 0xC200);
+
+        test.tick_oam_dma(&mut ppu, 1);
+        assert_eq!(ppu.oam[0], 0x01);
+        assert_eq!(ppu.get_oam_dma_source(), 0xC201);
+
+        test.tick_oam_dma(&mut ppu, 159);
+        assert!(!ppu.is_oam_dma_active());
+    }
+
+    #[test]
+    fn oam_dma_from_vram() {
+        let mut test = TestBus::new();
+        let mut ppu = new_ppu();
+        ppu.write_vram(0x8000, 0, 0x12);
+        ppu.write_vram(0x8000, 1, 0x34);
+
+        // VRAM isn't on the external bus, it's read from the bank selected
+        ppu.registers.vram_bank = 1;
+        ppu.write_byte(0xFF46, 0x80);
+        test.tick_oam_dma(&mut ppu, 2);
+        assert_eq!(ppu.oam[0], 0x34);
+    }
+}
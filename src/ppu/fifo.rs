@@ -0,0 +1,687 @@
+use std::collections::VecDeque;
+
+use crate::ppu::{PPU, LCDCBits, OAMAttributes};
+use crate::bitutils::*;
+use crate::machine::GameBoyModel;
+use crate::savestate::{StateWriter, StateReader, SaveStateError};
+
+// Mode 3 is 172 dots long at its shortest: 160 dots put out a pixel and 12 come before the first one,
+// as the hardware fetches the first tile twice and throws the first fetch away. The fetcher here only
+// fetches it once, taking 6 dots with the first pixel going out on the last of them, so 7 are left
+const MODE3_STARTUP_DOTS: u8 = 7;
+
+const MAX_OBJECTS_PER_LINE: usize = 10;
+
+#[derive(Copy, Clone, Default)]
+struct FifoPixel {
+    color: u8,
+    palette: u8,
+    // BG: tile attribute priority (GBC). OBJ: behind BG colors 1-3
+    priority: bool,
+    // OBJ: where the object is in OAM, on GBC the lower one wins where objects overlap
+    oam_index: u8,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum FetcherStep {
+    Tile = 0,
+    DataLow,
+    DataHigh,
+    Push,
+}
+
+#[derive(Copy, Clone, Default)]
+struct LineObject {
+    y: u8,
+    x: u8,
+    tile: u8,
+    flags: u8,
+    oam_index: u8,
+}
+
+// Background/window fetcher and the two pixel FIFOs, everything is read from the registers
+// as the line is drawn so writes done during mode 3 show up mid-line
+pub struct PixelFifo {
+    bg: VecDeque<FifoPixel>,
+    obj: VecDeque<FifoPixel>,
+    step: FetcherStep,
+    step_dots: u8,
+    tile_x: u8,
+    tile_number: u8,
+    tile_attribs: u8,
+    data_low: u8,
+    data_high: u8,
+    in_window: bool,
+    window_line: u8,
+    window_on_line: bool,
+    wy_triggered: bool,
+    lcd_x: u8,
+    discard: u8,
+    // dots the pixel output is paused for, the startup fetch and object fetches
+    stall: u8,
+    objects: Vec<LineObject>,
+    next_object: usize,
+    // where the background tile the last object fetch waited for starts, later objects on it don't wait again
+    object_wait_x: Option<u8>,
+    line: [u16; 160],
+}
+
+impl PixelFifo {
+    pub fn new() -> Self {
+        Self {
+            bg: VecDeque::with_capacity(16),
+            obj: VecDeque::with_capacity(8),
+            step: FetcherStep::Tile,
+            step_dots: 0,
+            tile_x: 0,
+            tile_number: 0,
+            tile_attribs: 0,
+            data_low: 0,
+            data_high: 0,
+            in_window: false,
+            window_line: 0,
+            window_on_line: false,
+            wy_triggered: false,
+            lcd_x: 0,
+            discard: 0,
+            stall: 0,
+            objects: vec!(),
+            next_object: 0,
+            object_wait_x: None,
+            line: [0; 160],
+        }
+    }
+
+    pub fn save_state(&self, w: &mut StateWriter) {
+        let write_pixels = |w: &mut StateWriter, pixels: &VecDeque<FifoPixel>| {
+            w.write_u8(pixels.len() as u8);
+            for p in pixels {
+                w.write_u8(p.color);
+                w.write_u8(p.palette);
+                w.write_bool(p.priority);
+                w.write_u8(p.oam_index);
+            }
+        };
+
+        write_pixels(w, &self.bg);
+        write_pixels(w, &self.obj);
+
+        w.write_u8(self.step as u8);
+        w.write_u8(self.step_dots);
+        w.write_u8(self.tile_x);
+        w.write_u8(self.tile_number);
+        w.write_u8(self.tile_attribs);
+        w.write_u8(self.data_low);
+        w.write_u8(self.data_high);
+        w.write_bool(self.in_window);
+        w.write_u8(self.window_line);
+        w.write_bool(self.window_on_line);
+        w.write_bool(self.wy_triggered);
+        w.write_u8(self.lcd_x);
+        w.write_u8(self.discard);
+        w.write_u8(self.stall);
+
+        w.write_u8(self.objects.len() as u8);
+        for o in &self.objects {
+            w.write_u8(o.y);
+            w.write_u8(o.x);
+            w.write_u8(o.tile);
+            w.write_u8(o.flags);
+            w.write_u8(o.oam_index);
+        }
+        w.write_u8(self.next_object as u8);
+        w.write_bool(self.object_wait_x.is_some());
+        w.write_u8(self.object_wait_x.unwrap_or(0));
+
+        for c in self.line.iter() {
+            w.write_u16(*c);
+        }
+    }
+
+    pub fn load_state(&mut self, r: &mut StateReader) -> Result<(), SaveStateError> {
+        let read_pixels = |r: &mut StateReader, pixels: &mut VecDeque<FifoPixel>, max: usize| -> Result<(), SaveStateError> {
+            let len = r.read_u8()? as usize;
+            if len > max {
+                return Err(SaveStateError::InvalidData("pixel FIFO length"));
+            }
+
+            pixels.clear();
+            for _ in 0..len {
+                pixels.push_back(FifoPixel {
+                    color: r.read_u8()? & 0x3,
+                    palette: r.read_u8()? & 0x7,
+                    priority: r.read_bool()?,
+                    oam_index: r.read_u8()?,
+                });
+            }
+
+            Ok(())
+        };
+
+        read_pixels(r, &mut self.bg, 16)?;
+        read_pixels(r, &mut self.obj, 8)?;
+
+        self.step = match r.read_u8()? {
+            0 => FetcherStep::Tile,
+            1 => FetcherStep::DataLow,
+            2 => FetcherStep::DataHigh,
+            3 => FetcherStep::Push,
+            _ => return Err(SaveStateError::InvalidData("pixel fetcher step"))
+        };
+        self.step_dots = r.read_u8()?;
+        self.tile_x = r.read_u8()?;
+        self.tile_number = r.read_u8()?;
+        self.tile_attribs = r.read_u8()?;
+        self.data_low = r.read_u8()?;
+        self.data_high = r.read_u8()?;
+        self.in_window = r.read_bool()?;
+        self.window_line = r.read_u8()?;
+        self.window_on_line = r.read_bool()?;
+        self.wy_triggered = r.read_bool()?;
+        self.lcd_x = r.read_u8()?.min(160);
+        self.discard = r.read_u8()?;
+        self.stall = r.read_u8()?;
+
+        let len = r.read_u8()? as usize;
+        if len > MAX_OBJECTS_PER_LINE {
+            return Err(SaveStateError::InvalidData("objects per line"));
+        }
+
+        self.objects.clear();
+        for _ in 0..len {
+            self.objects.push(LineObject {
+                y: r.read_u8()?,
+                x: r.read_u8()?,
+                tile: r.read_u8()?,
+                flags: r.read_u8()?,
+                oam_index: r.read_u8()?,
+            });
+        }
+        self.next_object = (r.read_u8()? as usize).min(len);
+        let waited = r.read_bool()?;
+        let wait_x = r.read_u8()?;
+        self.object_wait_x = if waited { Some(wait_x) } else { None };
+
+        for c in self.line.iter_mut() {
+            *c = r.read_u16()?;
+        }
+
+        Ok(())
+    }
+}
+
+impl PPU {
+    // Called when mode 3 starts, sets up the fetcher and does the OAM scan for the line
+    pub(super) fn fifo_start_line(&mut self) {
+        let ly = self.registers.ly;
+        let scx = self.registers.scx;
+        let objects = self.scan_line_objects();
+        let fifo = &mut self.fifo;
+
+        if ly == 0 {
+            fifo.window_line = 0;
+            fifo.wy_triggered = false;
+        }
+
+        if ly == self.registers.wpy {
+            fifo.wy_triggered = true;
+        }
+
+        fifo.bg.clear();
+        fifo.obj.clear();
+        fifo.step = FetcherStep::Tile;
+        fifo.step_dots = 0;
+        fifo.tile_x = 0;
+        fifo.in_window = false;
+        fifo.window_on_line = false;
+        fifo.lcd_x = 0;
+        fifo.discard = scx & 0x7;
+        fifo.stall = MODE3_STARTUP_DOTS;
+        fifo.objects = objects;
+        fifo.next_object = 0;
+        fifo.object_wait_x = None;
+    }
+
+    // Runs one dot of mode 3, returns true once the whole line went out
+    pub(super) fn fifo_tick(&mut self) -> bool {
+        if self.fifo.stall > 0 {
+            self.fifo.stall -= 1;
+            return false;
+        }
+
+        // an object starts at this pixel, the background fetch is paused while it's fetched
+        if self.fetch_next_object() {
+            return false;
+        }
+
+        self.tick_fetcher();
+
+        if self.check_window_start() {
+            return false;
+        }
+
+        self.output_pixel();
+
+        self.fifo.lcd_x == 160
+    }
+
+    // Called when mode 3 is done
+    pub(super) fn fifo_end_line(&mut self) -> [u16; 160] {
+        if self.fifo.window_on_line {
+            self.fifo.window_line = self.fifo.window_line.wrapping_add(1);
+        }
+
+        self.fifo.line
+    }
+
+    fn scan_line_objects(&self) -> Vec<LineObject> {
+        let mode_8x16 = get_flag2(self.registers.lcdc, LCDCBits::OBJSize as u8);
+        let height = if mode_8x16 { 16 } else { 8 };
+
+        let mut objects: Vec<LineObject> = vec!();
+
+        for i in 0..40 {
+            let entry = self.read_oam_entry(i);
+            let y = entry.y.wrapping_sub(16);
+
+            if entry.y == 0 || entry.y > 160 || self.registers.ly.wrapping_sub(y) >= height {
+                continue;
+            }
+
+            objects.push(LineObject {
+                y: entry.y,
+                x: entry.x,
+                tile: entry.tile,
+                flags: self.oam[i as usize * 4 + 3],
+                oam_index: i,
+            });
+
+            if objects.len() == MAX_OBJECTS_PER_LINE {
+                break;
+            }
+        }
+
+        // fetched from left to right, the sort is stable so OAM order breaks ties
+        objects.sort_by_key(|o| o.x);
+        objects
+    }
+
+    fn fetch_next_object(&mut self) -> bool {
+        if !get_flag2(self.registers.lcdc, LCDCBits::OBJDisplayEnable as u8) {
+            return false;
+        }
+
+        let fifo = &self.fifo;
+        let obj = match fifo.objects.get(fifo.next_object) {
+            Some(obj) if obj.x <= fifo.lcd_x + 8 => *obj,
+            _ => return false
+        };
+
+        self.fifo.next_object += 1;
+
+        // objects outside the screen still cost time, but there is nothing to draw
+        if obj.x >= 168 {
+            return true;
+        }
+
+        let flags = OAMAttributes::from_flags(obj.flags);
+        let mode_8x16 = get_flag2(self.registers.lcdc, LCDCBits::OBJSize as u8);
+        let height = if mode_8x16 { 16 } else { 8 };
+
+        let mut row = self.registers.ly.wrapping_sub(obj.y.wrapping_sub(16));
+        if flags.flip_y {
+            row = height - row - 1;
+        }
+
+        let tile = if mode_8x16 { obj.tile & 0xFE } else { obj.tile };
+        let bank = if self.hardware_model == GameBoyModel::GBC { flags.bank } else { 0 };
+        let data = self.read_tile_data(0x8000, bank, tile, row);
+
+        let palette = match self.hardware_model {
+            GameBoyModel::DMG => flags.palette,
+            GameBoyModel::GBC => flags.cgb_palette,
+        };
+
+        // merge into the object FIFO. Objects already in there were further left, on DMG they win over
+        // this one, on GBC the one first in OAM does
+        let lcd_x = self.fifo.lcd_x as i16;
+        let obj_x = obj.x as i16 - 8;
+
+        for i in 0..8 {
+            let pos = obj_x + i - lcd_x;
+            if pos < 0 {
+                continue;
+            }
+
+            let color = if flags.flip_x { data[7 - i as usize] } else { data[i as usize] };
+            let pixel = FifoPixel {
+                color,
+                palette,
+                priority: flags.priority,
+                oam_index: obj.oam_index,
+            };
+
+            let pos = pos as usize;
+            if pos >= self.fifo.obj.len() {
+                self.fifo.obj.push_back(pixel);
+                continue;
+            }
+
+            let old = self.fifo.obj[pos];
+            let wins = match self.hardware_model {
+                GameBoyModel::DMG => old.color == 0,
+                GameBoyModel::GBC => old.color == 0 || (color != 0 && obj.oam_index < old.oam_index),
+            };
+
+            if wins {
+                self.fifo.obj[pos] = pixel;
+            }
+        }
+
+        // The fetch takes 6 dots, before it the background fetcher finishes the tile under the object's
+        // first pixel, 5 dots less how far into that tile the object starts. Objects hanging off the
+        // left edge always wait the whole 5
+        let lcd_x = self.fifo.lcd_x;
+        let tile_offset = if obj.x < 8 {
+            0
+        }
+        else if self.fifo.in_window {
+            (lcd_x + 7).wrapping_sub(self.registers.wpx) % 8
+        }
+        else {
+            lcd_x.wrapping_add(self.registers.scx) % 8
+        };
+
+        let tile_x = lcd_x.wrapping_sub(tile_offset);
+        let wait = if self.fifo.object_wait_x == Some(tile_x) { 0 } else { 5u8.saturating_sub(tile_offset) };
+        self.fifo.object_wait_x = Some(tile_x);
+
+        // this dot is the first of the fetch
+        self.fifo.stall = 6 + wait - 1;
+
+        true
+    }
+
+    fn tick_fetcher(&mut self) {
+        let lcdc = self.registers.lcdc;
+
+        match self.fifo.step {
+            FetcherStep::Tile | FetcherStep::DataLow | FetcherStep::DataHigh => {
+                self.fifo.step_dots += 1;
+                if self.fifo.step_dots < 2 {
+                    return;
+                }
+
+                self.fifo.step_dots = 0;
+
+                match self.fifo.step {
+                    FetcherStep::Tile => {
+                        let address = self.fetcher_tile_map_address();
+                        self.fifo.tile_number = self.read_vram(address, 0);
+                        self.fifo.tile_attribs = match self.hardware_model {
+                            GameBoyModel::DMG => 0,
+                            GameBoyModel::GBC => self.read_vram(address, 1),
+                        };
+
+                        self.fifo.step = FetcherStep::DataLow;
+                    },
+
+                    FetcherStep::DataLow => {
+                        self.fifo.data_low = self.read_vram(self.fetcher_tile_data_address(lcdc), self.fetcher_bank());
+                        self.fifo.step = FetcherStep::DataHigh;
+                    },
+
+                    _ => {
+                        self.fifo.data_high = self.read_vram(self.fetcher_tile_data_address(lcdc) + 1, self.fetcher_bank());
+                        self.fifo.step = FetcherStep::Push;
+                        self.push_tile();
+                    }
+                }
+            },
+
+            FetcherStep::Push => self.push_tile(),
+        }
+    }
+
+    // the row of pixels is only pushed once the background FIFO is empty
+    fn push_tile(&mut self) {
+        if !self.fifo.bg.is_empty() {
+            return;
+        }
+
+        let attribs = self.fifo.tile_attribs;
+        let flip_x = get_bit(attribs, 5) != 0;
+
+        for i in 0..8 {
+            let bit = if flip_x { i } else { 7 - i };
+            let color = (((self.fifo.data_high >> bit) & 0x1) << 1) | ((self.fifo.data_low >> bit) & 0x1);
+
+            self.fifo.bg.push_back(FifoPixel {
+                color,
+                palette: attribs & 0x7,
+                priority: get_bit(attribs, 7) != 0,
+                oam_index: 0,
+            });
+        }
+
+        self.fifo.tile_x = self.fifo.tile_x.wrapping_add(1);
+        self.fifo.step = FetcherStep::Tile;
+    }
+
+    fn fetcher_tile_map_address(&self) -> u16 {
+        let lcdc = self.registers.lcdc;
+
+        let (map_select, x, y) = if self.fifo.in_window {
+            (LCDCBits::WindowTilemapDisplaySelect, self.fifo.tile_x, self.fifo.window_line)
+        }
+        else {
+            let x = (self.registers.scx / 8).wrapping_add(self.fifo.tile_x);
+            let y = self.registers.ly.wrapping_add(self.registers.scy);
+            (LCDCBits::BackgroundTilemapDisplaySelect, x, y)
+        };
+
+        let map_address: u16 = if get_flag2(lcdc, map_select as u8) { 0x9C00 } else { 0x9800 };
+        map_address + ((y / 8) as u16 % 32) * 32 + (x as u16 % 32)
+    }
+
+    fn fetcher_tile_data_address(&self, lcdc: u8) -> u16 {
+        let mut row = if self.fifo.in_window {
+            self.fifo.window_line % 8
+        }
+        else {
+            self.registers.ly.wrapping_add(self.registers.scy) % 8
+        };
+
+        if get_bit(self.fifo.tile_attribs, 6) != 0 {
+            row = 7 - row;
+        }
+
+        let tile = self.fifo.tile_number;
+        let tile_address = if get_flag2(lcdc, LCDCBits::TileDataSelect as u8) {
+            0x8000 + (tile as u16) * 16
+        }
+        else {
+            (0x9000 + (tile as i8 as i32) * 16) as u16
+        };
+
+        tile_address + (row as u16) * 2
+    }
+
+    fn fetcher_bank(&self) -> u16 {
+        get_bit(self.fifo.tile_attribs, 3) as u16
+    }
+
+    // Once the window is reached the background is thrown away and the fetcher starts over on the window tiles
+    fn check_window_start(&mut self) -> bool {
+        let fifo = &self.fifo;
+        let window_enabled = get_flag2(self.registers.lcdc, LCDCBits::WindowEnable as u8);
+
+        if fifo.in_window || !window_enabled || !fifo.wy_triggered || fifo.discard > 0 || fifo.lcd_x + 7 < self.registers.wpx {
+            return false;
+        }
+
+        let fifo = &mut self.fifo;
+        fifo.in_window = true;
+        fifo.window_on_line = true;
+        fifo.bg.clear();
+        fifo.step = FetcherStep::Tile;
+        fifo.step_dots = 0;
+        fifo.tile_x = 0;
+
+        // with WX < 7 the window starts partially off screen
+        fifo.discard = 7u8.saturating_sub(self.registers.wpx);
+
+        true
+    }
+
+    fn output_pixel(&mut self) {
+        let bg = match self.fifo.bg.pop_front() {
+            Some(p) => p,
+            None => return
+        };
+
+        // fine scroll, the first pixels of the line are thrown away
+        if self.fifo.discard > 0 {
+            self.fifo.discard -= 1;
+            return;
+        }
+
+        let obj = self.fifo.obj.pop_front();
+
+        let lcdc = self.registers.lcdc;
+        let bg_enabled = get_flag2(lcdc, LCDCBits::BGWindowDisplayPriority as u8);
+        let obj_enabled = get_flag2(lcdc, LCDCBits::OBJDisplayEnable as u8);
+
+        // on DMG, LCDC bit 0 turns the background off, on GBC it takes away its priority instead
+        let bg_color = if self.hardware_model == GameBoyModel::DMG && !bg_enabled { 0 } else { bg.color };
+
+        let obj = match obj {
+            Some(obj) if obj_enabled && obj.color != 0 => {
+                let bg_wins = match self.hardware_model {
+                    GameBoyModel::DMG => obj.priority && bg_color != 0,
+                    GameBoyModel::GBC => bg_enabled && bg_color != 0 && (bg.priority || obj.priority),
+                };
+
+                if bg_wins { None } else { Some(obj) }
+            },
+            _ => None
+        };
+
+        let color = match (self.hardware_model, obj) {
+            (GameBoyModel::DMG, Some(obj)) => {
+                let palette = if obj.palette == 1 { self.registers.obj_palette1 } else { self.registers.obj_palette0 };
+                ((palette >> (obj.color * 2)) & 0x3) as u16
+            },
+
+            (GameBoyModel::DMG, None) => ((self.registers.bg_palette >> (bg_color * 2)) & 0x3) as u16,
+
+            (GameBoyModel::GBC, Some(obj)) => {
+                let idx = (obj.palette * 8 + obj.color * 2) as usize;
+                (self.registers.cgb_obj_palette_data[idx] as u16) | ((self.registers.cgb_obj_palette_data[idx + 1] as u16) << 8)
+            },
+
+            (GameBoyModel::GBC, None) => {
+                let idx = (bg.palette * 8 + bg_color * 2) as usize;
+                (self.registers.cgb_bg_palette_data[idx] as u16) | ((self.registers.cgb_bg_palette_data[idx + 1] as u16) << 8)
+            }
+        };
+
+        self.fifo.line[self.fifo.lcd_x as usize] = color;
+        self.fifo.lcd_x += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ppu::PPURenderer;
+
+    // LCD, objects and background on, tiles at 8000
+    const LCDC: u8 = 0x93;
+
+    fn new_ppu(model: GameBoyModel) -> PPU {
+        let mut ppu = PPU::new(model, PPURenderer::PixelFifo);
+        ppu.registers.lcdc = LCDC;
+        ppu
+    }
+
+    fn set_object(ppu: &mut PPU, index: usize, x: u8, flags: u8) {
+        ppu.oam[index * 4..index * 4 + 4].copy_from_slice(&[16, x, 1, flags]);
+    }
+
+    // Dots from the start of mode 3 to the one that finishes the line
+    fn mode3_dots(ppu: &mut PPU) -> u32 {
+        ppu.fifo_start_line();
+
+        let mut dots = 1;
+        while !ppu.fifo_tick() {
+            dots += 1;
+        }
+
+        ppu.fifo_end_line();
+        dots
+    }
+
+    #[test]
+    fn mode3_length_scx() {
+        let mut ppu = new_ppu(GameBoyModel::DMG);
+
+        // the fine scroll pixels are fetched and thrown away, one dot each
+        for scx in 0..16 {
+            ppu.registers.scx = scx;
+            assert_eq!(mode3_dots(&mut ppu), 172 + (scx % 8) as u32, "SCX {}", scx);
+        }
+    }
+
+    #[test]
+    fn mode3_length_object() {
+        // 6 dots for the fetch, and up to 5 waiting for the background tile under the object's first pixel
+        for (scx, x, dots) in [(0, 0, 183), (0, 8, 183), (0, 12, 179), (0, 13, 178), (0, 16, 183), (0, 167, 178), (3, 0, 186), (3, 8, 183), (3, 13, 186)] {
+            let mut ppu = new_ppu(GameBoyModel::DMG);
+            ppu.registers.scx = scx;
+            set_object(&mut ppu, 0, x, 0);
+
+            assert_eq!(mode3_dots(&mut ppu), dots, "SCX {} X {}", scx, x);
+        }
+
+        // the second object on the same tile doesn't wait for the background again
+        let mut ppu = new_ppu(GameBoyModel::DMG);
+        set_object(&mut ppu, 0, 16, 0);
+        set_object(&mut ppu, 1, 18, 0);
+        assert_eq!(mode3_dots(&mut ppu), 189);
+
+        // objects are only fetched while they are enabled
+        ppu.registers.lcdc = LCDC & !0x02;
+        assert_eq!(mode3_dots(&mut ppu), 172);
+    }
+
+    #[test]
+    fn object_priority() {
+        // object 0 at 12-19 and object 1 at 8-15, they overlap on 12-15
+        let setup = |model| {
+            let mut ppu = new_ppu(model);
+            for i in 0..16 {
+                ppu.write_vram(0x8010 + i, 0, 0xFF);
+            }
+
+            set_object(&mut ppu, 0, 20, 0x00);
+            set_object(&mut ppu, 1, 16, 0x11);
+
+            ppu.registers.obj_palette0 = 0xC0;
+            ppu.registers.obj_palette1 = 0x40;
+            ppu.registers.cgb_obj_palette_data[6..8].copy_from_slice(&[0x01, 0x00]);
+            ppu.registers.cgb_obj_palette_data[14..16].copy_from_slice(&[0x02, 0x00]);
+
+            mode3_dots(&mut ppu);
+            ppu.fifo.line
+        };
+
+        // DMG: the leftmost object is on top
+        let line = setup(GameBoyModel::DMG);
+        assert_eq!(&line[8..20], &[1, 1, 1, 1, 1, 1, 1, 1, 3, 3, 3, 3]);
+
+        // GBC: the first object in OAM is, wherever it is
+        let line = setup(GameBoyModel::GBC);
+        assert_eq!(&line[8..20], &[2, 2, 2, 2, 1, 1, 1, 1, 1, 1, 1, 1]);
+    }
+}
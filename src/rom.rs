@@ -6,19 +6,48 @@ use std::io::prelude::*;
 mod mbc;
 mod mbc0;
 mod mbc1;
+mod mbc2;
 mod mbc3;
 mod mbc5;
+mod mbc7;
+mod huc1;
+mod huc3;
+mod mmm01;
+mod camera;
+mod tama5;
+mod header;
+mod archive;
+mod patch;
 
 use crate::rom::mbc::MBC;
 use crate::rom::mbc0::MBC0;
 use crate::rom::mbc1::MBC1;
+use crate::rom::mbc2::MBC2;
 use crate::rom::mbc3::MBC3;
 use crate::rom::mbc5::MBC5;
+use crate::rom::mbc7::MBC7;
+use crate::rom::huc1::HuC1;
+use crate::rom::huc3::HuC3;
+use crate::rom::mmm01::MMM01;
+use crate::rom::camera::PocketCamera;
+use crate::rom::tama5::TAMA5;
 use crate::machine::GameBoyModel;
+use crate::savestate::{StateWriter, StateReader, SaveStateError};
+use crate::infrared::InfraredPeer;
+
+pub use crate::rom::header::{CartridgeHeader, Destination, ROMError};
+
+const NINTENDO_LOGO: [u8; 48] = [
+    0xCE, 0xED, 0x66, 0x66, 0xCC, 0x0D, 0x00, 0x0B, 0x03, 0x73, 0x00, 0x83, 0x00, 0x0C, 0x00, 0x0D,
+    0x00, 0x08, 0x11, 0x1F, 0x88, 0x89, 0x00, 0x0E, 0xDC, 0xCC, 0x6E, 0xE6, 0xDD, 0xDD, 0xD9, 0x99,
+    0xBB, 0xBB, 0x67, 0x63, 0x6E, 0x0E, 0xEC, 0xCC, 0xDD, 0xDC, 0x99, 0x9F, 0xBB, 0xB9, 0x33, 0x3E,
+];
 
 pub struct ROM {
     rom_type: GameBoyModel,
     filename: String,
+    header: Option<CartridgeHeader>,
+    warnings: Vec<String>,
     mbc: Option<Box<dyn MBC>>
 }
 
@@ -33,62 +62,197 @@ impl ROM {
         Self {
             rom_type: GameBoyModel::DMG,
             filename: String::new(),
+            header: None,
+            warnings: vec!(),
             mbc: None
         }
     }
 
-    pub fn open(&mut self, filename : &str) {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ROMError> {
+        let mut rom = ROM::new();
+        rom.load(bytes)?;
+
+        Ok(rom)
+    }
+
+    // Opens a ROM, or the entry of a zip/gzip archive holding it. Battery saves are kept next to
+    // the file that was opened, so they are named after the archive.
+    // Patches are applied in order, without any a .ips/.ups/.bps named like the ROM is used
+    pub fn open(&mut self, filename : &str, entry: Option<&str>, patches: &[&str]) -> Result<(), ROMError> {
         // open the rom file
         self.filename = filename.to_owned();
-        let bytes = std::fs::read(&filename).expect("Failed to open ROM");
+        let bytes = ROM::read_file(filename, entry)?;
+        let bytes = ROM::apply_patches(bytes, filename, patches)?;
+
+        self.load(&bytes)?;
+        
+        if let Some(mbc) = &mut self.mbc {
+            // load ram contents if present
+            let mut path = PathBuf::from(filename);
+            path.set_extension("sav");
+
+            if path.exists() {
+                let bytes = std::fs::read(&path)?;
+                mbc.set_ram_contents(&bytes);
+            }
+        }
+
+        for warning in &self.warnings {
+            println!("Warning: {}", warning);
+        }
+
+        if let Some(header) = &self.header {
+            println!("Loaded ROM {}: {} bytes read. Title: {}. Type: {}.", filename, bytes.len(), header.title, header.cart_type_name());
+        }
+
+        Ok(())
+    }
+
+    pub fn load(&mut self, bytes: &[u8]) -> Result<(), ROMError> {
+        let header_offset = ROM::get_header_offset(bytes);
+        let header = CartridgeHeader::parse(&bytes[header_offset..])?;
 
-        let gbc_mode = bytes[0x143];
-        self.rom_type = match gbc_mode {
+        self.warnings = header.validate(bytes, header_offset);
+
+        self.rom_type = match header.cgb_flag {
             0x80 | 0xC0 => GameBoyModel::GBC,
             // 0x80 => GameBoyModel::DMG, // 0x80 is playable on GBC... but we default to DMG mode
             // 0xC0 => GameBoyModel::GBC,
             _ => GameBoyModel::DMG
         };
 
-        let cart_type = bytes[0x0147];
-        let rom_size = bytes[0x0148];
-        let ram_size = bytes[0x0149];
+        let cart_type = header.cart_type;
+        // patches can grow the ROM without fixing up the header, the banks follow what's really there
+        let rom_size = header.rom_size.max(ROM::get_rom_size_code(bytes.len())?);
+        let ram_size = header.ram_size;
+        let multicart = ROM::is_mbc1_multicart(bytes);
+
+        // the MBCs expect every bank of that size to be there, so a short file is
+        // padded with what an empty ROM reads as
+        let min_size = if header_offset == 0 { 0x8000 << rom_size } else { 0x8000 };
+        let mut data = bytes.to_vec();
+        if data.len() < min_size {
+            data.resize(min_size, 0xFF);
+        }
+        let bytes = &data[..];
 
         self.mbc = match cart_type {
             0x00 => {
-                Some(Box::new(MBC0::new(&bytes)))
+                Some(Box::new(MBC0::new(bytes)))
+            },
+            0x01..=0x03 => {
+                Some(Box::new(MBC1::new(rom_size, ram_size, multicart, bytes)?))
+            },
+            0x05 | 0x06 => {
+                Some(Box::new(MBC2::new(rom_size, bytes)?))
             },
-            0x01 | 0x02 | 0x03 => {
-                Some(Box::new(MBC1::new(rom_size, ram_size, &bytes)))
+            0x0B..=0x0D => {
+                Some(Box::new(MMM01::new(ram_size, bytes)?))
             },
-            0x11 | 0x12 | 0x13 => {
-                Some(Box::new(MBC3::new(rom_size, ram_size, &bytes)))
+            0x0F | 0x10 => {
+                Some(Box::new(MBC3::new(rom_size, ram_size, true, bytes)?))
             },
-            0x19 | 0x1A | 0x1B | 0x1C | 0x1D | 0x1E => {
-                Some(Box::new(MBC5::new(rom_size, ram_size, &bytes)))
+            0x11..=0x13 => {
+                Some(Box::new(MBC3::new(rom_size, ram_size, false, bytes)?))
+            },
+            0x19..=0x1E => {
+                Some(Box::new(MBC5::new(rom_size, ram_size, bytes)?))
+            },
+            0x22 => {
+                Some(Box::new(MBC7::new(rom_size, bytes)?))
+            },
+            0xFC => {
+                Some(Box::new(PocketCamera::new(rom_size, bytes)?))
+            },
+            0xFD => {
+                Some(Box::new(TAMA5::new(rom_size, bytes)?))
+            },
+            0xFE => {
+                Some(Box::new(HuC3::new(rom_size, ram_size, bytes)?))
+            },
+            0xFF => {
+                Some(Box::new(HuC1::new(rom_size, ram_size, bytes)?))
             }
-            _ => panic!("Unsupported Cart type: {:#04x}", cart_type)
+            _ => return Err(ROMError::UnsupportedCartType(cart_type))
         };
-        
-        if let Some(mbc) = &mut self.mbc {
-            // load ram contents if present
-            let mut path = PathBuf::from(filename);
-            path.set_extension("sav");
 
-            if path.exists() {
-                let bytes = std::fs::read(&path).expect("Failed to open RAM");
-                mbc.set_ram_contents(&bytes);
+        self.header = Some(header);
+
+        Ok(())
+    }
+
+    pub fn read_file(filename: &str, entry: Option<&str>) -> Result<Vec<u8>, ROMError> {
+        archive::read_rom_file(filename, entry)
+    }
+
+    pub fn apply_patches(mut bytes: Vec<u8>, filename: &str, patches: &[&str]) -> Result<Vec<u8>, ROMError> {
+        let patches: Vec<PathBuf> = if patches.is_empty() {
+            ["ips", "ups", "bps"].iter()
+                .map(|ext| PathBuf::from(filename).with_extension(ext))
+                .filter(|path| path.exists())
+                .collect()
+        }
+        else {
+            patches.iter().map(PathBuf::from).collect()
+        };
+
+        for path in patches {
+            println!("Applying patch {}", path.display());
+            bytes = patch::apply_patch(&bytes, &std::fs::read(&path)?)?;
+        }
+
+        Ok(bytes)
+    }
+
+    // MMM01 multicarts boot into a menu in the last 32KB of the ROM, so the header that describes
+    // the cart is there instead of at the start
+    fn get_header_offset(bytes: &[u8]) -> usize {
+        if bytes.len() > 0x8000 {
+            let offset = bytes.len() - 0x8000;
+
+            if let 0x0B..=0x0D = bytes[offset + 0x0147] {
+                return offset;
             }
         }
 
-        println!("Loaded ROM {}: {} bytes read. Type: {}.", filename, bytes.len(), cart_type);
+        0
+    }
+
+    // Smallest header ROM size that holds this many bytes, nothing past 8MB (code 8) is a cartridge
+    fn get_rom_size_code(len: usize) -> Result<u8, ROMError> {
+        (0..=8).find(|code| (0x8000 << code) >= len).ok_or(ROMError::TooLarge(len))
+    }
+
+    // MBC1M multicarts are 8Mbit carts made of 4 games of 2Mbit, each starting with its own header.
+    // The only way to tell them apart is to look for the logo of those headers
+    fn is_mbc1_multicart(bytes: &[u8]) -> bool {
+        if bytes.len() != 0x100000 {
+            return false;
+        }
+
+        (1..4).any(|game| {
+            let logo = game * 0x40000 + 0x104;
+            bytes[logo..logo + NINTENDO_LOGO.len()] == NINTENDO_LOGO
+        })
     }
 
     pub fn get_rom_type(&self) -> GameBoyModel {
         self.rom_type
     }
+
+    pub fn get_header(&self) -> Option<&CartridgeHeader> {
+        self.header.as_ref()
+    }
+
+    pub fn get_warnings(&self) -> &[String] {
+        &self.warnings
+    }
     
     pub fn close(&self) {
+        if self.filename.is_empty() {
+            return;
+        }
+
         let mut path = PathBuf::from(self.filename.to_owned());
         path.set_extension("sav");
         
@@ -100,6 +264,44 @@ impl ROM {
         }
     }
 
+    pub fn tick(&mut self, clocks: u32) {
+        if let Some(mbc) = &mut self.mbc {
+            mbc.tick(clocks);
+        }
+    }
+
+    pub fn set_tilt(&mut self, x: f32, y: f32) {
+        if let Some(mbc) = &mut self.mbc {
+            mbc.set_tilt(x, y);
+        }
+    }
+
+    pub fn set_infrared_peer(&mut self, peer: Box<dyn InfraredPeer>) {
+        if let Some(mbc) = &mut self.mbc {
+            mbc.set_infrared_peer(peer);
+        }
+    }
+
+    pub fn set_camera_image(&mut self, width: usize, height: usize, pixels: &[u8]) {
+        if let Some(mbc) = &mut self.mbc {
+            mbc.set_camera_image(width, height, pixels);
+        }
+    }
+
+    pub fn save_state(&self, w: &mut StateWriter) {
+        if let Some(mbc) = &self.mbc {
+            mbc.save_state(w);
+        }
+    }
+
+    pub fn load_state(&mut self, r: &mut StateReader) -> Result<(), SaveStateError> {
+        if let Some(mbc) = &mut self.mbc {
+            mbc.load_state(r)?;
+        }
+
+        Ok(())
+    }
+
     pub fn read_byte(&self, address: u16) -> u8 {
         if let Some(mbc) = &self.mbc {
             mbc.read_byte(address)
@@ -109,9 +311,56 @@ impl ROM {
         }
     }
 
+    pub fn get_rom_bank(&self, address: u16) -> u16 {
+        match &self.mbc {
+            Some(mbc) => mbc.get_rom_bank(address),
+            None => 0
+        }
+    }
+
+    // Reads from any bank, for the disassembler, which may look at banks that aren't mapped
+    pub fn read_bank_byte(&self, bank: u16, address: u16) -> u8 {
+        match &self.mbc {
+            Some(mbc) => mbc.read_bank_byte(bank, address),
+            None => 0xFF
+        }
+    }
+
+    pub fn get_bank_count(&self) -> u16 {
+        match &self.mbc {
+            Some(mbc) => mbc.get_rom_bank_count(),
+            None => 0
+        }
+    }
+
     pub fn write_byte(&mut self, address: u16, data: u8) {
         if let Some(mbc) = &mut self.mbc {
             mbc.write_byte(address, data);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_logo(bytes: &mut [u8], offset: usize) {
+        bytes[offset + 0x104..offset + 0x104 + NINTENDO_LOGO.len()].copy_from_slice(&NINTENDO_LOGO);
+    }
+
+    #[test]
+    fn mbc1_multicart() {
+        let mut bytes = vec![0; 0x100000];
+        with_logo(&mut bytes, 0);
+        assert!(!ROM::is_mbc1_multicart(&bytes));
+
+        // any of the other games having a header will do
+        with_logo(&mut bytes, 0x80000);
+        assert!(ROM::is_mbc1_multicart(&bytes));
+
+        // they are only ever 8Mbit
+        let mut bytes = vec![0; 0x80000];
+        with_logo(&mut bytes, 0x40000);
+        assert!(!ROM::is_mbc1_multicart(&bytes));
+    }
+}
@@ -0,0 +1,79 @@
+use std::io::{Cursor, Read};
+use std::ffi::OsStr;
+use std::path::Path;
+
+use flate2::read::GzDecoder;
+use zip::ZipArchive;
+
+use crate::rom::ROMError;
+use crate::rom::header::MAX_ROM_SIZE;
+
+const ZIP_MAGIC: [u8; 4] = [0x50, 0x4B, 0x03, 0x04];
+const GZIP_MAGIC: [u8; 2] = [0x1F, 0x8B];
+
+const ROM_EXTENSIONS: [&str; 2] = ["gb", "gbc"];
+
+// Reads a ROM file, unpacking it first if it's a zip or gzip archive. Archives are recognised
+// by their contents rather than the extension, so a misnamed file still loads
+pub fn read_rom_file(filename: &str, entry: Option<&str>) -> Result<Vec<u8>, ROMError> {
+    let bytes = std::fs::read(filename)?;
+
+    if bytes.starts_with(&ZIP_MAGIC) {
+        read_zip(bytes, entry)
+    }
+    else if bytes.starts_with(&GZIP_MAGIC) {
+        read_limited(GzDecoder::new(&bytes[..]))
+    }
+    else {
+        Ok(bytes)
+    }
+}
+
+// Picks the entry asked for, or the first one that looks like a ROM
+fn read_zip(bytes: Vec<u8>, entry: Option<&str>) -> Result<Vec<u8>, ROMError> {
+    let mut zip = ZipArchive::new(Cursor::new(bytes)).map_err(|e| ROMError::Archive(e.to_string()))?;
+
+    // file_names() doesn't keep the order of the archive, which "first ROM" depends on
+    let mut names = vec!();
+    for i in 0..zip.len() {
+        names.push(zip.by_index_raw(i).map_err(|e| ROMError::Archive(e.to_string()))?.name().to_owned());
+    }
+
+    let name = match entry {
+        // either the full path inside the archive or just the file name will do
+        Some(entry) => names.iter()
+            .find(|n| *n == entry || Path::new(n).file_name() == Some(OsStr::new(entry)))
+            .ok_or_else(|| ROMError::EntryNotFound(entry.to_owned()))?,
+
+        None => names.iter()
+            .filter(|n| !n.ends_with('/'))
+            .find(|n| is_rom_name(n))
+            .ok_or(ROMError::NoROMInArchive)?
+    };
+
+    let file = zip.by_name(name).map_err(|e| ROMError::Archive(e.to_string()))?;
+    if file.size() > MAX_ROM_SIZE as u64 {
+        return Err(ROMError::TooLarge(file.size() as usize));
+    }
+
+    read_limited(file)
+}
+
+// A small archive can unpack to any size, so stop reading once it can't be a ROM anymore
+fn read_limited<R: Read>(reader: R) -> Result<Vec<u8>, ROMError> {
+    let mut data = vec!();
+    reader.take(MAX_ROM_SIZE as u64 + 1).read_to_end(&mut data)?;
+
+    if data.len() > MAX_ROM_SIZE {
+        return Err(ROMError::TooLarge(data.len()));
+    }
+
+    Ok(data)
+}
+
+fn is_rom_name(name: &str) -> bool {
+    match Path::new(name).extension().and_then(|e| e.to_str()) {
+        Some(ext) => ROM_EXTENSIONS.iter().any(|r| ext.eq_ignore_ascii_case(r)),
+        None => false
+    }
+}
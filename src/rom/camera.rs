@@ -0,0 +1,312 @@
+use crate::rom::MBC;
+use crate::rom::ROMError;
+use crate::rom::mbc::rom_bank_count;
+use crate::savestate::{StateWriter, StateReader, SaveStateError};
+
+const CAMERA_WIDTH: usize = 128;
+const CAMERA_HEIGHT: usize = 112;
+
+const CAMERA_RAM_SIZE: usize = 0x20000;
+
+// Selecting a RAM bank with this bit set maps the sensor registers into A000-BFFF
+const CAMERA_REGISTERS_BANK: u8 = 0x10;
+const CAMERA_REGISTERS_SIZE: usize = 0x36;
+
+// A000 starts a capture on bit 0, A002-A003 is the exposure time and A006-A035 is a 4x4 matrix
+// of 3 thresholds per pixel used to turn the sensor output into 4 shades
+const CAMERA_CAPTURE: u8 = 0x01;
+const CAMERA_EXPOSURE: usize = 0x02;
+const CAMERA_MATRIX: usize = 0x06;
+
+// Exposure time the still image is taken as being correctly exposed at
+const CAMERA_NEUTRAL_EXPOSURE: u32 = 0x1000;
+
+// The captured picture ends up as tiles in RAM bank 0
+const CAMERA_PICTURE_ADDRESS: usize = 0x100;
+
+struct CameraRegisters {
+    ram_enabled: bool,
+    rom_bank: u8,
+    ram_bank: u8,
+}
+
+pub struct PocketCamera {
+    data: Vec<u8>,
+    ram: Vec<u8>,
+    registers: CameraRegisters,
+    sensor: [u8; CAMERA_REGISTERS_SIZE],
+    // what the sensor sees, one luminance byte per pixel
+    image: Vec<u8>,
+    num_rom_banks: u16,
+}
+
+impl PocketCamera {
+    pub fn new(rom_size: u8, data: &[u8]) -> Result<Self, ROMError> {
+        // calculate number of rom banks, a ROM bigger than the bank register can select is refused
+        let num_rom_banks = rom_bank_count(rom_size, 64)?;
+
+        Ok(Self {
+            data: data.to_vec(),
+            ram: vec!(0; CAMERA_RAM_SIZE),
+            registers: CameraRegisters {
+                ram_enabled: false,
+                rom_bank: 1,
+                ram_bank: 0,
+            },
+            sensor: [0; CAMERA_REGISTERS_SIZE],
+            // nothing loaded, the sensor sees a flat grey
+            image: vec!(0x80; CAMERA_WIDTH * CAMERA_HEIGHT),
+            num_rom_banks,
+        })
+    }
+
+    fn ram_address(&self, address: u16) -> usize {
+        ((self.registers.ram_bank & 0x0F) as usize * 0x2000) + (address - 0xA000) as usize
+    }
+
+    // Captures happen instantly, so the busy bit is already clear when the game checks it
+    fn capture(&mut self) {
+        let exposure = ((self.sensor[CAMERA_EXPOSURE] as u32) << 8) | (self.sensor[CAMERA_EXPOSURE + 1] as u32);
+
+        for y in 0..CAMERA_HEIGHT {
+            for x in 0..CAMERA_WIDTH {
+                let value = ((self.image[y * CAMERA_WIDTH + x] as u32) * exposure / CAMERA_NEUTRAL_EXPOSURE).min(0xFF) as u8;
+
+                let matrix = CAMERA_MATRIX + ((y & 3) * 4 + (x & 3)) * 3;
+                let color: u8 = if value < self.sensor[matrix] { 3 }
+                    else if value < self.sensor[matrix + 1] { 2 }
+                    else if value < self.sensor[matrix + 2] { 1 }
+                    else { 0 };
+
+                // 16x14 tiles of 2bpp data
+                let tile = (y / 8) * (CAMERA_WIDTH / 8) + (x / 8);
+                let addr = CAMERA_PICTURE_ADDRESS + tile * 16 + (y % 8) * 2;
+                let bit = 7 - (x % 8);
+
+                self.ram[addr] = (self.ram[addr] & !(1 << bit)) | ((color & 1) << bit);
+                self.ram[addr + 1] = (self.ram[addr + 1] & !(1 << bit)) | (((color >> 1) & 1) << bit);
+            }
+        }
+    }
+}
+
+impl MBC for PocketCamera {
+    fn get_rom(&self) -> &[u8] {
+        &self.data
+    }
+
+    fn get_rom_bank(&self, address: u16) -> u16 {
+        match address {
+            0x0000..=0x3FFF => 0,
+            _ => ((self.registers.rom_bank as u32) % (self.num_rom_banks as u32)) as u16
+        }
+    }
+
+    fn read_byte(&self, address: u16) -> u8 {
+        match address {
+            0x0000..=0x3FFF => {
+                self.data[address as usize]
+            },
+
+            0x4000..=0x7FFF => {
+                let bank: u32 = self.get_rom_bank(address) as u32;
+                let idx: u32 = (bank * 0x4000) + ((address - 0x4000) as u32);
+                self.data[idx as usize]
+            },
+
+            // only A000 can be read back from the sensor registers
+            0xA000..=0xBFFF if self.registers.ram_bank & CAMERA_REGISTERS_BANK != 0 => {
+                if address & 0x7F == 0 { self.sensor[0] } else { 0x00 }
+            },
+
+            // RAM can always be read, enabling it only allows writes
+            0xA000..=0xBFFF => {
+                self.ram[self.ram_address(address)]
+            },
+
+            _ => panic!("Invalid ROM read")
+        }
+    }
+
+    fn write_byte(&mut self, address: u16, data: u8) {
+        match address {
+            0x0000..=0x1FFF => {
+                self.registers.ram_enabled = (data & 0x0F) == 0x0A;
+            },
+
+            0x2000..=0x3FFF => {
+                self.registers.rom_bank = data & 0x3F;
+            },
+
+            0x4000..=0x5FFF => {
+                self.registers.ram_bank = data & 0x1F;
+            },
+
+            0x6000..=0x7FFF => {},
+
+            0xA000..=0xBFFF if self.registers.ram_bank & CAMERA_REGISTERS_BANK != 0 => {
+                let reg = (address & 0x7F) as usize;
+
+                if reg == 0 {
+                    self.sensor[0] = data & 0x07;
+
+                    if data & CAMERA_CAPTURE != 0 {
+                        self.capture();
+                        self.sensor[0] &= !CAMERA_CAPTURE;
+                    }
+                }
+                else if reg < CAMERA_REGISTERS_SIZE {
+                    self.sensor[reg] = data;
+                }
+            },
+
+            0xA000..=0xBFFF => {
+                if self.registers.ram_enabled {
+                    let ram_addr = self.ram_address(address);
+                    self.ram[ram_addr] = data;
+                }
+            },
+
+            _ => panic!("Invalid ROM write {:#06x}", address)
+        }
+    }
+
+    fn get_ram_contents(&self) -> Option<Vec<u8>> {
+        Some(self.ram.to_owned())
+    }
+
+    fn set_ram_contents(&mut self, data: &[u8]) {
+        let len = self.ram.len().min(data.len());
+        self.ram[0..len].copy_from_slice(&data[0..len]);
+    }
+
+    // Scales the picture to the sensor size, the pixels are 8 bit luminance
+    fn set_camera_image(&mut self, width: usize, height: usize, pixels: &[u8]) {
+        if width == 0 || height == 0 || pixels.len() < width * height {
+            return;
+        }
+
+        for y in 0..CAMERA_HEIGHT {
+            for x in 0..CAMERA_WIDTH {
+                let src_x = x * width / CAMERA_WIDTH;
+                let src_y = y * height / CAMERA_HEIGHT;
+                self.image[y * CAMERA_WIDTH + x] = pixels[src_y * width + src_x];
+            }
+        }
+    }
+
+    fn save_state(&self, w: &mut StateWriter) {
+        w.write_bool(self.registers.ram_enabled);
+        w.write_u8(self.registers.rom_bank);
+        w.write_u8(self.registers.ram_bank);
+        w.write_bytes(&self.sensor);
+        w.write_vec(&self.ram);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), SaveStateError> {
+        self.registers.ram_enabled = r.read_bool()?;
+        self.registers.rom_bank = r.read_u8()? & 0x3F;
+        self.registers.ram_bank = r.read_u8()? & 0x1F;
+        r.read_bytes(&mut self.sensor)?;
+        r.read_vec_into(&mut self.ram)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 1MB, each bank starts with its number
+    fn camera() -> PocketCamera {
+        let mut data = vec![0; 0x100000];
+        for bank in 0..64 {
+            data[bank * 0x4000] = bank as u8;
+        }
+
+        PocketCamera::new(5, &data).unwrap()
+    }
+
+    // Same 3 thresholds for every pixel of the matrix, then exposes the picture
+    fn capture(mbc: &mut PocketCamera, exposure: u16, thresholds: [u8; 3]) {
+        mbc.write_byte(0x4000, CAMERA_REGISTERS_BANK);
+        mbc.write_byte(0xA002, (exposure >> 8) as u8);
+        mbc.write_byte(0xA003, exposure as u8);
+
+        for i in 0..16 {
+            for (j, threshold) in thresholds.iter().enumerate() {
+                mbc.write_byte(0xA000 + (CAMERA_MATRIX + i * 3 + j) as u16, *threshold);
+            }
+        }
+
+        mbc.write_byte(0xA000, 0x03);
+        mbc.write_byte(0x4000, 0x00);
+    }
+
+    #[test]
+    fn banks() {
+        let mut mbc = camera();
+        mbc.write_byte(0x2000, 0x3F);
+        assert_eq!(mbc.read_byte(0x4000), 63);
+        mbc.write_byte(0x2000, 0x45);
+        assert_eq!(mbc.read_byte(0x4000), 5);
+
+        // RAM reads work while disabled, writes don't
+        mbc.write_byte(0x4000, 0x0F);
+        mbc.write_byte(0xA000, 0x55);
+        assert_eq!(mbc.read_byte(0xA000), 0x00);
+        mbc.write_byte(0x0000, 0x0A);
+        mbc.write_byte(0xA000, 0x55);
+        assert_eq!(mbc.read_byte(0xA000), 0x55);
+        assert_eq!(mbc.get_ram_contents().unwrap()[0x1E000], 0x55);
+    }
+
+    #[test]
+    fn sensor_registers() {
+        let mut mbc = camera();
+        mbc.write_byte(0x0000, 0x0A);
+        mbc.write_byte(0x4000, CAMERA_REGISTERS_BANK);
+
+        // only A000 reads back, and never hits RAM bank 0
+        mbc.write_byte(0xA000, 0x06);
+        mbc.write_byte(0xA001, 0x55);
+        assert_eq!((mbc.read_byte(0xA000), mbc.read_byte(0xA080), mbc.read_byte(0xA001)), (0x06, 0x06, 0x00));
+        assert_eq!(mbc.get_ram_contents().unwrap()[0], 0x00);
+
+        // the capture is done by the time the game looks
+        mbc.write_byte(0xA000, 0x07);
+        assert_eq!(mbc.read_byte(0xA000), 0x06);
+    }
+
+    #[test]
+    fn capture_shades() {
+        let mut mbc = camera();
+
+        // flat grey between the first two thresholds is shade 2
+        capture(&mut mbc, CAMERA_NEUTRAL_EXPOSURE as u16, [0x40, 0x90, 0xC0]);
+        let ram = mbc.get_ram_contents().unwrap();
+        assert_eq!((ram[CAMERA_PICTURE_ADDRESS], ram[CAMERA_PICTURE_ADDRESS + 1]), (0x00, 0xFF));
+
+        // twice the exposure saturates to white
+        capture(&mut mbc, (CAMERA_NEUTRAL_EXPOSURE * 2) as u16, [0x40, 0x90, 0xC0]);
+        let ram = mbc.get_ram_contents().unwrap();
+        assert_eq!((ram[CAMERA_PICTURE_ADDRESS], ram[CAMERA_PICTURE_ADDRESS + 1]), (0x00, 0x00));
+
+        // the picture is scaled to the sensor, a black left half covers the left 8 tiles
+        let mut pixels = vec![0xFF; 64 * 56];
+        for y in 0..56 {
+            for x in 0..32 {
+                pixels[y * 64 + x] = 0x00;
+            }
+        }
+
+        mbc.set_camera_image(64, 56, &pixels);
+        capture(&mut mbc, CAMERA_NEUTRAL_EXPOSURE as u16, [0x40, 0x90, 0xC0]);
+        let ram = mbc.get_ram_contents().unwrap();
+        let last_tile = CAMERA_PICTURE_ADDRESS + 16 * 14 * 16 - 16;
+        assert_eq!((ram[CAMERA_PICTURE_ADDRESS], ram[CAMERA_PICTURE_ADDRESS + 1]), (0xFF, 0xFF));
+        assert_eq!((ram[CAMERA_PICTURE_ADDRESS + 8 * 16], ram[CAMERA_PICTURE_ADDRESS + 8 * 16 + 1]), (0x00, 0x00));
+        assert_eq!((ram[last_tile], ram[last_tile + 1]), (0x00, 0x00));
+    }
+}
@@ -0,0 +1,230 @@
+use std::fmt;
+use std::io;
+
+// The header lives in 0100-014F, everything we parse is in 0134-014F
+const HEADER_END: usize = 0x150;
+
+// Nothing bigger than the largest cartridge (8MB) is a ROM, larger sizes are refused before allocating them
+pub const MAX_ROM_SIZE: usize = 0x800000;
+
+#[derive(Debug)]
+pub enum ROMError {
+    Io(io::Error),
+    Truncated(usize),
+    TooLarge(usize),
+    InvalidROMSize(u8),
+    InvalidRAMSize(u8),
+    UnsupportedCartType(u8),
+    Archive(String),
+    NoROMInArchive,
+    EntryNotFound(String),
+    InvalidPatch(&'static str),
+}
+
+impl fmt::Display for ROMError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ROMError::Io(e) => write!(f, "failed to read ROM: {}", e),
+            ROMError::Truncated(len) => write!(f, "ROM is too small to have a header ({} bytes)", len),
+            ROMError::TooLarge(len) => write!(f, "ROM is too large for a cartridge ({} bytes)", len),
+            ROMError::InvalidROMSize(v) => write!(f, "invalid ROM size in header: {:#04x}", v),
+            ROMError::InvalidRAMSize(v) => write!(f, "invalid RAM size in header: {:#04x}", v),
+            ROMError::UnsupportedCartType(v) => write!(f, "unsupported cart type: {:#04x}", v),
+            ROMError::Archive(e) => write!(f, "failed to read archive: {}", e),
+            ROMError::NoROMInArchive => write!(f, "no .gb or .gbc file found in archive"),
+            ROMError::EntryNotFound(name) => write!(f, "archive has no entry named {}", name),
+            ROMError::InvalidPatch(what) => write!(f, "failed to apply patch: {}", what),
+        }
+    }
+}
+
+impl std::error::Error for ROMError {}
+
+impl From<io::Error> for ROMError {
+    fn from(e: io::Error) -> Self {
+        ROMError::Io(e)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Destination {
+    Japan,
+    Overseas,
+}
+
+#[derive(Clone, Debug)]
+pub struct CartridgeHeader {
+    pub title: String,
+    pub manufacturer_code: String,
+    pub cgb_flag: u8,
+    pub new_licensee_code: String,
+    pub sgb_flag: u8,
+    pub cart_type: u8,
+    pub rom_size: u8,
+    pub ram_size: u8,
+    pub destination: Destination,
+    pub old_licensee_code: u8,
+    pub version: u8,
+    pub header_checksum: u8,
+    pub global_checksum: u16,
+}
+
+impl CartridgeHeader {
+    pub fn parse(bytes: &[u8]) -> Result<Self, ROMError> {
+        if bytes.len() < HEADER_END {
+            return Err(ROMError::Truncated(bytes.len()));
+        }
+
+        let cgb_flag = bytes[0x143];
+
+        // on CGB carts the end of the title was given to the manufacturer code and the CGB flag
+        let (title, manufacturer_code) = if cgb_flag & 0x80 != 0 {
+            (CartridgeHeader::parse_text(&bytes[0x134..0x13F]), CartridgeHeader::parse_text(&bytes[0x13F..0x143]))
+        }
+        else {
+            (CartridgeHeader::parse_text(&bytes[0x134..0x144]), String::new())
+        };
+
+        let header = Self {
+            title,
+            manufacturer_code,
+            cgb_flag,
+            new_licensee_code: CartridgeHeader::parse_text(&bytes[0x144..0x146]),
+            sgb_flag: bytes[0x146],
+            cart_type: bytes[0x147],
+            rom_size: bytes[0x148],
+            ram_size: bytes[0x149],
+            destination: if bytes[0x14A] == 0 { Destination::Japan } else { Destination::Overseas },
+            old_licensee_code: bytes[0x14B],
+            version: bytes[0x14C],
+            header_checksum: bytes[0x14D],
+            global_checksum: ((bytes[0x14E] as u16) << 8) | (bytes[0x14F] as u16),
+        };
+
+        if header.rom_size_bytes().is_none() {
+            return Err(ROMError::InvalidROMSize(header.rom_size));
+        }
+
+        if header.ram_size_bytes().is_none() {
+            return Err(ROMError::InvalidRAMSize(header.ram_size));
+        }
+
+        Ok(header)
+    }
+
+    // Text fields are padded with zeros, anything that isn't printable is dropped
+    fn parse_text(bytes: &[u8]) -> String {
+        bytes.iter()
+            .take_while(|&&c| c != 0)
+            .filter(|c| c.is_ascii_graphic() || **c == b' ')
+            .map(|&c| c as char)
+            .collect::<String>()
+            .trim_end()
+            .to_owned()
+    }
+
+    pub fn rom_size_bytes(&self) -> Option<usize> {
+        match self.rom_size {
+            0x00..=0x08 => Some(0x8000 << self.rom_size),
+            _ => None
+        }
+    }
+
+    pub fn ram_size_bytes(&self) -> Option<usize> {
+        match self.ram_size {
+            0 => Some(0),
+            1 => Some(0x800),
+            2 => Some(0x2000),
+            3 => Some(0x8000),
+            4 => Some(0x20000),
+            5 => Some(0x10000),
+            _ => None
+        }
+    }
+
+    pub fn is_sgb(&self) -> bool {
+        self.sgb_flag == 0x03
+    }
+
+    // The new licensee code is only used when the old one says so
+    pub fn licensee(&self) -> String {
+        if self.old_licensee_code == 0x33 {
+            self.new_licensee_code.to_owned()
+        }
+        else {
+            format!("{:02X}", self.old_licensee_code)
+        }
+    }
+
+    // The boot ROM refuses to start a cart if this doesn't match
+    pub fn compute_header_checksum(bytes: &[u8]) -> u8 {
+        bytes[0x134..=0x14C].iter().fold(0u8, |x, b| x.wrapping_sub(*b).wrapping_sub(1))
+    }
+
+    // Nothing checks this one on hardware. It covers the whole ROM except itself, which is in the header at header_offset
+    pub fn compute_global_checksum(bytes: &[u8], header_offset: usize) -> u16 {
+        bytes.iter().enumerate()
+            .filter(|(i, _)| *i != header_offset + 0x14E && *i != header_offset + 0x14F)
+            .fold(0u16, |sum, (_, b)| sum.wrapping_add(*b as u16))
+    }
+
+    // Things that are wrong with the file but don't stop it from running. bytes is the whole file,
+    // the header is at header_offset (not 0 on MMM01)
+    pub fn validate(&self, bytes: &[u8], header_offset: usize) -> Vec<String> {
+        let mut warnings = vec!();
+
+        let rom_size = self.rom_size_bytes().unwrap_or(0);
+        if bytes.len() < rom_size {
+            warnings.push(format!("header says the ROM is {}KB but the file is {}KB, the rest reads as 0xFF", rom_size / 1024, bytes.len() / 1024));
+        }
+        else if bytes.len() > rom_size {
+            warnings.push(format!("header says the ROM is {}KB but the file is {}KB", rom_size / 1024, bytes.len() / 1024));
+        }
+
+        let header_checksum = CartridgeHeader::compute_header_checksum(&bytes[header_offset..]);
+        if header_checksum != self.header_checksum {
+            warnings.push(format!("header checksum is {:#04x} but should be {:#04x}", self.header_checksum, header_checksum));
+        }
+
+        let global_checksum = CartridgeHeader::compute_global_checksum(bytes, header_offset);
+        if global_checksum != self.global_checksum {
+            warnings.push(format!("global checksum is {:#06x} but should be {:#06x}", self.global_checksum, global_checksum));
+        }
+
+        warnings
+    }
+
+    pub fn cart_type_name(&self) -> &'static str {
+        match self.cart_type {
+            0x00 => "ROM ONLY",
+            0x01 => "MBC1",
+            0x02 => "MBC1+RAM",
+            0x03 => "MBC1+RAM+BATTERY",
+            0x05 => "MBC2",
+            0x06 => "MBC2+BATTERY",
+            0x08 => "ROM+RAM",
+            0x09 => "ROM+RAM+BATTERY",
+            0x0B => "MMM01",
+            0x0C => "MMM01+RAM",
+            0x0D => "MMM01+RAM+BATTERY",
+            0x0F => "MBC3+TIMER+BATTERY",
+            0x10 => "MBC3+TIMER+RAM+BATTERY",
+            0x11 => "MBC3",
+            0x12 => "MBC3+RAM",
+            0x13 => "MBC3+RAM+BATTERY",
+            0x19 => "MBC5",
+            0x1A => "MBC5+RAM",
+            0x1B => "MBC5+RAM+BATTERY",
+            0x1C => "MBC5+RUMBLE",
+            0x1D => "MBC5+RUMBLE+RAM",
+            0x1E => "MBC5+RUMBLE+RAM+BATTERY",
+            0x20 => "MBC6",
+            0x22 => "MBC7+SENSOR+RUMBLE+RAM+BATTERY",
+            0xFC => "POCKET CAMERA",
+            0xFD => "BANDAI TAMA5",
+            0xFE => "HuC3",
+            0xFF => "HuC1+RAM+BATTERY",
+            _ => "UNKNOWN"
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use crate::rom::MBC;
+use crate::rom::ROMError;
+use crate::rom::mbc::{rom_bank_count, ram_bank_count};
+use crate::infrared::{InfraredPeer, NoLight};
+use crate::savestate::{StateWriter, StateReader, SaveStateError};
+
+// Writing this to 0000-1FFF maps the IR port into A000-BFFF, anything else maps RAM
+const HUC1_IR_MODE: u8 = 0x0E;
+
+struct HuC1Registers {
+    ir_mode: bool,
+    ir_led: bool,
+    rom_bank: u8,
+    ram_bank: u8,
+}
+
+pub struct HuC1 {
+    data: Vec<u8>,
+    ram: Vec<u8>,
+    registers: HuC1Registers,
+    infrared: Box<dyn InfraredPeer>,
+    num_rom_banks: u16,
+    num_ram_banks: u8,
+}
+
+impl HuC1 {
+    pub fn new(rom_size: u8, ram_size: u8, data: &[u8]) -> Result<Self, ROMError> {
+        // calculate number of rom banks, a ROM bigger than the bank register can select is refused
+        let num_rom_banks = rom_bank_count(rom_size, 64)?;
+
+        // and ram banks
+        let (num_ram_banks, vec_ram_size) = ram_bank_count(ram_size, 4)?;
+
+        Ok(Self {
+            data: data.to_vec(),
+            ram: vec!(0; vec_ram_size),
+            registers: HuC1Registers {
+                ir_mode: false,
+                ir_led: false,
+                rom_bank: 1,
+                ram_bank: 0,
+            },
+            infrared: Box::new(NoLight),
+            num_rom_banks,
+            num_ram_banks,
+        })
+    }
+
+    fn ram_address(&self, address: u16) -> usize {
+        let ram_bank: usize = if self.num_ram_banks <= 1 { 0 } else { (self.registers.ram_bank % self.num_ram_banks) as usize };
+        (ram_bank * 0x2000) + ((address - 0xA000) as usize) % self.ram.len().min(0x2000)
+    }
+}
+
+impl MBC for HuC1 {
+    fn get_rom(&self) -> &[u8] {
+        &self.data
+    }
+
+    fn get_rom_bank(&self, address: u16) -> u16 {
+        match address {
+            0x0000..=0x3FFF => 0,
+            _ => ((self.registers.rom_bank as u32) % (self.num_rom_banks as u32)) as u16
+        }
+    }
+
+    fn read_byte(&self, address: u16) -> u8 {
+        match address {
+            0x0000..=0x3FFF => {
+                self.data[address as usize]
+            },
+
+            0x4000..=0x7FFF => {
+                let bank: u32 = self.get_rom_bank(address) as u32;
+                let idx: u32 = (bank * 0x4000) + ((address - 0x4000) as u32);
+                self.data[idx as usize]
+            },
+
+            0xA000..=0xBFFF => {
+                // bit 0 is set while light is received
+                if self.registers.ir_mode {
+                    return 0xC0 | (self.infrared.is_receiving() as u8);
+                }
+
+                if self.ram.is_empty() {
+                    return 0xFF;
+                }
+
+                self.ram[self.ram_address(address)]
+            },
+
+            _ => panic!("Invalid ROM read")
+        }
+    }
+
+    fn write_byte(&mut self, address: u16, data: u8) {
+        match address {
+            // there is no RAM enable, this only switches between RAM and IR
+            0x0000..=0x1FFF => {
+                self.registers.ir_mode = (data & 0x0F) == HUC1_IR_MODE;
+            },
+
+            0x2000..=0x3FFF => {
+                self.registers.rom_bank = if (data & 0x3F) == 0 { 1 } else { data & 0x3F };
+            },
+
+            0x4000..=0x5FFF => {
+                self.registers.ram_bank = data & 0x03;
+            },
+
+            0x6000..=0x7FFF => {},
+
+            0xA000..=0xBFFF => {
+                if self.registers.ir_mode {
+                    self.registers.ir_led = data & 0x01 != 0;
+                    self.infrared.set_led(self.registers.ir_led);
+                }
+                else if !self.ram.is_empty() {
+                    let ram_addr = self.ram_address(address);
+                    self.ram[ram_addr] = data;
+                }
+            },
+
+            _ => panic!("Invalid ROM write {:#06x}", address)
+        }
+    }
+
+    fn get_ram_contents(&self) -> Option<Vec<u8>> {
+        Some(self.ram.to_owned())
+    }
+
+    fn set_ram_contents(&mut self, data: &[u8]) {
+        let len = self.ram.len().min(data.len());
+        self.ram[0..len].copy_from_slice(&data[0..len]);
+    }
+
+    fn set_infrared_peer(&mut self, peer: Box<dyn InfraredPeer>) {
+        self.infrared = peer;
+        self.infrared.set_led(self.registers.ir_led);
+    }
+
+    fn save_state(&self, w: &mut StateWriter) {
+        w.write_bool(self.registers.ir_mode);
+        w.write_bool(self.registers.ir_led);
+        w.write_u8(self.registers.rom_bank);
+        w.write_u8(self.registers.ram_bank);
+        w.write_vec(&self.ram);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), SaveStateError> {
+        self.registers.ir_mode = r.read_bool()?;
+        self.registers.ir_led = r.read_bool()?;
+        self.registers.rom_bank = r.read_u8()? & 0x3F;
+        self.registers.ram_bank = r.read_u8()? & 0x03;
+        r.read_vec_into(&mut self.ram)?;
+
+        self.infrared.set_led(self.registers.ir_led);
+
+        Ok(())
+    }
+}
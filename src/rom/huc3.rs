@@ -0,0 +1,488 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::rom::MBC;
+use crate::rom::ROMError;
+use crate::rom::mbc::{rom_bank_count, ram_bank_count, CLOCKS_PER_SECOND};
+use crate::infrared::{InfraredPeer, NoLight};
+use crate::savestate::{StateWriter, StateReader, SaveStateError};
+
+// Size of the RTC footer appended to the .sav file
+const RTC_FOOTER_SIZE: usize = 17;
+
+const MINUTES_PER_DAY: u64 = 1440;
+const CLOCKS_PER_MINUTE: u32 = CLOCKS_PER_SECOND * 60;
+
+// What A000-BFFF is mapped to, selected by writing to 0000-1FFF
+const MODE_RAM_READ: u8 = 0x00;
+const MODE_RAM: u8 = 0x0A;
+const MODE_RTC_COMMAND: u8 = 0x0B;
+const MODE_RTC_RESPONSE: u8 = 0x0C;
+const MODE_RTC_SEMAPHORE: u8 = 0x0D;
+const MODE_IR: u8 = 0x0E;
+
+// RTC commands, the upper nibble of the byte written in MODE_RTC_COMMAND
+const RTC_READ: u8 = 0x1;
+const RTC_WRITE: u8 = 0x3;
+const RTC_SET_ADDRESS_LOW: u8 = 0x4;
+const RTC_SET_ADDRESS_HIGH: u8 = 0x5;
+const RTC_EXTENDED: u8 = 0x6;
+
+const RTC_EXTENDED_STATUS: u8 = 0x2;
+
+struct HuC3Registers {
+    mode: u8,
+    ir_led: bool,
+    rom_bank: u8,
+    ram_bank: u8,
+}
+
+// The clock counts minutes and days, the game talks to it one nibble at a time through
+// a small address space where the time and the alarm live
+struct RTC {
+    minutes: u16,
+    days: u16,
+    alarm_minutes: u16,
+    alarm_days: u16,
+    alarm_enabled: bool,
+    // clocks into the current minute
+    clocks: u32,
+    command: u8,
+    response: u8,
+    address: u8,
+}
+
+impl RTC {
+    fn new() -> Self {
+        Self {
+            minutes: 0,
+            days: 0,
+            alarm_minutes: 0,
+            alarm_days: 0,
+            alarm_enabled: false,
+            clocks: 0,
+            command: 0,
+            response: 0,
+            address: 0,
+        }
+    }
+
+    fn now() -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+    }
+
+    // The clock follows emulated time, so it stops with the emulator and stays the same across runs
+    fn tick(&mut self, clocks: u32) {
+        self.clocks += clocks;
+        if self.clocks >= CLOCKS_PER_MINUTE {
+            self.clocks -= CLOCKS_PER_MINUTE;
+            self.advance(1);
+        }
+    }
+
+    fn advance(&mut self, minutes: u64) {
+        let total = self.minutes as u64 + minutes;
+        self.minutes = (total % MINUTES_PER_DAY) as u16;
+        self.days = self.days.wrapping_add((total / MINUTES_PER_DAY) as u16);
+    }
+
+    fn read_nibble(&self, address: u8) -> u8 {
+        let value = match address {
+            0x00..=0x02 => self.minutes >> (address * 4),
+            0x03..=0x06 => self.days >> ((address - 0x03) * 4),
+            0x58..=0x5A => self.alarm_minutes >> ((address - 0x58) * 4),
+            0x5B..=0x5E => self.alarm_days >> ((address - 0x5B) * 4),
+            0x5F => self.alarm_enabled as u16,
+            _ => 0
+        };
+
+        (value & 0x0F) as u8
+    }
+
+    fn write_nibble(&mut self, address: u8, data: u8) {
+        let set = |value: u16, shift: u8| (value & !(0x0F << shift)) | ((data as u16 & 0x0F) << shift);
+
+        match address {
+            0x00..=0x02 => self.minutes = set(self.minutes, address * 4) % (MINUTES_PER_DAY as u16),
+            0x03..=0x06 => self.days = set(self.days, (address - 0x03) * 4),
+            0x58..=0x5A => self.alarm_minutes = set(self.alarm_minutes, (address - 0x58) * 4),
+            0x5B..=0x5E => self.alarm_days = set(self.alarm_days, (address - 0x5B) * 4),
+            0x5F => self.alarm_enabled = data & 0x01 != 0,
+            _ => {}
+        }
+    }
+
+    fn execute(&mut self) {
+        let arg = self.command & 0x0F;
+
+        match (self.command >> 4) & 0x07 {
+            RTC_READ => {
+                self.response = self.read_nibble(self.address);
+                self.address = self.address.wrapping_add(1);
+            },
+
+            RTC_WRITE => {
+                self.write_nibble(self.address, arg);
+                self.address = self.address.wrapping_add(1);
+            },
+
+            RTC_SET_ADDRESS_LOW => self.address = (self.address & 0xF0) | arg,
+            RTC_SET_ADDRESS_HIGH => self.address = (self.address & 0x0F) | (arg << 4),
+
+            // the time is always readable, so the only extended command that matters is the status check
+            RTC_EXTENDED if arg == RTC_EXTENDED_STATUS => self.response = 0x01,
+
+            _ => {}
+        }
+    }
+
+    fn to_footer(&self) -> Vec<u8> {
+        let mut footer = Vec::with_capacity(RTC_FOOTER_SIZE);
+
+        footer.extend_from_slice(&RTC::now().to_le_bytes());
+        footer.extend_from_slice(&self.minutes.to_le_bytes());
+        footer.extend_from_slice(&self.days.to_le_bytes());
+        footer.extend_from_slice(&self.alarm_minutes.to_le_bytes());
+        footer.extend_from_slice(&self.alarm_days.to_le_bytes());
+        footer.push(self.alarm_enabled as u8);
+
+        footer
+    }
+
+    fn load_footer(&mut self, footer: &[u8]) {
+        let word = |i: usize| u16::from_le_bytes([footer[i], footer[i + 1]]);
+
+        let mut timestamp = [0; 8];
+        timestamp.copy_from_slice(&footer[0..8]);
+        let timestamp = u64::from_le_bytes(timestamp);
+
+        self.minutes = word(8) % (MINUTES_PER_DAY as u16);
+        self.days = word(10);
+        self.alarm_minutes = word(12);
+        self.alarm_days = word(14);
+        self.alarm_enabled = footer[16] != 0;
+
+        // catch up with the time that went by while the emulator was closed
+        let now = RTC::now();
+        if now > timestamp {
+            self.advance((now - timestamp) / 60);
+            self.clocks = ((now - timestamp) % 60) as u32 * CLOCKS_PER_SECOND;
+        }
+    }
+
+    fn save_state(&self, w: &mut StateWriter) {
+        w.write_u16(self.minutes);
+        w.write_u16(self.days);
+        w.write_u16(self.alarm_minutes);
+        w.write_u16(self.alarm_days);
+        w.write_bool(self.alarm_enabled);
+        w.write_u32(self.clocks);
+        w.write_u8(self.command);
+        w.write_u8(self.response);
+        w.write_u8(self.address);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), SaveStateError> {
+        self.minutes = r.read_u16()? % (MINUTES_PER_DAY as u16);
+        self.days = r.read_u16()?;
+        self.alarm_minutes = r.read_u16()?;
+        self.alarm_days = r.read_u16()?;
+        self.alarm_enabled = r.read_bool()?;
+        self.clocks = r.read_u32()?.min(CLOCKS_PER_MINUTE - 1);
+
+        self.command = r.read_u8()?;
+        self.response = r.read_u8()? & 0x0F;
+        self.address = r.read_u8()?;
+
+        Ok(())
+    }
+}
+
+pub struct HuC3 {
+    data: Vec<u8>,
+    ram: Vec<u8>,
+    registers: HuC3Registers,
+    rtc: RTC,
+    infrared: Box<dyn InfraredPeer>,
+    num_rom_banks: u16,
+    num_ram_banks: u8,
+}
+
+impl HuC3 {
+    pub fn new(rom_size: u8, ram_size: u8, data: &[u8]) -> Result<Self, ROMError> {
+        // calculate number of rom banks, a ROM bigger than the bank register can select is refused
+        let num_rom_banks = rom_bank_count(rom_size, 128)?;
+
+        // and ram banks
+        let (num_ram_banks, vec_ram_size) = ram_bank_count(ram_size, 16)?;
+
+        Ok(Self {
+            data: data.to_vec(),
+            ram: vec!(0; vec_ram_size),
+            registers: HuC3Registers {
+                mode: MODE_RAM_READ,
+                ir_led: false,
+                rom_bank: 1,
+                ram_bank: 0,
+            },
+            rtc: RTC::new(),
+            infrared: Box::new(NoLight),
+            num_rom_banks,
+            num_ram_banks,
+        })
+    }
+
+    fn ram_address(&self, address: u16) -> usize {
+        let ram_bank: usize = if self.num_ram_banks <= 1 { 0 } else { (self.registers.ram_bank % self.num_ram_banks) as usize };
+        (ram_bank * 0x2000) + ((address - 0xA000) as usize) % self.ram.len().min(0x2000)
+    }
+}
+
+impl MBC for HuC3 {
+    fn get_rom(&self) -> &[u8] {
+        &self.data
+    }
+
+    fn get_rom_bank(&self, address: u16) -> u16 {
+        match address {
+            0x0000..=0x3FFF => 0,
+            _ => ((self.registers.rom_bank as u32) % (self.num_rom_banks as u32)) as u16
+        }
+    }
+
+    fn read_byte(&self, address: u16) -> u8 {
+        match address {
+            0x0000..=0x3FFF => {
+                self.data[address as usize]
+            },
+
+            0x4000..=0x7FFF => {
+                let bank: u32 = self.get_rom_bank(address) as u32;
+                let idx: u32 = (bank * 0x4000) + ((address - 0x4000) as u32);
+                self.data[idx as usize]
+            },
+
+            0xA000..=0xBFFF => {
+                match self.registers.mode {
+                    MODE_RAM_READ | MODE_RAM if !self.ram.is_empty() => self.ram[self.ram_address(address)],
+
+                    // the last command with its result in the lower nibble
+                    MODE_RTC_RESPONSE => 0x80 | (self.rtc.command & 0x70) | self.rtc.response,
+
+                    // commands complete straight away, so the RTC is always ready
+                    MODE_RTC_SEMAPHORE => 0xFF,
+
+                    // bit 0 is set while light is received
+                    MODE_IR => 0xC0 | (self.infrared.is_receiving() as u8),
+
+                    _ => 0xFF
+                }
+            },
+
+            _ => panic!("Invalid ROM read")
+        }
+    }
+
+    fn write_byte(&mut self, address: u16, data: u8) {
+        match address {
+            0x0000..=0x1FFF => {
+                self.registers.mode = data & 0x0F;
+            },
+
+            0x2000..=0x3FFF => {
+                self.registers.rom_bank = if (data & 0x7F) == 0 { 1 } else { data & 0x7F };
+            },
+
+            0x4000..=0x5FFF => {
+                self.registers.ram_bank = data & 0x0F;
+            },
+
+            0x6000..=0x7FFF => {},
+
+            0xA000..=0xBFFF => {
+                match self.registers.mode {
+                    MODE_RAM if !self.ram.is_empty() => {
+                        let ram_addr = self.ram_address(address);
+                        self.ram[ram_addr] = data;
+                    },
+
+                    MODE_RTC_COMMAND => self.rtc.command = data & 0x7F,
+
+                    // clearing bit 0 runs the pending command
+                    MODE_RTC_SEMAPHORE if data & 0x01 == 0 => self.rtc.execute(),
+
+                    MODE_IR => {
+                        self.registers.ir_led = data & 0x01 != 0;
+                        self.infrared.set_led(self.registers.ir_led);
+                    },
+
+                    _ => {}
+                }
+            },
+
+            _ => panic!("Invalid ROM write {:#06x}", address)
+        }
+    }
+
+    fn tick(&mut self, clocks: u32) {
+        self.rtc.tick(clocks);
+    }
+
+    fn get_ram_contents(&self) -> Option<Vec<u8>> {
+        let mut contents = self.ram.to_owned();
+        contents.extend_from_slice(&self.rtc.to_footer());
+
+        Some(contents)
+    }
+
+    fn set_ram_contents(&mut self, data: &[u8]) {
+        let ram_len = self.ram.len().min(data.len());
+        self.ram[0..ram_len].copy_from_slice(&data[0..ram_len]);
+
+        let footer = &data[ram_len..];
+        if footer.len() == RTC_FOOTER_SIZE {
+            self.rtc.load_footer(footer);
+        }
+    }
+
+    fn set_infrared_peer(&mut self, peer: Box<dyn InfraredPeer>) {
+        self.infrared = peer;
+        self.infrared.set_led(self.registers.ir_led);
+    }
+
+    fn save_state(&self, w: &mut StateWriter) {
+        w.write_u8(self.registers.mode);
+        w.write_bool(self.registers.ir_led);
+        w.write_u8(self.registers.rom_bank);
+        w.write_u8(self.registers.ram_bank);
+        w.write_vec(&self.ram);
+        self.rtc.save_state(w);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), SaveStateError> {
+        self.registers.mode = r.read_u8()? & 0x0F;
+        self.registers.ir_led = r.read_bool()?;
+        self.registers.rom_bank = r.read_u8()? & 0x7F;
+        self.registers.ram_bank = r.read_u8()? & 0x0F;
+        r.read_vec_into(&mut self.ram)?;
+        self.rtc.load_state(r)?;
+
+        self.infrared.set_led(self.registers.ir_led);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn huc3() -> HuC3 {
+        HuC3::new(0, 2, &[0; 0x8000]).unwrap()
+    }
+
+    // Sends one command the way games do: write it, run it by clearing the semaphore, then read the response
+    fn command(mbc: &mut HuC3, command: u8) -> u8 {
+        mbc.write_byte(0x0000, MODE_RTC_COMMAND);
+        mbc.write_byte(0xA000, command);
+        mbc.write_byte(0x0000, MODE_RTC_SEMAPHORE);
+        mbc.write_byte(0xA000, 0xFE);
+        mbc.write_byte(0x0000, MODE_RTC_RESPONSE);
+        mbc.read_byte(0xA000)
+    }
+
+    fn set_address(mbc: &mut HuC3, address: u8) {
+        command(mbc, (RTC_SET_ADDRESS_LOW << 4) | (address & 0x0F));
+        command(mbc, (RTC_SET_ADDRESS_HIGH << 4) | (address >> 4));
+    }
+
+    #[test]
+    fn counts_emulated_minutes() {
+        let mut mbc = huc3();
+
+        for _ in 0..59 {
+            mbc.tick(CLOCKS_PER_SECOND);
+        }
+        assert_eq!(mbc.rtc.minutes, 0);
+
+        mbc.tick(CLOCKS_PER_SECOND);
+        assert_eq!((mbc.rtc.minutes, mbc.rtc.days, mbc.rtc.clocks), (1, 0, 0));
+    }
+
+    #[test]
+    fn minutes_roll_into_days() {
+        let mut rtc = RTC::new();
+        rtc.minutes = 1439;
+        rtc.advance(2);
+
+        assert_eq!((rtc.minutes, rtc.days), (1, 1));
+    }
+
+    #[test]
+    fn read_and_write_time() {
+        let mut mbc = huc3();
+
+        // minutes 0x123, days 0x0045, written a nibble at a time from address 0
+        set_address(&mut mbc, 0x00);
+        for nibble in [0x3, 0x2, 0x1, 0x5, 0x4, 0x0, 0x0] {
+            command(&mut mbc, (RTC_WRITE << 4) | nibble);
+        }
+        assert_eq!((mbc.rtc.minutes, mbc.rtc.days), (0x123, 0x45));
+
+        set_address(&mut mbc, 0x00);
+        let nibbles: Vec<u8> = (0..4).map(|_| command(&mut mbc, RTC_READ << 4) & 0x0F).collect();
+        assert_eq!(nibbles, [0x3, 0x2, 0x1, 0x5]);
+        assert_eq!(mbc.rtc.address, 0x04);
+    }
+
+    #[test]
+    fn response_register() {
+        let mut mbc = huc3();
+
+        // the command is echoed back above the result, and the RTC is always ready
+        assert_eq!(command(&mut mbc, (RTC_EXTENDED << 4) | RTC_EXTENDED_STATUS), 0x80 | (RTC_EXTENDED << 4) | 0x01);
+
+        mbc.write_byte(0x0000, MODE_RTC_SEMAPHORE);
+        assert_eq!(mbc.read_byte(0xA000), 0xFF);
+    }
+
+    #[test]
+    fn alarm() {
+        let mut mbc = huc3();
+
+        set_address(&mut mbc, 0x5F);
+        command(&mut mbc, (RTC_WRITE << 4) | 0x1);
+        assert!(mbc.rtc.alarm_enabled);
+    }
+
+    #[test]
+    fn save_state_keeps_clock() {
+        let mut mbc = huc3();
+        mbc.rtc.minutes = 100;
+        mbc.tick(CLOCKS_PER_SECOND * 30);
+
+        let mut w = StateWriter::new();
+        mbc.save_state(&mut w);
+
+        let mut loaded = huc3();
+        loaded.load_state(&mut StateReader::new(&w.into_bytes())).unwrap();
+
+        // no catching up with wall time, so it's exactly where it was
+        assert_eq!((loaded.rtc.minutes, loaded.rtc.clocks), (100, CLOCKS_PER_SECOND * 30));
+    }
+
+    #[test]
+    fn footer_round_trip() {
+        let mut mbc = huc3();
+        mbc.rtc.minutes = 600;
+        mbc.rtc.days = 12;
+        mbc.ram[0] = 0x42;
+
+        let contents = mbc.get_ram_contents().unwrap();
+        assert_eq!(contents.len(), 0x2000 + RTC_FOOTER_SIZE);
+
+        let mut loaded = huc3();
+        loaded.set_ram_contents(&contents);
+        assert_eq!((loaded.ram[0], loaded.rtc.days), (0x42, 12));
+        assert!((600..602).contains(&loaded.rtc.minutes));
+    }
+}
@@ -1,13 +1,89 @@
+use crate::savestate::{StateWriter, StateReader, SaveStateError};
+use crate::infrared::InfraredPeer;
+use crate::rom::ROMError;
+
+// Cartridge clocks have their own 32768 Hz crystal, counted here in 4 MHz clocks so they keep time in double speed too
+pub const CLOCKS_PER_SECOND: u32 = 4194304;
+
+// Number of 16KB banks for the header ROM size, refused if the mapper can't select that many
+pub fn rom_bank_count(rom_size: u8, max_banks: u16) -> Result<u16, ROMError> {
+    if rom_size > 8 || (2 << rom_size) > max_banks {
+        return Err(ROMError::InvalidROMSize(rom_size));
+    }
+
+    Ok(2 << rom_size)
+}
+
+// Number of 8KB banks and the size of the RAM for the header RAM size, refused if the mapper can't select that many
+pub fn ram_bank_count(ram_size: u8, max_banks: u8) -> Result<(u8, usize), ROMError> {
+    let (num_ram_banks, vec_ram_size) = match ram_size {
+        0 => (0, 0),
+        1 => (1, 0x800),
+        2 => (1, 0x2000),
+        3 => (4, 0x8000),
+        4 => (16, 0x20000),
+        5 => (8, 0x10000),
+        _ => return Err(ROMError::InvalidRAMSize(ram_size))
+    };
+
+    if num_ram_banks > max_banks {
+        return Err(ROMError::InvalidRAMSize(ram_size));
+    }
+
+    Ok((num_ram_banks, vec_ram_size))
+}
+
 pub trait MBC {
+    // The whole ROM, every bank whether it's mapped or not
+    fn get_rom(&self) -> &[u8];
+
+    // Reads from any bank, whatever is mapped. Addresses 0000-3FFF and 4000-7FFF both
+    // land at the start of the bank
+    fn read_bank_byte(&self, bank: u16, address: u16) -> u8 {
+        let offset = bank as usize * 0x4000 + (address & 0x3FFF) as usize;
+        self.get_rom().get(offset).copied().unwrap_or(0xFF)
+    }
+
+    fn get_rom_bank_count(&self) -> u16 {
+        (self.get_rom().len() / 0x4000) as u16
+    }
+
     #[allow(unused)]
     fn read_byte(&self, address: u16) -> u8 { 0 }
 
     #[allow(unused)]
     fn write_byte(&mut self, address: u16, data: u8) {}
+
+    // The ROM bank the CPU sees at this address
+    fn get_rom_bank(&self, address: u16) -> u16 {
+        if address < 0x4000 { 0 } else { 1 }
+    }
     
+    // Runs what the cartridge clocks by itself, in 4 MHz clocks whatever speed the CPU is at
+    #[allow(unused)]
+    fn tick(&mut self, clocks: u32) { }
+
     #[allow(unused)]
     fn get_ram_contents(&self) -> Option<Vec<u8>> { None }
 
     #[allow(unused)]
     fn set_ram_contents(&mut self, ram: &[u8]) { }
-}
\ No newline at end of file
+
+    // Accelerometer input for carts that have one, in g
+    #[allow(unused)]
+    fn set_tilt(&mut self, x: f32, y: f32) { }
+
+    // What the IR port sees on carts that have one
+    #[allow(unused)]
+    fn set_infrared_peer(&mut self, peer: Box<dyn InfraredPeer>) { }
+
+    // Picture in front of the Pocket Camera sensor, as 8 bit luminance
+    #[allow(unused)]
+    fn set_camera_image(&mut self, width: usize, height: usize, pixels: &[u8]) { }
+
+    #[allow(unused)]
+    fn save_state(&self, w: &mut StateWriter) { }
+
+    #[allow(unused)]
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), SaveStateError> { Ok(()) }
+}
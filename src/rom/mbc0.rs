@@ -14,6 +14,10 @@ impl MBC0 {
 }
 
 impl MBC for MBC0 {
+    fn get_rom(&self) -> &[u8] {
+        &self.data
+    }
+
     fn read_byte(&self, address: u16) -> u8 {
         match address {
             0x0000..=0x7FFF => {
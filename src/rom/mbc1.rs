@@ -1,4 +1,7 @@
 use crate::rom::MBC;
+use crate::rom::ROMError;
+use crate::rom::mbc::{rom_bank_count, ram_bank_count};
+use crate::savestate::{StateWriter, StateReader, SaveStateError};
 
 struct MBC1Registers {
     ram_enabled: bool,
@@ -11,26 +14,21 @@ pub struct MBC1 {
     data: Vec<u8>,
     ram: Vec<u8>,
     registers: MBC1Registers,
-    num_rom_banks: u8,
-    num_ram_banks: u8
+    num_rom_banks: u16,
+    num_ram_banks: u8,
+    // MBC1M multicarts leave bit 4 of BANK1 unconnected and wire BANK2 one bit lower
+    bank2_shift: u8,
 }
 
 impl MBC1 {
-    pub fn new(rom_size: u8, ram_size: u8, data: &[u8]) -> Self {
-        // calculate number of rom banks
-        let data_size = (0x8000 << rom_size) as usize;
-        let num_rom_banks = ((data_size as u32) / 0x4000) as u8;
+    pub fn new(rom_size: u8, ram_size: u8, multicart: bool, data: &[u8]) -> Result<Self, ROMError> {
+        // calculate number of rom banks, a ROM bigger than the bank register can select is refused
+        let num_rom_banks = rom_bank_count(rom_size, 128)?;
         
         // and ram banks
-        let (num_ram_banks, vec_ram_size) = match ram_size {
-            0 => (0, 0),
-            1 => (1, 0x800),
-            2 => (1, 0x2000),
-            3 => (4, 0x8000),
-            _ => panic!("Invalid RAM size for MBC1")
-        };
+        let (num_ram_banks, vec_ram_size) = ram_bank_count(ram_size, 4)?;
         
-        Self {
+        Ok(Self {
             data: data.to_vec(),
             ram: vec!(0; vec_ram_size),
             registers: MBC1Registers {
@@ -41,35 +39,54 @@ impl MBC1 {
             },
             num_rom_banks,
             num_ram_banks,
-        }
+            bank2_shift: if multicart { 4 } else { 5 },
+        })
+    }
+
+    fn bank1(&self) -> u8 {
+        if self.bank2_shift == 4 { self.registers.bank1 & 0x0F } else { self.registers.bank1 }
+    }
+
+    // 2KB RAM repeats across the whole area
+    fn ram_address(&self, address: u16) -> usize {
+        let ram_bank: usize = if self.registers.mode == 0 || self.num_ram_banks <= 1 { 0 } else { (self.registers.bank2 % self.num_ram_banks) as usize };
+        (ram_bank * 0x2000) + ((address - 0xA000) as usize) % self.ram.len().min(0x2000)
     }
 }
 
 impl MBC for MBC1 {
+    fn get_rom(&self) -> &[u8] {
+        &self.data
+    }
+
+    fn get_rom_bank(&self, address: u16) -> u16 {
+        let bank: u32 = match address {
+            // in mode 1 BANK2 also switches the lower area
+            0x0000..=0x3FFF if self.registers.mode == 0 => 0,
+            0x0000..=0x3FFF => (self.registers.bank2 << self.bank2_shift) as u32,
+            _ => (self.registers.bank2 as u32) << self.bank2_shift | (self.bank1() as u32)
+        };
+
+        (bank % (self.num_rom_banks as u32)) as u16
+    }
+
     fn read_byte(&self, address: u16) -> u8 {
         match address {
             0x0000..=0x3FFF => {
-                if self.registers.mode == 0 { 
-                    self.data[address as usize]
-                } 
-                else { 
-                    let bank: u32 = ((self.registers.bank2 << 5) as u32) % (self.num_rom_banks as u32);
-                    let idx: u32 = (bank * 0x4000) + (address as u32);
-                    self.data[idx as usize]
-                }                
+                let bank: u32 = self.get_rom_bank(address) as u32;
+                let idx: u32 = (bank * 0x4000) + (address as u32);
+                self.data[idx as usize]
             },
 
             0x4000..=0x7FFF => {
-                let bank: u32 = (((self.registers.bank2 as u32) << 5 | (self.registers.bank1 as u32)) as u32) % (self.num_rom_banks as u32);
+                let bank: u32 = self.get_rom_bank(address) as u32;
                 let idx: u32 = (bank * 0x4000) + ((address - 0x4000) as u32);
                 self.data[idx as usize]
             },
 
             0xA000..=0xBFFF => {
-                if self.registers.ram_enabled {
-                    let ram_bank: u32 = if self.registers.mode == 0 || self.num_ram_banks <= 1 { 0 } else { (self.registers.bank2 & 0x3) as u32 };
-                    let ram_addr: u32 = (ram_bank * 0x2000) + ((address - 0xA000) as u32);
-                    self.ram[ram_addr as usize]
+                if self.registers.ram_enabled && !self.ram.is_empty() {
+                    self.ram[self.ram_address(address)]
                 }
                 else {
                     0xff
@@ -101,10 +118,9 @@ impl MBC for MBC1 {
             },
 
             0xA000..=0xBFFF => {
-                if self.registers.ram_enabled {
-                    let ram_bank: u32 = if self.registers.mode == 0 || self.num_ram_banks <= 1 { 0 } else { (self.registers.bank2 & 0x3) as u32 };
-                    let ram_addr: u32 = (ram_bank * 0x2000) + ((address - 0xA000) as u32);
-                    self.ram[ram_addr as usize] = data;
+                if self.registers.ram_enabled && !self.ram.is_empty() {
+                    let ram_addr = self.ram_address(address);
+                    self.ram[ram_addr] = data;
 
                     // println!("RAM{}:{:#04x} {:#04x}", ram_bank, address, data);
                 }
@@ -119,6 +135,113 @@ impl MBC for MBC1 {
     }
 
     fn set_ram_contents(&mut self, data: &[u8]) {
-        self.ram.copy_from_slice(data);
+        let ram_len = self.ram.len().min(data.len());
+        self.ram[0..ram_len].copy_from_slice(&data[0..ram_len]);
+    }
+
+    fn save_state(&self, w: &mut StateWriter) {
+        w.write_bool(self.registers.ram_enabled);
+        w.write_u8(self.registers.mode);
+        w.write_u8(self.registers.bank1);
+        w.write_u8(self.registers.bank2);
+        w.write_vec(&self.ram);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), SaveStateError> {
+        self.registers.ram_enabled = r.read_bool()?;
+        self.registers.mode = r.read_u8()? & 0x1;
+        self.registers.bank1 = r.read_u8()? & 0x1F;
+        self.registers.bank2 = r.read_u8()? & 0x3;
+        r.read_vec_into(&mut self.ram)?;
+
+        Ok(())
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mbc1(ram_size: u8) -> MBC1 {
+        let mut mbc = MBC1::new(0, ram_size, false, &[0; 0x8000]).unwrap();
+        mbc.write_byte(0x0000, 0x0A);
+        mbc
+    }
+
+    // 1MB, each bank starts with its number
+    fn mbc1_1mb(multicart: bool) -> MBC1 {
+        let mut data = vec![0; 0x100000];
+        for bank in 0..64 {
+            data[bank * 0x4000] = bank as u8;
+        }
+
+        MBC1::new(5, 0, multicart, &data).unwrap()
+    }
+
+    #[test]
+    fn bank2_mapping() {
+        let mut mbc = mbc1_1mb(false);
+        mbc.write_byte(0x4000, 0x01);
+        mbc.write_byte(0x2000, 0x13);
+        assert_eq!(mbc.read_byte(0x4000), 0x33);
+
+        mbc.write_byte(0x6000, 0x01);
+        assert_eq!(mbc.read_byte(0x0000), 0x20);
+    }
+
+    #[test]
+    fn multicart_bank2_mapping() {
+        let mut mbc = mbc1_1mb(true);
+
+        // BANK2 is bits 4-5 and BANK1 loses its bit 4, so each game sees its own 16 banks
+        mbc.write_byte(0x4000, 0x02);
+        mbc.write_byte(0x2000, 0x13);
+        assert_eq!(mbc.read_byte(0x4000), 0x23);
+
+        // 0x10 isn't 0 so it isn't turned into 1, but bit 4 goes nowhere: the game's bank 0 shows up at 4000
+        mbc.write_byte(0x2000, 0x10);
+        assert_eq!(mbc.read_byte(0x4000), 0x20);
+
+        // in mode 1 the lower area switches between the games' first banks
+        assert_eq!(mbc.read_byte(0x0000), 0x00);
+        mbc.write_byte(0x6000, 0x01);
+        assert_eq!(mbc.read_byte(0x0000), 0x20);
+        mbc.write_byte(0x4000, 0x03);
+        assert_eq!(mbc.read_byte(0x0000), 0x30);
+    }
+
+    #[test]
+    fn no_ram() {
+        let mut mbc = mbc1(0);
+
+        mbc.write_byte(0xA000, 0x12);
+        assert_eq!(mbc.read_byte(0xA000), 0xFF);
+        assert_eq!(mbc.read_byte(0xBFFF), 0xFF);
+
+        mbc.set_ram_contents(&[0x34; 0x2000]);
+        assert_eq!(mbc.get_ram_contents().unwrap().len(), 0);
+    }
+
+    #[test]
+    fn small_ram_repeats() {
+        let mut mbc = mbc1(1);
+
+        mbc.write_byte(0xA801, 0x56);
+        assert_eq!(mbc.read_byte(0xA001), 0x56);
+        assert_eq!(mbc.read_byte(0xB801), 0x56);
+
+        // the bank bits don't select anything on a single bank
+        mbc.write_byte(0x6000, 0x01);
+        mbc.write_byte(0x4000, 0x03);
+        assert_eq!(mbc.read_byte(0xA001), 0x56);
+    }
+
+    #[test]
+    fn short_save() {
+        let mut mbc = mbc1(3);
+        mbc.set_ram_contents(&[0x78; 0x100]);
+
+        let ram = mbc.get_ram_contents().unwrap();
+        assert_eq!(ram.len(), 0x8000);
+        assert_eq!((ram[0xFF], ram[0x100]), (0x78, 0x00));
+    }
+}
@@ -0,0 +1,195 @@
+use crate::rom::MBC;
+use crate::rom::ROMError;
+use crate::rom::mbc::rom_bank_count;
+use crate::savestate::{StateWriter, StateReader, SaveStateError};
+
+// 512 half bytes of RAM built into the MBC itself
+const MBC2_RAM_SIZE: usize = 0x200;
+
+struct MBC2Registers {
+    ram_enabled: bool,
+    rom_bank: u8,
+}
+
+pub struct MBC2 {
+    data: Vec<u8>,
+    ram: Vec<u8>,
+    registers: MBC2Registers,
+    num_rom_banks: u16,
+}
+
+impl MBC2 {
+    pub fn new(rom_size: u8, data: &[u8]) -> Result<Self, ROMError> {
+        // calculate number of rom banks, a ROM bigger than the bank register can select is refused
+        let num_rom_banks = rom_bank_count(rom_size, 16)?;
+
+        Ok(Self {
+            data: data.to_vec(),
+            ram: vec!(0; MBC2_RAM_SIZE),
+            registers: MBC2Registers {
+                ram_enabled: false,
+                rom_bank: 1,
+            },
+            num_rom_banks,
+        })
+    }
+
+    // the RAM only has 9 address lines, so it repeats all over A000-BFFF
+    fn ram_address(address: u16) -> usize {
+        ((address - 0xA000) as usize) & (MBC2_RAM_SIZE - 1)
+    }
+}
+
+impl MBC for MBC2 {
+    fn get_rom(&self) -> &[u8] {
+        &self.data
+    }
+
+    fn get_rom_bank(&self, address: u16) -> u16 {
+        match address {
+            0x0000..=0x3FFF => 0,
+            _ => ((self.registers.rom_bank as u32) % (self.num_rom_banks as u32)) as u16
+        }
+    }
+
+    fn read_byte(&self, address: u16) -> u8 {
+        match address {
+            0x0000..=0x3FFF => {
+                self.data[address as usize]
+            },
+
+            0x4000..=0x7FFF => {
+                let bank: u32 = self.get_rom_bank(address) as u32;
+                let idx: u32 = (bank * 0x4000) + ((address - 0x4000) as u32);
+                self.data[idx as usize]
+            },
+
+            0xA000..=0xBFFF => {
+                if !self.registers.ram_enabled {
+                    return 0xFF;
+                }
+
+                // only the lower 4 bits are stored, the upper ones read back as 1
+                0xF0 | self.ram[MBC2::ram_address(address)]
+            },
+
+            _ => panic!("Invalid ROM read")
+        }
+    }
+
+    fn write_byte(&mut self, address: u16, data: u8) {
+        match address {
+            // RAMG / ROMB share the same range, bit 8 of the address picks the register
+            0x0000..=0x3FFF => {
+                if address & 0x100 == 0 {
+                    self.registers.ram_enabled = (data & 0x0F) == 0x0A;
+                }
+                else {
+                    self.registers.rom_bank = if (data & 0x0F) == 0 { 1 } else { data & 0x0F };
+                }
+            },
+
+            0x4000..=0x7FFF => {},
+
+            0xA000..=0xBFFF => {
+                if self.registers.ram_enabled {
+                    self.ram[MBC2::ram_address(address)] = data & 0x0F;
+                }
+            },
+
+            _ => panic!("Invalid ROM write {:#06x}", address)
+        }
+    }
+
+    fn get_ram_contents(&self) -> Option<Vec<u8>> {
+        Some(self.ram.to_owned())
+    }
+
+    fn set_ram_contents(&mut self, data: &[u8]) {
+        for (dest, src) in self.ram.iter_mut().zip(data.iter()) {
+            *dest = src & 0x0F;
+        }
+    }
+
+    fn save_state(&self, w: &mut StateWriter) {
+        w.write_bool(self.registers.ram_enabled);
+        w.write_u8(self.registers.rom_bank);
+        w.write_vec(&self.ram);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), SaveStateError> {
+        self.registers.ram_enabled = r.read_bool()?;
+        self.registers.rom_bank = r.read_u8()? & 0x0F;
+        r.read_vec_into(&mut self.ram)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 256KB, each bank starts with its number
+    fn mbc2() -> MBC2 {
+        let mut data = vec![0; 0x40000];
+        for bank in 0..16 {
+            data[bank * 0x4000] = bank as u8;
+        }
+
+        MBC2::new(3, &data).unwrap()
+    }
+
+    #[test]
+    fn half_byte_ram() {
+        let mut mbc = mbc2();
+        mbc.write_byte(0xA000, 0x5A);
+        assert_eq!(mbc.read_byte(0xA000), 0xFF);
+
+        mbc.write_byte(0x0000, 0x0A);
+        mbc.write_byte(0xA000, 0x5A);
+        assert_eq!(mbc.read_byte(0xA000), 0xFA);
+        assert_eq!(mbc.get_ram_contents().unwrap()[0], 0x0A);
+
+        // the 512 half bytes repeat across the whole area
+        assert_eq!(mbc.read_byte(0xA200), 0xFA);
+        assert_eq!(mbc.read_byte(0xBE00), 0xFA);
+        mbc.write_byte(0xB3FF, 0x03);
+        assert_eq!(mbc.read_byte(0xA1FF), 0xF3);
+
+        mbc.write_byte(0x0000, 0x00);
+        assert_eq!(mbc.read_byte(0xA000), 0xFF);
+    }
+
+    #[test]
+    fn register_select() {
+        let mut mbc = mbc2();
+
+        // address bit 8 set selects the ROM bank, clear the RAM enable
+        mbc.write_byte(0x0100, 0x0A);
+        assert_eq!(mbc.read_byte(0x4000), 10);
+        assert!(!mbc.registers.ram_enabled);
+
+        mbc.write_byte(0x3EFF, 0x0A);
+        assert!(mbc.registers.ram_enabled);
+        assert_eq!(mbc.read_byte(0x4000), 10);
+
+        // only 4 bits, and bank 0 maps to 1
+        mbc.write_byte(0x2100, 0x13);
+        assert_eq!(mbc.read_byte(0x4000), 3);
+        mbc.write_byte(0x2100, 0x00);
+        assert_eq!(mbc.read_byte(0x4000), 1);
+        mbc.write_byte(0x2100, 0x10);
+        assert_eq!(mbc.read_byte(0x4000), 1);
+    }
+
+    #[test]
+    fn saves_low_nibbles() {
+        let mut mbc = mbc2();
+        mbc.set_ram_contents(&[0xAB; 0x100]);
+
+        let ram = mbc.get_ram_contents().unwrap();
+        assert_eq!(ram.len(), MBC2_RAM_SIZE);
+        assert_eq!((ram[0], ram[0x100]), (0x0B, 0x00));
+    }
+}
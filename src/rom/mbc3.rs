@@ -1,51 +1,282 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
 use crate::rom::MBC;
+use crate::rom::ROMError;
+use crate::rom::mbc::{rom_bank_count, ram_bank_count, CLOCKS_PER_SECOND};
+use crate::savestate::{StateWriter, StateReader, SaveStateError};
+
+// Size of the RTC footer appended to the .sav file (VBA-M / BGB format)
+const RTC_FOOTER_SIZE: usize = 48;
+// Older emulators wrote a 32 bit timestamp instead
+const RTC_FOOTER_SIZE_LEGACY: usize = 44;
+
+const RTC_DAY_HIGH_MASK: u8 = 0xC1;
+const RTC_HALT: u8 = 1 << 6;
+const RTC_DAY_CARRY: u8 = 1 << 7;
 
 struct MBC3Registers {
     ram_enabled: bool,
-    mode: u8,
+    latch: u8,
     rom_bank: u8,
     ram_bank: u8,
 }
 
+#[derive(Copy, Clone, Default)]
+struct RTCRegisters {
+    seconds: u8,
+    minutes: u8,
+    hours: u8,
+    days_low: u8,
+    days_high: u8,
+}
+
+impl RTCRegisters {
+    fn read(&self, reg: u8) -> u8 {
+        match reg {
+            0x08 => self.seconds,
+            0x09 => self.minutes,
+            0x0A => self.hours,
+            0x0B => self.days_low,
+            0x0C => self.days_high,
+            _ => 0xFF
+        }
+    }
+
+    fn write(&mut self, reg: u8, data: u8) {
+        match reg {
+            0x08 => self.seconds = data & 0x3F,
+            0x09 => self.minutes = data & 0x3F,
+            0x0A => self.hours = data & 0x1F,
+            0x0B => self.days_low = data,
+            0x0C => self.days_high = data & RTC_DAY_HIGH_MASK,
+            _ => {}
+        }
+    }
+
+    fn days(&self) -> u64 {
+        (((self.days_high & 0x1) as u64) << 8) | (self.days_low as u64)
+    }
+
+    fn advance(&mut self, seconds: u64) {
+        if seconds == 0 || self.days_high & RTC_HALT != 0 {
+            return;
+        }
+
+        // out of range values written by the game tick until they overflow their bit width
+        // before they start counting normally, so walk those one second at a time
+        let mut remaining = seconds;
+        while remaining > 0 && (self.seconds >= 60 || self.minutes >= 60 || self.hours >= 24) {
+            self.tick_second();
+            remaining -= 1;
+        }
+
+        let total = self.days() * 86400 + (self.hours as u64) * 3600 + (self.minutes as u64) * 60 + (self.seconds as u64) + remaining;
+        let days = total / 86400;
+
+        self.seconds = (total % 60) as u8;
+        self.minutes = ((total / 60) % 60) as u8;
+        self.hours = ((total / 3600) % 24) as u8;
+        self.days_low = (days & 0xFF) as u8;
+        self.days_high = (self.days_high & !0x1) | (((days >> 8) & 0x1) as u8);
+
+        if days > 0x1FF {
+            self.days_high |= RTC_DAY_CARRY;
+        }
+    }
+
+    fn tick_second(&mut self) {
+        self.seconds = (self.seconds + 1) & 0x3F;
+        if self.seconds != 60 {
+            return;
+        }
+
+        self.seconds = 0;
+        self.minutes = (self.minutes + 1) & 0x3F;
+        if self.minutes != 60 {
+            return;
+        }
+
+        self.minutes = 0;
+        self.hours = (self.hours + 1) & 0x1F;
+        if self.hours != 24 {
+            return;
+        }
+
+        self.hours = 0;
+        let days = self.days() + 1;
+        self.days_low = (days & 0xFF) as u8;
+        self.days_high = (self.days_high & !0x1) | (((days >> 8) & 0x1) as u8);
+
+        if days > 0x1FF {
+            self.days_high |= RTC_DAY_CARRY;
+        }
+    }
+}
+
+struct RTC {
+    current: RTCRegisters,
+    latched: RTCRegisters,
+    // clocks into the current second
+    clocks: u32,
+}
+
+impl RTC {
+    fn new() -> Self {
+        Self {
+            current: RTCRegisters::default(),
+            latched: RTCRegisters::default(),
+            clocks: 0,
+        }
+    }
+
+    fn now() -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+    }
+
+    // The clock follows emulated time, so it stops with the emulator and stays the same across runs
+    fn tick(&mut self, clocks: u32) {
+        if self.current.days_high & RTC_HALT != 0 {
+            return;
+        }
+
+        self.clocks += clocks;
+        if self.clocks >= CLOCKS_PER_SECOND {
+            self.clocks -= CLOCKS_PER_SECOND;
+            self.current.advance(1);
+        }
+    }
+
+    fn latch(&mut self) {
+        self.latched = self.current;
+    }
+
+    fn write(&mut self, reg: u8, data: u8) {
+        // writing the seconds restarts the second that was being counted
+        if reg == 0x08 {
+            self.clocks = 0;
+        }
+
+        self.current.write(reg, data);
+    }
+
+    fn to_footer(&self) -> Vec<u8> {
+        let mut footer = Vec::with_capacity(RTC_FOOTER_SIZE);
+
+        for regs in &[self.current, self.latched] {
+            for v in &[regs.seconds, regs.minutes, regs.hours, regs.days_low, regs.days_high] {
+                footer.extend_from_slice(&(*v as u32).to_le_bytes());
+            }
+        }
+
+        footer.extend_from_slice(&RTC::now().to_le_bytes());
+        footer
+    }
+
+    fn load_footer(&mut self, footer: &[u8]) {
+        let value = |i: usize| footer[i * 4];
+
+        let regs = |base: usize| {
+            let mut r = RTCRegisters::default();
+            r.write(0x08, value(base));
+            r.write(0x09, value(base + 1));
+            r.write(0x0A, value(base + 2));
+            r.write(0x0B, value(base + 3));
+            r.write(0x0C, value(base + 4));
+            r
+        };
+
+        self.current = regs(0);
+        self.latched = regs(5);
+
+        let mut timestamp = [0; 8];
+        let len = footer.len() - 40;
+        timestamp[0..len].copy_from_slice(&footer[40..]);
+        let timestamp = u64::from_le_bytes(timestamp);
+
+        // catch up with the time that went by while the emulator was closed
+        let now = RTC::now();
+        if now > timestamp {
+            self.current.advance(now - timestamp);
+        }
+    }
+
+    fn save_state(&self, w: &mut StateWriter) {
+        for regs in &[self.current, self.latched] {
+            w.write_u8(regs.seconds);
+            w.write_u8(regs.minutes);
+            w.write_u8(regs.hours);
+            w.write_u8(regs.days_low);
+            w.write_u8(regs.days_high);
+        }
+
+        w.write_u32(self.clocks);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), SaveStateError> {
+        for regs in &mut [&mut self.current, &mut self.latched] {
+            regs.write(0x08, r.read_u8()?);
+            regs.write(0x09, r.read_u8()?);
+            regs.write(0x0A, r.read_u8()?);
+            regs.write(0x0B, r.read_u8()?);
+            regs.write(0x0C, r.read_u8()?);
+        }
+
+        self.clocks = r.read_u32()?.min(CLOCKS_PER_SECOND - 1);
+
+        Ok(())
+    }
+}
+
 pub struct MBC3 {
     data: Vec<u8>,
     ram: Vec<u8>,
     registers: MBC3Registers,
-    num_rom_banks: u8,
+    rtc: Option<RTC>,
+    num_rom_banks: u16,
     num_ram_banks: u8,
 }
 
 impl MBC3 {
-    pub fn new(rom_size: u8, ram_size: u8, data: &[u8]) -> Self {
-        // calculate number of rom banks
-        let data_size = (0x8000 << rom_size) as usize;
-        let num_rom_banks = ((data_size as u32) / 0x4000) as u8;
-        
+    pub fn new(rom_size: u8, ram_size: u8, has_rtc: bool, data: &[u8]) -> Result<Self, ROMError> {
+        // calculate number of rom banks, a ROM bigger than the bank register can select is refused
+        let num_rom_banks = rom_bank_count(rom_size, 128)?;
+
         // and ram banks
-        let (num_ram_banks, vec_ram_size) = match ram_size {
-            0 => (0, 0),
-            1 => (1, 0x800),
-            2 => (1, 0x2000),
-            3 => (4, 0x8000),
-            _ => panic!("Invalid RAM size for MBC1")
-        };
-        
-        Self {
+        let (num_ram_banks, vec_ram_size) = ram_bank_count(ram_size, 4)?;
+
+        Ok(Self {
             data: data.to_vec(),
             registers: MBC3Registers {
-                mode: 0,
                 ram_enabled: false,
+                latch: 0xFF,
                 rom_bank: 1,
                 ram_bank: 0,
             },
             ram: vec!(0; vec_ram_size),
+            rtc: if has_rtc { Some(RTC::new()) } else { None },
             num_rom_banks,
             num_ram_banks,
-        }
+        })
+    }
+
+    fn ram_address(&self, address: u16) -> usize {
+        let ram_bank: usize = if self.num_ram_banks <= 1 { 0 } else { (self.registers.ram_bank % self.num_ram_banks) as usize };
+        (ram_bank * 0x2000) + ((address - 0xA000) as usize) % self.ram.len().min(0x2000)
     }
 }
 
 impl MBC for MBC3 {
+    fn get_rom(&self) -> &[u8] {
+        &self.data
+    }
+
+    fn get_rom_bank(&self, address: u16) -> u16 {
+        match address {
+            0x0000..=0x3FFF => 0,
+            _ => ((self.registers.rom_bank as u32) % (self.num_rom_banks as u32)) as u16
+        }
+    }
+
     fn read_byte(&self, address: u16) -> u8 {
         match address {
             0x0000..=0x3FFF => {
@@ -53,51 +284,73 @@ impl MBC for MBC3 {
             },
 
             0x4000..=0x7FFF => {
-                let bank: u32 = (self.registers.rom_bank as u32) % (self.num_rom_banks as u32);
+                let bank: u32 = self.get_rom_bank(address) as u32;
                 let idx: u32 = (bank * 0x4000) + ((address - 0x4000) as u32);
                 self.data[idx as usize]
             },
 
             0xA000..=0xBFFF => {
-                if self.registers.ram_enabled {
-                    let ram_bank: u32 = if self.registers.mode == 0 || self.num_ram_banks <= 1 { 0 } else { (self.registers.ram_bank & 0x3) as u32 };
-                    let ram_addr: u32 = (ram_bank * 0x2000) + ((address - 0xA000) as u32);
-                    self.data[ram_addr as usize]
+                if !self.registers.ram_enabled {
+                    return 0xFF;
                 }
-                else {
-                    0xff
+
+                match self.registers.ram_bank {
+                    0x00..=0x03 if !self.ram.is_empty() => self.ram[self.ram_address(address)],
+                    0x08..=0x0C => match &self.rtc {
+                        Some(rtc) => rtc.latched.read(self.registers.ram_bank),
+                        None => 0xFF
+                    },
+                    _ => 0xFF
                 }
             },
 
             _ => panic!("Invalid ROM read")
         }
-        
+
     }
 
     fn write_byte(&mut self, address: u16, data: u8) {
         match address {
             0x0000..=0x1FFF => {
-                self.registers.ram_enabled = data == 0x0A;
+                self.registers.ram_enabled = (data & 0x0F) == 0x0A;
             },
 
             0x2000..=0x3FFF => {
                 self.registers.rom_bank = if (data & 0x7F) == 0 { 1 } else { data & 0x7F };
             },
-            
+
             // RAM bank number / RTC register select
-            0x4000..=0x5FFF => { 
-                self.registers.ram_bank = data & 0x3;
+            0x4000..=0x5FFF => {
+                self.registers.ram_bank = data & 0x0F;
             },
-            
-            0x6000..=0x7FFF => { 
-                self.registers.mode = data & 0x1;
+
+            // Latch clock data, writing 0x00 and then 0x01 copies the clock into the latched registers
+            0x6000..=0x7FFF => {
+                if self.registers.latch == 0x00 && data == 0x01 {
+                    if let Some(rtc) = &mut self.rtc {
+                        rtc.latch();
+                    }
+                }
+
+                self.registers.latch = data;
             },
 
             0xA000..=0xBFFF => {
-                if self.registers.ram_enabled {
-                    let ram_bank: u32 = if self.registers.mode == 0 || self.num_ram_banks <= 1 { 0 } else { (self.registers.ram_bank & 0x3) as u32 };
-                    let ram_addr: u32 = (ram_bank * 0x2000) + ((address - 0xA000) as u32);
-                    self.data[ram_addr as usize] = data;
+                if !self.registers.ram_enabled {
+                    return;
+                }
+
+                match self.registers.ram_bank {
+                    0x00..=0x03 if !self.ram.is_empty() => {
+                        let ram_addr = self.ram_address(address);
+                        self.ram[ram_addr] = data;
+                    },
+                    0x08..=0x0C => {
+                        if let Some(rtc) = &mut self.rtc {
+                            rtc.write(self.registers.ram_bank, data);
+                        }
+                    },
+                    _ => {}
                 }
             },
 
@@ -105,11 +358,218 @@ impl MBC for MBC3 {
         }
     }
 
+    fn tick(&mut self, clocks: u32) {
+        if let Some(rtc) = &mut self.rtc {
+            rtc.tick(clocks);
+        }
+    }
+
     fn get_ram_contents(&self) -> Option<Vec<u8>> {
-        Some(self.ram.to_owned())
+        let mut contents = self.ram.to_owned();
+
+        if let Some(rtc) = &self.rtc {
+            contents.extend_from_slice(&rtc.to_footer());
+        }
+
+        Some(contents)
     }
 
     fn set_ram_contents(&mut self, data: &[u8]) {
-        self.ram.copy_from_slice(data);
+        let ram_len = self.ram.len().min(data.len());
+        self.ram[0..ram_len].copy_from_slice(&data[0..ram_len]);
+
+        if let Some(rtc) = &mut self.rtc {
+            let footer = &data[ram_len..];
+
+            if footer.len() == RTC_FOOTER_SIZE || footer.len() == RTC_FOOTER_SIZE_LEGACY {
+                rtc.load_footer(footer);
+            }
+        }
+    }
+
+    fn save_state(&self, w: &mut StateWriter) {
+        w.write_bool(self.registers.ram_enabled);
+        w.write_u8(self.registers.latch);
+        w.write_u8(self.registers.rom_bank);
+        w.write_u8(self.registers.ram_bank);
+        w.write_vec(&self.ram);
+
+        w.write_bool(self.rtc.is_some());
+        if let Some(rtc) = &self.rtc {
+            rtc.save_state(w);
+        }
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), SaveStateError> {
+        self.registers.ram_enabled = r.read_bool()?;
+        self.registers.latch = r.read_u8()?;
+        self.registers.rom_bank = r.read_u8()? & 0x7F;
+        self.registers.ram_bank = r.read_u8()? & 0x0F;
+        r.read_vec_into(&mut self.ram)?;
+
+        if r.read_bool()? != self.rtc.is_some() {
+            return Err(SaveStateError::InvalidData("MBC3 RTC presence"));
+        }
+
+        if let Some(rtc) = &mut self.rtc {
+            rtc.load_state(r)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // MBC3+TIMER+RAM, with the RAM and clock registers enabled
+    fn mbc3() -> MBC3 {
+        let mut mbc = MBC3::new(0, 3, true, &[0; 0x8000]).unwrap();
+        mbc.write_byte(0x0000, 0x0A);
+        mbc
     }
-}
\ No newline at end of file
+
+    fn latch(mbc: &mut MBC3) {
+        mbc.write_byte(0x6000, 0x00);
+        mbc.write_byte(0x6000, 0x01);
+    }
+
+    fn read_rtc(mbc: &mut MBC3, reg: u8) -> u8 {
+        mbc.write_byte(0x4000, reg);
+        mbc.read_byte(0xA000)
+    }
+
+    fn write_rtc(mbc: &mut MBC3, reg: u8, data: u8) {
+        mbc.write_byte(0x4000, reg);
+        mbc.write_byte(0xA000, data);
+    }
+
+    #[test]
+    fn latch_keeps_the_time() {
+        let mut mbc = mbc3();
+        mbc.tick(CLOCKS_PER_SECOND);
+        assert_eq!(read_rtc(&mut mbc, 0x08), 0);
+
+        latch(&mut mbc);
+        assert_eq!(read_rtc(&mut mbc, 0x08), 1);
+
+        // the clock runs on, the latched registers don't
+        for _ in 0..3 {
+            mbc.tick(CLOCKS_PER_SECOND);
+        }
+        assert_eq!(read_rtc(&mut mbc, 0x08), 1);
+
+        // only 00 then 01 latches
+        mbc.write_byte(0x6000, 0x01);
+        assert_eq!(read_rtc(&mut mbc, 0x08), 1);
+
+        latch(&mut mbc);
+        assert_eq!(read_rtc(&mut mbc, 0x08), 4);
+    }
+
+    #[test]
+    fn counts_into_days() {
+        let mut mbc = mbc3();
+        write_rtc(&mut mbc, 0x08, 59);
+        write_rtc(&mut mbc, 0x09, 59);
+        write_rtc(&mut mbc, 0x0A, 23);
+        write_rtc(&mut mbc, 0x0B, 0xFF);
+        write_rtc(&mut mbc, 0x0C, 0x01);
+
+        // the last day there is, then the counter overflows and sets the carry
+        mbc.tick(CLOCKS_PER_SECOND);
+        latch(&mut mbc);
+        let time: Vec<u8> = (0x08..=0x0C).map(|r| read_rtc(&mut mbc, r)).collect();
+        assert_eq!(time, [0, 0, 0, 0, RTC_DAY_CARRY]);
+    }
+
+    #[test]
+    fn halt() {
+        let mut mbc = mbc3();
+        write_rtc(&mut mbc, 0x0C, RTC_HALT);
+
+        for _ in 0..10 {
+            mbc.tick(CLOCKS_PER_SECOND);
+        }
+        latch(&mut mbc);
+        assert_eq!(read_rtc(&mut mbc, 0x08), 0);
+        assert_eq!(read_rtc(&mut mbc, 0x0C), RTC_HALT);
+
+        write_rtc(&mut mbc, 0x0C, 0);
+        mbc.tick(CLOCKS_PER_SECOND);
+        latch(&mut mbc);
+        assert_eq!(read_rtc(&mut mbc, 0x08), 1);
+    }
+
+    #[test]
+    fn out_of_range_values() {
+        let mut mbc = mbc3();
+
+        // 63 seconds wraps to 0 without carrying into the minutes
+        write_rtc(&mut mbc, 0x08, 63);
+        mbc.tick(CLOCKS_PER_SECOND);
+        latch(&mut mbc);
+        assert_eq!((read_rtc(&mut mbc, 0x08), read_rtc(&mut mbc, 0x09)), (0, 0));
+    }
+
+    #[test]
+    fn footer_round_trip() {
+        let mut mbc = mbc3();
+        mbc.write_byte(0xA000, 0x12);
+        write_rtc(&mut mbc, 0x09, 42);
+        write_rtc(&mut mbc, 0x0B, 7);
+        latch(&mut mbc);
+        write_rtc(&mut mbc, 0x0A, 5);
+
+        let contents = mbc.get_ram_contents().unwrap();
+        assert_eq!(contents.len(), 0x8000 + RTC_FOOTER_SIZE);
+
+        let mut loaded = mbc3();
+        loaded.set_ram_contents(&contents);
+        loaded.write_byte(0x4000, 0x00);
+        assert_eq!(loaded.read_byte(0xA000), 0x12);
+
+        // latched as saved, while the clock itself may have moved on a second or two since the footer was written
+        let latched: Vec<u8> = (0x09..=0x0B).map(|r| read_rtc(&mut loaded, r)).collect();
+        assert_eq!(latched, [42, 0, 7]);
+
+        latch(&mut loaded);
+        let current: Vec<u8> = (0x09..=0x0B).map(|r| read_rtc(&mut loaded, r)).collect();
+        assert_eq!(current, [42, 5, 7]);
+        assert!(read_rtc(&mut loaded, 0x08) < 5);
+    }
+
+    #[test]
+    fn legacy_footer() {
+        let mbc = mbc3();
+        let mut contents = mbc.get_ram_contents().unwrap();
+        contents.truncate(0x8000 + RTC_FOOTER_SIZE_LEGACY);
+        contents[0x8000 + 4] = 30;
+
+        // the 32 bit timestamp is still recent, so the minutes stay put
+        let mut loaded = mbc3();
+        loaded.set_ram_contents(&contents);
+        latch(&mut loaded);
+        assert_eq!(read_rtc(&mut loaded, 0x09), 30);
+    }
+
+    #[test]
+    fn save_state_keeps_clock() {
+        let mut mbc = mbc3();
+        write_rtc(&mut mbc, 0x0A, 13);
+        latch(&mut mbc);
+        mbc.tick(1234);
+
+        let mut w = StateWriter::new();
+        mbc.save_state(&mut w);
+        let bytes = w.into_bytes();
+
+        let mut loaded = mbc3();
+        loaded.load_state(&mut StateReader::new(&bytes)).unwrap();
+        assert_eq!(read_rtc(&mut loaded, 0x0A), 13);
+
+        let rtc = loaded.rtc.as_ref().unwrap();
+        assert_eq!((rtc.current.hours, rtc.clocks), (13, 1234));
+    }
+}
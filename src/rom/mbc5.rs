@@ -1,4 +1,7 @@
 use crate::rom::MBC;
+use crate::rom::ROMError;
+use crate::rom::mbc::{rom_bank_count, ram_bank_count};
+use crate::savestate::{StateWriter, StateReader, SaveStateError};
 
 struct MBC5Registers {
     ram_enabled: bool,
@@ -11,25 +14,18 @@ pub struct MBC5 {
     ram: Vec<u8>,
     registers: MBC5Registers,
     num_rom_banks: u16,
-    num_ram_banks: u16
+    num_ram_banks: u8
 }
 
 impl MBC5 {
-    pub fn new(rom_size: u8, ram_size: u8, data: &[u8]) -> Self {
-        // calculate number of rom banks
-        let data_size = (0x8000 << rom_size) as usize;
-        let num_rom_banks = ((data_size as u32) / 0x4000) as u16;
+    pub fn new(rom_size: u8, ram_size: u8, data: &[u8]) -> Result<Self, ROMError> {
+        // calculate number of rom banks, a ROM bigger than the bank register can select is refused
+        let num_rom_banks = rom_bank_count(rom_size, 512)?;
         
         // and ram banks 
-        let (num_ram_banks, vec_ram_size) = match ram_size {
-            0 => (0, 0),
-            1 => (1, 0x800),
-            2 => (1, 0x2000),
-            3 => (4, 0x8000),
-            _ => panic!("Invalid RAM size for MBC1")
-        };
+        let (num_ram_banks, vec_ram_size) = ram_bank_count(ram_size, 16)?;
         
-        Self {
+        Ok(Self {
             data: data.to_vec(),
             ram: vec!(0; vec_ram_size),
             registers: MBC5Registers {
@@ -39,11 +35,28 @@ impl MBC5 {
             },
             num_rom_banks,
             num_ram_banks,
-        }
+        })
+    }
+
+    // 2KB RAM repeats across the whole area
+    fn ram_address(&self, address: u16) -> usize {
+        let ram_bank: usize = if self.num_ram_banks <= 1 { 0 } else { (self.registers.ram_bank % self.num_ram_banks) as usize };
+        (ram_bank * 0x2000) + ((address - 0xA000) as usize) % self.ram.len().min(0x2000)
     }
 }
 
 impl MBC for MBC5 {
+    fn get_rom(&self) -> &[u8] {
+        &self.data
+    }
+
+    fn get_rom_bank(&self, address: u16) -> u16 {
+        match address {
+            0x0000..=0x3FFF => 0,
+            _ => ((self.registers.rom_bank as u32) % (self.num_rom_banks as u32)) as u16
+        }
+    }
+
     fn read_byte(&self, address: u16) -> u8 {
         match address {
             0x0000..=0x3FFF => {
@@ -51,16 +64,14 @@ impl MBC for MBC5 {
             },
 
             0x4000..=0x7FFF => {
-                let bank: u32 = (self.registers.rom_bank as u32) % (self.num_rom_banks as u32);
+                let bank: u32 = self.get_rom_bank(address) as u32;
                 let idx: u32 = (bank * 0x4000) + ((address - 0x4000) as u32);
                 self.data[idx as usize]
             },
 
             0xA000..=0xBFFF => {
-                if self.registers.ram_enabled {
-                    let ram_bank: u32 = if self.num_ram_banks <= 1 { 0 } else { self.registers.ram_bank as u32 };
-                    let ram_addr: u32 = (ram_bank * 0x2000) + ((address - 0xA000) as u32);
-                    self.ram[ram_addr as usize]
+                if self.registers.ram_enabled && !self.ram.is_empty() {
+                    self.ram[self.ram_address(address)]
                 }
                 else {
                     0xff
@@ -88,14 +99,13 @@ impl MBC for MBC5 {
 
             // RAM bank number / RTC register select
             0x4000..=0x5FFF => { 
-                self.registers.ram_bank = data & 0x3;
+                self.registers.ram_bank = data & 0x0F;
             },
             
             0xA000..=0xBFFF => {
-                if self.registers.ram_enabled {
-                    let ram_bank: u32 = if self.num_ram_banks <= 1 { 0 } else { (self.registers.ram_bank & 0x3) as u32 };
-                    let ram_addr: u32 = (ram_bank * 0x2000) + ((address - 0xA000) as u32);
-                    self.ram[ram_addr as usize] = data;
+                if self.registers.ram_enabled && !self.ram.is_empty() {
+                    let ram_addr = self.ram_address(address);
+                    self.ram[ram_addr] = data;
                 }
             },
 
@@ -108,6 +118,72 @@ impl MBC for MBC5 {
     }
 
     fn set_ram_contents(&mut self, data: &[u8]) {
-        self.ram.copy_from_slice(data);
+        let ram_len = self.ram.len().min(data.len());
+        self.ram[0..ram_len].copy_from_slice(&data[0..ram_len]);
+    }
+
+    fn save_state(&self, w: &mut StateWriter) {
+        w.write_bool(self.registers.ram_enabled);
+        w.write_u16(self.registers.rom_bank);
+        w.write_u8(self.registers.ram_bank);
+        w.write_vec(&self.ram);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), SaveStateError> {
+        self.registers.ram_enabled = r.read_bool()?;
+        self.registers.rom_bank = r.read_u16()? & 0x1FF;
+        self.registers.ram_bank = r.read_u8()? & 0x0F;
+        r.read_vec_into(&mut self.ram)?;
+
+        Ok(())
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mbc5(ram_size: u8) -> MBC5 {
+        let mut mbc = MBC5::new(0, ram_size, &[0; 0x8000]).unwrap();
+        mbc.write_byte(0x0000, 0x0A);
+        mbc
+    }
+
+    #[test]
+    fn no_ram() {
+        let mut mbc = mbc5(0);
+
+        mbc.write_byte(0xA000, 0x12);
+        assert_eq!(mbc.read_byte(0xA000), 0xFF);
+        assert_eq!(mbc.read_byte(0xBFFF), 0xFF);
+
+        mbc.set_ram_contents(&[0x34; 0x2000]);
+        assert_eq!(mbc.get_ram_contents().unwrap().len(), 0);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn small_ram_repeats() {
+        let mut mbc = mbc5(1);
+
+        mbc.write_byte(0xA801, 0x56);
+        assert_eq!(mbc.read_byte(0xA001), 0x56);
+        assert_eq!(mbc.read_byte(0xB801), 0x56);
+
+        mbc.write_byte(0x4000, 0x05);
+        assert_eq!(mbc.read_byte(0xA001), 0x56);
+    }
+
+    #[test]
+    fn short_save() {
+        let mut mbc = mbc5(3);
+        mbc.set_ram_contents(&[0x78; 0x100]);
+
+        let ram = mbc.get_ram_contents().unwrap();
+        assert_eq!(ram.len(), 0x8000);
+        assert_eq!((ram[0xFF], ram[0x100]), (0x78, 0x00));
+
+        // the banks are 8KB apart
+        mbc.write_byte(0x4000, 0x01);
+        mbc.write_byte(0xA000, 0x9A);
+        assert_eq!(mbc.get_ram_contents().unwrap()[0x2000], 0x9A);
+    }
+}
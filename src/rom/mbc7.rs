@@ -0,0 +1,438 @@
+use crate::rom::MBC;
+use crate::rom::ROMError;
+use crate::rom::mbc::rom_bank_count;
+use crate::savestate::{StateWriter, StateReader, SaveStateError};
+
+// 93LC56 serial EEPROM, 128 words of 16 bits
+const EEPROM_SIZE: usize = 0x100;
+const EEPROM_ADDRESS_MASK: u8 = 0x7F;
+
+// Start bit + 2 opcode bits + 8 address bits, WRITE and WRAL are followed by 16 data bits
+const EEPROM_COMMAND_BITS: u8 = 11;
+const EEPROM_DATA_BITS: u8 = 16;
+
+// Bits of the Ax8x register
+const EEPROM_CS: u8 = 1 << 7;
+const EEPROM_CLK: u8 = 1 << 6;
+const EEPROM_DI: u8 = 1 << 1;
+const EEPROM_DO: u8 = 1;
+
+// Accelerometer reading when the cart is held flat, and how much 1g moves it
+const ACCEL_CENTER: f32 = 0x81D0 as f32;
+const ACCEL_GRAVITY: f32 = 0x70 as f32;
+const ACCEL_ERASED: u16 = 0x8000;
+
+#[derive(Copy, Clone, PartialEq)]
+enum EEPROMState {
+    // waiting for the start bit
+    Idle,
+    Command,
+    Reading,
+    // the command is done, nothing else happens until CS goes low
+    Done,
+}
+
+struct EEPROM {
+    data: Vec<u8>,
+    state: EEPROMState,
+    cs: bool,
+    clk: bool,
+    di: bool,
+    data_out: bool,
+    write_enabled: bool,
+    command: u32,
+    command_bits: u8,
+    address: u8,
+    read_buffer: u16,
+    read_bits: u8,
+}
+
+impl EEPROM {
+    fn new() -> Self {
+        Self {
+            data: vec!(0xFF; EEPROM_SIZE),
+            state: EEPROMState::Idle,
+            cs: false,
+            clk: false,
+            di: false,
+            data_out: true,
+            write_enabled: false,
+            command: 0,
+            command_bits: 0,
+            address: 0,
+            read_buffer: 0,
+            read_bits: 0,
+        }
+    }
+
+    fn read_word(&self, address: u8) -> u16 {
+        let idx = (address & EEPROM_ADDRESS_MASK) as usize * 2;
+        (self.data[idx] as u16) | ((self.data[idx + 1] as u16) << 8)
+    }
+
+    fn write_word(&mut self, address: u8, value: u16) {
+        let idx = (address & EEPROM_ADDRESS_MASK) as usize * 2;
+        self.data[idx] = (value & 0xFF) as u8;
+        self.data[idx + 1] = (value >> 8) as u8;
+    }
+
+    fn read_register(&self) -> u8 {
+        let mut value = 0;
+
+        if self.cs { value |= EEPROM_CS; }
+        if self.clk { value |= EEPROM_CLK; }
+        if self.di { value |= EEPROM_DI; }
+        if self.data_out { value |= EEPROM_DO; }
+
+        value
+    }
+
+    fn write_register(&mut self, data: u8) {
+        let cs = data & EEPROM_CS != 0;
+        let clk = data & EEPROM_CLK != 0;
+        self.di = data & EEPROM_DI != 0;
+
+        if !cs {
+            // deselecting the chip aborts whatever was going on
+            self.state = EEPROMState::Idle;
+            self.data_out = true;
+        }
+        else if clk && !self.clk {
+            // everything is clocked in on the rising edge
+            self.clock();
+        }
+
+        self.cs = cs;
+        self.clk = clk;
+    }
+
+    fn clock(&mut self) {
+        match self.state {
+            EEPROMState::Idle => {
+                if self.di {
+                    self.state = EEPROMState::Command;
+                    self.command = 1;
+                    self.command_bits = 1;
+                }
+            },
+
+            EEPROMState::Command => {
+                self.command = (self.command << 1) | (self.di as u32);
+                self.command_bits += 1;
+
+                if self.command_bits == EEPROM_COMMAND_BITS {
+                    self.run_command();
+                }
+                else if self.command_bits == EEPROM_COMMAND_BITS + EEPROM_DATA_BITS {
+                    self.run_write();
+                }
+            },
+
+            // data goes out MSB first, reads keep going into the next word until CS is dropped
+            EEPROMState::Reading => {
+                self.data_out = self.read_buffer & 0x8000 != 0;
+                self.read_buffer <<= 1;
+                self.read_bits -= 1;
+
+                if self.read_bits == 0 {
+                    self.address = (self.address + 1) & EEPROM_ADDRESS_MASK;
+                    self.read_buffer = self.read_word(self.address);
+                    self.read_bits = 16;
+                }
+            },
+
+            EEPROMState::Done => {}
+        }
+    }
+
+    fn run_command(&mut self) {
+        let opcode = (self.command >> 8) & 0x3;
+        self.address = (self.command as u8) & EEPROM_ADDRESS_MASK;
+
+        match opcode {
+            // READ, a dummy 0 bit comes out before the data
+            0b10 => {
+                self.state = EEPROMState::Reading;
+                self.read_buffer = self.read_word(self.address);
+                self.read_bits = 16;
+                self.data_out = false;
+            },
+
+            // WRITE, needs the data first
+            0b01 => {},
+
+            // ERASE
+            0b11 => {
+                if self.write_enabled {
+                    self.write_word(self.address, 0xFFFF);
+                }
+
+                self.finish();
+            },
+
+            // the extended commands use the top address bits as opcode
+            _ => match (self.command >> 6) & 0x3 {
+                // EWEN
+                0b11 => {
+                    self.write_enabled = true;
+                    self.finish();
+                },
+
+                // EWDS
+                0b00 => {
+                    self.write_enabled = false;
+                    self.finish();
+                },
+
+                // ERAL
+                0b10 => {
+                    if self.write_enabled {
+                        self.data.iter_mut().for_each(|b| *b = 0xFF);
+                    }
+
+                    self.finish();
+                },
+
+                // WRAL, needs the data first
+                _ => {}
+            }
+        }
+    }
+
+    fn run_write(&mut self) {
+        let value = (self.command & 0xFFFF) as u16;
+        let all = (self.command >> 24) & 0x3 == 0;
+
+        if self.write_enabled {
+            if all {
+                for address in 0..=EEPROM_ADDRESS_MASK {
+                    self.write_word(address, value);
+                }
+            }
+            else {
+                self.write_word(self.address, value);
+            }
+        }
+
+        self.finish();
+    }
+
+    // writes happen instantly, so DO reports the chip as ready straight away
+    fn finish(&mut self) {
+        self.state = EEPROMState::Done;
+        self.data_out = true;
+    }
+
+    fn save_state(&self, w: &mut StateWriter) {
+        w.write_vec(&self.data);
+        w.write_u8(match self.state {
+            EEPROMState::Idle => 0,
+            EEPROMState::Command => 1,
+            EEPROMState::Reading => 2,
+            EEPROMState::Done => 3,
+        });
+        w.write_bool(self.cs);
+        w.write_bool(self.clk);
+        w.write_bool(self.di);
+        w.write_bool(self.data_out);
+        w.write_bool(self.write_enabled);
+        w.write_u32(self.command);
+        w.write_u8(self.command_bits);
+        w.write_u8(self.address);
+        w.write_u16(self.read_buffer);
+        w.write_u8(self.read_bits);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), SaveStateError> {
+        r.read_vec_into(&mut self.data)?;
+        self.state = match r.read_u8()? {
+            0 => EEPROMState::Idle,
+            1 => EEPROMState::Command,
+            2 => EEPROMState::Reading,
+            3 => EEPROMState::Done,
+            _ => return Err(SaveStateError::InvalidData("MBC7 EEPROM state"))
+        };
+        self.cs = r.read_bool()?;
+        self.clk = r.read_bool()?;
+        self.di = r.read_bool()?;
+        self.data_out = r.read_bool()?;
+        self.write_enabled = r.read_bool()?;
+        self.command = r.read_u32()?;
+        self.command_bits = r.read_u8()?;
+        self.address = r.read_u8()? & EEPROM_ADDRESS_MASK;
+        self.read_buffer = r.read_u16()?;
+        self.read_bits = r.read_u8()?;
+
+        Ok(())
+    }
+}
+
+struct MBC7Registers {
+    ram_enabled1: bool,
+    ram_enabled2: bool,
+    rom_bank: u8,
+    // the latch only takes a new reading after it has been erased
+    latch_erased: bool,
+    x_latch: u16,
+    y_latch: u16,
+}
+
+pub struct MBC7 {
+    data: Vec<u8>,
+    eeprom: EEPROM,
+    registers: MBC7Registers,
+    num_rom_banks: u16,
+    tilt_x: f32,
+    tilt_y: f32,
+}
+
+impl MBC7 {
+    pub fn new(rom_size: u8, data: &[u8]) -> Result<Self, ROMError> {
+        // calculate number of rom banks, a ROM bigger than the bank register can select is refused
+        let num_rom_banks = rom_bank_count(rom_size, 128)?;
+
+        Ok(Self {
+            data: data.to_vec(),
+            eeprom: EEPROM::new(),
+            registers: MBC7Registers {
+                ram_enabled1: false,
+                ram_enabled2: false,
+                rom_bank: 1,
+                latch_erased: false,
+                x_latch: ACCEL_ERASED,
+                y_latch: ACCEL_ERASED,
+            },
+            num_rom_banks,
+            tilt_x: 0.0,
+            tilt_y: 0.0,
+        })
+    }
+
+    fn is_ram_enabled(&self) -> bool {
+        self.registers.ram_enabled1 && self.registers.ram_enabled2
+    }
+
+    fn latch_accelerometer(&mut self) {
+        // tilting right lowers X, tilting towards the player raises Y
+        self.registers.x_latch = (ACCEL_CENTER - ACCEL_GRAVITY * self.tilt_x) as u16;
+        self.registers.y_latch = (ACCEL_CENTER + ACCEL_GRAVITY * self.tilt_y) as u16;
+    }
+}
+
+impl MBC for MBC7 {
+    fn get_rom(&self) -> &[u8] {
+        &self.data
+    }
+
+    fn get_rom_bank(&self, address: u16) -> u16 {
+        match address {
+            0x0000..=0x3FFF => 0,
+            _ => ((self.registers.rom_bank as u32) % (self.num_rom_banks as u32)) as u16
+        }
+    }
+
+    fn read_byte(&self, address: u16) -> u8 {
+        match address {
+            0x0000..=0x3FFF => {
+                self.data[address as usize]
+            },
+
+            0x4000..=0x7FFF => {
+                let bank: u32 = self.get_rom_bank(address) as u32;
+                let idx: u32 = (bank * 0x4000) + ((address - 0x4000) as u32);
+                self.data[idx as usize]
+            },
+
+            // the registers repeat every 0x100 bytes, bits 4-7 of the address pick one
+            0xA000..=0xAFFF if self.is_ram_enabled() => {
+                match (address >> 4) & 0x0F {
+                    0x2 => (self.registers.x_latch & 0xFF) as u8,
+                    0x3 => (self.registers.x_latch >> 8) as u8,
+                    0x4 => (self.registers.y_latch & 0xFF) as u8,
+                    0x5 => (self.registers.y_latch >> 8) as u8,
+                    // there is no Z axis
+                    0x6 => 0x00,
+                    0x8 => self.eeprom.read_register(),
+                    _ => 0xFF
+                }
+            },
+
+            0xA000..=0xBFFF => 0xFF,
+
+            _ => panic!("Invalid ROM read")
+        }
+    }
+
+    fn write_byte(&mut self, address: u16, data: u8) {
+        match address {
+            0x0000..=0x1FFF => {
+                self.registers.ram_enabled1 = (data & 0x0F) == 0x0A;
+            },
+
+            0x2000..=0x3FFF => {
+                self.registers.rom_bank = data & 0x7F;
+            },
+
+            0x4000..=0x5FFF => {
+                self.registers.ram_enabled2 = data == 0x40;
+            },
+
+            0x6000..=0x7FFF => {},
+
+            0xA000..=0xAFFF if self.is_ram_enabled() => {
+                match (address >> 4) & 0x0F {
+                    // writing 0x55 and then 0xAA takes a new accelerometer reading
+                    0x0 if data == 0x55 => {
+                        self.registers.latch_erased = true;
+                        self.registers.x_latch = ACCEL_ERASED;
+                        self.registers.y_latch = ACCEL_ERASED;
+                    },
+                    0x1 if data == 0xAA && self.registers.latch_erased => {
+                        self.registers.latch_erased = false;
+                        self.latch_accelerometer();
+                    },
+                    0x8 => self.eeprom.write_register(data),
+                    _ => {}
+                }
+            },
+
+            0xA000..=0xBFFF => {},
+
+            _ => panic!("Invalid ROM write {:#06x}", address)
+        }
+    }
+
+    fn get_ram_contents(&self) -> Option<Vec<u8>> {
+        Some(self.eeprom.data.to_owned())
+    }
+
+    fn set_ram_contents(&mut self, data: &[u8]) {
+        let len = self.eeprom.data.len().min(data.len());
+        self.eeprom.data[0..len].copy_from_slice(&data[0..len]);
+    }
+
+    fn set_tilt(&mut self, x: f32, y: f32) {
+        self.tilt_x = x.clamp(-2.0, 2.0);
+        self.tilt_y = y.clamp(-2.0, 2.0);
+    }
+
+    fn save_state(&self, w: &mut StateWriter) {
+        w.write_bool(self.registers.ram_enabled1);
+        w.write_bool(self.registers.ram_enabled2);
+        w.write_u8(self.registers.rom_bank);
+        w.write_bool(self.registers.latch_erased);
+        w.write_u16(self.registers.x_latch);
+        w.write_u16(self.registers.y_latch);
+        self.eeprom.save_state(w);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), SaveStateError> {
+        self.registers.ram_enabled1 = r.read_bool()?;
+        self.registers.ram_enabled2 = r.read_bool()?;
+        self.registers.rom_bank = r.read_u8()? & 0x7F;
+        self.registers.latch_erased = r.read_bool()?;
+        self.registers.x_latch = r.read_u16()?;
+        self.registers.y_latch = r.read_u16()?;
+        self.eeprom.load_state(r)
+    }
+}
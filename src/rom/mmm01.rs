@@ -0,0 +1,304 @@
+use crate::rom::MBC;
+use crate::rom::ROMError;
+use crate::rom::mbc::ram_bank_count;
+use crate::savestate::{StateWriter, StateReader, SaveStateError};
+
+// Until a game is picked the MMM01 is "unmapped": ROM bank bits 1-8 are forced high so the last 32KB
+// of the ROM (where the menu lives) shows up at 0000-7FFF. The menu then writes the base bank of the game
+// into the extra register bits and sets the map enable bit, which locks them and leaves the cart
+// behaving like a MBC1 restricted to that game's banks.
+struct MMM01Registers {
+    mapped: bool,
+    ram_enabled: bool,
+    mode: u8,
+    mode_locked: bool,
+    rom_bank_low: u8,
+    rom_bank_mid: u8,
+    rom_bank_high: u8,
+    // ROMB bits 1-4 the game can't change once mapped
+    rom_bank_mask: u8,
+    ram_bank_low: u8,
+    ram_bank_high: u8,
+    // RAMB bits the game can't change once mapped
+    ram_bank_mask: u8,
+}
+
+pub struct MMM01 {
+    data: Vec<u8>,
+    ram: Vec<u8>,
+    registers: MMM01Registers,
+    num_rom_banks: u16,
+    num_ram_banks: u8,
+}
+
+impl MMM01 {
+    pub fn new(ram_size: u8, data: &[u8]) -> Result<Self, ROMError> {
+        // the header describes the menu, the whole file is what the MMM01 can address
+        let num_rom_banks = ((data.len() / 0x4000) as u16).max(2);
+
+        // and ram banks
+        let (num_ram_banks, vec_ram_size) = ram_bank_count(ram_size, 16)?;
+
+        Ok(Self {
+            data: data.to_vec(),
+            ram: vec!(0; vec_ram_size),
+            registers: MMM01Registers {
+                mapped: false,
+                ram_enabled: false,
+                mode: 0,
+                mode_locked: false,
+                rom_bank_low: 1,
+                rom_bank_mid: 0,
+                rom_bank_high: 0,
+                rom_bank_mask: 0,
+                ram_bank_low: 0,
+                ram_bank_high: 0,
+                ram_bank_mask: 0,
+            },
+            num_rom_banks,
+            num_ram_banks,
+        })
+    }
+
+    fn rom_bank(&self, upper: bool) -> u32 {
+        let mask = if self.registers.mapped { (self.registers.rom_bank_mask << 1) & 0x1E } else { 0 };
+
+        let low = if upper {
+            // like MBC1, the game can't select its bank 0 in the upper area
+            if self.registers.rom_bank_low & !mask == 0 { self.registers.rom_bank_low | 1 } else { self.registers.rom_bank_low }
+        }
+        else {
+            self.registers.rom_bank_low & mask
+        };
+
+        let mut bank = ((self.registers.rom_bank_high as u32) << 7) | ((self.registers.rom_bank_mid as u32) << 5) | (low as u32);
+        if !self.registers.mapped {
+            bank |= 0x1FE;
+        }
+
+        bank % (self.num_rom_banks as u32)
+    }
+
+    fn ram_address(&self, address: u16) -> usize {
+        let low = if self.registers.mode == 0 { self.registers.ram_bank_low & self.registers.ram_bank_mask } else { self.registers.ram_bank_low };
+        let bank = (self.registers.ram_bank_high << 2) | low;
+
+        let ram_bank: usize = if self.num_ram_banks <= 1 { 0 } else { (bank % self.num_ram_banks) as usize };
+        (ram_bank * 0x2000) + ((address - 0xA000) as usize) % self.ram.len().min(0x2000)
+    }
+}
+
+impl MBC for MMM01 {
+    fn get_rom(&self) -> &[u8] {
+        &self.data
+    }
+
+    fn get_rom_bank(&self, address: u16) -> u16 {
+        self.rom_bank(address >= 0x4000) as u16
+    }
+
+    fn read_byte(&self, address: u16) -> u8 {
+        match address {
+            0x0000..=0x3FFF => {
+                let idx: u32 = (self.rom_bank(false) * 0x4000) + (address as u32);
+                self.data[idx as usize]
+            },
+
+            0x4000..=0x7FFF => {
+                let idx: u32 = (self.rom_bank(true) * 0x4000) + ((address - 0x4000) as u32);
+                self.data[idx as usize]
+            },
+
+            0xA000..=0xBFFF => {
+                if !self.registers.ram_enabled || self.ram.is_empty() {
+                    return 0xFF;
+                }
+
+                self.ram[self.ram_address(address)]
+            },
+
+            _ => panic!("Invalid ROM read")
+        }
+    }
+
+    fn write_byte(&mut self, address: u16, data: u8) {
+        let mapped = self.registers.mapped;
+
+        match address {
+            // RAM enable, while unmapped also the RAMB mask and the map enable bit
+            0x0000..=0x1FFF => {
+                self.registers.ram_enabled = (data & 0x0F) == 0x0A;
+
+                if !mapped {
+                    self.registers.ram_bank_mask = (data >> 4) & 0x03;
+                    self.registers.mapped = data & 0x40 != 0;
+                }
+            },
+
+            // ROM bank, while unmapped also bits 5-6 of it
+            0x2000..=0x3FFF => {
+                let mask = if mapped { (self.registers.rom_bank_mask << 1) & 0x1E } else { 0 };
+                self.registers.rom_bank_low = (self.registers.rom_bank_low & mask) | (data & 0x1F & !mask);
+
+                if !mapped {
+                    self.registers.rom_bank_mid = (data >> 5) & 0x03;
+                }
+            },
+
+            // RAM bank, while unmapped also the upper RAM and ROM bank bits
+            0x4000..=0x5FFF => {
+                let mask = if mapped { self.registers.ram_bank_mask } else { 0 };
+                self.registers.ram_bank_low = (self.registers.ram_bank_low & mask) | (data & 0x03 & !mask);
+
+                if !mapped {
+                    self.registers.ram_bank_high = (data >> 2) & 0x03;
+                    self.registers.rom_bank_high = (data >> 4) & 0x03;
+                    self.registers.mode_locked = data & 0x40 != 0;
+                }
+            },
+
+            // Banking mode, while unmapped also the ROMB mask. The multiplex bit isn't supported
+            0x6000..=0x7FFF => {
+                if !self.registers.mode_locked {
+                    self.registers.mode = data & 0x01;
+                }
+
+                if !mapped {
+                    self.registers.rom_bank_mask = (data >> 2) & 0x0F;
+                }
+            },
+
+            0xA000..=0xBFFF => {
+                if self.registers.ram_enabled && !self.ram.is_empty() {
+                    let ram_addr = self.ram_address(address);
+                    self.ram[ram_addr] = data;
+                }
+            },
+
+            _ => panic!("Invalid ROM write {:#06x}", address)
+        }
+    }
+
+    fn get_ram_contents(&self) -> Option<Vec<u8>> {
+        Some(self.ram.to_owned())
+    }
+
+    fn set_ram_contents(&mut self, data: &[u8]) {
+        let len = self.ram.len().min(data.len());
+        self.ram[0..len].copy_from_slice(&data[0..len]);
+    }
+
+    fn save_state(&self, w: &mut StateWriter) {
+        w.write_bool(self.registers.mapped);
+        w.write_bool(self.registers.ram_enabled);
+        w.write_u8(self.registers.mode);
+        w.write_bool(self.registers.mode_locked);
+        w.write_u8(self.registers.rom_bank_low);
+        w.write_u8(self.registers.rom_bank_mid);
+        w.write_u8(self.registers.rom_bank_high);
+        w.write_u8(self.registers.rom_bank_mask);
+        w.write_u8(self.registers.ram_bank_low);
+        w.write_u8(self.registers.ram_bank_high);
+        w.write_u8(self.registers.ram_bank_mask);
+        w.write_vec(&self.ram);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), SaveStateError> {
+        self.registers.mapped = r.read_bool()?;
+        self.registers.ram_enabled = r.read_bool()?;
+        self.registers.mode = r.read_u8()? & 0x01;
+        self.registers.mode_locked = r.read_bool()?;
+        self.registers.rom_bank_low = r.read_u8()? & 0x1F;
+        self.registers.rom_bank_mid = r.read_u8()? & 0x03;
+        self.registers.rom_bank_high = r.read_u8()? & 0x03;
+        self.registers.rom_bank_mask = r.read_u8()? & 0x0F;
+        self.registers.ram_bank_low = r.read_u8()? & 0x03;
+        self.registers.ram_bank_high = r.read_u8()? & 0x03;
+        self.registers.ram_bank_mask = r.read_u8()? & 0x03;
+        r.read_vec_into(&mut self.ram)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 512KB, each bank starts with its number
+    fn mmm01(ram_size: u8) -> MMM01 {
+        let mut data = vec![0; 0x80000];
+        for bank in 0..32 {
+            data[bank * 0x4000] = bank as u8;
+        }
+
+        MMM01::new(ram_size, &data).unwrap()
+    }
+
+    #[test]
+    fn unmapped_menu() {
+        let mut mbc = mmm01(0);
+        assert_eq!((mbc.read_byte(0x0000), mbc.read_byte(0x4000)), (30, 31));
+
+        // bank writes don't move the menu until the cart is mapped
+        mbc.write_byte(0x2000, 0x05);
+        assert_eq!((mbc.read_byte(0x0000), mbc.read_byte(0x4000)), (30, 31));
+    }
+
+    #[test]
+    fn mapped_game() {
+        let mut mbc = mmm01(0);
+
+        // a 128KB game at bank 8: ROMB bits 3-4 locked, then map it
+        mbc.write_byte(0x2000, 0x08);
+        mbc.write_byte(0x6000, 0x30);
+        mbc.write_byte(0x0000, 0x40);
+        assert_eq!((mbc.read_byte(0x0000), mbc.read_byte(0x4000)), (8, 9));
+
+        mbc.write_byte(0x2000, 0x03);
+        assert_eq!(mbc.read_byte(0x4000), 11);
+
+        // the game can't leave its own banks, and its bank 0 maps to 1
+        mbc.write_byte(0x2000, 0x7F);
+        assert_eq!((mbc.read_byte(0x0000), mbc.read_byte(0x4000)), (8, 15));
+        mbc.write_byte(0x2000, 0x00);
+        assert_eq!(mbc.read_byte(0x4000), 9);
+
+        // and can't unmap the cart again
+        mbc.write_byte(0x0000, 0x00);
+        mbc.write_byte(0x6000, 0x00);
+        assert_eq!((mbc.read_byte(0x0000), mbc.read_byte(0x4000)), (8, 9));
+    }
+
+    #[test]
+    fn ram_bank_mask() {
+        let mut mbc = mmm01(3);
+
+        // RAM bank 2 with both RAMB bits locked
+        mbc.write_byte(0x4000, 0x02);
+        mbc.write_byte(0x0000, 0x7A);
+        mbc.write_byte(0x4000, 0x01);
+        mbc.write_byte(0xA000, 0x55);
+        assert_eq!(mbc.read_byte(0xA000), 0x55);
+        assert_eq!(mbc.get_ram_contents().unwrap()[0x4000], 0x55);
+    }
+
+    #[test]
+    fn no_ram() {
+        let mut mbc = mmm01(0);
+        mbc.write_byte(0x0000, 0x0A);
+        mbc.write_byte(0xA000, 0x55);
+        assert_eq!(mbc.read_byte(0xA000), 0xFF);
+        assert!(mbc.get_ram_contents().unwrap().is_empty());
+    }
+
+    #[test]
+    fn short_save() {
+        let mut mbc = mmm01(3);
+        mbc.set_ram_contents(&[0xAB; 0x100]);
+
+        let ram = mbc.get_ram_contents().unwrap();
+        assert_eq!(ram.len(), 0x8000);
+        assert_eq!((ram[0xFF], ram[0x100]), (0xAB, 0x00));
+    }
+}
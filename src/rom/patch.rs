@@ -0,0 +1,503 @@
+use std::convert::TryFrom;
+
+use crate::rom::ROMError;
+use crate::rom::header::MAX_ROM_SIZE;
+
+const IPS_MAGIC: &[u8] = b"PATCH";
+const IPS_EOF: usize = 0x454F46;
+const UPS_MAGIC: &[u8] = b"UPS1";
+const BPS_MAGIC: &[u8] = b"BPS1";
+
+// UPS and BPS end with the CRC32 of the source, the target and the patch itself
+const FOOTER_SIZE: usize = 12;
+
+const BPS_SOURCE_READ: u64 = 0;
+const BPS_TARGET_READ: u64 = 1;
+const BPS_SOURCE_COPY: u64 = 2;
+const BPS_TARGET_COPY: u64 = 3;
+
+// Applies an IPS, UPS or BPS patch, the format is picked from the header of the patch
+pub fn apply_patch(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, ROMError> {
+    if patch.starts_with(IPS_MAGIC) {
+        apply_ips(rom, patch)
+    }
+    else if patch.starts_with(UPS_MAGIC) {
+        apply_ups(rom, patch)
+    }
+    else if patch.starts_with(BPS_MAGIC) {
+        apply_bps(rom, patch)
+    }
+    else {
+        Err(ROMError::InvalidPatch("unknown patch format"))
+    }
+}
+
+struct PatchReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> PatchReader<'a> {
+    fn new(data: &'a [u8], pos: usize) -> Self {
+        Self { data, pos }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], ROMError> {
+        if self.pos + len > self.data.len() {
+            return Err(ROMError::InvalidPatch("patch is truncated"));
+        }
+
+        let bytes = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+
+        Ok(bytes)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, ROMError> {
+        Ok(self.take(1)?[0])
+    }
+
+    // big endian, IPS only
+    fn read_be(&mut self, len: usize) -> Result<usize, ROMError> {
+        Ok(self.take(len)?.iter().fold(0, |v, b| (v << 8) | (*b as usize)))
+    }
+
+    fn read_u32(&mut self) -> Result<u32, ROMError> {
+        let b = self.take(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    // UPS and BPS numbers are 7 bits per byte, with the last byte flagged by bit 7. Each
+    // continuation adds one so that every number has a single encoding
+    fn read_number(&mut self) -> Result<u64, ROMError> {
+        let mut value: u64 = 0;
+        let mut shift: u64 = 1;
+
+        loop {
+            let x = self.read_u8()? as u64;
+            value += (x & 0x7F) * shift;
+
+            if x & 0x80 != 0 {
+                break;
+            }
+
+            // nothing in a Game Boy patch comes close, this just stops a broken one from overflowing
+            if shift >= (1 << 42) {
+                return Err(ROMError::InvalidPatch("number too large"));
+            }
+
+            shift <<= 7;
+            value += shift;
+        }
+
+        Ok(value)
+    }
+}
+
+fn apply_ips(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, ROMError> {
+    let mut target = rom.to_vec();
+    let mut r = PatchReader::new(patch, IPS_MAGIC.len());
+
+    loop {
+        let offset = r.read_be(3)?;
+        if offset == IPS_EOF {
+            break;
+        }
+
+        let size = r.read_be(2)?;
+
+        // a zero size is a run of the same byte
+        let (size, run) = if size == 0 { (r.read_be(2)?, Some(r.read_u8()?)) } else { (size, None) };
+
+        if offset + size > MAX_ROM_SIZE {
+            return Err(ROMError::InvalidPatch("patched ROM would be too large"));
+        }
+
+        if target.len() < offset + size {
+            target.resize(offset + size, 0);
+        }
+
+        match run {
+            Some(value) => target[offset..offset + size].iter_mut().for_each(|b| *b = value),
+            None => target[offset..offset + size].copy_from_slice(r.take(size)?),
+        }
+    }
+
+    // some patches shrink the ROM by putting its new size after EOF
+    if let Ok(size) = r.read_be(3) {
+        target.truncate(size);
+    }
+
+    Ok(target)
+}
+
+// Checks the source and patch checksums and returns the expected target one
+fn check_footer(rom: &[u8], patch: &[u8]) -> Result<u32, ROMError> {
+    if patch.len() < FOOTER_SIZE {
+        return Err(ROMError::InvalidPatch("patch is truncated"));
+    }
+
+    let mut r = PatchReader::new(patch, patch.len() - FOOTER_SIZE);
+    let source_crc = r.read_u32()?;
+    let target_crc = r.read_u32()?;
+    let patch_crc = r.read_u32()?;
+
+    if crc32fast::hash(&patch[..patch.len() - 4]) != patch_crc {
+        return Err(ROMError::InvalidPatch("patch checksum mismatch"));
+    }
+
+    if crc32fast::hash(rom) != source_crc {
+        return Err(ROMError::InvalidPatch("patch was made for a different ROM"));
+    }
+
+    Ok(target_crc)
+}
+
+fn check_target_size(target_size: usize) -> Result<(), ROMError> {
+    if target_size > MAX_ROM_SIZE {
+        return Err(ROMError::InvalidPatch("patched ROM would be too large"));
+    }
+
+    Ok(())
+}
+
+fn check_target(target: &[u8], target_crc: u32) -> Result<(), ROMError> {
+    if crc32fast::hash(target) != target_crc {
+        return Err(ROMError::InvalidPatch("patched ROM checksum mismatch"));
+    }
+
+    Ok(())
+}
+
+fn apply_ups(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, ROMError> {
+    let target_crc = check_footer(rom, patch)?;
+
+    let mut r = PatchReader::new(patch, UPS_MAGIC.len());
+    let source_size = r.read_number()? as usize;
+    let target_size = r.read_number()? as usize;
+
+    if source_size != rom.len() {
+        return Err(ROMError::InvalidPatch("patch was made for a different ROM"));
+    }
+
+    check_target_size(target_size)?;
+
+    // the patch is a list of XOR runs, anything past the end of the source is taken as zero
+    let mut target = rom.to_vec();
+    target.resize(target_size, 0);
+
+    let end = patch.len() - FOOTER_SIZE;
+    let mut pos: usize = 0;
+
+    while r.pos < end {
+        pos += r.read_number()? as usize;
+
+        loop {
+            let x = r.read_u8()?;
+            if pos < target.len() {
+                target[pos] ^= x;
+            }
+            pos += 1;
+
+            // the zero that ends a run also counts as an unchanged byte
+            if x == 0 {
+                break;
+            }
+        }
+    }
+
+    check_target(&target, target_crc)?;
+
+    Ok(target)
+}
+
+fn apply_bps(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, ROMError> {
+    let target_crc = check_footer(rom, patch)?;
+
+    let mut r = PatchReader::new(patch, BPS_MAGIC.len());
+    let source_size = r.read_number()? as usize;
+    let target_size = r.read_number()? as usize;
+    let metadata_size = r.read_number()? as usize;
+    r.take(metadata_size)?;
+
+    if source_size != rom.len() {
+        return Err(ROMError::InvalidPatch("patch was made for a different ROM"));
+    }
+
+    check_target_size(target_size)?;
+
+    let mut target: Vec<u8> = Vec::with_capacity(target_size);
+    let mut source_offset: i64 = 0;
+    let mut target_offset: i64 = 0;
+
+    let end = patch.len() - FOOTER_SIZE;
+
+    while r.pos < end {
+        let data = r.read_number()?;
+        let length = ((data >> 2) + 1) as usize;
+
+        if target.len() + length > target_size {
+            return Err(ROMError::InvalidPatch("patch writes past the end of the ROM"));
+        }
+
+        match data & 0x03 {
+            // the same bytes as the source at this position
+            BPS_SOURCE_READ => {
+                let start = target.len();
+                let bytes = rom.get(start..start + length).ok_or(ROMError::InvalidPatch("source read out of bounds"))?;
+                target.extend_from_slice(bytes);
+            },
+
+            // bytes stored in the patch
+            BPS_TARGET_READ => {
+                target.extend_from_slice(r.take(length)?);
+            },
+
+            // bytes from anywhere in the source, relative to where the last copy ended
+            BPS_SOURCE_COPY => {
+                source_offset += read_offset(&mut r)?;
+
+                let start = usize::try_from(source_offset).map_err(|_| ROMError::InvalidPatch("source copy out of bounds"))?;
+                let bytes = rom.get(start..start + length).ok_or(ROMError::InvalidPatch("source copy out of bounds"))?;
+                target.extend_from_slice(bytes);
+
+                source_offset += length as i64;
+            },
+
+            // bytes from what has been written so far, one at a time as the ranges can overlap
+            BPS_TARGET_COPY => {
+                target_offset += read_offset(&mut r)?;
+
+                for _ in 0..length {
+                    let byte = usize::try_from(target_offset).ok()
+                        .and_then(|i| target.get(i).copied())
+                        .ok_or(ROMError::InvalidPatch("target copy out of bounds"))?;
+
+                    target.push(byte);
+                    target_offset += 1;
+                }
+            },
+
+            _ => unreachable!()
+        }
+    }
+
+    if target.len() != target_size {
+        return Err(ROMError::InvalidPatch("patched ROM has the wrong size"));
+    }
+
+    check_target(&target, target_crc)?;
+
+    Ok(target)
+}
+
+// Copy offsets are stored as a magnitude with the sign in bit 0
+fn read_offset(r: &mut PatchReader) -> Result<i64, ROMError> {
+    let data = r.read_number()?;
+    let offset = (data >> 1) as i64;
+
+    Ok(if data & 0x01 != 0 { -offset } else { offset })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn number(mut value: u64) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        loop {
+            let x = (value & 0x7F) as u8;
+            value >>= 7;
+
+            if value == 0 {
+                bytes.push(0x80 | x);
+                return bytes;
+            }
+
+            bytes.push(x);
+            value -= 1;
+        }
+    }
+
+    fn offset(value: i64) -> Vec<u8> {
+        number(((value.unsigned_abs()) << 1) | (value < 0) as u64)
+    }
+
+    fn with_footer(mut patch: Vec<u8>, source: &[u8], target: &[u8]) -> Vec<u8> {
+        patch.extend_from_slice(&crc32fast::hash(source).to_le_bytes());
+        patch.extend_from_slice(&crc32fast::hash(target).to_le_bytes());
+        patch.extend_from_slice(&crc32fast::hash(&patch).to_le_bytes());
+        patch
+    }
+
+    fn ips(records: &[u8]) -> Vec<u8> {
+        [IPS_MAGIC, records, b"EOF"].concat()
+    }
+
+    fn error(result: Result<Vec<u8>, ROMError>) -> &'static str {
+        match result {
+            Err(ROMError::InvalidPatch(what)) => what,
+            Err(e) => panic!("unexpected error: {}", e),
+            Ok(_) => panic!("patch should have failed"),
+        }
+    }
+
+    #[test]
+    fn number_round_trip() {
+        for value in [0, 1, 0x7F, 0x80, 0x407F, 0x4080, 0x123456] {
+            let bytes = number(value);
+            assert_eq!(PatchReader::new(&bytes, 0).read_number().unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn ips_records() {
+        let patch = ips(&[0x00, 0x00, 0x01, 0x00, 0x02, 0xAA, 0xBB]);
+        assert_eq!(apply_patch(&[0; 4], &patch).unwrap(), [0x00, 0xAA, 0xBB, 0x00]);
+    }
+
+    #[test]
+    fn ips_rle() {
+        let patch = ips(&[0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x03, 0x77]);
+        assert_eq!(apply_patch(&[0; 5], &patch).unwrap(), [0x00, 0x77, 0x77, 0x77, 0x00]);
+    }
+
+    #[test]
+    fn ips_grows_rom() {
+        let patch = ips(&[0x00, 0x00, 0x05, 0x00, 0x01, 0xCC]);
+        assert_eq!(apply_patch(&[0x11; 2], &patch).unwrap(), [0x11, 0x11, 0x00, 0x00, 0x00, 0xCC]);
+    }
+
+    #[test]
+    fn ips_truncate() {
+        let mut patch = ips(&[0x00, 0x00, 0x00, 0x00, 0x01, 0x22]);
+        patch.extend_from_slice(&[0x00, 0x00, 0x02]);
+        assert_eq!(apply_patch(&[0x11; 4], &patch).unwrap(), [0x22, 0x11]);
+    }
+
+    #[test]
+    fn ips_too_large() {
+        let patch = ips(&[0xFF, 0xFF, 0xFF, 0x00, 0x00, 0xFF, 0xFF, 0x00]);
+        assert_eq!(error(apply_patch(&[0; 4], &patch)), "patched ROM would be too large");
+    }
+
+    #[test]
+    fn ips_truncated() {
+        let patch = [IPS_MAGIC, &[0x00, 0x00, 0x01, 0x00, 0x04, 0xAA]].concat();
+        assert_eq!(error(apply_patch(&[0; 8], &patch)), "patch is truncated");
+    }
+
+    fn ups(source: &[u8], target: &[u8], runs: &[u8]) -> Vec<u8> {
+        let body = [UPS_MAGIC, &number(source.len() as u64), &number(target.len() as u64), runs].concat();
+        with_footer(body, source, target)
+    }
+
+    #[test]
+    fn ups_xor_runs() {
+        let source = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06];
+        let target = [0x01, 0xFF, 0xFE, 0x04, 0x05, 0x16, 0x00, 0x42];
+
+        // the zero ending a run skips a byte too, so each run starts one past where the last left off
+        let runs = [&number(1)[..], &[0x02 ^ 0xFF, 0x03 ^ 0xFE, 0x00], &number(1), &[0x06 ^ 0x16, 0x00], &number(0), &[0x42, 0x00]].concat();
+        let patch = ups(&source, &target, &runs);
+
+        assert_eq!(apply_patch(&source, &patch).unwrap(), target);
+    }
+
+    #[test]
+    fn ups_shrinks_rom() {
+        let source = [0x01, 0x02, 0x03, 0x04];
+        let target = [0x01, 0x02];
+        let patch = ups(&source, &target, &[]);
+
+        assert_eq!(apply_patch(&source, &patch).unwrap(), target);
+    }
+
+    #[test]
+    fn ups_too_large() {
+        let body = [UPS_MAGIC, &number(4), &number(1 << 40)].concat();
+        let patch = with_footer(body, &[0; 4], &[]);
+
+        assert_eq!(error(apply_patch(&[0; 4], &patch)), "patched ROM would be too large");
+    }
+
+    fn bps(source: &[u8], target: &[u8], actions: &[u8]) -> Vec<u8> {
+        let body = [BPS_MAGIC, &number(source.len() as u64), &number(target.len() as u64), &number(0), actions].concat();
+        with_footer(body, source, target)
+    }
+
+    fn action(kind: u64, length: u64) -> Vec<u8> {
+        number(((length - 1) << 2) | kind)
+    }
+
+    #[test]
+    fn bps_actions() {
+        let source = b"ABCDEFGH";
+        let target = b"ABCDxyGHABABABAB";
+
+        let actions = [
+            action(BPS_SOURCE_READ, 4),
+            action(BPS_TARGET_READ, 2), b"xy".to_vec(),
+            // forwards to GH, then back to the start
+            action(BPS_SOURCE_COPY, 2), offset(6),
+            action(BPS_SOURCE_COPY, 2), offset(-8),
+            // overlaps what it writes, repeating AB
+            action(BPS_TARGET_COPY, 4), offset(8),
+            // back to the start of the target
+            action(BPS_TARGET_COPY, 2), offset(-12),
+        ].concat();
+        let patch = bps(source, target, &actions);
+
+        assert_eq!(apply_patch(source, &patch).unwrap(), target);
+    }
+
+    #[test]
+    fn bps_copy_out_of_bounds() {
+        let source = b"ABCD";
+        let target = b"AB";
+
+        let patch = bps(source, target, &[action(BPS_SOURCE_COPY, 2), offset(-1)].concat());
+        assert_eq!(error(apply_patch(source, &patch)), "source copy out of bounds");
+
+        let patch = bps(source, target, &[action(BPS_TARGET_COPY, 2), offset(0)].concat());
+        assert_eq!(error(apply_patch(source, &patch)), "target copy out of bounds");
+    }
+
+    #[test]
+    fn bps_too_large() {
+        let body = [BPS_MAGIC, &number(4), &number(1 << 40), &number(0)].concat();
+        let patch = with_footer(body, &[0; 4], &[]);
+
+        assert_eq!(error(apply_patch(&[0; 4], &patch)), "patched ROM would be too large");
+    }
+
+    #[test]
+    fn patch_checksum_mismatch() {
+        let source = [0x01, 0x02];
+        let mut patch = ups(&source, &source, &[]);
+        let last = patch.len() - 1;
+        patch[last] ^= 0xFF;
+
+        assert_eq!(error(apply_patch(&source, &patch)), "patch checksum mismatch");
+    }
+
+    #[test]
+    fn source_checksum_mismatch() {
+        let patch = bps(&[0x01, 0x02], &[0x01, 0x02], &action(BPS_SOURCE_READ, 2));
+        assert_eq!(error(apply_patch(&[0x01, 0x03], &patch)), "patch was made for a different ROM");
+    }
+
+    #[test]
+    fn target_checksum_mismatch() {
+        let source = [0x01, 0x02];
+        let patch = ups(&source, &[0x01, 0x02], &[&number(0)[..], &[0x10, 0x00]].concat());
+
+        assert_eq!(error(apply_patch(&source, &patch)), "patched ROM checksum mismatch");
+    }
+
+    #[test]
+    fn unknown_format() {
+        assert_eq!(error(apply_patch(&[0; 4], b"NOPE")), "unknown patch format");
+    }
+}
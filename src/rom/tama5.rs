@@ -0,0 +1,578 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::rom::MBC;
+use crate::rom::ROMError;
+use crate::rom::mbc::{rom_bank_count, CLOCKS_PER_SECOND};
+use crate::savestate::{StateWriter, StateReader, SaveStateError};
+
+const TAMA5_RAM_SIZE: usize = 0x20;
+
+// Size of the RTC footer appended to the .sav file: the clock, the alarm page and a timestamp
+const RTC_FOOTER_SIZE: usize = 7 + 16 + 8;
+
+// Registers are picked by writing their number to A001, their value goes through A000 one nibble at a time
+const REG_ROM_BANK_LOW: u8 = 0x0;
+const REG_ROM_BANK_HIGH: u8 = 0x1;
+const REG_DATA_LOW: u8 = 0x4;
+const REG_DATA_HIGH: u8 = 0x5;
+// bit 0 is bit 4 of the address, the rest is the command
+const REG_ADDRESS_HIGH: u8 = 0x6;
+// writing it runs the command
+const REG_ADDRESS_LOW: u8 = 0x7;
+const REG_READY: u8 = 0xA;
+const REG_READ_LOW: u8 = 0xC;
+const REG_READ_HIGH: u8 = 0xD;
+
+const COMMAND_RAM_WRITE: u8 = 0x0;
+const COMMAND_RAM_READ: u8 = 0x1;
+const COMMAND_RTC_WRITE: u8 = 0x2;
+const COMMAND_RTC_READ: u8 = 0x3;
+
+// The year is two digits with a leap year every 4, so dates repeat every 100 years
+const DAYS_PER_4_YEARS: u64 = 366 + 365 * 3;
+const DAYS_PER_100_YEARS: u64 = DAYS_PER_4_YEARS * 25;
+
+// Time kept by the TC8521 clock chip, read and written as BCD digits
+#[derive(Copy, Clone)]
+struct Clock {
+    seconds: u8,
+    minutes: u8,
+    hours: u8,
+    weekday: u8,
+    day: u8,
+    month: u8,
+    year: u8,
+}
+
+impl Clock {
+    fn days_in_month(year: u8, month: u8) -> u8 {
+        match month {
+            2 => if year.is_multiple_of(4) { 29 } else { 28 },
+            4 | 6 | 9 | 11 => 30,
+            _ => 31
+        }
+    }
+
+    // Days since 1 January of year 00
+    fn day_number(&self) -> u64 {
+        let year = self.year as u64;
+        let months: u64 = (1..self.month).map(|m| Clock::days_in_month(self.year, m) as u64).sum();
+
+        // years before this one, and the leap years among them
+        year * 365 + year.div_ceil(4) + months + (self.day as u64 - 1)
+    }
+
+    fn set_day_number(&mut self, days: u64) {
+        let days = days % DAYS_PER_100_YEARS;
+
+        // each 4 years start with the leap one
+        let rest = days % DAYS_PER_4_YEARS;
+        let (year, mut day) = if rest < 366 { (0, rest) } else { (1 + (rest - 366) / 365, (rest - 366) % 365) };
+        self.year = ((days / DAYS_PER_4_YEARS) * 4 + year) as u8;
+
+        self.month = 1;
+        while day >= Clock::days_in_month(self.year, self.month) as u64 {
+            day -= Clock::days_in_month(self.year, self.month) as u64;
+            self.month += 1;
+        }
+
+        self.day = day as u8 + 1;
+    }
+
+    fn advance(&mut self, seconds: u64) {
+        let total = self.seconds as u64 + seconds;
+        self.seconds = (total % 60) as u8;
+
+        let total = self.minutes as u64 + total / 60;
+        self.minutes = (total % 60) as u8;
+
+        let total = self.hours as u64 + total / 60;
+        self.hours = (total % 24) as u8;
+
+        let days = total / 24;
+        if days > 0 {
+            self.weekday = ((self.weekday as u64 + days) % 7) as u8;
+            self.set_day_number(self.day_number() + days);
+        }
+    }
+
+    // Page 0 of the TC8521, every register is a single BCD digit
+    fn read_digit(&self, reg: u8) -> u8 {
+        match reg {
+            0x0 => self.seconds % 10,
+            0x1 => self.seconds / 10,
+            0x2 => self.minutes % 10,
+            0x3 => self.minutes / 10,
+            0x4 => self.hours % 10,
+            0x5 => self.hours / 10,
+            0x6 => self.weekday,
+            0x7 => self.day % 10,
+            0x8 => self.day / 10,
+            0x9 => self.month % 10,
+            0xA => self.month / 10,
+            0xB => self.year % 10,
+            0xC => self.year / 10,
+            _ => 0
+        }
+    }
+
+    fn write_digit(&mut self, reg: u8, digit: u8) {
+        let digit = digit % 10;
+        let units = |value: u8| (value / 10) * 10 + digit;
+        let tens = |value: u8| digit * 10 + value % 10;
+
+        match reg {
+            0x0 => self.seconds = units(self.seconds) % 60,
+            0x1 => self.seconds = tens(self.seconds) % 60,
+            0x2 => self.minutes = units(self.minutes) % 60,
+            0x3 => self.minutes = tens(self.minutes) % 60,
+            0x4 => self.hours = units(self.hours) % 24,
+            0x5 => self.hours = tens(self.hours) % 24,
+            0x6 => self.weekday = digit % 7,
+            0x7 => self.day = units(self.day).clamp(1, 31),
+            0x8 => self.day = tens(self.day).clamp(1, 31),
+            0x9 => self.month = units(self.month).clamp(1, 12),
+            0xA => self.month = tens(self.month).clamp(1, 12),
+            0xB => self.year = units(self.year),
+            0xC => self.year = tens(self.year),
+            _ => {}
+        }
+    }
+}
+
+struct RTC {
+    clock: Clock,
+    // page 1 of the TC8521, the alarm time and the clock settings, just stored for the game
+    alarm: [u8; 16],
+    // clocks into the current second
+    clocks: u32,
+}
+
+impl RTC {
+    fn new() -> Self {
+        Self {
+            clock: Clock {
+                seconds: 0,
+                minutes: 0,
+                hours: 0,
+                weekday: 0,
+                day: 1,
+                month: 1,
+                year: 0,
+            },
+            alarm: [0; 16],
+            clocks: 0,
+        }
+    }
+
+    fn now() -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+    }
+
+    // The clock follows emulated time, so it stops with the emulator and stays the same across runs
+    fn tick(&mut self, clocks: u32) {
+        self.clocks += clocks;
+        if self.clocks >= CLOCKS_PER_SECOND {
+            self.clocks -= CLOCKS_PER_SECOND;
+            self.clock.advance(1);
+        }
+    }
+
+    // bit 4 of the address picks the page
+    fn read(&self, address: u8) -> u8 {
+        if address & 0x10 == 0 {
+            self.clock.read_digit(address & 0x0F)
+        }
+        else {
+            self.alarm[(address & 0x0F) as usize]
+        }
+    }
+
+    fn write(&mut self, address: u8, data: u8) {
+        // writing the seconds restarts the second that was being counted
+        if address <= 0x01 {
+            self.clocks = 0;
+        }
+
+        if address & 0x10 == 0 {
+            self.clock.write_digit(address & 0x0F, data);
+        }
+        else {
+            self.alarm[(address & 0x0F) as usize] = data & 0x0F;
+        }
+    }
+
+    fn to_footer(&self) -> Vec<u8> {
+        let c = &self.clock;
+
+        let mut footer = Vec::with_capacity(RTC_FOOTER_SIZE);
+        footer.extend_from_slice(&[c.seconds, c.minutes, c.hours, c.weekday, c.day, c.month, c.year]);
+        footer.extend_from_slice(&self.alarm);
+        footer.extend_from_slice(&RTC::now().to_le_bytes());
+
+        footer
+    }
+
+    fn load_registers(&mut self, registers: &[u8]) {
+        self.clock = Clock {
+            seconds: registers[0] % 60,
+            minutes: registers[1] % 60,
+            hours: registers[2] % 24,
+            weekday: registers[3] % 7,
+            day: registers[4].clamp(1, 31),
+            month: registers[5].clamp(1, 12),
+            year: registers[6] % 100,
+        };
+
+        for (dest, src) in self.alarm.iter_mut().zip(registers[7..23].iter()) {
+            *dest = src & 0x0F;
+        }
+    }
+
+    fn load_footer(&mut self, footer: &[u8]) {
+        self.load_registers(footer);
+
+        let mut timestamp = [0; 8];
+        timestamp.copy_from_slice(&footer[23..31]);
+        let timestamp = u64::from_le_bytes(timestamp);
+
+        // catch up with the time that went by while the emulator was closed
+        let now = RTC::now();
+        if now > timestamp {
+            self.clock.advance(now - timestamp);
+        }
+    }
+
+    fn save_state(&self, w: &mut StateWriter) {
+        let c = &self.clock;
+        w.write_bytes(&[c.seconds, c.minutes, c.hours, c.weekday, c.day, c.month, c.year]);
+        w.write_bytes(&self.alarm);
+        w.write_u32(self.clocks);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), SaveStateError> {
+        let mut registers = [0; 7 + 16];
+        r.read_bytes(&mut registers)?;
+        self.load_registers(&registers);
+        self.clocks = r.read_u32()?.min(CLOCKS_PER_SECOND - 1);
+
+        Ok(())
+    }
+}
+
+struct TAMA5Registers {
+    selected: u8,
+    values: [u8; 16],
+    // last byte read back by a RAM or RTC read command
+    read: u8,
+}
+
+pub struct TAMA5 {
+    data: Vec<u8>,
+    ram: Vec<u8>,
+    registers: TAMA5Registers,
+    rtc: RTC,
+    num_rom_banks: u16,
+}
+
+impl TAMA5 {
+    pub fn new(rom_size: u8, data: &[u8]) -> Result<Self, ROMError> {
+        // calculate number of rom banks, a ROM bigger than the bank register can select is refused
+        let num_rom_banks = rom_bank_count(rom_size, 32)?;
+
+        let mut values = [0; 16];
+        values[REG_ROM_BANK_LOW as usize] = 1;
+
+        Ok(Self {
+            data: data.to_vec(),
+            ram: vec!(0; TAMA5_RAM_SIZE),
+            registers: TAMA5Registers {
+                selected: 0,
+                values,
+                read: 0,
+            },
+            rtc: RTC::new(),
+            num_rom_banks,
+        })
+    }
+
+    fn rom_bank(&self) -> u8 {
+        ((self.registers.values[REG_ROM_BANK_HIGH as usize] & 0x01) << 4) | self.registers.values[REG_ROM_BANK_LOW as usize]
+    }
+
+    fn execute(&mut self) {
+        let values = &self.registers.values;
+        let address = ((values[REG_ADDRESS_HIGH as usize] & 0x01) << 4) | values[REG_ADDRESS_LOW as usize];
+        let data = (values[REG_DATA_HIGH as usize] << 4) | values[REG_DATA_LOW as usize];
+
+        match values[REG_ADDRESS_HIGH as usize] >> 1 {
+            COMMAND_RAM_WRITE => self.ram[address as usize] = data,
+            COMMAND_RAM_READ => self.registers.read = self.ram[address as usize],
+            COMMAND_RTC_WRITE => self.rtc.write(address, data & 0x0F),
+            COMMAND_RTC_READ => self.registers.read = self.rtc.read(address),
+            _ => {}
+        }
+    }
+}
+
+impl MBC for TAMA5 {
+    fn get_rom(&self) -> &[u8] {
+        &self.data
+    }
+
+    fn get_rom_bank(&self, address: u16) -> u16 {
+        match address {
+            0x0000..=0x3FFF => 0,
+            _ => ((self.rom_bank() as u32) % (self.num_rom_banks as u32)) as u16
+        }
+    }
+
+    fn read_byte(&self, address: u16) -> u8 {
+        match address {
+            0x0000..=0x3FFF => {
+                self.data[address as usize]
+            },
+
+            0x4000..=0x7FFF => {
+                let bank: u32 = self.get_rom_bank(address) as u32;
+                let idx: u32 = (bank * 0x4000) + ((address - 0x4000) as u32);
+                self.data[idx as usize]
+            },
+
+            // A000 reads back a nibble of the selected register
+            0xA000..=0xBFFF if address & 0x01 == 0 => {
+                match self.registers.selected {
+                    // commands complete straight away, so the chip is always ready
+                    REG_READY => 0xF1,
+                    REG_READ_LOW => 0xF0 | (self.registers.read & 0x0F),
+                    REG_READ_HIGH => 0xF0 | (self.registers.read >> 4),
+                    _ => 0xFF
+                }
+            },
+
+            0xA000..=0xBFFF => 0xFF,
+
+            _ => panic!("Invalid ROM read")
+        }
+    }
+
+    fn write_byte(&mut self, address: u16, data: u8) {
+        match address {
+            0x0000..=0x7FFF => {},
+
+            // A000 - register value
+            0xA000..=0xBFFF if address & 0x01 == 0 => {
+                let reg = self.registers.selected;
+                self.registers.values[reg as usize] = data & 0x0F;
+
+                if reg == REG_ADDRESS_LOW {
+                    self.execute();
+                }
+            },
+
+            // A001 - register select
+            0xA000..=0xBFFF => {
+                self.registers.selected = data & 0x0F;
+            },
+
+            _ => panic!("Invalid ROM write {:#06x}", address)
+        }
+    }
+
+    fn tick(&mut self, clocks: u32) {
+        self.rtc.tick(clocks);
+    }
+
+    fn get_ram_contents(&self) -> Option<Vec<u8>> {
+        let mut contents = self.ram.to_owned();
+        contents.extend_from_slice(&self.rtc.to_footer());
+
+        Some(contents)
+    }
+
+    fn set_ram_contents(&mut self, data: &[u8]) {
+        let ram_len = self.ram.len().min(data.len());
+        self.ram[0..ram_len].copy_from_slice(&data[0..ram_len]);
+
+        let footer = &data[ram_len..];
+        if footer.len() == RTC_FOOTER_SIZE {
+            self.rtc.load_footer(footer);
+        }
+    }
+
+    fn save_state(&self, w: &mut StateWriter) {
+        w.write_u8(self.registers.selected);
+        w.write_bytes(&self.registers.values);
+        w.write_u8(self.registers.read);
+        w.write_vec(&self.ram);
+        self.rtc.save_state(w);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), SaveStateError> {
+        self.registers.selected = r.read_u8()? & 0x0F;
+        r.read_bytes(&mut self.registers.values)?;
+        self.registers.values.iter_mut().for_each(|v| *v &= 0x0F);
+        self.registers.read = r.read_u8()?;
+        r.read_vec_into(&mut self.ram)?;
+        self.rtc.load_state(r)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tama5() -> TAMA5 {
+        TAMA5::new(0, &[0; 0x8000]).unwrap()
+    }
+
+    fn set_register(mbc: &mut TAMA5, reg: u8, value: u8) {
+        mbc.write_byte(0xA001, reg);
+        mbc.write_byte(0xA000, value);
+    }
+
+    // Runs a command the way games do: data first, then the address, whose low nibble starts it
+    fn command(mbc: &mut TAMA5, command: u8, address: u8, data: u8) -> u8 {
+        set_register(mbc, REG_DATA_LOW, data & 0x0F);
+        set_register(mbc, REG_DATA_HIGH, data >> 4);
+        set_register(mbc, REG_ADDRESS_HIGH, (command << 1) | (address >> 4));
+        set_register(mbc, REG_ADDRESS_LOW, address & 0x0F);
+
+        mbc.write_byte(0xA001, REG_READ_LOW);
+        let low = mbc.read_byte(0xA000) & 0x0F;
+        mbc.write_byte(0xA001, REG_READ_HIGH);
+        let high = mbc.read_byte(0xA000) & 0x0F;
+
+        (high << 4) | low
+    }
+
+    fn date(clock: &Clock) -> (u8, u8, u8, u8) {
+        (clock.year, clock.month, clock.day, clock.weekday)
+    }
+
+    #[test]
+    fn counts_emulated_seconds() {
+        let mut mbc = tama5();
+
+        mbc.tick(CLOCKS_PER_SECOND - 1);
+        assert_eq!(mbc.rtc.clock.seconds, 0);
+
+        mbc.tick(1);
+        assert_eq!((mbc.rtc.clock.seconds, mbc.rtc.clocks), (1, 0));
+
+        for _ in 0..59 {
+            mbc.tick(CLOCKS_PER_SECOND);
+        }
+        assert_eq!((mbc.rtc.clock.seconds, mbc.rtc.clock.minutes), (0, 1));
+    }
+
+    #[test]
+    fn days_roll_into_months_and_years() {
+        let mut rtc = RTC::new();
+        rtc.clock.hours = 23;
+        rtc.clock.minutes = 59;
+        rtc.clock.seconds = 59;
+        rtc.clock.day = 31;
+        rtc.clock.month = 12;
+        rtc.clock.year = 99;
+        rtc.clock.weekday = 6;
+
+        rtc.clock.advance(1);
+        assert_eq!((rtc.clock.hours, rtc.clock.minutes, rtc.clock.seconds), (0, 0, 0));
+        assert_eq!(date(&rtc.clock), (0, 1, 1, 0));
+
+        // 00 is a leap year
+        rtc.clock.advance(59 * 24 * 60 * 60);
+        assert_eq!(date(&rtc.clock), (0, 2, 29, 3));
+
+        rtc.clock.advance(366 * 24 * 60 * 60);
+        assert_eq!(date(&rtc.clock), (1, 3, 1, 5));
+
+        // four years are always 1461 days, whatever the starting point
+        rtc.clock.advance(1461 * 24 * 60 * 60);
+        assert_eq!(date(&rtc.clock), (5, 3, 1, 3));
+    }
+
+    #[test]
+    fn selects_rom_bank() {
+        let mut data = vec![0; 0x10000];
+        data[0x4000] = 0x41;
+        data[0xC000] = 0x43;
+        let mut mbc = TAMA5::new(1, &data).unwrap();
+
+        assert_eq!(mbc.read_byte(0x4000), 0x41);
+
+        set_register(&mut mbc, REG_ROM_BANK_LOW, 0x3);
+        assert_eq!(mbc.read_byte(0x4000), 0x43);
+    }
+
+    #[test]
+    fn ram_commands() {
+        let mut mbc = tama5();
+
+        command(&mut mbc, COMMAND_RAM_WRITE, 0x13, 0xA5);
+        assert_eq!(mbc.ram[0x13], 0xA5);
+        assert_eq!(command(&mut mbc, COMMAND_RAM_READ, 0x13, 0), 0xA5);
+
+        mbc.write_byte(0xA001, REG_READY);
+        assert_eq!(mbc.read_byte(0xA000), 0xF1);
+    }
+
+    #[test]
+    fn rtc_commands() {
+        let mut mbc = tama5();
+
+        // minutes 42, then the alarm page
+        command(&mut mbc, COMMAND_RTC_WRITE, 0x02, 2);
+        command(&mut mbc, COMMAND_RTC_WRITE, 0x03, 4);
+        command(&mut mbc, COMMAND_RTC_WRITE, 0x15, 0x7);
+        assert_eq!(mbc.rtc.clock.minutes, 42);
+
+        assert_eq!(command(&mut mbc, COMMAND_RTC_READ, 0x02, 0), 2);
+        assert_eq!(command(&mut mbc, COMMAND_RTC_READ, 0x03, 0), 4);
+        assert_eq!(command(&mut mbc, COMMAND_RTC_READ, 0x15, 0), 0x7);
+
+        // reading the clock doesn't move it
+        assert_eq!(mbc.rtc.clock.seconds, 0);
+    }
+
+    #[test]
+    fn save_state_keeps_clock() {
+        let mut mbc = tama5();
+        mbc.rtc.clock.advance(1234567);
+        mbc.tick(1000);
+
+        let mut w = StateWriter::new();
+        mbc.save_state(&mut w);
+        let bytes = w.into_bytes();
+
+        let mut loaded = tama5();
+        loaded.load_state(&mut StateReader::new(&bytes)).unwrap();
+
+        assert_eq!(date(&loaded.rtc.clock), date(&mbc.rtc.clock));
+        assert_eq!(loaded.rtc.clock.seconds, mbc.rtc.clock.seconds);
+        assert_eq!(loaded.rtc.clocks, 1000);
+    }
+
+    #[test]
+    fn footer_round_trip() {
+        let mut mbc = tama5();
+        mbc.ram[0] = 0x12;
+        mbc.rtc.clock.day = 15;
+        mbc.rtc.alarm[3] = 0x9;
+
+        let contents = mbc.get_ram_contents().unwrap();
+        assert_eq!(contents.len(), TAMA5_RAM_SIZE + RTC_FOOTER_SIZE);
+
+        let mut loaded = tama5();
+        loaded.set_ram_contents(&contents);
+
+        assert_eq!(loaded.ram[0], 0x12);
+        assert_eq!(loaded.rtc.alarm[3], 0x9);
+        // the footer was written just now, so at most a few seconds went by
+        assert_eq!(date(&loaded.rtc.clock), (0, 1, 15, 0));
+        assert!(loaded.rtc.clock.seconds < 5);
+    }
+}
@@ -0,0 +1,275 @@
+use std::fmt;
+
+pub const SAVE_STATE_MAGIC: &[u8; 4] = b"RGBS";
+pub const SAVE_STATE_VERSION: u16 = 12;
+
+#[derive(Debug)]
+pub enum SaveStateError {
+    InvalidMagic,
+    UnsupportedVersion(u16),
+    ModelMismatch,
+    UnexpectedEof,
+    InvalidData(&'static str),
+}
+
+impl fmt::Display for SaveStateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SaveStateError::InvalidMagic => write!(f, "not a save state file"),
+            SaveStateError::UnsupportedVersion(v) => write!(f, "unsupported save state version {}", v),
+            SaveStateError::ModelMismatch => write!(f, "save state was created for a different hardware model"),
+            SaveStateError::UnexpectedEof => write!(f, "save state is truncated"),
+            SaveStateError::InvalidData(what) => write!(f, "invalid save state data: {}", what),
+        }
+    }
+}
+
+impl std::error::Error for SaveStateError {}
+
+pub struct StateWriter {
+    data: Vec<u8>,
+}
+
+impl Default for StateWriter {
+    fn default() -> Self {
+        StateWriter::new()
+    }
+}
+
+impl StateWriter {
+    pub fn new() -> Self {
+        Self {
+            data: vec!()
+        }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.data
+    }
+
+    pub fn write_u8(&mut self, v: u8) {
+        self.data.push(v);
+    }
+
+    pub fn write_bool(&mut self, v: bool) {
+        self.data.push(v as u8);
+    }
+
+    pub fn write_u16(&mut self, v: u16) {
+        self.data.extend_from_slice(&v.to_le_bytes());
+    }
+
+    pub fn write_i16(&mut self, v: i16) {
+        self.data.extend_from_slice(&v.to_le_bytes());
+    }
+
+    pub fn write_u32(&mut self, v: u32) {
+        self.data.extend_from_slice(&v.to_le_bytes());
+    }
+
+    pub fn write_u64(&mut self, v: u64) {
+        self.data.extend_from_slice(&v.to_le_bytes());
+    }
+
+    pub fn write_f32(&mut self, v: f32) {
+        self.data.extend_from_slice(&v.to_le_bytes());
+    }
+
+    // Fixed size block, the reader must know the length
+    pub fn write_bytes(&mut self, v: &[u8]) {
+        self.data.extend_from_slice(v);
+    }
+
+    // Variable size block, prefixed with its length
+    pub fn write_vec(&mut self, v: &[u8]) {
+        self.write_u32(v.len() as u32);
+        self.data.extend_from_slice(v);
+    }
+}
+
+pub struct StateReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> StateReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            position: 0
+        }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], SaveStateError> {
+        if self.position + len > self.data.len() {
+            return Err(SaveStateError::UnexpectedEof);
+        }
+
+        let slice = &self.data[self.position..self.position + len];
+        self.position += len;
+
+        Ok(slice)
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, SaveStateError> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn read_bool(&mut self) -> Result<bool, SaveStateError> {
+        Ok(self.read_u8()? != 0)
+    }
+
+    pub fn read_u16(&mut self) -> Result<u16, SaveStateError> {
+        let b = self.take(2)?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    pub fn read_i16(&mut self) -> Result<i16, SaveStateError> {
+        let b = self.take(2)?;
+        Ok(i16::from_le_bytes([b[0], b[1]]))
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32, SaveStateError> {
+        let b = self.take(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    pub fn read_u64(&mut self) -> Result<u64, SaveStateError> {
+        let b = self.take(8)?;
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(b);
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    pub fn read_f32(&mut self) -> Result<f32, SaveStateError> {
+        let b = self.take(4)?;
+        Ok(f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    pub fn read_bytes(&mut self, dest: &mut [u8]) -> Result<(), SaveStateError> {
+        let b = self.take(dest.len())?;
+        dest.copy_from_slice(b);
+        Ok(())
+    }
+
+    pub fn read_vec(&mut self) -> Result<Vec<u8>, SaveStateError> {
+        let len = self.read_u32()? as usize;
+        Ok(self.take(len)?.to_vec())
+    }
+
+    // Reads a length prefixed block that must match the size of dest
+    pub fn read_vec_into(&mut self, dest: &mut [u8]) -> Result<(), SaveStateError> {
+        let len = self.read_u32()? as usize;
+        if len != dest.len() {
+            return Err(SaveStateError::InvalidData("memory block size mismatch"));
+        }
+
+        self.read_bytes(dest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::machine::{Machine, GameBoyModel};
+
+    fn new_machine(model: GameBoyModel) -> Machine {
+        let mut machine = Machine::from_rom_bytes(&[0; 0x8000], Some(model)).unwrap();
+        machine.start(true);
+        machine
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut w = StateWriter::new();
+        w.write_u8(0x12);
+        w.write_bool(true);
+        w.write_u16(0x3456);
+        w.write_i16(-2);
+        w.write_u32(0x789ABCDE);
+        w.write_u64(0x0102030405060708);
+        w.write_f32(1.5);
+        w.write_bytes(&[1, 2, 3]);
+        w.write_vec(&[4, 5]);
+        w.write_vec(&[6, 7, 8]);
+        let bytes = w.into_bytes();
+
+        let mut r = StateReader::new(&bytes);
+        assert_eq!(r.read_u8().unwrap(), 0x12);
+        assert!(r.read_bool().unwrap());
+        assert_eq!(r.read_u16().unwrap(), 0x3456);
+        assert_eq!(r.read_i16().unwrap(), -2);
+        assert_eq!(r.read_u32().unwrap(), 0x789ABCDE);
+        assert_eq!(r.read_u64().unwrap(), 0x0102030405060708);
+        assert_eq!(r.read_f32().unwrap(), 1.5);
+
+        let mut block = [0; 3];
+        r.read_bytes(&mut block).unwrap();
+        assert_eq!(block, [1, 2, 3]);
+        assert_eq!(r.read_vec().unwrap(), [4, 5]);
+
+        let mut block = [0; 3];
+        r.read_vec_into(&mut block).unwrap();
+        assert_eq!(block, [6, 7, 8]);
+
+        assert!(matches!(r.read_u8(), Err(SaveStateError::UnexpectedEof)));
+    }
+
+    #[test]
+    fn truncated() {
+        assert!(matches!(StateReader::new(&[1]).read_u16(), Err(SaveStateError::UnexpectedEof)));
+        assert!(matches!(StateReader::new(&[1, 2, 3]).read_u32(), Err(SaveStateError::UnexpectedEof)));
+        assert!(matches!(StateReader::new(&[5, 0, 0, 0, 1, 2]).read_vec(), Err(SaveStateError::UnexpectedEof)));
+
+        // a block of the wrong size is refused before anything is copied
+        let mut block = [0; 3];
+        assert!(matches!(StateReader::new(&[2, 0, 0, 0, 1, 2]).read_vec_into(&mut block), Err(SaveStateError::InvalidData(_))));
+        assert_eq!(block, [0; 3]);
+    }
+
+    #[test]
+    fn machine_round_trip() {
+        let mut machine = new_machine(GameBoyModel::DMG);
+        for _ in 0..1000 {
+            machine.step();
+        }
+
+        let state = machine.save_state();
+        let pc = machine.get_registers().pc;
+
+        for _ in 0..1000 {
+            machine.step();
+        }
+        assert_ne!(machine.get_registers().pc, pc);
+
+        machine.load_state(&state).unwrap();
+        assert_eq!(machine.get_registers().pc, pc);
+        assert_eq!(machine.save_state(), state);
+    }
+
+    #[test]
+    fn machine_rejects_bad_states() {
+        let mut machine = new_machine(GameBoyModel::DMG);
+        let state = machine.save_state();
+
+        let mut bad = state.clone();
+        bad[0] = b'X';
+        assert!(matches!(machine.load_state(&bad), Err(SaveStateError::InvalidMagic)));
+
+        let mut bad = state.clone();
+        bad[4..6].copy_from_slice(&(SAVE_STATE_VERSION - 1).to_le_bytes());
+        assert!(matches!(machine.load_state(&bad), Err(SaveStateError::UnsupportedVersion(v)) if v == SAVE_STATE_VERSION - 1));
+
+        let gbc_state = new_machine(GameBoyModel::GBC).save_state();
+        assert!(matches!(machine.load_state(&gbc_state), Err(SaveStateError::ModelMismatch)));
+
+        // a truncated state fails without touching the machine
+        for _ in 0..100 {
+            machine.step();
+        }
+        let before = machine.save_state();
+
+        assert!(matches!(machine.load_state(&state[0..state.len() / 2]), Err(SaveStateError::UnexpectedEof)));
+        assert_eq!(machine.save_state(), before);
+    }
+}
@@ -3,7 +3,8 @@ use crate::machine::GameBoyModel;
 pub struct Screen {
     model: GameBoyModel, 
     framebuffer: Box<[u32]>,
-    vblank: bool
+    vblank: bool,
+    frame_count: u64,
 }
 
 const DMG_SCREEN_COLORS: [u32; 4] = [
@@ -18,7 +19,8 @@ impl Screen {
         Self {
             model,
             framebuffer: vec!(0; 160*144).into_boxed_slice(),
-            vblank: false
+            vblank: false,
+            frame_count: 0,
         }
     }
 
@@ -31,26 +33,24 @@ impl Screen {
     pub fn set_scanline(&mut self, line: u8, data: &[u16; 160]) {
         let rng = (line as usize * 160)..(line as usize * 160 + 160);
 
-        let colors: Vec<u32>;
-
-        match self.model {
+        let colors: Vec<u32> = match self.model {
             GameBoyModel::DMG => {
-                colors = data.iter().map(|v| -> u32 { 
+                data.iter().map(|v| -> u32 { 
                     DMG_SCREEN_COLORS[*v as usize] 
-                }).collect();
+                }).collect()
             }
 
             GameBoyModel::GBC => {
-                colors = data.iter().map(|v| {
+                data.iter().map(|v| {
                     let r = ((((v & 0x1F) as f32) / 31.0) * 255.0) as u32;
                     let g = ((((v >> 5) & 0x1F) as f32 / 31.0) * 255.0) as u32;
                     let b = ((((v >> 10) & 0x1F) as f32 / 31.0) * 255.0) as u32;
         
                     // r << 24 | g << 16 | b << 8
                     b << 16 | g << 8 | r
-                }).collect();
+                }).collect()
             }
-        }        
+        };
 
         self.framebuffer[rng].copy_from_slice(&colors);
     }
@@ -61,5 +61,13 @@ impl Screen {
 
     pub fn set_vblank(&mut self, v: bool) {
         self.vblank = v;
+
+        if v {
+            self.frame_count += 1;
+        }
+    }
+
+    pub fn get_frame_count(&self) -> u64 {
+        self.frame_count
     }
 }
\ No newline at end of file
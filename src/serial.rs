@@ -1,22 +1,412 @@
+use crate::cpu::{Interrupts, CPUInterrupts};
+use crate::machine::GameBoyModel;
+use crate::savestate::{StateWriter, StateReader, SaveStateError};
+pub use self::peers::{Disconnected, ByteLogger, Loopback};
+pub use self::link_cable::LinkCable;
+mod peers;
+mod link_cable;
+
+const SC_TRANSFER: u8 = 1 << 7;
+const SC_FAST_CLOCK: u8 = 1 << 1;
+const SC_INTERNAL_CLOCK: u8 = 1;
+
+const CLOCKS_PER_BIT: u16 = 512; // 8192 Hz
+const CLOCKS_PER_BIT_FAST: u16 = 16; // 262144 Hz, CGB only
+
+const SYNC_PERIOD: u32 = 70224; // clocks, once per frame
+
+// Whatever is plugged into the other end of the link port
+pub trait LinkPeer {
+    // We are driving the clock and just shifted out a whole byte, returns the byte that was shifted in.
+    // Peers that can't answer right away return None, and poll_transfer is called until they do
+    fn transfer(&mut self, data: u8) -> Option<u8>;
+
+    fn poll_transfer(&mut self) -> Option<u8> {
+        Some(0xFF)
+    }
+
+    // The other side drives the clock, called while we wait for it with the byte we have ready.
+    // Returns the byte it sent us once it has clocked a whole transfer
+    fn transfer_external(&mut self, _data: u8) -> Option<u8> {
+        None
+    }
+
+    // The game stopped an externally clocked transfer before the other side clocked it, the byte it had ready is withdrawn
+    fn cancel_external(&mut self) {}
+
+    // Called once per frame worth of clocks, lets peers in another process keep both machines in step
+    fn sync(&mut self) {}
+
+    // True while the other side is too far behind, the machine stops running until it catches up
+    fn is_behind(&mut self) -> bool {
+        false
+    }
+}
+
+struct SerialState {
+    bits_remaining: u8,
+    clock_counter: u16,
+    sync_counter: u32,
+    // all 8 bits went out and the peer hasn't answered yet
+    waiting_reply: bool,
+    waiting_peer: bool,
+}
+
 pub struct Serial {
+    model: GameBoyModel,
+    data: u8,
+    control: u8,
+    state: SerialState,
+    // only kept when asked for, games polling the link port would grow it forever
+    output: Option<Vec<u8>>,
+    peer: Box<dyn LinkPeer>,
 }
 
 impl Serial {
-    pub fn new() -> Self {
+    pub fn new(model: GameBoyModel) -> Self {
         Self {
-            
+            model,
+            data: 0,
+            control: 0,
+            state: SerialState {
+                bits_remaining: 0,
+                clock_counter: 0,
+                sync_counter: 0,
+                waiting_reply: false,
+                waiting_peer: false,
+            },
+            output: None,
+            peer: Box::new(Disconnected),
+        }
+    }
+
+    pub fn set_peer(&mut self, peer: Box<dyn LinkPeer>) {
+        self.peer = peer;
+    }
+
+    // Starts or stops keeping the bytes the game sends out
+    pub fn set_capture(&mut self, capture: bool) {
+        self.output = if capture { Some(vec!()) } else { None };
+    }
+
+    // Bytes the game has sent out through the link port since capture started (test ROMs print their results this way)
+    pub fn get_output(&self) -> &[u8] {
+        self.output.as_deref().unwrap_or(&[])
+    }
+
+    pub fn save_state(&self, w: &mut StateWriter) {
+        w.write_u8(self.data);
+        w.write_u8(self.control);
+        // a transfer waiting on the peer is saved one bit short, so the byte is sent again after loading
+        w.write_u8(if self.state.waiting_reply { 1 } else { self.state.bits_remaining });
+        w.write_u16(self.state.clock_counter);
+    }
+
+    pub fn load_state(&mut self, r: &mut StateReader) -> Result<(), SaveStateError> {
+        self.data = r.read_u8()?;
+        self.control = r.read_u8()? & self.control_mask();
+        self.state.bits_remaining = r.read_u8()?;
+        self.state.clock_counter = r.read_u16()?;
+        self.state.waiting_reply = false;
+
+        // a transfer we clock ourselves always has bits left to shift
+        let clocking = self.control & (SC_TRANSFER | SC_INTERNAL_CLOCK) == SC_TRANSFER | SC_INTERNAL_CLOCK;
+        if self.state.bits_remaining > 8 || (clocking && self.state.bits_remaining == 0) {
+            return Err(SaveStateError::InvalidData("serial bits remaining"));
+        }
+
+        Ok(())
+    }
+
+    fn control_mask(&self) -> u8 {
+        match self.model {
+            GameBoyModel::DMG => SC_TRANSFER | SC_INTERNAL_CLOCK,
+            GameBoyModel::GBC => SC_TRANSFER | SC_FAST_CLOCK | SC_INTERNAL_CLOCK,
+        }
+    }
+
+    fn clocks_per_bit(&self) -> u16 {
+        if self.control & SC_FAST_CLOCK != 0 { CLOCKS_PER_BIT_FAST } else { CLOCKS_PER_BIT }
+    }
+
+    // The machine holds off running while a peer in another process catches up with us
+    pub fn is_waiting_for_peer(&mut self) -> bool {
+        if self.state.waiting_peer {
+            self.state.waiting_peer = self.peer.is_behind();
+        }
+
+        self.state.waiting_peer
+    }
+
+    pub fn tick(&mut self, interrupts: &mut CPUInterrupts) {
+        self.state.sync_counter += 1;
+        if self.state.sync_counter == SYNC_PERIOD {
+            self.state.sync_counter = 0;
+            self.peer.sync();
+            self.state.waiting_peer = self.peer.is_behind();
+        }
+
+        if self.control & SC_TRANSFER == 0 {
+            return;
+        }
+
+        if self.state.waiting_reply {
+            if let Some(received) = self.peer.poll_transfer() {
+                self.data = received;
+                self.finish_transfer(interrupts);
+            }
+
+            return;
+        }
+
+        // External clock, it's up to the other side to do the transfer
+        if self.control & SC_INTERNAL_CLOCK == 0 {
+            if let Some(received) = self.peer.transfer_external(self.data) {
+                self.data = received;
+                self.finish_transfer(interrupts);
+            }
+
+            return;
         }
+
+        self.state.clock_counter += 1;
+        if self.state.clock_counter < self.clocks_per_bit() {
+            return;
+        }
+
+        self.state.clock_counter = 0;
+        self.state.bits_remaining -= 1;
+
+        // The byte is exchanged with the peer once all 8 bits went out
+        if self.state.bits_remaining == 0 {
+            if let Some(output) = &mut self.output {
+                output.push(self.data);
+            }
+
+            match self.peer.transfer(self.data) {
+                Some(received) => {
+                    self.data = received;
+                    self.finish_transfer(interrupts);
+                },
+                None => self.state.waiting_reply = true
+            }
+        }
+    }
+
+    fn finish_transfer(&mut self, interrupts: &mut CPUInterrupts) {
+        self.control &= !SC_TRANSFER;
+        self.state.bits_remaining = 0;
+        self.state.waiting_reply = false;
+        interrupts.raise_interrupt(Interrupts::Serial);
     }
 
     pub fn read_byte(&self, address: u16) -> u8 {
         match address {
-            0xFF01 => 0,
-            0xFF02 => 0x7E,
+            // FF01 - SB - Serial transfer data (R/W)
+            0xFF01 => self.data,
+
+            // FF02 - SC - Serial Transfer Control (R/W)
+            0xFF02 => !self.control_mask() | self.control,
+
             _ => unreachable!()
         }
     }
 
-    pub fn write_byte(&mut self, _address: u16, _data: u8) {
-        // println!("serial: {}", data as char);
+    pub fn write_byte(&mut self, address: u16, data: u8) {
+        match address {
+            0xFF01 => self.data = data,
+            0xFF02 => {
+                let was_external = self.control & (SC_TRANSFER | SC_INTERNAL_CLOCK) == SC_TRANSFER;
+                self.control = data & self.control_mask();
+
+                if was_external && self.control & (SC_TRANSFER | SC_INTERNAL_CLOCK) != SC_TRANSFER {
+                    self.peer.cancel_external();
+                }
+
+                if self.control & SC_TRANSFER != 0 {
+                    self.state.bits_remaining = 8;
+                    self.state.clock_counter = 0;
+                    self.state.waiting_reply = false;
+                }
+            },
+            _ => unreachable!()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use super::*;
+
+    const IF_SERIAL: u8 = 1 << (Interrupts::Serial as u8);
+
+    // Answers with a byte only after being polled a few times, like a peer over the network
+    struct SlowPeer {
+        polls: u8,
+    }
+
+    impl LinkPeer for SlowPeer {
+        fn transfer(&mut self, _data: u8) -> Option<u8> {
+            None
+        }
+
+        fn poll_transfer(&mut self) -> Option<u8> {
+            self.polls += 1;
+            if self.polls == 3 { Some(0x5A) } else { None }
+        }
     }
-}
\ No newline at end of file
+
+    // Keeps the byte the game has ready until the other side clocks it, like the link cable
+    struct ExternalPeer {
+        ready: Rc<Cell<Option<u8>>>,
+    }
+
+    impl LinkPeer for ExternalPeer {
+        fn transfer(&mut self, _data: u8) -> Option<u8> {
+            Some(0xFF)
+        }
+
+        fn transfer_external(&mut self, data: u8) -> Option<u8> {
+            self.ready.set(Some(data));
+            None
+        }
+
+        fn cancel_external(&mut self) {
+            self.ready.set(None);
+        }
+    }
+
+    fn new_interrupts() -> CPUInterrupts {
+        let mut interrupts = CPUInterrupts::new();
+        interrupts.flags = 0;
+        interrupts
+    }
+
+    fn start(serial: &mut Serial, data: u8, control: u8) {
+        serial.write_byte(0xFF01, data);
+        serial.write_byte(0xFF02, control);
+    }
+
+    // Ticks until the transfer is done, returns how many clocks that took
+    fn run(serial: &mut Serial, interrupts: &mut CPUInterrupts, max: u32) -> Option<u32> {
+        for clocks in 1..=max {
+            serial.tick(interrupts);
+
+            if interrupts.flags & IF_SERIAL != 0 {
+                return Some(clocks);
+            }
+        }
+
+        None
+    }
+
+    #[test]
+    fn internal_clock() {
+        let mut serial = Serial::new(GameBoyModel::DMG);
+        let mut interrupts = new_interrupts();
+        start(&mut serial, 0x42, 0x81);
+
+        assert_eq!(run(&mut serial, &mut interrupts, 10000), Some(8 * 512));
+        assert_eq!(serial.read_byte(0xFF02) & SC_TRANSFER, 0);
+        // nothing plugged in reads as all ones
+        assert_eq!(serial.read_byte(0xFF01), 0xFF);
+    }
+
+    #[test]
+    fn fast_clock() {
+        let mut serial = Serial::new(GameBoyModel::GBC);
+        let mut interrupts = new_interrupts();
+        start(&mut serial, 0x42, 0x83);
+        assert_eq!(run(&mut serial, &mut interrupts, 10000), Some(8 * 16));
+
+        // there is no fast clock on DMG
+        let mut serial = Serial::new(GameBoyModel::DMG);
+        let mut interrupts = new_interrupts();
+        start(&mut serial, 0x42, 0x83);
+        assert_eq!(run(&mut serial, &mut interrupts, 10000), Some(8 * 512));
+    }
+
+    #[test]
+    fn external_clock() {
+        // nobody drives the clock, so the transfer never ends
+        let mut serial = Serial::new(GameBoyModel::DMG);
+        let mut interrupts = new_interrupts();
+        start(&mut serial, 0x42, 0x80);
+
+        assert_eq!(run(&mut serial, &mut interrupts, 10000), None);
+        assert_eq!(serial.read_byte(0xFF02) & SC_TRANSFER, SC_TRANSFER);
+    }
+
+    #[test]
+    fn external_clock_cancel() {
+        let ready = Rc::new(Cell::new(None));
+        let mut serial = Serial::new(GameBoyModel::DMG);
+        let mut interrupts = new_interrupts();
+        serial.set_peer(Box::new(ExternalPeer { ready: ready.clone() }));
+        start(&mut serial, 0x42, 0x80);
+        run(&mut serial, &mut interrupts, 100);
+        assert_eq!(ready.get(), Some(0x42));
+
+        // restarting keeps the byte on offer
+        serial.write_byte(0xFF02, 0x80);
+        assert_eq!(ready.get(), Some(0x42));
+
+        serial.write_byte(0xFF02, 0x00);
+        assert_eq!(ready.get(), None);
+
+        // and so does switching to the internal clock
+        start(&mut serial, 0x24, 0x80);
+        run(&mut serial, &mut interrupts, 100);
+        assert_eq!(ready.get(), Some(0x24));
+        serial.write_byte(0xFF02, 0x81);
+        assert_eq!(ready.get(), None);
+        assert_eq!(interrupts.flags & IF_SERIAL, 0);
+    }
+
+    #[test]
+    fn peers() {
+        let mut serial = Serial::new(GameBoyModel::DMG);
+        let mut interrupts = new_interrupts();
+        serial.set_peer(Box::new(Loopback));
+        serial.set_capture(true);
+        start(&mut serial, 0x42, 0x81);
+
+        run(&mut serial, &mut interrupts, 10000).unwrap();
+        assert_eq!(serial.read_byte(0xFF01), 0x42);
+        assert_eq!(serial.get_output(), [0x42]);
+
+        // the interrupt waits for the answer
+        let mut serial = Serial::new(GameBoyModel::DMG);
+        let mut interrupts = new_interrupts();
+        serial.set_peer(Box::new(SlowPeer { polls: 0 }));
+        start(&mut serial, 0x42, 0x81);
+
+        assert_eq!(run(&mut serial, &mut interrupts, 10000), Some(8 * 512 + 3));
+        assert_eq!(serial.read_byte(0xFF01), 0x5A);
+    }
+
+    #[test]
+    fn save_state() {
+        let mut serial = Serial::new(GameBoyModel::GBC);
+        let mut interrupts = new_interrupts();
+        start(&mut serial, 0x42, 0x83);
+        for _ in 0..40 {
+            serial.tick(&mut interrupts);
+        }
+
+        let mut w = StateWriter::new();
+        serial.save_state(&mut w);
+        let bytes = w.into_bytes();
+
+        let mut loaded = Serial::new(GameBoyModel::GBC);
+        loaded.load_state(&mut StateReader::new(&bytes)).unwrap();
+        assert_eq!(run(&mut loaded, &mut interrupts, 10000), Some(8 * 16 - 40));
+
+        // an internally clocked transfer with no bits left can't be
+        let bad = [0x42, 0x81, 0, 0, 0];
+        assert!(loaded.load_state(&mut StateReader::new(&bad)).is_err());
+    }
+}
@@ -0,0 +1,244 @@
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+
+use crate::serial::LinkPeer;
+
+const LINK_MAGIC: &[u8; 4] = b"RGBL";
+const LINK_VERSION: u8 = 1;
+
+// Every message on the wire is a kind byte followed by a data byte
+const MSG_SYNC: u8 = 1;
+const MSG_TRANSFER: u8 = 2;
+const MSG_REPLY: u8 = 3;
+
+// How long a transfer waits for the other side before giving up on it, the machine keeps running meanwhile
+const REPLY_TIMEOUT: Duration = Duration::from_secs(2);
+// How many frames we run ahead of the other emulator before holding off for it
+const MAX_LEAD: i64 = 4;
+
+// Prefixing the address with unix: uses a unix domain socket instead of TCP
+const UNIX_PREFIX: &str = "unix:";
+
+// State shared with the thread reading from the socket
+struct SharedState {
+    // byte we have in SB waiting for the other side to clock it out
+    ready: Option<u8>,
+    // byte the other side sent us while it was driving the clock
+    received: Option<u8>,
+    connected: bool,
+}
+
+// Link cable to another emulator over a socket.
+//
+// Whichever side uses the internal clock sends its byte and waits for the other side to answer with
+// the contents of its SB. A thread keeps reading from the socket so the answer is given even when that
+// emulator is busy, and both sides exchange a sync message every frame so neither CPU gets too far ahead.
+// Nothing here blocks the emulation, the transfer just lasts until the answer comes in.
+pub struct LinkCable {
+    writer: Arc<Mutex<Box<dyn Write + Send>>>,
+    shared: Arc<Mutex<SharedState>>,
+    replies: Receiver<u8>,
+    syncs: Receiver<u8>,
+    // when the transfer we are waiting an answer for was sent
+    transfer_start: Option<Instant>,
+    // syncs we sent minus the ones we got
+    lead: i64,
+}
+
+impl LinkCable {
+    // Waits for the other emulator to connect
+    pub fn listen(address: &str) -> io::Result<Self> {
+        #[cfg(unix)]
+        {
+            if let Some(path) = address.strip_prefix(UNIX_PREFIX) {
+                // a socket file left over by a previous session would make bind fail
+                std::fs::remove_file(path).ok();
+
+                let (stream, _) = UnixListener::bind(path)?.accept()?;
+                return Self::new(Box::new(stream.try_clone()?), Box::new(stream));
+            }
+        }
+
+        let (stream, _) = TcpListener::bind(address)?.accept()?;
+        stream.set_nodelay(true)?;
+
+        Self::new(Box::new(stream.try_clone()?), Box::new(stream))
+    }
+
+    pub fn connect(address: &str) -> io::Result<Self> {
+        #[cfg(unix)]
+        {
+            if let Some(path) = address.strip_prefix(UNIX_PREFIX) {
+                let stream = UnixStream::connect(path)?;
+                return Self::new(Box::new(stream.try_clone()?), Box::new(stream));
+            }
+        }
+
+        let stream = TcpStream::connect(address)?;
+        stream.set_nodelay(true)?;
+
+        Self::new(Box::new(stream.try_clone()?), Box::new(stream))
+    }
+
+    pub fn new(mut reader: Box<dyn Read + Send>, mut writer: Box<dyn Write + Send>) -> io::Result<Self> {
+        // Both ends introduce themselves before anything else
+        writer.write_all(LINK_MAGIC)?;
+        writer.write_all(&[LINK_VERSION])?;
+        writer.flush()?;
+
+        let mut hello = [0; 5];
+        reader.read_exact(&mut hello)?;
+        if &hello[0..4] != LINK_MAGIC || hello[4] != LINK_VERSION {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "the other side is not a compatible link cable"));
+        }
+
+        let writer = Arc::new(Mutex::new(writer));
+        let shared = Arc::new(Mutex::new(SharedState {
+            ready: None,
+            received: None,
+            connected: true,
+        }));
+
+        let (reply_sender, replies) = channel();
+        let (sync_sender, syncs) = channel();
+
+        let thread_writer = writer.clone();
+        let thread_shared = shared.clone();
+        thread::spawn(move || {
+            LinkCable::read_messages(reader, thread_writer, thread_shared.clone(), reply_sender, sync_sender);
+            thread_shared.lock().unwrap().connected = false;
+            println!("Link cable disconnected");
+        });
+
+        Ok(Self {
+            writer,
+            shared,
+            replies,
+            syncs,
+            transfer_start: None,
+            lead: 0,
+        })
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.shared.lock().unwrap().connected
+    }
+
+    fn read_messages(mut reader: Box<dyn Read + Send>, writer: Arc<Mutex<Box<dyn Write + Send>>>, shared: Arc<Mutex<SharedState>>, replies: Sender<u8>, syncs: Sender<u8>) {
+        let mut msg = [0; 2];
+
+        while reader.read_exact(&mut msg).is_ok() {
+            match msg[0] {
+                // The other side clocked a byte out, answer with ours if the game set up a transfer,
+                // otherwise the line just reads high
+                MSG_TRANSFER => {
+                    let reply = {
+                        let mut shared = shared.lock().unwrap();
+                        match shared.ready.take() {
+                            Some(data) => {
+                                shared.received = Some(msg[1]);
+                                data
+                            },
+                            None => 0xFF
+                        }
+                    };
+
+                    if LinkCable::send(&writer, MSG_REPLY, reply).is_err() {
+                        return;
+                    }
+                },
+
+                MSG_REPLY => if replies.send(msg[1]).is_err() { return },
+                MSG_SYNC => if syncs.send(msg[1]).is_err() { return },
+
+                _ => return
+            }
+        }
+    }
+
+    fn send(writer: &Mutex<Box<dyn Write + Send>>, kind: u8, data: u8) -> io::Result<()> {
+        let mut writer = writer.lock().unwrap();
+        writer.write_all(&[kind, data])?;
+        writer.flush()
+    }
+}
+
+impl LinkPeer for LinkCable {
+    fn transfer(&mut self, data: u8) -> Option<u8> {
+        if !self.is_connected() || LinkCable::send(&self.writer, MSG_TRANSFER, data).is_err() {
+            return Some(0xFF);
+        }
+
+        self.transfer_start = Some(Instant::now());
+        self.poll_transfer()
+    }
+
+    // A peer that went away or doesn't answer in time reads as the line floating high
+    fn poll_transfer(&mut self) -> Option<u8> {
+        let reply = match self.replies.try_recv() {
+            Ok(data) => Some(data),
+            Err(TryRecvError::Disconnected) => Some(0xFF),
+            Err(TryRecvError::Empty) if !self.is_connected() => Some(0xFF),
+            Err(TryRecvError::Empty) => match self.transfer_start {
+                Some(start) if start.elapsed() < REPLY_TIMEOUT => None,
+                _ => Some(0xFF)
+            }
+        };
+
+        if reply.is_some() {
+            self.transfer_start = None;
+        }
+
+        reply
+    }
+
+    fn transfer_external(&mut self, data: u8) -> Option<u8> {
+        let mut shared = self.shared.lock().unwrap();
+
+        match shared.received.take() {
+            Some(received) => Some(received),
+            None => {
+                shared.ready = Some(data);
+                None
+            }
+        }
+    }
+
+    // A byte the other side clocks after this is answered with 0xFF, like when no transfer was set up
+    fn cancel_external(&mut self) {
+        let mut shared = self.shared.lock().unwrap();
+        shared.ready = None;
+        shared.received = None;
+    }
+
+    fn sync(&mut self) {
+        if !self.is_connected() || LinkCable::send(&self.writer, MSG_SYNC, 0).is_err() {
+            return;
+        }
+
+        self.lead += 1;
+    }
+
+    fn is_behind(&mut self) -> bool {
+        loop {
+            match self.syncs.try_recv() {
+                Ok(_) => self.lead -= 1,
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.shared.lock().unwrap().connected = false;
+                    break;
+                }
+            }
+        }
+
+        // a paused or slow peer lets us run a few frames ahead, then we wait for it without blocking the frontend
+        self.is_connected() && self.lead > MAX_LEAD
+    }
+}
@@ -0,0 +1,37 @@
+use std::io::Write;
+
+use crate::serial::LinkPeer;
+
+// No cable plugged in, the input line floats high
+pub struct Disconnected;
+
+impl LinkPeer for Disconnected {
+    fn transfer(&mut self, _data: u8) -> Option<u8> {
+        Some(0xFF)
+    }
+}
+
+// Prints everything sent through the port to stdout, test ROMs report their results this way
+pub struct ByteLogger;
+
+impl LinkPeer for ByteLogger {
+    fn transfer(&mut self, data: u8) -> Option<u8> {
+        let mut stdout = std::io::stdout();
+        stdout.write_all(&[data]).ok();
+
+        if data == b'\n' {
+            stdout.flush().ok();
+        }
+
+        Some(0xFF)
+    }
+}
+
+// The output line is wired back into the input, so every byte sent is received back
+pub struct Loopback;
+
+impl LinkPeer for Loopback {
+    fn transfer(&mut self, data: u8) -> Option<u8> {
+        Some(data)
+    }
+}
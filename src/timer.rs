@@ -1,4 +1,5 @@
 use crate::cpu::{Interrupts, CPUInterrupts};
+use crate::savestate::{StateWriter, StateReader, SaveStateError};
 
 struct TimerRegisters {
     internal_counter: u16,
@@ -39,6 +40,34 @@ impl Timer {
         }
     }
 
+    pub fn save_state(&self, w: &mut StateWriter) {
+        let registers = &self.registers;
+
+        w.write_u16(registers.internal_counter);
+        w.write_bool(registers.timer_enabled);
+        w.write_u8(registers.timer_frequency);
+        w.write_u8(registers.timer_counter);
+        w.write_u8(registers.timer_modulo);
+        w.write_bool(registers.timer_overflow);
+        w.write_u8(registers.timer_overflow_counter);
+        w.write_u8(self.prev_and_result);
+    }
+
+    pub fn load_state(&mut self, r: &mut StateReader) -> Result<(), SaveStateError> {
+        let registers = &mut self.registers;
+
+        registers.internal_counter = r.read_u16()?;
+        registers.timer_enabled = r.read_bool()?;
+        registers.timer_frequency = r.read_u8()? & 0x3;
+        registers.timer_counter = r.read_u8()?;
+        registers.timer_modulo = r.read_u8()?;
+        registers.timer_overflow = r.read_bool()?;
+        registers.timer_overflow_counter = r.read_u8()?;
+        self.prev_and_result = r.read_u8()?;
+
+        Ok(())
+    }
+
     pub fn tick(&mut self, interrupts: &mut CPUInterrupts) {
         let registers = &mut self.registers;
 
@@ -95,7 +124,7 @@ impl Timer {
     }
 
     pub fn write_byte(&mut self, address: u16, data: u8) {
-        let mut registers = &mut self.registers;
+        let registers = &mut self.registers;
 
         match address {
             // FF04 DIV
@@ -0,0 +1,16 @@
+mod common;
+
+use common::{run_acid2, report};
+use rust_gameboy::GameBoyModel;
+
+#[test]
+#[ignore = "needs the test ROMs, see tests/common/mod.rs"]
+fn dmg_acid2() {
+    report("acid2/dmg-acid2.gb", run_acid2("acid2/dmg-acid2.gb", "acid2/dmg-acid2.png", GameBoyModel::DMG, 600));
+}
+
+#[test]
+#[ignore = "needs the test ROMs, see tests/common/mod.rs"]
+fn cgb_acid2() {
+    report("acid2/cgb-acid2.gbc", run_acid2("acid2/cgb-acid2.gbc", "acid2/cgb-acid2.png", GameBoyModel::GBC, 600));
+}
@@ -0,0 +1,33 @@
+mod common;
+
+use common::{run_blargg, report};
+
+macro_rules! blargg_tests {
+    ($($name:ident: $rom:expr, $frames:expr;)*) => {
+        $(
+            #[test]
+            #[ignore = "needs the test ROMs, see tests/common/mod.rs"]
+            fn $name() {
+                report($rom, run_blargg($rom, $frames));
+            }
+        )*
+    }
+}
+
+blargg_tests! {
+    cpu_instrs_01_special: "blarg/cpu_instrs/individual/01-special.gb", 600;
+    cpu_instrs_02_interrupts: "blarg/cpu_instrs/individual/02-interrupts.gb", 600;
+    cpu_instrs_03_op_sp_hl: "blarg/cpu_instrs/individual/03-op sp,hl.gb", 600;
+    cpu_instrs_04_op_r_imm: "blarg/cpu_instrs/individual/04-op r,imm.gb", 600;
+    cpu_instrs_05_op_rp: "blarg/cpu_instrs/individual/05-op rp.gb", 600;
+    cpu_instrs_06_ld_r_r: "blarg/cpu_instrs/individual/06-ld r,r.gb", 600;
+    cpu_instrs_07_jr_jp_call_ret_rst: "blarg/cpu_instrs/individual/07-jr,jp,call,ret,rst.gb", 600;
+    cpu_instrs_08_misc_instrs: "blarg/cpu_instrs/individual/08-misc instrs.gb", 600;
+    cpu_instrs_09_op_r_r: "blarg/cpu_instrs/individual/09-op r,r.gb", 1200;
+    cpu_instrs_10_bit_ops: "blarg/cpu_instrs/individual/10-bit ops.gb", 1200;
+    cpu_instrs_11_op_a_hl: "blarg/cpu_instrs/individual/11-op a,(hl).gb", 1200;
+    instr_timing: "blarg/instr_timing/instr_timing.gb", 600;
+    mem_timing_01_read_timing: "blarg/mem_timing/individual/01-read_timing.gb", 600;
+    mem_timing_02_write_timing: "blarg/mem_timing/individual/02-write_timing.gb", 600;
+    mem_timing_03_modify_timing: "blarg/mem_timing/individual/03-modify_timing.gb", 600;
+}
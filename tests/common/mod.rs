@@ -0,0 +1,228 @@
+// Shared harness for the test ROM conformance suites.
+//
+// The ROMs themselves are not distributed with the emulator. Point RUST_GAMEBOY_TEST_ROMS at a
+// directory laid out like this (it defaults to ./roms):
+//
+//   blarg/cpu_instrs/individual/01-special.gb ...
+//   blarg/instr_timing/instr_timing.gb
+//   blarg/mem_timing/individual/01-read_timing.gb ...
+//   mooneye/acceptance/...
+//   acid2/dmg-acid2.gb, acid2/dmg-acid2.png
+//   acid2/cgb-acid2.gbc, acid2/cgb-acid2.png
+//
+// Since the ROMs may not be there the tests are #[ignore]d, run them with cargo test -- --ignored.
+// Once asked for, a ROM that can't be found or fails to load fails its test.
+
+#![allow(dead_code)]
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+
+use rust_gameboy::{Machine, GameBoyModel, SCREEN_WIDTH, SCREEN_HEIGHT};
+
+// LD B,B is used by mooneye and acid2 as a software breakpoint
+const OPCODE_LD_B_B: u16 = 0x40;
+
+// Every step runs at least one machine cycle, so a frame never takes more steps than this.
+// The frame count doesn't move while the LCD is off, so the loops are bounded by steps too.
+const MAX_STEPS_PER_FRAME: u64 = 70224 / 4;
+
+const OUTPUT_CHECK_STEPS: u64 = 4096;
+
+pub enum TestResult {
+    Passed,
+    Failed(String),
+}
+
+pub fn rom_path(relative: &str) -> PathBuf {
+    let base = match std::env::var("RUST_GAMEBOY_TEST_ROMS") {
+        Ok(dir) => PathBuf::from(dir),
+        Err(_) => PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("roms"),
+    };
+
+    base.join(relative)
+}
+
+fn load_machine(relative: &str, model: Option<GameBoyModel>) -> Result<Machine, String> {
+    let path = rom_path(relative);
+    let bytes = std::fs::read(&path).map_err(|e| format!("can't read {}: {}", path.display(), e))?;
+
+    let mut machine = Machine::from_rom_bytes(&bytes, model).map_err(|e| format!("can't load {}: {:?}", path.display(), e))?;
+    machine.start(true);
+
+    Ok(machine)
+}
+
+// Runs until the ROM executes LD B,B or the frame limit is reached
+fn run_until_breakpoint(machine: &mut Machine, max_frames: u64) -> bool {
+    for _ in 0..max_frames.saturating_mul(MAX_STEPS_PER_FRAME) {
+        if machine.get_frame_count() >= max_frames {
+            break;
+        }
+
+        machine.step();
+
+        if machine.get_registers().next_opcode == OPCODE_LD_B_B {
+            return true;
+        }
+    }
+
+    false
+}
+
+// Blargg's ROMs print their results through the serial port and end with "Passed" or "Failed"
+pub fn run_blargg(relative: &str, max_frames: u64) -> TestResult {
+    let mut machine = match load_machine(relative, None) {
+        Ok(machine) => machine,
+        Err(e) => return TestResult::Failed(e),
+    };
+
+    machine.set_serial_capture(true);
+
+    let mut steps: u64 = 0;
+    while machine.get_frame_count() < max_frames && steps < max_frames.saturating_mul(MAX_STEPS_PER_FRAME) {
+        // step instead of run_frame, which doesn't come back until VBlank
+        machine.step();
+        steps += 1;
+
+        // the output is looked at once per frame, or every so often when the LCD is off
+        if machine.is_vblank() {
+            machine.get_framebuffer();
+        }
+        else if !steps.is_multiple_of(OUTPUT_CHECK_STEPS) {
+            continue;
+        }
+
+        let output = String::from_utf8_lossy(machine.get_serial_output());
+        if output.contains("Passed") {
+            return TestResult::Passed;
+        }
+
+        if output.contains("Failed") {
+            return TestResult::Failed(output.trim().to_owned());
+        }
+    }
+
+    let output = String::from_utf8_lossy(machine.get_serial_output());
+    TestResult::Failed(format!("timed out after {} frames: {}", max_frames, output.trim()))
+}
+
+// Mooneye's ROMs load the fibonacci sequence into the registers on success before LD B,B
+pub fn run_mooneye(relative: &str, max_frames: u64) -> TestResult {
+    let mut machine = match load_machine(relative, None) {
+        Ok(machine) => machine,
+        Err(e) => return TestResult::Failed(e),
+    };
+
+    if !run_until_breakpoint(&mut machine, max_frames) {
+        return TestResult::Failed(format!("timed out after {} frames", max_frames));
+    }
+
+    let r = machine.get_registers();
+    if r.bc == 0x0305 && r.de == 0x080D && r.hl == 0x1522 {
+        TestResult::Passed
+    }
+    else {
+        TestResult::Failed(format!("BC: {:#06X} DE: {:#06X} HL: {:#06X}", r.bc, r.de, r.hl))
+    }
+}
+
+// The acid2 ROMs draw a face and hit LD B,B, the screen is then compared against the reference image
+pub fn run_acid2(relative: &str, reference: &str, model: GameBoyModel, max_frames: u64) -> TestResult {
+    let mut machine = match load_machine(relative, Some(model)) {
+        Ok(machine) => machine,
+        Err(e) => return TestResult::Failed(e),
+    };
+
+    let reference = match load_reference(reference) {
+        Some(pixels) => pixels,
+        None => return TestResult::Failed(format!("can't load the reference image {}", reference)),
+    };
+
+    if !run_until_breakpoint(&mut machine, max_frames) {
+        return TestResult::Failed(format!("timed out after {} frames", max_frames));
+    }
+
+    // let the frame that was being drawn finish
+    machine.get_framebuffer();
+    for _ in 0..MAX_STEPS_PER_FRAME {
+        machine.step();
+
+        if machine.is_vblank() {
+            break;
+        }
+    }
+
+    let framebuffer: Vec<u32> = machine.get_framebuffer().to_vec();
+    let actual = normalize(&framebuffer, model);
+    let expected = normalize(&reference, model);
+
+    if hash(&actual) == hash(&expected) {
+        TestResult::Passed
+    }
+    else {
+        let mismatches = actual.iter().zip(expected.iter()).filter(|(a, b)| a != b).count();
+        TestResult::Failed(format!("framebuffer differs from the reference in {} pixels", mismatches))
+    }
+}
+
+// Reference images are decoded into the same 0x00BBGGRR layout as the framebuffer
+fn load_reference(relative: &str) -> Option<Vec<u32>> {
+    let file = std::fs::File::open(rom_path(relative)).ok()?;
+    let decoder = png::Decoder::new(file);
+    let mut reader = decoder.read_info().ok()?;
+
+    let mut data = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut data).ok()?;
+
+    if info.width != SCREEN_WIDTH || info.height != SCREEN_HEIGHT {
+        return None;
+    }
+
+    let channels = match info.color_type {
+        png::ColorType::Rgb => 3,
+        png::ColorType::Rgba => 4,
+        _ => return None,
+    };
+
+    Some(data[0..info.buffer_size()].chunks_exact(channels).map(|p| {
+        (p[2] as u32) << 16 | (p[1] as u32) << 8 | p[0] as u32
+    }).collect())
+}
+
+// The reference images and the emulator use different color curves, so compare shades instead:
+// grey levels on DMG and 5 bit RGB on GBC
+fn normalize(pixels: &[u32], model: GameBoyModel) -> Vec<u32> {
+    pixels.iter().map(|c| {
+        let r = c & 0xFF;
+        let g = (c >> 8) & 0xFF;
+        let b = (c >> 16) & 0xFF;
+
+        match model {
+            GameBoyModel::DMG => match r {
+                0xC0..=0xFF => 0,
+                0x70..=0xBF => 1,
+                0x20..=0x6F => 2,
+                _ => 3
+            },
+            GameBoyModel::GBC => {
+                let to5 = |v: u32| (v * 31 + 127) / 255;
+                to5(b) << 10 | to5(g) << 5 | to5(r)
+            }
+        }
+    }).collect()
+}
+
+fn hash(pixels: &[u32]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    pixels.hash(&mut hasher);
+    hasher.finish()
+}
+
+pub fn report(name: &str, result: TestResult) {
+    match result {
+        TestResult::Passed => println!("{}: passed", name),
+        TestResult::Failed(reason) => panic!("{}: failed: {}", name, reason),
+    }
+}
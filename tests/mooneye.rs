@@ -0,0 +1,60 @@
+mod common;
+
+use common::{run_mooneye, report};
+
+macro_rules! mooneye_tests {
+    ($($name:ident: $rom:expr;)*) => {
+        $(
+            #[test]
+            #[ignore = "needs the test ROMs, see tests/common/mod.rs"]
+            fn $name() {
+                report($rom, run_mooneye($rom, 600));
+            }
+        )*
+    }
+}
+
+mooneye_tests! {
+    add_sp_e_timing: "mooneye/acceptance/add_sp_e_timing.gb";
+    call_timing: "mooneye/acceptance/call_timing.gb";
+    call_cc_timing: "mooneye/acceptance/call_cc_timing.gb";
+    di_timing: "mooneye/acceptance/di_timing-GS.gb";
+    div_timing: "mooneye/acceptance/div_timing.gb";
+    ei_sequence: "mooneye/acceptance/ei_sequence.gb";
+    ei_timing: "mooneye/acceptance/ei_timing.gb";
+    halt_ime0_ei: "mooneye/acceptance/halt_ime0_ei.gb";
+    halt_ime1_timing: "mooneye/acceptance/halt_ime1_timing.gb";
+    if_ie_registers: "mooneye/acceptance/if_ie_registers.gb";
+    intr_timing: "mooneye/acceptance/intr_timing.gb";
+    jp_timing: "mooneye/acceptance/jp_timing.gb";
+    ld_hl_sp_e_timing: "mooneye/acceptance/ld_hl_sp_e_timing.gb";
+    oam_dma_restart: "mooneye/acceptance/oam_dma_restart.gb";
+    oam_dma_start: "mooneye/acceptance/oam_dma_start.gb";
+    oam_dma_timing: "mooneye/acceptance/oam_dma_timing.gb";
+    pop_timing: "mooneye/acceptance/pop_timing.gb";
+    push_timing: "mooneye/acceptance/push_timing.gb";
+    rapid_di_ei: "mooneye/acceptance/rapid_di_ei.gb";
+    ret_timing: "mooneye/acceptance/ret_timing.gb";
+    ret_cc_timing: "mooneye/acceptance/ret_cc_timing.gb";
+    reti_timing: "mooneye/acceptance/reti_timing.gb";
+    reti_intr_timing: "mooneye/acceptance/reti_intr_timing.gb";
+    rst_timing: "mooneye/acceptance/rst_timing.gb";
+    bits_mem_oam: "mooneye/acceptance/bits/mem_oam.gb";
+    bits_reg_f: "mooneye/acceptance/bits/reg_f.gb";
+    bits_unused_hwio: "mooneye/acceptance/bits/unused_hwio-GS.gb";
+    instr_daa: "mooneye/acceptance/instr/daa.gb";
+    interrupts_ie_push: "mooneye/acceptance/interrupts/ie_push.gb";
+    oam_dma_basic: "mooneye/acceptance/oam_dma/basic.gb";
+    oam_dma_reg_read: "mooneye/acceptance/oam_dma/reg_read.gb";
+    ppu_intr_2_0_timing: "mooneye/acceptance/ppu/intr_2_0_timing.gb";
+    ppu_stat_irq_blocking: "mooneye/acceptance/ppu/stat_irq_blocking.gb";
+    timer_div_write: "mooneye/acceptance/timer/div_write.gb";
+    timer_rapid_toggle: "mooneye/acceptance/timer/rapid_toggle.gb";
+    timer_tim00: "mooneye/acceptance/timer/tim00.gb";
+    timer_tim01: "mooneye/acceptance/timer/tim01.gb";
+    timer_tim10: "mooneye/acceptance/timer/tim10.gb";
+    timer_tim11: "mooneye/acceptance/timer/tim11.gb";
+    timer_tima_reload: "mooneye/acceptance/timer/tima_reload.gb";
+    timer_tma_write_reloading: "mooneye/acceptance/timer/tma_write_reloading.gb";
+    mbc1_multicart_rom_8mb: "mooneye/emulator-only/mbc1/multicart_rom_8Mb.gb";
+}